@@ -0,0 +1,335 @@
+use crate::checksums::{self, SHA256SUMS};
+use anyhow::{Context, anyhow};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use time::OffsetDateTime;
+use zip::CompressionMethod;
+use zip::write::{SimpleFileOptions, StreamWriter};
+
+/// Compression level for `.tar.zst` archives (zstd's default).
+const ZSTD_LEVEL: i32 = 3;
+
+type Output = Box<dyn Write + Send>;
+
+/// Where archive bytes go: straight to the output, or through age encryption first.
+enum Sink {
+    Plain(Output),
+    Encrypted(age::stream::StreamWriter<Output>),
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Sink::Plain(w) => w.write(buf),
+            Sink::Encrypted(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Sink::Plain(w) => w.flush(),
+            Sink::Encrypted(w) => w.flush(),
+        }
+    }
+}
+
+impl Sink {
+    /// Writes the final encrypted chunk, if any, and flushes the output.
+    fn finish(self) -> std::io::Result<()> {
+        let mut out = match self {
+            Sink::Plain(w) => w,
+            Sink::Encrypted(w) => w.finish()?,
+        };
+        out.flush()
+    }
+}
+
+/// Container format of `export --archive`, picked from the file name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    TarZst,
+    Zip,
+}
+
+impl ArchiveKind {
+    /// `.tar.zst`/`.tzst` or `.zip`, optionally followed by `.age`; `-` (stdout) is a `.tar.zst`
+    /// stream.
+    pub fn from_path(path: &Path) -> anyhow::Result<Self> {
+        let name = path.to_string_lossy().to_lowercase();
+        let name = name.strip_suffix(".age").unwrap_or(&name);
+        if name == "-" || name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+            Ok(ArchiveKind::TarZst)
+        } else if name.ends_with(".zip") {
+            Ok(ArchiveKind::Zip)
+        } else {
+            Err(anyhow!(
+                "can't tell the archive format of {path:?}; use a .tar.zst or .zip name (or - for a .tar.zst stream on stdout)"
+            ))
+        }
+    }
+}
+
+enum Inner {
+    TarZst(tar::Builder<zstd::Encoder<'static, Sink>>),
+    Zip(Box<zip::ZipWriter<StreamWriter<Sink>>>),
+}
+
+/// An archive still being written, with the checksum of every entry so far.
+struct Open {
+    inner: Inner,
+    sums: Vec<(String, String)>,
+}
+
+/// Streams an export into a `.tar.zst` or `.zip` archive as the files are produced, instead of
+/// writing them to a directory. Entries are added from any number of export workers.
+///
+/// `SHA256SUMS` is computed on the fly and written last, by [`ArchiveWriter::finish`].
+pub struct ArchiveWriter {
+    path: PathBuf,
+    open: Mutex<Option<Open>>,
+}
+
+impl std::fmt::Debug for ArchiveWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArchiveWriter")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ArchiveWriter {
+    /// Creates the archive at `path` (`-` for stdout). Its format follows the file name. With
+    /// `recipients`, the whole archive is encrypted to them with age (X25519).
+    pub fn create(path: &Path, recipients: &[age::x25519::Recipient]) -> anyhow::Result<Self> {
+        let kind = ArchiveKind::from_path(path)?;
+        let output: Output = if path == Path::new("-") {
+            Box::new(std::io::stdout())
+        } else {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent).with_context(|| format!("create {parent:?}"))?;
+            }
+            let file = std::fs::File::create(path).with_context(|| format!("create {path:?}"))?;
+            Box::new(std::io::BufWriter::new(file))
+        };
+        Self::new(path.to_path_buf(), kind, output, recipients)
+    }
+
+    fn new(
+        path: PathBuf,
+        kind: ArchiveKind,
+        output: Output,
+        recipients: &[age::x25519::Recipient],
+    ) -> anyhow::Result<Self> {
+        let sink = if recipients.is_empty() {
+            Sink::Plain(output)
+        } else {
+            let encryptor = age::Encryptor::with_recipients(
+                recipients.iter().map(|r| r as &dyn age::Recipient),
+            )
+            .context("set up encryption")?;
+            Sink::Encrypted(
+                encryptor
+                    .wrap_output(output)
+                    .context("start encrypted stream")?,
+            )
+        };
+        let inner = match kind {
+            ArchiveKind::TarZst => {
+                let encoder = zstd::Encoder::new(sink, ZSTD_LEVEL).context("start zstd stream")?;
+                let mut tar = tar::Builder::new(encoder);
+                tar.mode(tar::HeaderMode::Deterministic);
+                Inner::TarZst(tar)
+            }
+            ArchiveKind::Zip => Inner::Zip(Box::new(zip::ZipWriter::new_stream(sink))),
+        };
+        Ok(Self {
+            path,
+            open: Mutex::new(Some(Open {
+                inner,
+                sums: Vec::new(),
+            })),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Adds a file at `rel` (a path relative to the export root) with `modified_at` as its mtime.
+    pub fn add(&self, rel: &Path, data: &[u8], modified_at: OffsetDateTime) -> anyhow::Result<()> {
+        let name = entry_name(rel)?;
+        let mut guard = self.open.lock().unwrap_or_else(|e| e.into_inner());
+        let open = guard
+            .as_mut()
+            .ok_or_else(|| anyhow!("archive already finished"))?;
+        match &mut open.inner {
+            Inner::TarZst(tar) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(data.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(modified_at.unix_timestamp().max(0) as u64);
+                tar.append_data(&mut header, &name, data)
+                    .with_context(|| format!("add {name} to archive"))?;
+            }
+            Inner::Zip(zip) => {
+                let options = SimpleFileOptions::default()
+                    .compression_method(CompressionMethod::Deflated)
+                    .last_modified_time(zip_time(modified_at));
+                zip.start_file(name.as_str(), options)
+                    .with_context(|| format!("add {name} to archive"))?;
+                zip.write_all(data)
+                    .with_context(|| format!("add {name} to archive"))?;
+            }
+        }
+        open.sums.push((name, checksums::sha256_bytes(data)));
+        Ok(())
+    }
+
+    /// Appends `SHA256SUMS` and completes the archive. Returns the number of files in it, not
+    /// counting `SHA256SUMS`.
+    pub fn finish(&self) -> anyhow::Result<usize> {
+        let Open { inner, mut sums } = self
+            .open
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+            .ok_or_else(|| anyhow!("archive already finished"))?;
+        sums.sort();
+        let listing: String = sums
+            .iter()
+            .map(|(name, hash)| format!("{hash}  {name}\n"))
+            .collect();
+        let now = OffsetDateTime::now_utc();
+        let sink = match inner {
+            Inner::TarZst(mut tar) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(listing.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(now.unix_timestamp().max(0) as u64);
+                tar.append_data(&mut header, SHA256SUMS, listing.as_bytes())
+                    .context("add SHA256SUMS to archive")?;
+                tar.into_inner()
+                    .context("finish tar stream")?
+                    .finish()
+                    .context("finish zstd stream")?
+            }
+            Inner::Zip(mut zip) => {
+                let options = SimpleFileOptions::default()
+                    .compression_method(CompressionMethod::Deflated)
+                    .last_modified_time(zip_time(now));
+                zip.start_file(SHA256SUMS, options)
+                    .context("add SHA256SUMS to archive")?;
+                zip.write_all(listing.as_bytes())
+                    .context("add SHA256SUMS to archive")?;
+                zip.finish().context("finish zip")?.into_inner()
+            }
+        };
+        sink.finish()
+            .with_context(|| format!("write {:?}", self.path))?;
+        Ok(sums.len())
+    }
+}
+
+/// `/`-separated entry name for a relative export path.
+fn entry_name(rel: &Path) -> anyhow::Result<String> {
+    let mut parts = Vec::new();
+    for component in rel.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy()),
+            Component::CurDir => {}
+            _ => return Err(anyhow!("archive entries must be relative paths: {rel:?}")),
+        }
+    }
+    if parts.is_empty() {
+        return Err(anyhow!("empty archive entry name"));
+    }
+    Ok(parts.join("/"))
+}
+
+/// Zip timestamps only cover 1980–2107; dates outside that become 1980-01-01.
+fn zip_time(t: OffsetDateTime) -> zip::DateTime {
+    let t = t.to_offset(time::UtcOffset::UTC);
+    u16::try_from(t.year())
+        .ok()
+        .and_then(|year| {
+            zip::DateTime::from_date_and_time(
+                year,
+                t.month().into(),
+                t.day(),
+                t.hour(),
+                t.minute(),
+                t.second(),
+            )
+            .ok()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::sync::Arc;
+
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn kind_follows_the_file_name() {
+        let kind = |p: &str| ArchiveKind::from_path(Path::new(p)).ok();
+        assert_eq!(kind("backup.tar.zst"), Some(ArchiveKind::TarZst));
+        assert_eq!(kind("backup.TZST"), Some(ArchiveKind::TarZst));
+        assert_eq!(kind("-"), Some(ArchiveKind::TarZst));
+        assert_eq!(kind("backup.zip"), Some(ArchiveKind::Zip));
+        assert_eq!(kind("backup.tar.zst.age"), Some(ArchiveKind::TarZst));
+        assert_eq!(kind("backup.zip.age"), Some(ArchiveKind::Zip));
+        assert_eq!(kind("backup.tar.gz"), None);
+    }
+
+    #[test]
+    fn tar_zst_stream_holds_files_and_checksums() {
+        let buf = Shared::default();
+        let archive =
+            ArchiveWriter::new("-".into(), ArchiveKind::TarZst, Box::new(buf.clone()), &[])
+                .unwrap();
+        let t = OffsetDateTime::from_unix_timestamp(1_766_188_800).unwrap();
+        archive
+            .add(Path::new("Personal/Alpha-n1/contents.md"), b"hello", t)
+            .unwrap();
+        assert_eq!(archive.finish().unwrap(), 1);
+        assert!(archive.add(Path::new("late.md"), b"", t).is_err());
+
+        let bytes = buf.0.lock().unwrap().clone();
+        let mut tar = tar::Archive::new(zstd::Decoder::new(bytes.as_slice()).unwrap());
+        let mut entries = Vec::new();
+        for entry in tar.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let mut data = String::new();
+            entry.read_to_string(&mut data).unwrap();
+            let mtime = entry.header().mtime().unwrap();
+            entries.push((entry.path().unwrap().display().to_string(), data, mtime));
+        }
+        assert_eq!(entries[0].0, "Personal/Alpha-n1/contents.md");
+        assert_eq!(entries[0].1, "hello");
+        assert_eq!(entries[0].2, 1_766_188_800);
+        assert_eq!(entries[1].0, SHA256SUMS);
+        assert_eq!(
+            entries[1].1,
+            format!(
+                "{}  Personal/Alpha-n1/contents.md\n",
+                checksums::sha256_bytes(b"hello")
+            )
+        );
+    }
+}
@@ -0,0 +1,382 @@
+//! [`AsyncNotesBackend`]: the [`NotesBackend`] operations as futures, for tokio programs.
+//!
+//! Every backend call blocks (on `osascript` or SQLite), so [`BlockingAdapter`] runs them on
+//! tokio's blocking thread pool instead of tying up the async worker threads.
+
+use crate::cancel::CancelToken;
+use crate::error::{NotesError, Result};
+use crate::model::{Account, Folder, Note, NoteSummary};
+use crate::transport::NotesBackend;
+use anyhow::anyhow;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// How many streamed note summaries may wait for the receiver before the backend pauses.
+const STREAM_BUFFER: usize = 64;
+
+/// The async counterpart of [`NotesBackend`]; see it for what each method does.
+pub trait AsyncNotesBackend: Send + Sync {
+    fn list_accounts(&self) -> impl Future<Output = Result<Vec<Account>>> + Send;
+    fn list_folders(&self, account: &str) -> impl Future<Output = Result<Vec<Folder>>> + Send;
+    fn list_notes(&self, account: &str) -> impl Future<Output = Result<Vec<NoteSummary>>> + Send;
+    fn list_notes_in_folder(
+        &self,
+        account: &str,
+        folder_path: &[String],
+    ) -> impl Future<Output = Result<Vec<NoteSummary>>> + Send;
+
+    /// Note summaries as they're found. The stream ends after an error, once `cancel` fires, or
+    /// early if the receiver is dropped.
+    fn stream_note_summaries(
+        &self,
+        account: &str,
+        folder_path: Option<&[String]>,
+        cancel: &CancelToken,
+    ) -> mpsc::Receiver<Result<NoteSummary>>;
+
+    fn list_deleted_notes(
+        &self,
+        account: &str,
+    ) -> impl Future<Output = Result<Vec<NoteSummary>>> + Send;
+    fn get_note(&self, id: &str) -> impl Future<Output = Result<Note>> + Send;
+    fn create_note_html(
+        &self,
+        account: &str,
+        folder_path: &[String],
+        title: &str,
+        body_html: &str,
+    ) -> impl Future<Output = Result<String>> + Send;
+    fn set_note_title(&self, id: &str, title: &str) -> impl Future<Output = Result<()>> + Send;
+    fn set_note_body_html(
+        &self,
+        id: &str,
+        body_html: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
+    fn append_note_body_html(
+        &self,
+        id: &str,
+        body_html: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
+    fn delete_note(&self, id: &str) -> impl Future<Output = Result<()>> + Send;
+    fn purge_note(&self, id: &str) -> impl Future<Output = Result<()>> + Send;
+    fn move_note(
+        &self,
+        id: &str,
+        account: &str,
+        folder_path: &[String],
+    ) -> impl Future<Output = Result<()>> + Send;
+    fn open_note(&self, id: &str) -> impl Future<Output = Result<()>> + Send;
+    fn attach_file(&self, id: &str, path: &Path) -> impl Future<Output = Result<()>> + Send;
+
+    fn create_folder(
+        &self,
+        account: &str,
+        parent_path: &[String],
+        name: &str,
+    ) -> impl Future<Output = Result<String>> + Send;
+    fn rename_folder(
+        &self,
+        account: &str,
+        folder_path: &[String],
+        name: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
+    fn move_folder(
+        &self,
+        account: &str,
+        folder_path: &[String],
+        parent_path: &[String],
+    ) -> impl Future<Output = Result<()>> + Send;
+    fn delete_folder(
+        &self,
+        account: &str,
+        folder_path: &[String],
+    ) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// Any [`NotesBackend`] as an [`AsyncNotesBackend`], running each call with
+/// [`tokio::task::spawn_blocking`]. Must be used from within a tokio runtime.
+///
+/// ```no_run
+/// # async fn example() -> anyhow::Result<()> {
+/// use apple_notes_cli::backend::{AsyncNotesBackend, Backend, BlockingAdapter, make_backend};
+///
+/// let notes = BlockingAdapter::from(make_backend(None, Backend::Auto)?);
+/// let (folders, accounts) = tokio::join!(notes.list_folders("iCloud"), notes.list_accounts());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct BlockingAdapter<B: ?Sized> {
+    backend: Arc<B>,
+}
+
+impl<B: ?Sized> Clone for BlockingAdapter<B> {
+    fn clone(&self) -> Self {
+        Self {
+            backend: Arc::clone(&self.backend),
+        }
+    }
+}
+
+impl<B: NotesBackend + ?Sized + 'static> BlockingAdapter<B> {
+    pub fn new(backend: Arc<B>) -> Self {
+        Self { backend }
+    }
+
+    /// Runs `call` on the blocking pool.
+    fn run<T: Send + 'static>(
+        &self,
+        call: impl FnOnce(&B) -> Result<T> + Send + 'static,
+    ) -> impl Future<Output = Result<T>> + Send + 'static {
+        let backend = Arc::clone(&self.backend);
+        async move {
+            tokio::task::spawn_blocking(move || call(&backend))
+                .await
+                .unwrap_or_else(|e| Err(NotesError::Other(anyhow!("backend call failed: {e}"))))
+        }
+    }
+}
+
+impl From<Box<dyn NotesBackend>> for BlockingAdapter<dyn NotesBackend> {
+    fn from(backend: Box<dyn NotesBackend>) -> Self {
+        Self::new(backend.into())
+    }
+}
+
+impl<B: NotesBackend + ?Sized + 'static> AsyncNotesBackend for BlockingAdapter<B> {
+    fn list_accounts(&self) -> impl Future<Output = Result<Vec<Account>>> + Send {
+        self.run(|b| b.list_accounts())
+    }
+
+    fn list_folders(&self, account: &str) -> impl Future<Output = Result<Vec<Folder>>> + Send {
+        let account = account.to_string();
+        self.run(move |b| b.list_folders(&account))
+    }
+
+    fn list_notes(&self, account: &str) -> impl Future<Output = Result<Vec<NoteSummary>>> + Send {
+        let account = account.to_string();
+        self.run(move |b| b.list_notes(&account))
+    }
+
+    fn list_notes_in_folder(
+        &self,
+        account: &str,
+        folder_path: &[String],
+    ) -> impl Future<Output = Result<Vec<NoteSummary>>> + Send {
+        let (account, folder_path) = (account.to_string(), folder_path.to_vec());
+        self.run(move |b| b.list_notes_in_folder(&account, &folder_path))
+    }
+
+    fn stream_note_summaries(
+        &self,
+        account: &str,
+        folder_path: Option<&[String]>,
+        cancel: &CancelToken,
+    ) -> mpsc::Receiver<Result<NoteSummary>> {
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+        let (account, folder_path) = (account.to_string(), folder_path.map(<[String]>::to_vec));
+        // A dropped receiver stops the listing without cancelling the caller's token.
+        let cancel = cancel.child();
+        let backend = Arc::clone(&self.backend);
+        tokio::task::spawn_blocking(move || {
+            let streamed = backend.stream_note_summaries_cancellable(
+                &account,
+                folder_path.as_deref(),
+                &cancel,
+                &mut |n| {
+                    if tx.blocking_send(Ok(n)).is_err() {
+                        cancel.cancel();
+                    }
+                },
+            );
+            if let Err(e) = streamed
+                && !tx.is_closed()
+            {
+                let _ = tx.blocking_send(Err(e));
+            }
+        });
+        rx
+    }
+
+    fn list_deleted_notes(
+        &self,
+        account: &str,
+    ) -> impl Future<Output = Result<Vec<NoteSummary>>> + Send {
+        let account = account.to_string();
+        self.run(move |b| b.list_deleted_notes(&account))
+    }
+
+    fn get_note(&self, id: &str) -> impl Future<Output = Result<Note>> + Send {
+        let id = id.to_string();
+        self.run(move |b| b.get_note(&id))
+    }
+
+    fn create_note_html(
+        &self,
+        account: &str,
+        folder_path: &[String],
+        title: &str,
+        body_html: &str,
+    ) -> impl Future<Output = Result<String>> + Send {
+        let (account, folder_path) = (account.to_string(), folder_path.to_vec());
+        let (title, body_html) = (title.to_string(), body_html.to_string());
+        self.run(move |b| b.create_note_html(&account, &folder_path, &title, &body_html))
+    }
+
+    fn set_note_title(&self, id: &str, title: &str) -> impl Future<Output = Result<()>> + Send {
+        let (id, title) = (id.to_string(), title.to_string());
+        self.run(move |b| b.set_note_title(&id, &title))
+    }
+
+    fn set_note_body_html(
+        &self,
+        id: &str,
+        body_html: &str,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let (id, body_html) = (id.to_string(), body_html.to_string());
+        self.run(move |b| b.set_note_body_html(&id, &body_html))
+    }
+
+    fn append_note_body_html(
+        &self,
+        id: &str,
+        body_html: &str,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let (id, body_html) = (id.to_string(), body_html.to_string());
+        self.run(move |b| b.append_note_body_html(&id, &body_html))
+    }
+
+    fn delete_note(&self, id: &str) -> impl Future<Output = Result<()>> + Send {
+        let id = id.to_string();
+        self.run(move |b| b.delete_note(&id))
+    }
+
+    fn purge_note(&self, id: &str) -> impl Future<Output = Result<()>> + Send {
+        let id = id.to_string();
+        self.run(move |b| b.purge_note(&id))
+    }
+
+    fn move_note(
+        &self,
+        id: &str,
+        account: &str,
+        folder_path: &[String],
+    ) -> impl Future<Output = Result<()>> + Send {
+        let (id, account, folder_path) =
+            (id.to_string(), account.to_string(), folder_path.to_vec());
+        self.run(move |b| b.move_note(&id, &account, &folder_path))
+    }
+
+    fn open_note(&self, id: &str) -> impl Future<Output = Result<()>> + Send {
+        let id = id.to_string();
+        self.run(move |b| b.open_note(&id))
+    }
+
+    fn attach_file(&self, id: &str, path: &Path) -> impl Future<Output = Result<()>> + Send {
+        let (id, path) = (id.to_string(), path.to_path_buf());
+        self.run(move |b| b.attach_file(&id, &path))
+    }
+
+    fn create_folder(
+        &self,
+        account: &str,
+        parent_path: &[String],
+        name: &str,
+    ) -> impl Future<Output = Result<String>> + Send {
+        let (account, parent_path, name) =
+            (account.to_string(), parent_path.to_vec(), name.to_string());
+        self.run(move |b| b.create_folder(&account, &parent_path, &name))
+    }
+
+    fn rename_folder(
+        &self,
+        account: &str,
+        folder_path: &[String],
+        name: &str,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let (account, folder_path, name) =
+            (account.to_string(), folder_path.to_vec(), name.to_string());
+        self.run(move |b| b.rename_folder(&account, &folder_path, &name))
+    }
+
+    fn move_folder(
+        &self,
+        account: &str,
+        folder_path: &[String],
+        parent_path: &[String],
+    ) -> impl Future<Output = Result<()>> + Send {
+        let (account, folder_path, parent_path) = (
+            account.to_string(),
+            folder_path.to_vec(),
+            parent_path.to_vec(),
+        );
+        self.run(move |b| b.move_folder(&account, &folder_path, &parent_path))
+    }
+
+    fn delete_folder(
+        &self,
+        account: &str,
+        folder_path: &[String],
+    ) -> impl Future<Output = Result<()>> + Send {
+        let (account, folder_path) = (account.to_string(), folder_path.to_vec());
+        self.run(move |b| b.delete_folder(&account, &folder_path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::FixtureBackend;
+
+    fn adapter() -> BlockingAdapter<dyn NotesBackend> {
+        let backend = FixtureBackend::from_path(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/basic.json"),
+        )
+        .unwrap();
+        BlockingAdapter::from(Box::new(backend) as Box<dyn NotesBackend>)
+    }
+
+    fn block_on<F: Future>(f: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(f)
+    }
+
+    #[test]
+    fn calls_run_on_the_blocking_pool() {
+        let notes = adapter();
+        let (note, folders, missing) = block_on(async {
+            tokio::join!(
+                notes.get_note("n2"),
+                notes.list_folders("iCloud"),
+                notes.get_note("missing")
+            )
+        });
+        assert_eq!(note.unwrap().title, "Beta");
+        assert_eq!(folders.unwrap().len(), 2);
+        assert!(matches!(missing, Err(NotesError::NotFound(_))));
+    }
+
+    #[test]
+    fn summaries_stream_through_a_channel() {
+        let notes = adapter();
+        let titles = block_on(async {
+            let mut rx = notes.stream_note_summaries("iCloud", None, &CancelToken::new());
+            let mut titles = Vec::new();
+            while let Some(n) = rx.recv().await {
+                titles.push(n.unwrap().title);
+            }
+            titles
+        });
+        assert_eq!(titles.len(), 3);
+
+        let errors = block_on(async {
+            let mut rx = notes.stream_note_summaries("Nowhere", None, &CancelToken::new());
+            rx.recv().await
+        });
+        assert!(matches!(errors, Some(Err(NotesError::NotFound(_)))));
+    }
+}
@@ -0,0 +1,39 @@
+//! Reading and writing Apple Notes from Rust.
+//!
+//! Everything goes through the [`NotesBackend`] trait, implemented by:
+//!
+//! - [`OsascriptBackend`]: drives Notes.app with `osascript` (JXA/AppleScript). Works everywhere
+//!   Notes.app runs, but every call starts a script.
+//! - [`HybridBackend`]: reads straight from the Notes database ([`NotesDb`]), which is much
+//!   faster, and writes through `osascript`.
+//! - [`FixtureBackend`]: canned data from a JSON file, for tests and demos. Writes are accepted
+//!   and discarded.
+//!
+//! [`make_backend`] picks one the way the `apple-notes` binary does.
+//!
+//! ```no_run
+//! use apple_notes_cli::backend::{Backend, NotesBackend, make_backend};
+//!
+//! let notes = make_backend(None, Backend::Auto)?;
+//! for folder in notes.list_folders("iCloud")? {
+//!     println!("{}", folder.path_string());
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! Backend methods return [`NotesError`], whose variants tell a missing note from a denied
+//! permission. Long listings take a [`CancelToken`] to stop early.
+//!
+//! With the `async` feature, `AsyncNotesBackend` offers the same operations as futures, and
+//! `BlockingAdapter` wraps any backend to run its calls on tokio's blocking pool.
+
+#[cfg(feature = "async")]
+pub use crate::async_backend::{AsyncNotesBackend, BlockingAdapter};
+#[cfg(unix)]
+pub use crate::cancel::cancel_on_interrupt;
+pub use crate::cancel::{CancelToken, Cancelled};
+pub use crate::db::{DbUnavailable, NotesDb, use_db_path};
+pub use crate::error::{NotesError, Result};
+pub use crate::fixture::FixtureBackend;
+pub use crate::model::{Account, Folder, Note, NoteSummary};
+pub use crate::transport::{Backend, HybridBackend, NotesBackend, OsascriptBackend, make_backend};
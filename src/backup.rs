@@ -1,6 +1,18 @@
+use crate::archive::ArchiveWriter;
+use crate::cancel::{CancelToken, Cancelled};
+use crate::checksums;
+use crate::cli::PartialFailure;
+use crate::error::NotesError;
+use crate::html_export::{self, AttachmentMode};
+use crate::journal;
+use crate::manifest;
 use crate::model::{BackupNoteMetadata, Folder, NoteSummary};
+use crate::notestore;
+use crate::notetable;
+use crate::pdf;
 use crate::progress;
 use crate::render;
+use crate::site;
 use crate::transport::NotesBackend;
 use anyhow::{Context, anyhow};
 use crossbeam_channel as channel;
@@ -11,6 +23,7 @@ use std::collections::HashMap;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock};
 use time::OffsetDateTime;
 
 #[derive(Debug, Clone)]
@@ -36,6 +49,41 @@ impl FolderIndex {
     pub fn folder_path_string(&self, folder_id: &str) -> Option<String> {
         self.by_id.get(folder_id).map(|f| f.path_string())
     }
+
+    pub fn contains(&self, folder_id: &str) -> bool {
+        self.by_id.contains_key(folder_id)
+    }
+
+    /// The folder at exactly `path`.
+    pub fn find(&self, path: &[String]) -> Option<&Folder> {
+        self.by_id.values().find(|f| f.path == path)
+    }
+
+    /// Whether `folder_id` is `ancestor_id` or one of the folders below it.
+    pub fn is_within(&self, folder_id: &str, ancestor_id: &str) -> bool {
+        match (self.by_id.get(folder_id), self.by_id.get(ancestor_id)) {
+            (Some(folder), Some(ancestor)) => {
+                folder.account == ancestor.account && folder.path.starts_with(&ancestor.path)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Where `export --all-accounts` puts an account's notes.
+pub fn account_dir(root: &Path, account: &str) -> PathBuf {
+    root.join(sanitize(account))
+}
+
+/// Keeps `root` and the folders below it. Errors if no folder has exactly the path `root`.
+pub fn folder_subtree(folders: Vec<Folder>, root: &[String]) -> anyhow::Result<Vec<Folder>> {
+    if !folders.iter().any(|f| f.path == root) {
+        return Err(NotesError::NotFound(format!("folder not found: {}", root.join(" > "))).into());
+    }
+    Ok(folders
+        .into_iter()
+        .filter(|f| f.path.starts_with(root))
+        .collect())
 }
 
 #[derive(Debug, Clone)]
@@ -67,145 +115,333 @@ impl HtmlExport {
     }
 }
 
+/// Narrows an export to notes matching `--query`, `--modified-after` and `--created-before`.
+#[derive(Debug, Clone, Default)]
+pub struct NoteFilter {
+    /// Case-insensitive substring of the title or the Markdown contents.
+    pub query: Option<String>,
+    pub modified_after: Option<OffsetDateTime>,
+    pub created_before: Option<OffsetDateTime>,
+}
+
+impl NoteFilter {
+    pub fn matches_dates(&self, created_at: OffsetDateTime, modified_at: OffsetDateTime) -> bool {
+        self.modified_after.is_none_or(|t| modified_at > t)
+            && self.created_before.is_none_or(|t| created_at < t)
+    }
+
+    pub fn matches_text(&self, title: &str, contents_md: &str) -> bool {
+        let Some(query) = &self.query else {
+            return true;
+        };
+        let query = query.to_lowercase();
+        title.to_lowercase().contains(&query) || contents_md.to_lowercase().contains(&query)
+    }
+}
+
+/// Output format of `export`. Every format also writes `metadata.json` and `contents.md`, so
+/// `verify`, `diff` and `backup restore` work on any export.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// Markdown only.
+    #[default]
+    Md,
+    /// Also a standalone `note.html` per note, viewable in any browser.
+    Html,
+    /// Also a `note.pdf` per note (rendered with `textutil` + `cupsfilter`).
+    Pdf,
+    /// A static site: `note.html` pages plus an `index.html` with client-side search.
+    Site,
+    /// A single `.epub` book at `--out` (written by [`crate::epub`], not the directory export).
+    Epub,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    /// Number of export worker threads (decode/render + IO).
+    pub jobs: usize,
+    pub html: HtmlExport,
+    /// What to write per note besides `metadata.json` and `contents.md`.
+    pub format: ExportFormat,
+    /// How `--format html` pages reference images.
+    pub attachments: AttachmentMode,
+    /// Only export notes in this folder and its subfolders.
+    pub folder: Option<Vec<String>>,
+    /// Only export notes matching this.
+    pub filter: NoteFilter,
+    /// Checked between notes; a fired token aborts the export with a `Cancelled` error.
+    pub cancel: CancelToken,
+    /// Stream every file into this archive instead of writing it to disk. The output directory
+    /// is then a path inside the archive (empty for its root).
+    pub archive: Option<Arc<ArchiveWriter>>,
+    /// Records every note written, for `--journal`.
+    pub journal: Option<Arc<journal::Recorder>>,
+    /// Keep notes the previous export into the same directory wrote, when they're known to be
+    /// unchanged, and delete the ones that are gone (`export --watch`).
+    pub incremental: bool,
+}
+
+impl ExportOptions {
+    /// Applies [`Self::folder`] to the account's folders.
+    fn select_folders(&self, folders: Vec<Folder>) -> anyhow::Result<Vec<Folder>> {
+        match &self.folder {
+            Some(root) => folder_subtree(folders, root),
+            None => Ok(folders),
+        }
+    }
+
+    fn effective_jobs(&self) -> anyhow::Result<usize> {
+        if self.jobs == 0 {
+            return Err(anyhow!("--jobs must be >= 1"));
+        }
+        Ok(self.jobs.min(16))
+    }
+
+    fn page(
+        &self,
+        title: &str,
+        body_html: Option<&str>,
+        folder_path: &[String],
+    ) -> Option<html_export::Page> {
+        let body_html = body_html?;
+        if matches!(self.format, ExportFormat::Md | ExportFormat::Epub) {
+            return None;
+        }
+        // Note pages live at `<root>/<folder path…>/<note dir>/note.html`.
+        let index_href = (self.format == ExportFormat::Site).then(|| {
+            format!(
+                "{}{}",
+                "../".repeat(folder_path.len() + 1),
+                site::INDEX_HTML
+            )
+        });
+        Some(html_export::Page {
+            title: title.to_string(),
+            body_html: body_html.to_string(),
+            attachments: self.attachments,
+            index_href,
+        })
+    }
+
+    /// Where files for `out_dir` end up, for messages.
+    fn destination(&self, out_dir: &Path) -> PathBuf {
+        match &self.archive {
+            Some(archive) => archive.path().join(out_dir),
+            None => out_dir.to_path_buf(),
+        }
+    }
+
+    /// Whether any note's raw HTML has to be fetched from Notes.app (over Apple Events).
+    pub fn fetches_html(&self) -> bool {
+        !self.html_fetch().is_none()
+    }
+
+    /// Which notes need their raw HTML fetched from Notes.app.
+    fn html_fetch(&self) -> HtmlExport {
+        match self.format {
+            ExportFormat::Html | ExportFormat::Pdf | ExportFormat::Site => HtmlExport::All,
+            ExportFormat::Md | ExportFormat::Epub => self.html.clone(),
+        }
+    }
+}
+
 pub fn export_all(
     backend: &dyn NotesBackend,
     account: &str,
     out_dir: String,
-    jobs: usize,
-    html: HtmlExport,
-) -> anyhow::Result<()> {
-    if jobs == 0 {
-        return Err(anyhow!("--jobs must be >= 1"));
-    }
-    let jobs = jobs.min(16);
+    opts: &ExportOptions,
+) -> anyhow::Result<u64> {
+    let jobs = opts.effective_jobs()?;
+    let cancel = &opts.cancel;
 
     let out_dir = PathBuf::from(out_dir);
-    std::fs::create_dir_all(&out_dir).with_context(|| format!("create {out_dir:?}"))?;
+    if opts.archive.is_none() {
+        std::fs::create_dir_all(&out_dir).with_context(|| format!("create {out_dir:?}"))?;
+    }
 
     let spinner = progress::spinner("Loading folders…");
-    let folders = backend.list_folders(account)?;
+    let folders = opts.select_folders(backend.list_folders(account)?)?;
     if let Some(spinner) = spinner {
         spinner.finish_and_clear();
     }
     let folder_index = FolderIndex::new(&folders)?;
+    write_folders_json(&out_dir, &folders, opts)?;
+    let manifest = manifest::Collector::new(&out_dir);
+    let resume = resume_from(&out_dir, account, opts);
 
     let spinner = progress::spinner("Indexing notes…");
-    let notes = backend.list_notes(account)?;
+    let mut notes = Vec::new();
+    backend.stream_note_summaries_cancellable(account, None, cancel, &mut |n| {
+        if opts.folder.is_none() || folder_index.contains(&n.folder_id) {
+            notes.push(n)
+        }
+    })?;
     if let Some(spinner) = spinner {
         spinner.finish_and_clear();
     }
+    skip_locked(&mut notes);
+
+    let mut planned = Vec::with_capacity(notes.len());
+    for n in &notes {
+        let folder_path = folder_index
+            .folder_path(&n.folder_id)
+            .ok_or_else(|| anyhow!("note {} references unknown folder id {}", n.id, n.folder_id))?;
+        planned.push((
+            n.id.clone(),
+            export_path(&out_dir, &folder_path, &n.title, &n.id)?,
+        ));
+    }
+    let mut note_dirs = disambiguate_note_dirs(planned);
 
     let total = notes.len() as u64;
     let pb = progress::bar(total, "Exporting notes…");
 
     // Note content is still sourced from Notes via Apple Events (`osascript`).
     // We intentionally serialize `get_note` calls, and only parallelize render+IO.
-    let exported = if jobs == 1 {
-        let mut exported = 0u64;
-        let mut started = 0u64;
-        for n in notes {
-            started += 1;
-            if let Some(pb) = &pb {
-                pb.set_message(format!(
-                    "Fetching {}/{}: {}",
-                    started,
-                    total,
-                    truncate_title(&n.title)
-                ));
-            }
-            let item = build_item(
-                backend,
-                account,
-                &out_dir,
-                &folder_index,
-                n,
-                pb.as_ref(),
-                &html,
-            )?;
-            write_item(&item)?;
-            if let Some(pb) = &pb {
-                pb.inc(1);
-            }
-            exported += 1;
-        }
-        exported
-    } else {
-        let (work_tx, work_rx) = channel::bounded::<WorkItem>(jobs * 2);
-        let (done_tx, done_rx) = channel::unbounded::<anyhow::Result<()>>();
-        let stop = AtomicBool::new(false);
-
-        std::thread::scope(|scope| -> anyhow::Result<u64> {
-            for _ in 0..jobs {
-                let work_rx = work_rx.clone();
-                let done_tx = done_tx.clone();
-                let stop = &stop;
-                scope.spawn(move || {
-                    while let Ok(item) = work_rx.recv() {
-                        if stop.load(Ordering::Relaxed) {
-                            break;
-                        }
-                        let res = write_item(&item);
-                        if res.is_err() {
-                            stop.store(true, Ordering::Relaxed);
-                        }
-                        let _ = done_tx.send(res);
-                    }
-                });
-            }
-
-            drop(done_tx);
-            drop(work_rx);
-
-            let mut sent = 0u64;
+    let exported = resumable(&out_dir, account, &manifest, opts, || {
+        if jobs == 1 {
+            let mut exported = 0u64;
+            let mut started = 0u64;
             for n in notes {
-                if stop.load(Ordering::Relaxed) {
-                    break;
-                }
+                cancel.check()?;
+                started += 1;
                 if let Some(pb) = &pb {
                     pb.set_message(format!(
                         "Fetching {}/{}: {}",
-                        sent + 1,
+                        started,
                         total,
                         truncate_title(&n.title)
                     ));
                 }
-                let item = build_item(
+                let note_dir = note_dirs.remove(&n.id).context("note dir was planned")?;
+                if let Some(written) = resume.written(&n.id, &note_dir, n.modified_at) {
+                    keep_written(written, &n.title, opts, &manifest);
+                    exported += 1;
+                } else if let Some(item) = build_item(
                     backend,
                     account,
-                    &out_dir,
                     &folder_index,
+                    note_dir,
                     n,
                     pb.as_ref(),
-                    &html,
-                )?;
-                work_tx.send(item).ok();
-                sent += 1;
-            }
-            drop(work_tx);
-
-            let mut completed = 0u64;
-            while completed < sent {
-                let res = done_rx.recv().context("worker hung up")?;
-                res?;
-                completed += 1;
+                    opts,
+                )? {
+                    write_item(&item, opts, &manifest)?;
+                    exported += 1;
+                }
                 if let Some(pb) = &pb {
                     pb.inc(1);
                 }
             }
-            Ok(completed)
-        })?
-    };
+            Ok(exported)
+        } else {
+            let (work_tx, work_rx) = channel::bounded::<WorkItem>(jobs * 2);
+            let (done_tx, done_rx) = channel::unbounded::<anyhow::Result<()>>();
+            let stop = AtomicBool::new(false);
+
+            std::thread::scope(|scope| -> anyhow::Result<u64> {
+                for _ in 0..jobs {
+                    let work_rx = work_rx.clone();
+                    let done_tx = done_tx.clone();
+                    let stop = &stop;
+                    let manifest = &manifest;
+                    scope.spawn(move || {
+                        while let Ok(item) = work_rx.recv() {
+                            if stop.load(Ordering::Relaxed) {
+                                break;
+                            }
+                            let res = write_item(&item, opts, manifest);
+                            if res.is_err() {
+                                stop.store(true, Ordering::Relaxed);
+                            }
+                            let _ = done_tx.send(res);
+                        }
+                    });
+                }
+
+                drop(done_tx);
+                drop(work_rx);
+
+                let mut sent = 0u64;
+                let mut kept = 0u64;
+                for n in notes {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    cancel.check()?;
+                    if let Some(pb) = &pb {
+                        pb.set_message(format!(
+                            "Fetching {}/{}: {}",
+                            sent + 1,
+                            total,
+                            truncate_title(&n.title)
+                        ));
+                    }
+                    let note_dir = note_dirs.remove(&n.id).context("note dir was planned")?;
+                    if let Some(written) = resume.written(&n.id, &note_dir, n.modified_at) {
+                        keep_written(written, &n.title, opts, &manifest);
+                        kept += 1;
+                        if let Some(pb) = &pb {
+                            pb.inc(1);
+                        }
+                        continue;
+                    }
+                    let item = build_item(
+                        backend,
+                        account,
+                        &folder_index,
+                        note_dir,
+                        n,
+                        pb.as_ref(),
+                        opts,
+                    )?;
+                    let Some(item) = item else {
+                        if let Some(pb) = &pb {
+                            pb.inc(1);
+                        }
+                        continue;
+                    };
+                    work_tx.send(item).ok();
+                    sent += 1;
+                }
+                drop(work_tx);
+
+                let mut completed = 0u64;
+                while completed < sent {
+                    let res = done_rx.recv().context("worker hung up")?;
+                    res?;
+                    completed += 1;
+                    if let Some(pb) = &pb {
+                        pb.inc(1);
+                    }
+                }
+                Ok(completed + kept)
+            })
+        }
+    })?;
+
+    if opts.incremental && opts.archive.is_none() {
+        prune_removed(&out_dir, account, &resume, &manifest)?;
+    }
+    write_manifest_json(&out_dir, account, &manifest, opts)?;
+    // An archive gets its `SHA256SUMS` when it's finished.
+    if opts.archive.is_none() {
+        if opts.format == ExportFormat::Site {
+            site::write_index(&out_dir)?;
+        }
+        checksums::write_sha256sums(&out_dir)?;
+    }
 
     if let Some(pb) = pb {
-        pb.finish_with_message(format!(
-            "Exported {}/{} notes to {}",
+        pb.finish_with_message(exported_message(
             exported,
             total,
-            out_dir.display()
+            &resume,
+            &opts.destination(&out_dir),
         ));
     }
 
-    Ok(())
+    Ok(exported)
 }
 
 fn truncate_title(title: &str) -> String {
@@ -239,24 +475,172 @@ fn export_path(
     Ok(dir.join(note_dir))
 }
 
+/// Folder listing (with timestamps, when the backend knows them) at the export root.
+pub const FOLDERS_JSON: &str = "folders.json";
+
+fn write_folders_json(
+    out_dir: &Path,
+    folders: &[Folder],
+    opts: &ExportOptions,
+) -> anyhow::Result<()> {
+    let mut folders = folders.to_vec();
+    folders.sort_by(|a, b| a.path.cmp(&b.path));
+    write_root_file(
+        &out_dir.join(FOLDERS_JSON),
+        &serde_json::to_string_pretty(&folders)?,
+        opts,
+    )
+}
+
+fn write_manifest_json(
+    out_dir: &Path,
+    account: &str,
+    notes: &manifest::Collector,
+    opts: &ExportOptions,
+) -> anyhow::Result<()> {
+    let manifest = notes.manifest(account);
+    write_root_file(
+        &out_dir.join(manifest::MANIFEST_JSON),
+        &serde_json::to_string_pretty(&manifest)?,
+        opts,
+    )
+}
+
+/// Runs `export`; if it fails part-way through a directory export, leaves a partial
+/// `manifest.json` listing the notes already written, so re-running the same export resumes
+/// from there instead of starting over. Such a failure is a [`PartialFailure`] unless it was
+/// a cancellation.
+fn resumable(
+    out_dir: &Path,
+    account: &str,
+    notes: &manifest::Collector,
+    opts: &ExportOptions,
+    export: impl FnOnce() -> anyhow::Result<u64>,
+) -> anyhow::Result<u64> {
+    let err = match export() {
+        Err(e) if opts.archive.is_none() => e,
+        result => return result,
+    };
+    let mut manifest = notes.manifest(account);
+    manifest.partial = true;
+    // Best effort: the export's own error is the one worth reporting.
+    if let Ok(json) = serde_json::to_string_pretty(&manifest) {
+        let _ = std::fs::write(out_dir.join(manifest::MANIFEST_JSON), json);
+    }
+    let cancelled = err
+        .chain()
+        .any(|c| c.is::<Cancelled>() || matches!(c.downcast_ref(), Some(NotesError::Cancelled(_))));
+    if cancelled || manifest.notes.is_empty() {
+        return Err(err);
+    }
+    Err(PartialFailure(format!(
+        "{err:#} (after exporting {} notes; run the same export again to resume)",
+        manifest.notes.len()
+    ))
+    .into())
+}
+
+/// What's already in `out_dir` from an interrupted (or, with [`ExportOptions::incremental`], the
+/// previous) run. Archives always start over.
+fn resume_from(out_dir: &Path, account: &str, opts: &ExportOptions) -> manifest::Resume {
+    match opts.archive {
+        Some(_) => manifest::Resume::default(),
+        None => manifest::Resume::load(out_dir, account, opts.incremental),
+    }
+}
+
+/// Deletes the directories of notes the previous incremental run wrote that are gone now, then
+/// any folder directories that leaves empty.
+fn prune_removed(
+    out_dir: &Path,
+    account: &str,
+    resume: &manifest::Resume,
+    notes: &manifest::Collector,
+) -> anyhow::Result<()> {
+    for dir in resume.removed(&notes.manifest(account)) {
+        match std::fs::remove_dir_all(&dir) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_context(|| format!("remove {dir:?}")),
+        }
+        // `remove_dir` only succeeds on empty directories.
+        for parent in dir.ancestors().skip(1) {
+            if parent == out_dir
+                || !parent.starts_with(out_dir)
+                || std::fs::remove_dir(parent).is_err()
+            {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Drops locked notes, whose bodies are encrypted, saying on stderr which were skipped.
+pub(crate) fn skip_locked(notes: &mut Vec<NoteSummary>) {
+    let locked: Vec<String> = notes
+        .iter()
+        .filter(|n| n.locked)
+        .map(|n| format!("{:?}", n.title))
+        .collect();
+    if locked.is_empty() {
+        return;
+    }
+    notes.retain(|n| !n.locked);
+    eprintln!(
+        "Skipped {} locked notes (unlock them in Notes to export them): {}",
+        locked.len(),
+        locked.join(", ")
+    );
+}
+
+/// The progress bar's closing line.
+fn exported_message(exported: u64, total: u64, resume: &manifest::Resume, dest: &Path) -> String {
+    match resume.reused() {
+        0 => format!("Exported {exported}/{total} notes to {}", dest.display()),
+        reused => format!(
+            "Exported {exported}/{total} notes to {} (resumed: {reused} already written)",
+            dest.display()
+        ),
+    }
+}
+
+/// Writes a file at the export root, or adds it to `--archive`.
+fn write_root_file(path: &Path, contents: &str, opts: &ExportOptions) -> anyhow::Result<()> {
+    let _span = tracing::debug_span!("write").entered();
+    match &opts.archive {
+        Some(archive) => archive.add(path, contents.as_bytes(), OffsetDateTime::now_utc()),
+        None => std::fs::write(path, contents).with_context(|| format!("write {path:?}")),
+    }
+}
+
 #[derive(Debug, Clone)]
 struct WorkItem {
+    id: String,
+    title: String,
     note_dir: PathBuf,
     metadata_json: String,
     contents_md: String,
     contents_html: Option<String>,
+    format: ExportFormat,
+    page: Option<html_export::Page>,
+    created_at: OffsetDateTime,
+    modified_at: OffsetDateTime,
 }
 
 fn build_item(
     backend: &dyn NotesBackend,
     account: &str,
-    out_dir: &Path,
     folder_index: &FolderIndex,
+    note_dir: PathBuf,
     n: NoteSummary,
     _pb: Option<&indicatif::ProgressBar>,
-    html: &HtmlExport,
-) -> anyhow::Result<WorkItem> {
+    opts: &ExportOptions,
+) -> anyhow::Result<Option<WorkItem>> {
     let note = backend.get_note(&n.id)?;
+    if !opts.filter.matches_dates(note.created_at, note.modified_at) {
+        return Ok(None);
+    }
     let folder_path = folder_index.folder_path(&note.folder_id).ok_or_else(|| {
         anyhow!(
             "note {} references unknown folder id {}",
@@ -266,11 +650,15 @@ fn build_item(
     })?;
 
     let contents_md = render::note_to_markdown(&note);
-    let contents_html = if html.wants(&note.id) {
+    if !opts.filter.matches_text(&note.title, &contents_md) {
+        return Ok(None);
+    }
+    let contents_html = if opts.html.wants(&note.id) {
         Some(note.body_html.clone())
     } else {
         None
     };
+    let page = opts.page(&note.title, Some(&note.body_html), &folder_path);
     let metadata = BackupNoteMetadata {
         id: note.id.clone(),
         title: note.title.clone(),
@@ -278,167 +666,338 @@ fn build_item(
         folder_path: folder_path.clone(),
         created_at: note.created_at,
         modified_at: note.modified_at,
+        shared: false,
+        participants: Vec::new(),
     };
 
-    let note_dir = export_path(out_dir, &folder_path, &note.title, &note.id)?;
     let metadata_json = serde_json::to_string_pretty(&metadata)?;
-    Ok(WorkItem {
+    Ok(Some(WorkItem {
+        id: note.id.clone(),
+        title: note.title.clone(),
         note_dir,
         metadata_json,
         contents_md,
         contents_html,
-    })
+        format: opts.format,
+        page,
+        created_at: note.created_at,
+        modified_at: note.modified_at,
+    }))
 }
 
-fn write_item(item: &WorkItem) -> anyhow::Result<()> {
-    std::fs::create_dir_all(&item.note_dir)
-        .with_context(|| format!("create {:?}", item.note_dir))?;
+fn write_item(
+    item: &WorkItem,
+    opts: &ExportOptions,
+    manifest: &manifest::Collector,
+) -> anyhow::Result<()> {
+    let _span = tracing::debug_span!("write").entered();
+    let mut files = vec![
+        (
+            PathBuf::from("metadata.json"),
+            item.metadata_json.clone().into_bytes(),
+        ),
+        (
+            PathBuf::from("contents.md"),
+            item.contents_md.clone().into_bytes(),
+        ),
+    ];
+    if let Some(html) = &item.contents_html {
+        files.push((PathBuf::from("contents.html"), html.clone().into_bytes()));
+    }
+    if let (Some(page), ExportFormat::Html | ExportFormat::Site) = (&item.page, item.format) {
+        files.extend(page.files());
+    }
 
-    let meta_path = item.note_dir.join("metadata.json");
-    std::fs::write(&meta_path, &item.metadata_json)
-        .with_context(|| format!("write {meta_path:?}"))?;
+    // Only recorded once every file is written, so a partial manifest never lists a note whose
+    // directory is incomplete.
+    let record = || {
+        manifest.record(
+            &item.id,
+            &item.note_dir,
+            item.modified_at,
+            &item.contents_md,
+        );
+        if let Some(journal) = &opts.journal {
+            journal.record(&item.id, &item.title, item.modified_at, &item.contents_md);
+        }
+    };
 
-    let contents_path = item.note_dir.join("contents.md");
-    std::fs::write(&contents_path, &item.contents_md)
-        .with_context(|| format!("write {contents_path:?}"))?;
+    if let Some(archive) = &opts.archive {
+        for (rel, data) in &files {
+            archive.add(&item.note_dir.join(rel), data, item.modified_at)?;
+        }
+        record();
+        return Ok(());
+    }
 
-    if let Some(html) = &item.contents_html {
-        let html_path = item.note_dir.join("contents.html");
-        std::fs::write(&html_path, html).with_context(|| format!("write {html_path:?}"))?;
+    for (rel, data) in &files {
+        let path = item.note_dir.join(rel);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("create {dir:?}"))?;
+        }
+        std::fs::write(&path, data).with_context(|| format!("write {path:?}"))?;
+    }
+    if let (Some(page), ExportFormat::Pdf) = (&item.page, item.format) {
+        pdf::write_pdf(&page.to_inline_html(), &item.note_dir.join(pdf::NOTE_PDF))?;
     }
 
+    // Children first, so stamping a file doesn't bump its directory's mtime afterwards.
+    for entry in walkdir::WalkDir::new(&item.note_dir).contents_first(true) {
+        let entry = entry.context("walk note dir")?;
+        set_note_times(entry.path(), item.created_at, item.modified_at)?;
+    }
+    record();
     Ok(())
 }
 
+/// Keeps a note an interrupted export already wrote, instead of exporting it again.
+fn keep_written(
+    written: manifest::Written,
+    title: &str,
+    opts: &ExportOptions,
+    manifest: &manifest::Collector,
+) {
+    let note = written.note;
+    if let Some(journal) = &opts.journal {
+        journal.record(&note.id, title, note.modified_at, &written.contents_md);
+    }
+    manifest.push(note);
+}
+
+/// Stamps a note's dates onto a file written for it, so exports sort by real note dates in Finder
+/// and `ls -t`. The creation date can only be set on macOS.
+fn set_note_times(
+    path: &Path,
+    created_at: OffsetDateTime,
+    modified_at: OffsetDateTime,
+) -> anyhow::Result<()> {
+    let times = std::fs::FileTimes::new()
+        .set_accessed(modified_at.into())
+        .set_modified(modified_at.into());
+    #[cfg(target_os = "macos")]
+    let times = {
+        use std::os::macos::fs::FileTimesExt;
+        times.set_created(created_at.into())
+    };
+    #[cfg(not(target_os = "macos"))]
+    let _ = created_at;
+    std::fs::File::open(path)
+        .and_then(|f| f.set_times(times))
+        .with_context(|| format!("set times on {path:?}"))
+}
+
 fn note_dir_name(title: &str, note_id: &str) -> String {
     let mut base = title.trim().to_string();
     if base.is_empty() {
         base = "Untitled".to_string();
     }
     if base.len() > 80 {
-        base.truncate(80);
+        let mut end = 80;
+        while !base.is_char_boundary(end) {
+            end -= 1;
+        }
+        base.truncate(end);
     }
     let base = sanitize(&base);
     let short_id = note_id.rsplit('/').next().unwrap_or(note_id);
     format!("{base}-{short_id}")
 }
 
-pub fn export_all_db(
-    account: &str,
-    out_dir: String,
-    jobs: usize,
-    html: HtmlExport,
-) -> anyhow::Result<()> {
-    if jobs == 0 {
-        return Err(anyhow!("--jobs must be >= 1"));
+/// Resolves note directories that would land on the same path.
+///
+/// Paths are compared case-insensitively (APFS and HFS+ default to case-insensitive), and every
+/// note in a colliding group gets a suffix derived from its full id, so the result does not depend
+/// on the order notes were listed in.
+fn disambiguate_note_dirs(planned: Vec<(String, PathBuf)>) -> HashMap<String, PathBuf> {
+    let mut groups: HashMap<String, usize> = HashMap::new();
+    for (_, dir) in &planned {
+        *groups.entry(collision_key(dir)).or_default() += 1;
     }
-    let jobs = jobs.min(16);
+    planned
+        .into_iter()
+        .map(|(id, dir)| {
+            if groups[&collision_key(&dir)] < 2 {
+                return (id, dir);
+            }
+            let hash = checksums::sha256_bytes(id.as_bytes());
+            let mut name = dir.file_name().unwrap_or_default().to_os_string();
+            name.push(format!("-{}", &hash[..8]));
+            let dir = dir.with_file_name(name);
+            (id, dir)
+        })
+        .collect()
+}
+
+fn collision_key(dir: &Path) -> String {
+    dir.to_string_lossy().to_lowercase()
+}
+
+pub fn export_all_db(account: &str, out_dir: String, opts: &ExportOptions) -> anyhow::Result<u64> {
+    let jobs = opts.effective_jobs()?;
+    let cancel = &opts.cancel;
 
     let db = crate::db::NotesDb::open_default()?;
     let out_dir = PathBuf::from(out_dir);
-    std::fs::create_dir_all(&out_dir).with_context(|| format!("create {out_dir:?}"))?;
+    if opts.archive.is_none() {
+        std::fs::create_dir_all(&out_dir).with_context(|| format!("create {out_dir:?}"))?;
+    }
 
     let spinner = progress::spinner("Loading folders…");
-    let folders = db.list_folders(account)?;
+    let folders = opts.select_folders(db.list_folders(account)?)?;
     if let Some(spinner) = spinner {
         spinner.finish_and_clear();
     }
     let folder_index = FolderIndex::new(&folders)?;
+    write_folders_json(&out_dir, &folders, opts)?;
+    let manifest = manifest::Collector::new(&out_dir);
+    let resume = resume_from(&out_dir, account, opts);
 
     let spinner = progress::spinner("Indexing notes…");
-    let note_rows = list_db_notes(account, &html)?;
+    let subtree = opts.folder.is_some().then_some(&folder_index);
+    let note_rows = list_db_notes(account, &opts.html_fetch(), subtree, &opts.filter)?;
     if let Some(spinner) = spinner {
         spinner.finish_and_clear();
     }
 
+    let mut planned = Vec::with_capacity(note_rows.len());
+    for row in &note_rows {
+        let folder_path = folder_index
+            .folder_path(&row.folder_id)
+            .unwrap_or_else(|| vec!["Unknown".to_string()]);
+        planned.push((
+            row.id.clone(),
+            export_path(&out_dir, &folder_path, &row.title, &row.id)?,
+        ));
+    }
+    let note_dirs = disambiguate_note_dirs(planned);
+
     let total = note_rows.len() as u64;
     let pb = progress::bar(total, "Exporting notes…");
 
     let (task_tx, task_rx) = channel::bounded::<DbNoteRow>(jobs * 2);
-    let (done_tx, done_rx) = channel::unbounded::<anyhow::Result<()>>();
+    let (done_tx, done_rx) = channel::unbounded::<anyhow::Result<bool>>();
     let stop = AtomicBool::new(false);
 
-    let exported = std::thread::scope(|scope| -> anyhow::Result<u64> {
-        for _ in 0..jobs {
-            let task_rx = task_rx.clone();
-            let done_tx = done_tx.clone();
-            let folder_index = &folder_index;
-            let out_dir = &out_dir;
-            let account = account.to_string();
-            let pb = pb.clone();
-            let stop = &stop;
-
-            scope.spawn(move || {
-                let conn = match open_notes_db_readonly() {
-                    Ok(c) => c,
-                    Err(e) => {
-                        let _ = done_tx.send(Err(e));
-                        stop.store(true, Ordering::Relaxed);
-                        return;
-                    }
-                };
-                while let Ok(row) = task_rx.recv() {
-                    if stop.load(Ordering::Relaxed) {
-                        break;
-                    }
-                    let res =
-                        export_one_db(&account, out_dir, folder_index, &row, &conn, pb.as_ref());
-                    if res.is_err() {
-                        stop.store(true, Ordering::Relaxed);
+    let exported = resumable(&out_dir, account, &manifest, opts, || {
+        std::thread::scope(|scope| -> anyhow::Result<u64> {
+            for _ in 0..jobs {
+                let task_rx = task_rx.clone();
+                let done_tx = done_tx.clone();
+                let folder_index = &folder_index;
+                let note_dirs = &note_dirs;
+                let manifest = &manifest;
+                let resume = &resume;
+                let account = account.to_string();
+                let pb = pb.clone();
+                let stop = &stop;
+
+                scope.spawn(move || {
+                    let conn = match open_notes_db_readonly() {
+                        Ok(c) => c,
+                        Err(e) => {
+                            let _ = done_tx.send(Err(e));
+                            stop.store(true, Ordering::Relaxed);
+                            return;
+                        }
+                    };
+                    while let Ok(row) = task_rx.recv() {
+                        if stop.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        let note_dir = &note_dirs[&row.id];
+                        if let Some(written) =
+                            resume.written(&row.id, note_dir, Some(row.modified_at))
+                        {
+                            keep_written(written, &row.title, opts, manifest);
+                            let _ = done_tx.send(Ok(true));
+                            continue;
+                        }
+                        let res = build_db_item(
+                            &account,
+                            note_dir,
+                            folder_index,
+                            &row,
+                            &conn,
+                            pb.as_ref(),
+                            opts,
+                        )
+                        .and_then(|item| match item {
+                            Some(item) => write_item(&item, opts, manifest).map(|()| true),
+                            None => Ok(false),
+                        });
+                        if res.is_err() {
+                            stop.store(true, Ordering::Relaxed);
+                        }
+                        let _ = done_tx.send(res);
                     }
-                    let _ = done_tx.send(res);
-                }
-            });
-        }
+                });
+            }
 
-        drop(done_tx);
-        drop(task_rx);
+            drop(done_tx);
+            drop(task_rx);
 
-        let mut queued = 0u64;
-        for row in note_rows {
-            if stop.load(Ordering::Relaxed) {
-                break;
-            }
-            queued += 1;
-            if let Some(pb) = &pb {
-                pb.set_message(format!(
-                    "Queued {}/{}: {}",
-                    queued,
-                    total,
-                    truncate_title(&row.title)
-                ));
-            }
-            if task_tx.send(row).is_err() {
-                break;
+            let mut queued = 0u64;
+            for row in note_rows {
+                if stop.load(Ordering::Relaxed) || cancel.is_cancelled() {
+                    break;
+                }
+                queued += 1;
+                if let Some(pb) = &pb {
+                    pb.set_message(format!(
+                        "Queued {}/{}: {}",
+                        queued,
+                        total,
+                        truncate_title(&row.title)
+                    ));
+                }
+                if task_tx.send(row).is_err() {
+                    break;
+                }
             }
-        }
-        drop(task_tx);
+            drop(task_tx);
 
-        let mut completed = 0u64;
-        while let Ok(res) = done_rx.recv() {
-            res?;
-            completed += 1;
-            if let Some(pb) = &pb {
-                pb.inc(1);
-            }
-            if completed >= total || stop.load(Ordering::Relaxed) {
-                break;
+            let mut completed = 0u64;
+            let mut written = 0u64;
+            while let Ok(res) = done_rx.recv() {
+                if res? {
+                    written += 1;
+                }
+                completed += 1;
+                if let Some(pb) = &pb {
+                    pb.inc(1);
+                }
+                if completed >= total || stop.load(Ordering::Relaxed) {
+                    break;
+                }
             }
-        }
+            cancel.check()?;
 
-        Ok(completed)
+            Ok(written)
+        })
     })?;
 
+    if opts.incremental && opts.archive.is_none() {
+        prune_removed(&out_dir, account, &resume, &manifest)?;
+    }
+    write_manifest_json(&out_dir, account, &manifest, opts)?;
+    // An archive gets its `SHA256SUMS` when it's finished.
+    if opts.archive.is_none() {
+        if opts.format == ExportFormat::Site {
+            site::write_index(&out_dir)?;
+        }
+        checksums::write_sha256sums(&out_dir)?;
+    }
+
     if let Some(pb) = pb {
-        pb.finish_with_message(format!(
-            "Exported {}/{} notes to {}",
+        pb.finish_with_message(exported_message(
             exported,
             total,
-            out_dir.display()
+            &resume,
+            &opts.destination(&out_dir),
         ));
     }
 
-    Ok(())
+    Ok(exported)
 }
 
 #[derive(Debug, Clone)]
@@ -451,9 +1010,20 @@ struct DbNoteRow {
     body_html: Option<String>,
 }
 
-fn list_db_notes(account: &str, include_html: &HtmlExport) -> anyhow::Result<Vec<DbNoteRow>> {
+/// Lists the account's notes, keeping only those in `folders` (when given) whose dates match
+/// `filter`.
+fn list_db_notes(
+    account: &str,
+    include_html: &HtmlExport,
+    folders: Option<&FolderIndex>,
+    filter: &NoteFilter,
+) -> anyhow::Result<Vec<DbNoteRow>> {
     let db = crate::db::NotesDb::open_default()?;
-    let notes = db.list_notes(account)?;
+    let mut notes = db.list_notes(account)?;
+    if let Some(folders) = folders {
+        notes.retain(|n| folders.contains(&n.folder_id));
+    }
+    skip_locked(&mut notes);
 
     // Hydrate dates from DB. Best-effort; schema-specific.
     let store_uuid = db_store_uuid()?;
@@ -463,6 +1033,9 @@ fn list_db_notes(account: &str, include_html: &HtmlExport) -> anyhow::Result<Vec
     for n in notes {
         let pk = parse_coredata_pk(&n.id)?;
         let (created, modified) = select_note_dates(&conn, pk)?;
+        if !filter.matches_dates(created, modified) {
+            continue;
+        }
         out.push(DbNoteRow {
             id: format!("x-coredata://{}/ICNote/p{}", store_uuid, pk),
             title: n.title,
@@ -507,25 +1080,31 @@ fn list_db_notes(account: &str, include_html: &HtmlExport) -> anyhow::Result<Vec
     Ok(out)
 }
 
-fn export_one_db(
+fn build_db_item(
     account: &str,
-    out_dir: &Path,
+    note_dir: &Path,
     folder_index: &FolderIndex,
     row: &DbNoteRow,
     conn: &rusqlite::Connection,
     pb: Option<&indicatif::ProgressBar>,
-) -> anyhow::Result<()> {
+    opts: &ExportOptions,
+) -> anyhow::Result<Option<WorkItem>> {
     if let Some(pb) = pb {
         pb.set_message(format!("Decoding: {}", truncate_title(&row.title)));
     }
     let pk = parse_coredata_pk(&row.id)?;
     let data = load_note_data(conn, pk)?;
-    let contents_md = decode_note_markdown(&data).unwrap_or_else(|_| String::new());
-    let contents_html = row.body_html.clone();
+    let sharing = note_sharing(conn, pk)?;
+    let contents_md = decode_note_markdown(&data, Some(conn)).unwrap_or_else(|_| String::new());
+    if !opts.filter.matches_text(&row.title, &contents_md) {
+        return Ok(None);
+    }
+    let contents_html = row.body_html.clone().filter(|_| opts.html.wants(&row.id));
 
     let folder_path = folder_index
         .folder_path(&row.folder_id)
         .unwrap_or_else(|| vec!["Unknown".to_string()]);
+    let page = opts.page(&row.title, row.body_html.as_deref(), &folder_path);
 
     let metadata = BackupNoteMetadata {
         id: row.id.clone(),
@@ -534,34 +1113,323 @@ fn export_one_db(
         folder_path: folder_path.clone(),
         created_at: row.created_at,
         modified_at: row.modified_at,
+        shared: sharing.is_some(),
+        participants: sharing.unwrap_or_default(),
     };
 
-    let note_dir = export_path(out_dir, &folder_path, &row.title, &row.id)?;
     let metadata_json = serde_json::to_string_pretty(&metadata)?;
 
-    write_item(&WorkItem {
-        note_dir,
+    Ok(Some(WorkItem {
+        id: row.id.clone(),
+        title: row.title.clone(),
+        note_dir: note_dir.to_path_buf(),
         metadata_json,
         contents_md,
         contents_html,
-    })
+        format: opts.format,
+        page,
+        created_at: row.created_at,
+        modified_at: row.modified_at,
+    }))
 }
 
-fn open_notes_db_readonly() -> anyhow::Result<rusqlite::Connection> {
-    let db_path = if let Some(p) = std::env::var_os("APPLE_NOTES_DB_PATH") {
-        std::path::PathBuf::from(p)
+/// A note directory found in an existing export tree.
+#[derive(Debug, Clone)]
+pub struct ExportedNote {
+    pub dir: PathBuf,
+    pub metadata: BackupNoteMetadata,
+}
+
+/// Reads every `metadata.json` under an export root.
+///
+/// If `root` is a snapshot root (see `export --snapshot`), the `latest` snapshot is scanned.
+pub fn scan_export(root: &Path) -> anyhow::Result<Vec<ExportedNote>> {
+    let latest = root.join("latest");
+    let root = if latest.is_dir() {
+        latest
     } else {
-        std::path::PathBuf::from(std::env::var("HOME").context("HOME not set")?)
-            .join("Library/Group Containers/group.com.apple.notes/NoteStore.sqlite")
+        root.to_path_buf()
     };
+    if !root.is_dir() {
+        return Err(anyhow!("export directory not found: {}", root.display()));
+    }
 
-    rusqlite::Connection::open_with_flags(
-        db_path,
-        rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY
-            | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX
-            | rusqlite::OpenFlags::SQLITE_OPEN_SHARED_CACHE,
-    )
-    .context("open Notes DB")
+    let mut out = Vec::new();
+    for entry in walkdir::WalkDir::new(&root) {
+        let entry = entry.context("walk export")?;
+        if !entry.file_type().is_file() || entry.file_name() != "metadata.json" {
+            continue;
+        }
+        let data = std::fs::read_to_string(entry.path())
+            .with_context(|| format!("read {:?}", entry.path()))?;
+        let metadata: BackupNoteMetadata =
+            serde_json::from_str(&data).with_context(|| format!("parse {:?}", entry.path()))?;
+        let dir = entry
+            .path()
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| root.clone());
+        out.push(ExportedNote { dir, metadata });
+    }
+    out.sort_by(|a, b| a.metadata.id.cmp(&b.metadata.id));
+    Ok(out)
+}
+
+/// Reads an exported note directory back into its metadata and an HTML body for the write path.
+///
+/// Prefers the raw `contents.html` when it was exported; otherwise `contents.md` is rendered.
+pub fn read_exported_note(dir: &Path) -> anyhow::Result<(BackupNoteMetadata, String)> {
+    let meta_path = dir.join("metadata.json");
+    let data = std::fs::read_to_string(&meta_path)
+        .with_context(|| format!("read {meta_path:?} (is this an exported note directory?)"))?;
+    let metadata: BackupNoteMetadata =
+        serde_json::from_str(&data).with_context(|| format!("parse {meta_path:?}"))?;
+
+    let html_path = dir.join("contents.html");
+    let md_path = dir.join("contents.md");
+    let body_html = if html_path.is_file() {
+        std::fs::read_to_string(&html_path).with_context(|| format!("read {html_path:?}"))?
+    } else if md_path.is_file() {
+        let md = std::fs::read_to_string(&md_path).with_context(|| format!("read {md_path:?}"))?;
+        render::markdown_to_html(&md)
+    } else {
+        return Err(anyhow!(
+            "{} has neither contents.html nor contents.md",
+            dir.display()
+        ));
+    };
+    Ok((metadata, body_html))
+}
+
+/// Current state of a note in the live account, as export would see it.
+#[derive(Debug, Clone)]
+pub struct LiveNote {
+    pub id: String,
+    pub title: String,
+    pub modified_at: OffsetDateTime,
+    /// Rendered `contents.md`, when requested.
+    pub contents_md: Option<String>,
+}
+
+pub fn live_notes(
+    backend: &dyn NotesBackend,
+    account: &str,
+    with_contents: bool,
+) -> anyhow::Result<Vec<LiveNote>> {
+    let spinner = progress::spinner("Indexing notes…");
+    let notes = backend.list_notes(account)?;
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+
+    let pb = progress::bar(notes.len() as u64, "Checking notes…");
+    let mut out = Vec::with_capacity(notes.len());
+    for n in notes {
+        if let Some(pb) = &pb {
+            pb.set_message(format!("Checking: {}", truncate_title(&n.title)));
+        }
+        let note = backend.get_note(&n.id)?;
+        out.push(LiveNote {
+            contents_md: with_contents.then(|| render::note_to_markdown(&note)),
+            id: note.id,
+            title: note.title,
+            modified_at: note.modified_at,
+        });
+        if let Some(pb) = &pb {
+            pb.inc(1);
+        }
+    }
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
+    }
+    Ok(out)
+}
+
+pub fn live_notes_db(account: &str, with_contents: bool) -> anyhow::Result<Vec<LiveNote>> {
+    let spinner = progress::spinner("Indexing notes…");
+    let rows = list_db_notes(account, &HtmlExport::None, None, &NoteFilter::default())?;
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+
+    let conn = if with_contents {
+        Some(open_notes_db_readonly()?)
+    } else {
+        None
+    };
+    let mut out = Vec::with_capacity(rows.len());
+    for row in rows {
+        let contents_md = match &conn {
+            Some(conn) => {
+                let data = load_note_data(conn, parse_coredata_pk(&row.id)?)?;
+                Some(decode_note_markdown(&data, Some(conn)).unwrap_or_default())
+            }
+            None => None,
+        };
+        out.push(LiveNote {
+            id: row.id,
+            title: row.title,
+            modified_at: row.modified_at,
+            contents_md,
+        });
+    }
+    Ok(out)
+}
+
+/// A note's metadata as read from the database, without going through Notes.app.
+#[derive(Debug, Clone)]
+pub struct DbNoteMeta {
+    pub title: String,
+    pub folder_id: String,
+    pub created_at: OffsetDateTime,
+    pub modified_at: OffsetDateTime,
+    pub attachments: usize,
+    pub words: usize,
+    /// `Some` (with the participants' email addresses) when the note is shared.
+    pub sharing: Option<Vec<String>>,
+}
+
+/// Reads the metadata of the note `id` (an `x-coredata://` id) straight from the database.
+pub fn note_meta_db(id: &str) -> anyhow::Result<DbNoteMeta> {
+    let conn = open_notes_db_readonly()?;
+    let pk = parse_coredata_pk(id)?;
+    let (title, folder_pk): (Option<String>, i64) = conn
+        .query_row(
+            "SELECT ZTITLE1, ZFOLDER FROM ZICCLOUDSYNCINGOBJECT WHERE Z_ENT = 12 AND Z_PK = ?",
+            [pk],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| NotesError::NotFound(format!("note not found: {id}")))?;
+    let (created_at, modified_at) = select_note_dates(&conn, pk)?;
+    let (words, attachments) = count_words_and_attachments(&load_note_data(&conn, pk)?, &conn);
+    Ok(DbNoteMeta {
+        title: title.unwrap_or_else(|| "Untitled".to_string()),
+        folder_id: format!("x-coredata://{}/ICFolder/p{}", db_store_uuid()?, folder_pk),
+        created_at,
+        modified_at,
+        attachments,
+        words,
+        sharing: note_sharing(&conn, pk)?,
+    })
+}
+
+static EMAIL: LazyLock<regex::bytes::Regex> = LazyLock::new(|| {
+    regex::bytes::Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}")
+        .expect("valid regex")
+});
+
+/// Whether the note `pk` is shared through iCloud collaboration, with the email addresses of the
+/// people it's shared with (sorted, owner included). Shared notes keep their `CKShare` in
+/// `ZSERVERSHAREDATA`, an archived plist; the addresses are picked out of it, best-effort.
+/// Schemas without that column have no shared notes.
+fn note_sharing(conn: &rusqlite::Connection, note_pk: i64) -> anyhow::Result<Option<Vec<String>>> {
+    if !crate::db::table_columns(conn, "ZICCLOUDSYNCINGOBJECT")?.contains("ZSERVERSHAREDATA") {
+        return Ok(None);
+    }
+    let share: Option<Vec<u8>> = conn
+        .query_row(
+            "SELECT ZSERVERSHAREDATA FROM ZICCLOUDSYNCINGOBJECT WHERE Z_ENT = 12 AND Z_PK = ?",
+            [note_pk],
+            |row| row.get(0),
+        )
+        .optional()
+        .with_context(|| format!("read sharing data for note pk {note_pk}"))?
+        .flatten();
+    Ok(share.map(|data| {
+        let participants: std::collections::BTreeSet<String> = EMAIL
+            .find_iter(&data)
+            .map(|m| String::from_utf8_lossy(m.as_bytes()).to_lowercase())
+            .collect();
+        participants.into_iter().collect()
+    }))
+}
+
+/// Type prefix of the attachments Notes uses for inline hashtags, mentions and links.
+const INLINE_ATTACHMENT_UTI: &str = "com.apple.notes.inlinetextattachment";
+
+/// Words and attachments (images, files, tables; not inline hashtags or mentions) in a note blob.
+fn count_words_and_attachments(data: &[u8], conn: &rusqlite::Connection) -> (usize, usize) {
+    let parsed = decompress_blob(data).and_then(|d| notestore::parse(&d));
+    match parsed {
+        Ok(body) => {
+            let attachments = body
+                .runs
+                .iter()
+                .filter_map(|run| run.attachment.as_ref())
+                .filter(|a| !a.type_uti.starts_with(INLINE_ATTACHMENT_UTI))
+                .count();
+            (render::word_count(&body.text), attachments)
+        }
+        Err(_) => {
+            let text = decode_note_markdown(data, Some(conn)).unwrap_or_default();
+            (render::word_count(&text), 0)
+        }
+    }
+}
+
+/// The plain text of each of `ids` (`x-coredata://` note ids), read from the Notes database.
+/// Notes whose body can't be read are left out.
+pub fn note_texts_db(ids: &[String]) -> anyhow::Result<HashMap<String, String>> {
+    note_bodies_db(ids, |data, conn| {
+        decompress_blob(data)
+            .and_then(|d| notestore::parse(&d))
+            .map(|body| body.text)
+            .or_else(|_| decode_note_markdown(data, Some(conn)))
+    })
+}
+
+/// Like [`note_texts_db`], as Markdown (keeping links and formatting).
+pub fn note_markdown_db(ids: &[String]) -> anyhow::Result<HashMap<String, String>> {
+    note_bodies_db(ids, |data, conn| decode_note_markdown(data, Some(conn)))
+}
+
+/// Why [`note_markdown_for_rewrite_db`] won't rewrite a note with attachments.
+pub const HAS_ATTACHMENTS: &str = "it has attachments";
+
+/// Like [`note_markdown_db`], for bodies that are about to be written back: each comes with why
+/// it can't be without losing content, if so (attachments, which Markdown doesn't carry, or a body
+/// that could only be scraped as plain text).
+pub fn note_markdown_for_rewrite_db(
+    ids: &[String],
+) -> anyhow::Result<HashMap<String, (String, Option<&'static str>)>> {
+    note_bodies_db(ids, |data, conn| {
+        let blocker = match decompress_blob(data).and_then(|d| notestore::parse(&d)) {
+            Ok(body)
+                if body
+                    .runs
+                    .iter()
+                    .filter_map(|run| run.attachment.as_ref())
+                    .any(|a| !a.type_uti.starts_with(INLINE_ATTACHMENT_UTI)) =>
+            {
+                Some(HAS_ATTACHMENTS)
+            }
+            Ok(_) => None,
+            Err(_) => Some("its body could only be read as plain text"),
+        };
+        Ok((decode_note_markdown(data, Some(conn))?, blocker))
+    })
+}
+
+fn note_bodies_db<T>(
+    ids: &[String],
+    decode: impl Fn(&[u8], &rusqlite::Connection) -> anyhow::Result<T>,
+) -> anyhow::Result<HashMap<String, T>> {
+    let conn = open_notes_db_readonly()?;
+    let mut bodies = HashMap::new();
+    for id in ids {
+        let Ok(data) = parse_coredata_pk(id).and_then(|pk| load_note_data(&conn, pk)) else {
+            continue;
+        };
+        if let Ok(body) = decode(&data, &conn) {
+            bodies.insert(id.clone(), body);
+        }
+    }
+    Ok(bodies)
+}
+
+fn open_notes_db_readonly() -> anyhow::Result<rusqlite::Connection> {
+    crate::db::open_readonly(&crate::db::notes_db_path()?)
 }
 
 fn db_store_uuid() -> anyhow::Result<String> {
@@ -616,12 +1484,10 @@ fn select_note_dates(
 
     let created = raw.c3.or(raw.c2).or(raw.c1).unwrap_or(0.0);
     let modified = raw.m1.or(raw.m2).unwrap_or(created);
-    Ok((apple_epoch_seconds(created), apple_epoch_seconds(modified)))
-}
-
-fn apple_epoch_seconds(secs: f64) -> OffsetDateTime {
-    let base = OffsetDateTime::from_unix_timestamp(978307200).unwrap(); // 2001-01-01T00:00:00Z
-    base + time::Duration::milliseconds((secs * 1000.0) as i64)
+    Ok((
+        crate::db::apple_epoch_seconds(created),
+        crate::db::apple_epoch_seconds(modified),
+    ))
 }
 
 fn load_note_data(conn: &rusqlite::Connection, note_pk: i64) -> anyhow::Result<Vec<u8>> {
@@ -637,16 +1503,29 @@ fn load_note_data(conn: &rusqlite::Connection, note_pk: i64) -> anyhow::Result<V
     Ok(data.unwrap_or_default())
 }
 
-fn decode_note_markdown(data: &[u8]) -> anyhow::Result<String> {
-    let decoded = if data.starts_with(&[0x1f, 0x8b]) {
-        gunzip(data).context("gunzip note blob")?
-    } else if data.len() >= 2 && data[0] == 0x78 {
-        // Many Notes blobs are zlib-compressed.
-        inflate_zlib(data).context("zlib decode note blob")?
-    } else {
-        data.to_vec()
+/// `conn` is used to look up embedded objects (tables); without it they are dropped.
+fn decode_note_markdown(
+    data: &[u8],
+    conn: Option<&rusqlite::Connection>,
+) -> anyhow::Result<String> {
+    let decoded = decompress_blob(data)?;
+
+    // Current Notes versions store a `NoteStoreProto`; decode it faithfully when we can.
+    let mut resolve = |attachment: &notestore::Attachment| {
+        if attachment.type_uti != notetable::TABLE_UTI {
+            return None;
+        }
+        let data = load_mergeable_data(conn?, &attachment.identifier).ok()??;
+        let rows = notetable::decode(&decompress_blob(&data).ok()?).ok()?;
+        notetable::to_markdown(&rows)
     };
+    if let Ok(md) = notestore::to_markdown_with(&decoded, &mut resolve)
+        && !md.trim().is_empty()
+    {
+        return Ok(md);
+    }
 
+    // Older or unknown formats: fall back to scraping readable text.
     if let Ok(s) = std::str::from_utf8(&decoded) {
         let s = s.trim_matches('\0').trim();
         if looks_like_human_text(s) {
@@ -661,6 +1540,32 @@ fn decode_note_markdown(data: &[u8]) -> anyhow::Result<String> {
     Ok(text)
 }
 
+fn decompress_blob(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if data.starts_with(&[0x1f, 0x8b]) {
+        gunzip(data).context("gunzip note blob")
+    } else if data.len() >= 2 && data[0] == 0x78 {
+        // Many Notes blobs are zlib-compressed.
+        inflate_zlib(data).context("zlib decode note blob")
+    } else {
+        Ok(data.to_vec())
+    }
+}
+
+/// Reads the CRDT payload of an embedded object (e.g. a table) by its attachment identifier.
+fn load_mergeable_data(
+    conn: &rusqlite::Connection,
+    identifier: &str,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    conn.query_row(
+        "SELECT ZMERGEABLEDATA1 FROM ZICCLOUDSYNCINGOBJECT WHERE ZIDENTIFIER = ? LIMIT 1",
+        [identifier],
+        |row| row.get::<_, Option<Vec<u8>>>(0),
+    )
+    .optional()
+    .map(Option::flatten)
+    .with_context(|| format!("read table data for attachment {identifier}"))
+}
+
 fn gunzip(data: &[u8]) -> anyhow::Result<Vec<u8>> {
     let mut dec = GzDecoder::new(data);
     let mut out = Vec::new();
@@ -738,6 +1643,79 @@ mod tests {
     use flate2::write::ZlibEncoder;
     use std::io::Write;
 
+    #[test]
+    fn note_filter_matches_query_and_date_bounds() {
+        let filter = NoteFilter {
+            query: Some("Taxes".into()),
+            modified_after: Some(time::macros::datetime!(2025-01-01 00:00 UTC)),
+            created_before: Some(time::macros::datetime!(2025-06-01 00:00 UTC)),
+        };
+        assert!(filter.matches_text("2025 TAXES", ""));
+        assert!(filter.matches_text("Receipts", "file the taxes"));
+        assert!(!filter.matches_text("Receipts", "groceries"));
+
+        let may = time::macros::datetime!(2025-05-01 00:00 UTC);
+        let july = time::macros::datetime!(2025-07-01 00:00 UTC);
+        let old = time::macros::datetime!(2024-12-01 00:00 UTC);
+        assert!(filter.matches_dates(may, july));
+        assert!(!filter.matches_dates(july, july));
+        assert!(!filter.matches_dates(old, old));
+        assert!(NoteFilter::default().matches_dates(old, old));
+    }
+
+    #[test]
+    fn folder_subtree_keeps_root_and_descendants() {
+        let folder = |id: &str, path: &[&str]| Folder {
+            id: id.into(),
+            name: path.last().unwrap().to_string(),
+            account: "iCloud".into(),
+            path: path.iter().map(|p| p.to_string()).collect(),
+            created_at: None,
+            modified_at: None,
+            smart: false,
+        };
+        let folders = vec![
+            folder("1", &["Personal"]),
+            folder("2", &["Personal", "Archive"]),
+            folder("3", &["Personal", "Archive", "2024"]),
+            folder("4", &["Personal Stuff"]),
+        ];
+        let root = ["Personal".to_string(), "Archive".to_string()];
+        let ids: Vec<_> = folder_subtree(folders.clone(), &root)
+            .unwrap()
+            .into_iter()
+            .map(|f| f.id)
+            .collect();
+        assert_eq!(ids, ["2", "3"]);
+        assert!(folder_subtree(folders, &["Work".to_string()]).is_err());
+    }
+
+    #[test]
+    fn folder_index_finds_folders_by_path_and_knows_their_subtrees() {
+        let folder = |id: &str, path: &[&str]| Folder {
+            id: id.into(),
+            name: path.last().unwrap().to_string(),
+            account: "iCloud".into(),
+            path: path.iter().map(|p| p.to_string()).collect(),
+            created_at: None,
+            modified_at: None,
+            smart: false,
+        };
+        let index = FolderIndex::new(&[
+            folder("1", &["Personal"]),
+            folder("2", &["Personal", "Archive"]),
+            folder("3", &["Personal Stuff"]),
+        ])
+        .unwrap();
+        let path = ["Personal".to_string(), "Archive".to_string()];
+        assert_eq!(index.find(&path).unwrap().id, "2");
+        assert!(index.find(&path[1..]).is_none());
+        assert!(index.is_within("2", "1"));
+        assert!(index.is_within("1", "1"));
+        assert!(!index.is_within("1", "2"));
+        assert!(!index.is_within("3", "1"));
+    }
+
     #[test]
     fn export_path_uses_folder_structure_and_safe_filename() {
         let root = Path::new("/tmp/out");
@@ -760,7 +1738,7 @@ mod tests {
         enc.write_all(payload).unwrap();
         let gz = enc.finish().unwrap();
 
-        let out = decode_note_markdown(&gz).unwrap();
+        let out = decode_note_markdown(&gz, None).unwrap();
         assert!(out.contains("Hello from Notes!"));
         assert!(out.contains("Second line."));
     }
@@ -772,17 +1750,63 @@ mod tests {
         enc.write_all(payload).unwrap();
         let z = enc.finish().unwrap();
 
-        let out = decode_note_markdown(&z).unwrap();
+        let out = decode_note_markdown(&z, None).unwrap();
         assert!(out.contains("Hello from Notes via zlib!"));
         assert!(out.contains("Second line."));
     }
 
+    #[test]
+    fn decode_note_markdown_decodes_gzipped_protobuf() {
+        let proto = crate::notestore::tests::note_proto(&[
+            ("Title\n", Some(0), 0, None),
+            ("Body ", None, 0, None),
+            ("bold", None, 1, None),
+        ]);
+        let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+        enc.write_all(&proto).unwrap();
+        let gz = enc.finish().unwrap();
+
+        assert_eq!(
+            decode_note_markdown(&gz, None).unwrap(),
+            "# Title\n\nBody **bold**\n"
+        );
+    }
+
     #[test]
     fn decode_note_markdown_accepts_plain_utf8() {
-        let out = decode_note_markdown(b"Hi\r\nThere").unwrap();
+        let out = decode_note_markdown(b"Hi\r\nThere", None).unwrap();
         assert_eq!(out, "Hi\nThere");
     }
 
+    #[test]
+    fn read_exported_note_prefers_html_then_markdown() {
+        let dir = tempfile::tempdir().unwrap();
+        let metadata = BackupNoteMetadata {
+            id: "n1".into(),
+            title: "Hello".into(),
+            account: "iCloud".into(),
+            folder_path: vec!["Personal".into()],
+            created_at: OffsetDateTime::UNIX_EPOCH,
+            modified_at: OffsetDateTime::UNIX_EPOCH,
+            shared: false,
+            participants: Vec::new(),
+        };
+        std::fs::write(
+            dir.path().join("metadata.json"),
+            serde_json::to_string(&metadata).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("contents.md"), "# Hello\n\n**hi**").unwrap();
+
+        let (meta, html) = read_exported_note(dir.path()).unwrap();
+        assert_eq!(meta, metadata);
+        assert!(html.contains("<strong>hi</strong>"));
+
+        std::fs::write(dir.path().join("contents.html"), "<div>raw</div>").unwrap();
+        let (_, html) = read_exported_note(dir.path()).unwrap();
+        assert_eq!(html, "<div>raw</div>");
+    }
+
     #[test]
     fn truncate_title_shortens() {
         let long = "a".repeat(200);
@@ -791,6 +1815,38 @@ mod tests {
         assert!(t.chars().count() <= 61);
     }
 
+    #[test]
+    fn disambiguate_note_dirs_suffixes_every_colliding_note() {
+        let root = Path::new("/tmp/out/Personal");
+        let dirs = disambiguate_note_dirs(vec![
+            ("a://ICNote/p1".into(), root.join("Todo-p1")),
+            ("b://ICNote/p1".into(), root.join("todo-p1")),
+            ("a://ICNote/p2".into(), root.join("Todo-p2")),
+        ]);
+
+        assert_eq!(dirs["a://ICNote/p2"], root.join("Todo-p2"));
+        let a = &dirs["a://ICNote/p1"];
+        let b = &dirs["b://ICNote/p1"];
+        assert_ne!(collision_key(a), collision_key(b));
+        assert!(
+            a.to_string_lossy()
+                .starts_with("/tmp/out/Personal/Todo-p1-")
+        );
+
+        // Deterministic regardless of listing order.
+        let again = disambiguate_note_dirs(vec![
+            ("b://ICNote/p1".into(), root.join("todo-p1")),
+            ("a://ICNote/p1".into(), root.join("Todo-p1")),
+        ]);
+        assert_eq!(&again["a://ICNote/p1"], a);
+    }
+
+    #[test]
+    fn note_dir_name_truncates_on_char_boundary() {
+        let name = note_dir_name(&"é".repeat(60), "x-coredata://UUID/ICNote/p1");
+        assert!(name.ends_with("-p1"));
+    }
+
     #[test]
     fn note_dir_name_includes_short_id_and_sanitizes() {
         let name = note_dir_name("Hello/World", "x-coredata://UUID/ICNote/p123");
@@ -0,0 +1,140 @@
+use std::sync::Arc;
+#[cfg(unix)]
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Cooperative cancellation for long-running listing and export operations.
+///
+/// Clones share the same flag, so a token can be handed to a worker while another thread (or a
+/// signal handler, UI event loop, request timeout, …) calls [`CancelToken::cancel`]. A token can
+/// also carry a deadline, after which it behaves as if it had been cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    flag: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+    parent: Option<Arc<CancelToken>>,
+}
+
+/// Returned by operations that stopped because their [`CancelToken`] fired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cancelled {
+    Cancelled,
+    DeadlineExceeded,
+}
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cancelled::Cancelled => f.write_str("operation cancelled"),
+            Cancelled::DeadlineExceeded => f.write_str("operation timed out"),
+        }
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    /// A token that fires whenever this one does, but whose own [`CancelToken::cancel`] leaves
+    /// this one alone, e.g. to stop sibling workers once one of them fails.
+    pub fn child(&self) -> Self {
+        Self {
+            flag: Arc::default(),
+            deadline: None,
+            parent: Some(Arc::new(self.clone())),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.check().is_err()
+    }
+
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.flag.load(Ordering::Relaxed) {
+            return Err(Cancelled::Cancelled);
+        }
+        if let Some(deadline) = self.deadline
+            && Instant::now() >= deadline
+        {
+            return Err(Cancelled::DeadlineExceeded);
+        }
+        match &self.parent {
+            Some(parent) => parent.check(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(unix)]
+static INTERRUPT: OnceLock<CancelToken> = OnceLock::new();
+
+/// Makes the first Ctrl-C cancel `token` instead of killing the process, so exports stop between
+/// notes and `osascript` children are reaped. A second Ctrl-C exits immediately.
+#[cfg(unix)]
+pub fn cancel_on_interrupt(token: &CancelToken) {
+    extern "C" fn on_sigint(_: libc::c_int) {
+        match INTERRUPT.get() {
+            Some(token) if !token.flag.load(Ordering::Relaxed) => token.cancel(),
+            _ => unsafe {
+                libc::signal(libc::SIGINT, libc::SIG_DFL);
+                libc::raise(libc::SIGINT);
+            },
+        }
+    }
+
+    if INTERRUPT.set(token.clone()).is_ok() {
+        unsafe {
+            libc::signal(libc::SIGINT, on_sigint as *const () as libc::sighandler_t);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel_is_shared_between_clones() {
+        let token = CancelToken::new();
+        let other = token.clone();
+        assert!(token.check().is_ok());
+        other.cancel();
+        assert_eq!(token.check(), Err(Cancelled::Cancelled));
+    }
+
+    #[test]
+    fn child_follows_parent_but_not_the_other_way() {
+        let parent = CancelToken::new();
+        let child = parent.child();
+        child.cancel();
+        assert_eq!(child.check(), Err(Cancelled::Cancelled));
+        assert!(parent.check().is_ok());
+
+        let child = parent.child();
+        parent.cancel();
+        assert_eq!(child.check(), Err(Cancelled::Cancelled));
+    }
+
+    #[test]
+    fn deadline_in_the_past_is_exceeded() {
+        let token = CancelToken::new().with_timeout(Duration::ZERO);
+        assert_eq!(token.check(), Err(Cancelled::DeadlineExceeded));
+    }
+}
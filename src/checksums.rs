@@ -0,0 +1,90 @@
+use anyhow::Context;
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Name of the checksum file written at the root of every export.
+pub const SHA256SUMS: &str = "SHA256SUMS";
+
+pub fn sha256_bytes(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+pub fn sha256_file(path: &Path) -> anyhow::Result<String> {
+    let mut file = std::fs::File::open(path).with_context(|| format!("open {path:?}"))?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = file
+            .read(&mut buf)
+            .with_context(|| format!("read {path:?}"))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Writes `SHA256SUMS` at `root`, covering every file below it.
+///
+/// The format matches `shasum -a 256` / `sha256sum` output, so a backup can be checked years later
+/// with `cd <export> && shasum -a 256 -c SHA256SUMS`, no CLI required.
+pub fn write_sha256sums(root: &Path) -> anyhow::Result<usize> {
+    let mut entries = Vec::new();
+    for entry in walkdir::WalkDir::new(root) {
+        let entry = entry.context("walk export")?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let rel = entry.path().strip_prefix(root)?;
+        if rel == Path::new(SHA256SUMS) {
+            continue;
+        }
+        let rel = rel
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        entries.push((rel, sha256_file(entry.path())?));
+    }
+    entries.sort();
+
+    let path = root.join(SHA256SUMS);
+    let mut out = std::io::BufWriter::new(
+        std::fs::File::create(&path).with_context(|| format!("create {path:?}"))?,
+    );
+    for (rel, hash) in &entries {
+        writeln!(out, "{hash}  {rel}").with_context(|| format!("write {path:?}"))?;
+    }
+    out.flush().with_context(|| format!("write {path:?}"))?;
+    Ok(entries.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn write_sha256sums_lists_every_file_in_shasum_format() {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("Personal/Note-p1")).unwrap();
+        std::fs::write(dir.path().join("Personal/Note-p1/contents.md"), "hi").unwrap();
+        std::fs::write(dir.path().join("top.json"), "{}").unwrap();
+
+        assert_eq!(write_sha256sums(dir.path()).unwrap(), 2);
+        // Re-running must not checksum the previous SHA256SUMS.
+        assert_eq!(write_sha256sums(dir.path()).unwrap(), 2);
+
+        let sums = std::fs::read_to_string(dir.path().join(SHA256SUMS)).unwrap();
+        assert_eq!(
+            sums,
+            format!(
+                "{}  Personal/Note-p1/contents.md\n{}  top.json\n",
+                sha256_bytes(b"hi"),
+                sha256_bytes(b"{}")
+            )
+        );
+    }
+}
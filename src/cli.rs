@@ -1,14 +1,64 @@
+use crate::archive::ArchiveWriter;
 use crate::backup;
-use crate::model::{Folder, NoteSummary};
+use crate::cancel::CancelToken;
+use crate::clipboard;
+use crate::config::{Config, FolderSettings};
+use crate::dedupe::{DedupeBy, duplicate_clusters, same_body};
+use crate::diff;
+use crate::epub;
+use crate::error::NotesError;
+use crate::grep;
+use crate::hooks;
+use crate::html_export;
+use crate::http;
+use crate::links;
+use crate::lint;
+use crate::logging::LogFormat;
+use crate::model::{Folder, Note, NoteSummary};
+use crate::pager;
+use crate::pdf;
 use crate::progress;
+use crate::rename;
 use crate::render;
+use crate::replace;
+use crate::resolve::{self, resolve_note};
+use crate::rpc;
+use crate::schedule;
+use crate::snapshot;
 use crate::tables;
-use crate::transport::NotesBackend;
+use crate::templates;
+use crate::todo;
+use crate::transport::{Backend, DEFAULT_FOLDER, NotesBackend};
+use crate::tui;
+use crate::verify;
 use anyhow::{Context, anyhow};
 use clap::{Parser, Subcommand, ValueEnum};
 use comfy_table::Cell;
-use std::io::{self, IsTerminal, Read};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, IsTerminal, Read, Write};
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+/// `--account` when neither the flag, `APPLE_NOTES_ACCOUNT` nor the config file sets one.
+pub const DEFAULT_ACCOUNT: &str = "iCloud";
+
+/// `export --jobs` when neither the flag, `APPLE_NOTES_JOBS` nor the config file sets it.
+const DEFAULT_JOBS: usize = 4;
+
+/// A command that did part of its work and failed the rest; `apple-notes` exits with 6.
+#[derive(Debug)]
+pub struct PartialFailure(pub String);
+
+impl std::fmt::Display for PartialFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for PartialFailure {}
 
 #[derive(Debug, Parser)]
 #[command(
@@ -28,18 +78,55 @@ First run on macOS may prompt for Automation permission (osascript → Notes).
 "#
 )]
 pub struct Args {
-    /// Notes account to target (default: iCloud).
-    #[arg(long, default_value = "iCloud", global = true)]
-    pub account: String,
+    /// Notes account to target [default: iCloud].
+    #[arg(long, global = true)]
+    pub account: Option<String>,
 
-    /// Backend for reads (writes always use `osascript`).
-    #[arg(long, default_value = "auto", global = true)]
-    pub backend: Backend,
+    /// Backend for reads (writes always use `osascript`) [default: auto].
+    #[arg(long, global = true)]
+    pub backend: Option<Backend>,
+
+    /// Read settings from this file instead of `~/.config/apple-notes/config.toml`.
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
 
     /// Output JSON for machine consumption.
     #[arg(long, global = true)]
     pub json: bool,
 
+    /// Suppress progress output and informational messages.
+    #[arg(long, short = 'q', global = true)]
+    pub quiet: bool,
+
+    /// Log backend calls and their timing to stderr; `-vv` adds the scripts and SQL they run.
+    #[arg(long, short = 'v', global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Format of `-v` log lines.
+    #[arg(long, global = true, value_enum, default_value = "text")]
+    pub log_format: LogFormat,
+
+    /// Print how long the command spent listing, fetching, rendering and writing when it ends.
+    #[arg(long, global = true)]
+    pub timing: bool,
+
+    /// Refuse to run commands that modify notes or folders.
+    #[arg(long, global = true)]
+    pub read_only: bool,
+
+    /// Print what a command would change instead of changing it.
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Abort the command if it runs longer than this many seconds.
+    #[arg(long, global = true, value_name = "SECS")]
+    pub timeout: Option<u64>,
+
+    /// Read from this Notes database (a copied `NoteStore.sqlite`) instead of the local one.
+    /// Implies `--backend db` when the backend is `auto`; works on any platform.
+    #[arg(long, global = true, value_name = "PATH")]
+    pub db_path: Option<PathBuf>,
+
     /// Use a local fixture backend instead of `osascript` (for tests/dev only).
     #[arg(long, global = true, value_name = "PATH", hide = true)]
     pub fixture: Option<PathBuf>,
@@ -48,16 +135,6 @@ pub struct Args {
     pub cmd: Command,
 }
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
-pub enum Backend {
-    /// Auto-detect the fastest available backend (prefers DB when present).
-    Auto,
-    /// Use `osascript` for all reads and writes.
-    Osascript,
-    /// Use the Apple Notes database for reads (macOS only); writes still use `osascript`.
-    Db,
-}
-
 #[derive(Debug, Subcommand)]
 pub enum Command {
     Accounts {
@@ -72,31 +149,154 @@ pub enum Command {
         #[command(subcommand)]
         cmd: NotesCmd,
     },
-    /// Export all notes to a folder structure on disk.
-    Export {
-        /// Output directory. Created if it doesn't exist.
-        #[arg(long)]
-        out: String,
-        /// Number of export worker threads (decode/render + IO).
-        #[arg(long, default_value_t = 4)]
-        jobs: usize,
-        /// Also write `contents.html` (raw HTML). This is slower and may require Notes.app permissions.
-        #[arg(long, conflicts_with_all = ["no_html", "html_only"])]
-        with_html: bool,
-        /// Write `contents.html` only for specific note ids (repeatable).
-        #[arg(long, value_name = "ID", conflicts_with_all = ["no_html", "with_html"])]
-        html_only: Vec<String>,
-        /// Do not write `contents.html` (raw HTML). (Deprecated; default is no HTML.)
-        #[arg(long, hide = true)]
-        no_html: bool,
+    /// Capture text as a new note in the inbox folder, titled with the time and its first line.
+    Quick {
+        /// Text to capture. Read from stdin when omitted or `-`.
+        text: Option<String>,
+        /// Folder to capture into (default: the config file's `default_folder`).
+        #[arg(long)]
+        folder: Option<String>,
+    },
+    /// List the checklist items of every note as one task list, with the note each is in.
+    Todos {
+        /// Only look in this folder path (e.g. "Personal > Archive").
+        #[arg(long)]
+        folder: Option<String>,
+        /// Only list unchecked items.
+        #[arg(long)]
+        open_only: bool,
     },
+    /// Export all notes to a folder structure on disk.
+    Export(Box<ExportArgs>),
 
-    /// Deprecated: use `apple-notes export ...`.
-    #[command(hide = true)]
+    /// Inspect and maintain existing exports.
     Backup {
         #[command(subcommand)]
         cmd: BackupCmd,
     },
+    /// Browse, preview and edit notes interactively.
+    Tui,
+    /// Serve the notes to other programs: JSON-RPC over stdio, or a local HTTP API.
+    #[command(group(clap::ArgGroup::new("transport").required(true).args(["stdio", "http"])))]
+    Serve {
+        /// Read newline-delimited JSON-RPC 2.0 requests from stdin and answer on stdout.
+        #[arg(long)]
+        stdio: bool,
+        /// Serve a REST API on this address (e.g. 127.0.0.1:7878).
+        #[arg(long, value_name = "ADDR")]
+        http: Option<std::net::SocketAddr>,
+        /// Require `Authorization: Bearer <TOKEN>` on every HTTP request (default:
+        /// `$APPLE_NOTES_SERVE_TOKEN`).
+        #[arg(long, requires = "http")]
+        token: Option<String>,
+    },
+}
+
+impl Command {
+    /// Turns off the parts of commands with a preview of their own that would apply it, for
+    /// `--dry-run`. Other writes are reported by the dry-run backend instead.
+    pub fn preview_only(&mut self) {
+        match self {
+            Command::Notes {
+                cmd: NotesCmd::Rename { yes, .. },
+            } => *yes = false,
+            Command::Notes {
+                cmd: NotesCmd::RenameAll { dry_run, .. } | NotesCmd::Replace { dry_run, .. },
+            } => *dry_run = true,
+            Command::Notes {
+                cmd: NotesCmd::Dedupe { apply, .. },
+            } => *apply = false,
+            _ => {}
+        }
+    }
+
+    /// The command's name if it modifies notes or folders (used to enforce `--read-only`).
+    pub fn mutation(&self) -> Option<&'static str> {
+        match self {
+            Command::Accounts { cmd } => match cmd {
+                AccountsCmd::List => None,
+            },
+            Command::Folders { cmd } => match cmd {
+                FoldersCmd::List { .. } => None,
+                FoldersCmd::Create { .. } => Some("folders create"),
+                FoldersCmd::Rename { .. } => Some("folders rename"),
+                FoldersCmd::Move { .. } => Some("folders move"),
+                FoldersCmd::Delete { .. } => Some("folders delete"),
+            },
+            Command::Notes { cmd } => match cmd {
+                NotesCmd::List { .. } | NotesCmd::Show { .. } => None,
+                NotesCmd::Create { .. } => Some("notes create"),
+                // A bulk rename without `--yes` only prints a preview.
+                NotesCmd::Rename {
+                    query: Some(_),
+                    yes: false,
+                    ..
+                } => None,
+                NotesCmd::Rename { .. } => Some("notes rename"),
+                NotesCmd::RenameAll { .. } => Some("notes rename-all"),
+                NotesCmd::SetBody { .. } => Some("notes set-body"),
+                NotesCmd::Append { .. } => Some("notes append"),
+                NotesCmd::Move { .. } => Some("notes move"),
+                NotesCmd::Delete { .. } => Some("notes delete"),
+                NotesCmd::Merge { .. } => Some("notes merge"),
+                NotesCmd::Dedupe { apply: true, .. } => Some("notes dedupe --apply"),
+                NotesCmd::Dedupe { .. } => None,
+                NotesCmd::Replace { .. } => Some("notes replace"),
+                NotesCmd::Watch { .. }
+                | NotesCmd::Open { .. }
+                | NotesCmd::Url { .. }
+                | NotesCmd::Wordcount { .. }
+                | NotesCmd::Lint { .. }
+                | NotesCmd::Graph { .. }
+                | NotesCmd::Backlinks { .. }
+                | NotesCmd::Grep { .. }
+                | NotesCmd::Todo {
+                    cmd: TodoCmd::List { .. },
+                }
+                | NotesCmd::Trash {
+                    cmd: TrashCmd::List,
+                } => None,
+                NotesCmd::Todo {
+                    cmd: TodoCmd::Toggle { .. },
+                } => Some("notes todo toggle"),
+                NotesCmd::Trash {
+                    cmd: TrashCmd::Restore { .. },
+                } => Some("notes trash restore"),
+            },
+            Command::Quick { .. } => Some("quick"),
+            Command::Todos { .. } => None,
+            Command::Export(_) => None,
+            Command::Backup { cmd } => match cmd {
+                BackupCmd::Export(_)
+                | BackupCmd::Verify { .. }
+                | BackupCmd::Diff { .. }
+                | BackupCmd::Schedule { .. } => None,
+                BackupCmd::Restore { .. } => Some("backup restore"),
+            },
+            // Edits are blocked inside the browser instead, so read-only mode can still browse.
+            Command::Tui => None,
+            // Mutating methods are refused per call instead.
+            Command::Serve { .. } => None,
+        }
+    }
+
+    /// Whether the command checks its cancel token, so the first Ctrl-C can stop it cleanly.
+    /// Every other command keeps the default Ctrl-C, which ends the process at once.
+    #[cfg(unix)]
+    pub fn stops_on_cancel(&self) -> bool {
+        matches!(
+            self,
+            Command::Export(_)
+                | Command::Backup {
+                    cmd: BackupCmd::Export(_)
+                }
+                | Command::Notes {
+                    cmd: NotesCmd::List { .. }
+                        | NotesCmd::Watch { .. }
+                        | NotesCmd::Dedupe { apply: true, .. }
+                }
+        )
+    }
 }
 
 #[derive(Debug, Subcommand)]
@@ -107,9 +307,12 @@ pub enum AccountsCmd {
 #[derive(Debug, Subcommand)]
 pub enum FoldersCmd {
     List {
-        /// Print as a simple tree.
+        /// Print as a tree, with each folder's note count and id.
         #[arg(long)]
         tree: bool,
+        /// With `--tree`, show only this many levels.
+        #[arg(long, requires = "tree")]
+        depth: Option<usize>,
     },
     Create {
         /// Parent folder path (e.g. "Personal" or "Personal > Archive").
@@ -127,6 +330,16 @@ pub enum FoldersCmd {
         #[arg(long)]
         name: String,
     },
+    /// Move a folder, with its notes and subfolders, under another folder.
+    Move {
+        /// Folder path to move.
+        #[arg(long)]
+        folder: String,
+        /// Folder path to move it into.
+        #[arg(long)]
+        parent: String,
+    },
+    /// Delete a folder, moving its notes to Recently Deleted.
     Delete {
         /// Folder path to delete.
         #[arg(long)]
@@ -143,110 +356,665 @@ pub enum NotesCmd {
         /// Filter notes to a folder path (e.g. "Personal > Archive").
         #[arg(long)]
         folder: Option<String>,
-        /// Filter notes by title substring (case-insensitive).
-        #[arg(long)]
+        /// Filter notes by title substring (case-insensitive unless `--case-sensitive`).
+        #[arg(long, conflicts_with = "regex")]
         query: Option<String>,
+        /// Filter notes by a regular expression matched against the title (case-insensitive
+        /// unless `--case-sensitive`), e.g. `^\d{4}-\d{2}-\d{2}`.
+        #[arg(long, value_name = "PATTERN")]
+        regex: Option<String>,
+        /// Match `--query` and `--regex` case-sensitively.
+        #[arg(long)]
+        case_sensitive: bool,
         /// Limit number of rows printed (after filters).
         #[arg(long, short = 'n')]
         limit: Option<usize>,
+        /// Skip this many rows (after filters and sorting) before printing, to page through a
+        /// listing with `--limit`.
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+        /// Print `id<TAB>folder<TAB>title` lines for fuzzy finders like fzf (pipe the chosen
+        /// line to `notes show -`).
+        #[arg(long, conflicts_with = "output")]
+        picker: bool,
+        /// Output format; `alfred` prints Script Filter JSON for Alfred/Raycast workflows.
+        #[arg(long, value_enum, default_value = "table")]
+        output: ListOutput,
+        /// Sort order (default: title). Applies to every output format; `none` keeps the
+        /// backend's order, which lets `--limit` stop loading once it has enough notes.
+        #[arg(long, value_enum)]
+        sort: Option<ListSort>,
+        /// Reverse the sort order (e.g. `--sort modified --reverse` for newest first).
+        #[arg(long)]
+        reverse: bool,
+        /// Only notes modified after this date (`YYYY-MM-DD`, UTC, RFC 3339, or a time ago like
+        /// `7d`, `12h`, `2w`).
+        #[arg(long, value_name = "DATE", value_parser = parse_date)]
+        modified_after: Option<OffsetDateTime>,
+        /// Only notes modified before this date.
+        #[arg(long, value_name = "DATE", value_parser = parse_date)]
+        modified_before: Option<OffsetDateTime>,
+        /// Only notes created after this date.
+        #[arg(long, value_name = "DATE", value_parser = parse_date)]
+        created_after: Option<OffsetDateTime>,
+        /// Only notes created before this date.
+        #[arg(long, value_name = "DATE", value_parser = parse_date)]
+        created_before: Option<OffsetDateTime>,
+        /// Add a one-line snippet of each note's body, to tell apart notes with similar titles.
+        #[arg(long, conflicts_with = "picker")]
+        preview: bool,
+        /// Print one table per folder (or, with `--json`, `{folder, notes}` groups) instead of
+        /// a single flat table.
+        #[arg(long, conflicts_with = "picker")]
+        group_by_folder: bool,
+        /// Only pinned notes (needs the Notes database; other backends can't tell).
+        #[arg(long)]
+        pinned: bool,
     },
     Show {
-        /// Note id (e.g. x-coredata://...).
-        id: String,
+        /// Note id (e.g. x-coredata://...), UUID, a unique prefix of either, or a unique part of
+        /// the title; `-` reads a `notes list --picker` line from stdin.
+        #[arg(required_unless_present_any = ["from_picker_line", "ids_from"])]
+        id: Option<String>,
+        /// Show the note of a `notes list --picker` line instead of `<ID>`.
+        #[arg(long, value_name = "LINE", conflicts_with = "id")]
+        from_picker_line: Option<String>,
+        /// Show every note listed in this file, one id per line (`-` reads stdin).
+        #[arg(
+            long,
+            value_name = "PATH",
+            conflicts_with_all = ["id", "from_picker_line", "pdf", "copy", "meta", "head"]
+        )]
+        ids_from: Option<String>,
         /// Output markdown (not ANSI-rendered).
         #[arg(long)]
         markdown: bool,
         /// Print raw HTML body.
         #[arg(long)]
         html: bool,
+        /// Render the note to a PDF file instead of printing it.
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["markdown", "html"])]
+        pdf: Option<PathBuf>,
+        /// Copy the note's Markdown (with `--html`, its HTML) to the clipboard instead of
+        /// printing it.
+        #[arg(long, conflicts_with = "pdf")]
+        copy: bool,
+        /// Print only the title, folder, dates, attachment count and word count.
+        #[arg(long, conflicts_with_all = ["markdown", "html", "pdf", "copy"])]
+        meta: bool,
+        /// Print straight to the terminal instead of paging notes taller than the screen.
+        #[arg(long)]
+        no_pager: bool,
+        /// Only show the first N non-blank lines of the body.
+        #[arg(long, value_name = "N", conflicts_with_all = ["html", "pdf", "copy", "meta"])]
+        head: Option<usize>,
     },
     Create {
-        /// Folder path (e.g. "Personal > Archive").
+        /// Folder path (e.g. "Personal > Archive"). Defaults to the config file's
+        /// `default_folder`, then the account's default Notes folder.
         #[arg(long)]
-        folder: String,
+        folder: Option<String>,
+        /// Note title. Defaults to the body's first non-empty line, as in Notes.
         #[arg(long)]
-        title: String,
+        title: Option<String>,
         /// Plain text body.
-        #[arg(long, conflicts_with_all = ["body_file", "stdin"])]
+        #[arg(long, conflicts_with_all = ["body_file", "stdin", "clipboard"])]
         body: Option<String>,
         /// Read body from a file.
-        #[arg(long, value_name = "PATH", conflicts_with_all = ["body", "stdin"])]
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["body", "stdin", "clipboard"])]
         body_file: Option<String>,
         /// Read body from stdin.
-        #[arg(long, conflicts_with_all = ["body", "body_file"])]
+        #[arg(long, conflicts_with_all = ["body", "body_file", "clipboard"])]
         stdin: bool,
+        /// Read body from the clipboard.
+        #[arg(long, conflicts_with_all = ["body", "body_file", "stdin"])]
+        clipboard: bool,
         /// Treat body as Markdown (stored as HTML).
         #[arg(long, conflicts_with = "html")]
         markdown: bool,
         /// Treat body as raw HTML (stored as-is).
         #[arg(long, conflicts_with = "markdown")]
         html: bool,
+        /// Body from a Markdown template: a name in `~/.config/apple-notes/templates/` (without
+        /// `.md`) or a path. `{{date}}`, `{{title}}` and `--var` names are substituted.
+        #[arg(long, value_name = "NAME", conflicts_with_all = ["body", "body_file", "stdin", "clipboard", "html"])]
+        template: Option<String>,
+        /// Template variable (repeatable), e.g. `--var attendee=Bob`.
+        #[arg(long, value_name = "KEY=VALUE", requires = "template", value_parser = templates::parse_var)]
+        var: Vec<(String, String)>,
+        /// Attach this file to the new note (repeatable).
+        #[arg(long, value_name = "PATH")]
+        attach: Vec<PathBuf>,
+        /// Show the new note in Notes.app afterwards.
+        #[arg(long)]
+        open: bool,
     },
+    /// Rename one note, or many at once with `--query`/`--replace`.
     Rename {
-        id: String,
+        /// Note to rename (with `--title`): an id, UUID, unique prefix, or unique part of the
+        /// title.
+        #[arg(required_unless_present = "query", conflicts_with = "query")]
+        id: Option<String>,
+        /// New title for `<ID>`.
+        #[arg(long, required_unless_present = "query", conflicts_with = "query")]
+        title: Option<String>,
+        /// Regex matched against every note title; matching notes are renamed with `--replace`.
+        #[arg(long, requires = "replace")]
+        query: Option<String>,
+        /// Replacement for the first match of `--query`; `$1` / `${name}` insert capture groups.
+        #[arg(long, requires = "query", allow_hyphen_values = true)]
+        replace: Option<String>,
+        /// Only rename notes in this folder (e.g. "Personal > Archive").
+        #[arg(long, requires = "query")]
+        folder: Option<String>,
+        /// Apply a `--query` rename. Without it, only the preview is printed.
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Rename every note whose title matches a regex, e.g. to strip a date prefix. Prints the
+    /// old and new titles.
+    RenameAll {
+        /// Regex matched against every note title.
+        #[arg(long = "match", value_name = "REGEX")]
+        pattern: String,
+        /// New title template for the first match; `$1` / `${name}` insert capture groups.
+        #[arg(long, value_name = "TEMPLATE", allow_hyphen_values = true)]
+        to: String,
+        /// Only rename notes in this folder (e.g. "Personal > Archive").
         #[arg(long)]
-        title: String,
+        folder: Option<String>,
+        /// Only print the renames, without applying them (set by the global `--dry-run`).
+        #[arg(skip)]
+        dry_run: bool,
     },
     SetBody {
         id: String,
-        #[arg(long, conflicts_with_all = ["body_file", "stdin"])]
+        #[arg(long, conflicts_with_all = ["body_file", "stdin", "clipboard"])]
         body: Option<String>,
-        #[arg(long, value_name = "PATH", conflicts_with_all = ["body", "stdin"])]
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["body", "stdin", "clipboard"])]
         body_file: Option<String>,
-        #[arg(long, conflicts_with_all = ["body", "body_file"])]
+        #[arg(long, conflicts_with_all = ["body", "body_file", "clipboard"])]
         stdin: bool,
+        /// Read body from the clipboard.
+        #[arg(long, conflicts_with_all = ["body", "body_file", "stdin"])]
+        clipboard: bool,
         #[arg(long, conflicts_with = "html")]
         markdown: bool,
         /// Treat body as raw HTML (stored as-is).
         #[arg(long, conflicts_with = "markdown")]
         html: bool,
+        /// Show the note in Notes.app afterwards.
+        #[arg(long)]
+        open: bool,
     },
     Append {
         id: String,
-        #[arg(long, conflicts_with_all = ["body_file", "stdin"])]
+        #[arg(long, conflicts_with_all = ["body_file", "stdin", "clipboard"])]
         body: Option<String>,
-        #[arg(long, value_name = "PATH", conflicts_with_all = ["body", "stdin"])]
+        /// Append a checklist item (a checkbox in Notes) instead of a body; repeatable.
+        #[arg(
+            long,
+            value_name = "TEXT",
+            conflicts_with_all = ["body", "body_file", "stdin", "clipboard", "markdown", "html"]
+        )]
+        todo: Vec<String>,
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["body", "stdin", "clipboard"])]
         body_file: Option<String>,
-        #[arg(long, conflicts_with_all = ["body", "body_file"])]
+        #[arg(long, conflicts_with_all = ["body", "body_file", "clipboard"])]
         stdin: bool,
+        /// Read body from the clipboard.
+        #[arg(long, conflicts_with_all = ["body", "body_file", "stdin"])]
+        clipboard: bool,
         #[arg(long, conflicts_with = "html")]
         markdown: bool,
         /// Treat body as raw HTML (stored as-is).
         #[arg(long, conflicts_with = "markdown")]
         html: bool,
+        /// Show the note in Notes.app afterwards.
+        #[arg(long)]
+        open: bool,
     },
     Move {
-        id: String,
+        #[arg(required_unless_present = "ids_from")]
+        id: Option<String>,
+        /// Move every note listed in this file, one id per line (`-` reads stdin).
+        #[arg(long, value_name = "PATH", conflicts_with = "id")]
+        ids_from: Option<String>,
+        /// Target folder path. Defaults to the config file's `default_folder`.
         #[arg(long)]
-        folder: String,
+        folder: Option<String>,
     },
+    /// Move a note to Recently Deleted (`notes trash restore` brings it back).
     Delete {
-        id: String,
+        #[arg(required_unless_present = "ids_from")]
+        id: Option<String>,
+        /// Delete every note listed in this file, one id per line (`-` reads stdin).
+        #[arg(long, value_name = "PATH", conflicts_with = "id")]
+        ids_from: Option<String>,
         /// Required to actually delete.
         #[arg(long)]
         yes: bool,
+        /// Also delete the note from Recently Deleted, so it can't be restored.
+        #[arg(long)]
+        permanent: bool,
+    },
+    /// Combine notes into one, each under a heading with its title: append them to an existing
+    /// note (`--into`) or to a new one (`--new-title`).
+    #[command(group(clap::ArgGroup::new("target").required(true).args(["into", "new_title"])))]
+    Merge {
+        /// Notes to merge, in order: ids, UUIDs, unique prefixes, or unique parts of titles.
+        #[arg(required = true)]
+        ids: Vec<String>,
+        /// Append the notes to this note.
+        #[arg(long)]
+        into: Option<String>,
+        /// Create a new note with this title, in the first note's folder.
+        #[arg(long)]
+        new_title: Option<String>,
+        /// Move the merged notes to Recently Deleted afterwards (needs `--yes`). Refused if any
+        /// of them has attachments, which aren't merged.
+        #[arg(long)]
+        delete_sources: bool,
+        /// Required with `--delete-sources`.
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Show the note in Notes.app, for editing with the full editor.
+    Open { id: String },
+    /// Print a `notes://` link that opens the note in Notes.app (needs the Notes database).
+    Url { id: String },
+    /// Count the words and characters of one note, a folder, or the whole account.
+    #[command(group(clap::ArgGroup::new("scope").required(true).args(["id", "folder", "all"])))]
+    Wordcount {
+        /// Note id, UUID, a unique prefix of either, or a unique part of the title.
+        id: Option<String>,
+        /// Every note in this folder path (e.g. "Personal > Archive").
+        #[arg(long)]
+        folder: Option<String>,
+        /// Every note in the account.
+        #[arg(long)]
+        all: bool,
+    },
+    /// Find notes with the same title and/or body. Prints the duplicates; `--apply` deletes all
+    /// but the newest of each group. Locked notes and notes without a modification date are left
+    /// out.
+    Dedupe {
+        /// What duplicates must share: `both` (title and body), `title`, or `content` (body).
+        #[arg(long, value_enum, default_value = "both")]
+        by: DedupeBy,
+        /// Only look in this folder path (e.g. "Personal > Archive").
+        #[arg(long)]
+        folder: Option<String>,
+        /// Delete every duplicate but the most recently modified one (asks first at a terminal).
+        #[arg(long)]
+        apply: bool,
+        /// With `--apply`, delete without asking.
+        #[arg(long, requires = "apply")]
+        yes: bool,
+        /// With `--by title`, also delete duplicates whose body differs from the kept note's.
+        #[arg(long)]
+        any_body: bool,
+    },
+    /// Report empty notes, notes outside any folder, titles repeated as the first line, and
+    /// links to notes that don't exist.
+    Lint {
+        /// Only check notes in this folder path (e.g. "Personal > Archive").
+        #[arg(long)]
+        folder: Option<String>,
+    },
+    /// Print how notes link to each other (`applenotes:note/…` links), for Graphviz or other
+    /// tools. Needs note UUIDs, so the Notes database.
+    Graph {
+        #[arg(long, value_enum, default_value = "dot")]
+        format: GraphFormat,
+        /// Only notes in this folder path (e.g. "Personal > Archive").
+        #[arg(long)]
+        folder: Option<String>,
+    },
+    /// List the notes that link to a note (with `applenotes:note/…` links). Needs note UUIDs,
+    /// so the Notes database.
+    Backlinks {
+        /// Note id, UUID, a unique prefix of either, or a unique part of the title.
+        id: String,
+    },
+    /// Search note bodies for a regular expression and print the matching lines, like grep.
+    Grep {
+        /// Regular expression to look for, line by line.
+        pattern: String,
+        /// Lines of context to print around each match.
+        #[arg(short = 'C', long, value_name = "NUM", default_value_t = 0)]
+        context: usize,
+        /// Match case-insensitively.
+        #[arg(short = 'i', long)]
+        ignore_case: bool,
+        /// Print only the ids of the notes that match, one per line.
+        #[arg(short = 'l', long)]
+        files_with_matches: bool,
+        /// Only search notes in this folder path (e.g. "Personal > Archive").
+        #[arg(long)]
+        folder: Option<String>,
+    },
+    /// Find and replace in note bodies: a regular expression applied to each note's Markdown,
+    /// which is then stored as HTML again. Prints a diff per changed note.
+    Replace {
+        /// Regular expression to replace (every match).
+        #[arg(long)]
+        query: String,
+        /// Replacement text; `$1` / `${name}` insert capture groups.
+        #[arg(long, allow_hyphen_values = true)]
+        with: String,
+        /// Only change notes in this folder path (e.g. "Personal > Archive").
+        #[arg(long)]
+        folder: Option<String>,
+        /// Only print the diffs, without changing any note (set by the global `--dry-run`).
+        #[arg(skip)]
+        dry_run: bool,
+    },
+    /// A note's checklist items.
+    Todo {
+        #[command(subcommand)]
+        cmd: TodoCmd,
+    },
+    /// Notes in Recently Deleted, where deleted notes stay for 30 days.
+    Trash {
+        #[command(subcommand)]
+        cmd: TrashCmd,
+    },
+    /// Print one JSON line per note created, modified or deleted as changes happen (needs the
+    /// Notes database). Stop with Ctrl-C.
+    Watch {
+        /// How often to check the database for changes, in seconds.
+        #[arg(long, value_name = "SECS", default_value_t = 2)]
+        interval: u64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ListSort {
+    /// The order the backend lists notes in.
+    #[value(name = "none")]
+    Unsorted,
+    Title,
+    Created,
+    Modified,
+    /// By folder path, then title.
+    Folder,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GraphFormat {
+    /// Graphviz DOT (`dot -Tsvg`).
+    Dot,
+    /// `{"nodes": [{id, title, uuid, links}]}`, `links` being the ids a note links to.
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ListOutput {
+    Table,
+    /// Same as `--json`.
+    Json,
+    /// Alfred Script Filter JSON (`items` with `title`, `subtitle` and `arg`), also read by
+    /// Raycast script commands.
+    Alfred,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TodoCmd {
+    /// List the note's checklist items with their numbers.
+    List {
+        /// Note id, UUID, a unique prefix of either, or a unique part of the title.
+        id: String,
+    },
+    /// Check an unchecked item, or uncheck a checked one.
+    Toggle {
+        /// Note id, UUID, a unique prefix of either, or a unique part of the title.
+        id: String,
+        /// The item's number (see `notes todo list`) or a unique part of its text.
+        #[arg(long)]
+        item: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TrashCmd {
+    /// List the notes in Recently Deleted, most recently deleted first.
+    List,
+    /// Move a note out of Recently Deleted into a folder.
+    Restore {
+        /// Id, UUID, a unique prefix of either, or a unique part of the title of a note in
+        /// Recently Deleted.
+        id: String,
+        /// Folder path to restore into. Defaults to the config file's `default_folder`, then
+        /// the account's "Notes" folder.
+        #[arg(long)]
+        folder: Option<String>,
     },
 }
 
 #[derive(Debug, Subcommand)]
 pub enum BackupCmd {
-    Export {
-        /// Output directory. Created if it doesn't exist.
+    /// Deprecated: use `apple-notes export ...`.
+    #[command(hide = true)]
+    Export(Box<ExportArgs>),
+    /// Compare an export against the live account and report missing, extra, and stale notes.
+    Verify {
+        /// Export directory (or snapshot root) to check.
+        #[arg(long)]
+        dir: PathBuf,
+        /// Also compare `contents.md` hashes against the live note contents (slower).
+        #[arg(long)]
+        contents: bool,
+    },
+    /// Show notes added, removed, or modified between two exports.
+    Diff {
+        /// Older export directory (or snapshot).
+        a: PathBuf,
+        /// Newer export directory (or snapshot).
+        b: PathBuf,
+        /// Also print unified diffs of `contents.md` for modified notes.
+        #[arg(long)]
+        patch: bool,
+    },
+    /// Recreate a note from an exported note directory (`metadata.json` + contents).
+    Restore {
+        /// Exported note directory (contains `metadata.json`).
+        note_dir: PathBuf,
+        /// Target folder path in the note's original account (default: the note's original
+        /// folder). With `--replace`, the existing note moves there.
+        #[arg(long)]
+        folder: Option<String>,
+        /// Overwrite the existing note with the same id instead of creating a copy.
         #[arg(long)]
-        out: String,
-        /// Number of export worker threads (render + IO). Note fetching is serialized for safety.
-        #[arg(long, default_value_t = 4)]
-        jobs: usize,
-        /// Also write `contents.html` (raw HTML). This is slower and may require Notes.app permissions.
-        #[arg(long, conflicts_with_all = ["no_html", "html_only"])]
-        with_html: bool,
-        /// Write `contents.html` only for specific note ids (repeatable).
-        #[arg(long, value_name = "ID", conflicts_with_all = ["no_html", "with_html"])]
-        html_only: Vec<String>,
-        /// Do not write `contents.html` (raw HTML). (Deprecated; default is no HTML.)
-        #[arg(long, hide = true)]
-        no_html: bool,
+        replace: bool,
     },
+    /// Run `apple-notes export` periodically with a launchd user agent.
+    Schedule {
+        #[command(subcommand)]
+        cmd: ScheduleCmd,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ScheduleCmd {
+    /// Write and load a launch agent that exports to `--out` on a schedule (replacing any
+    /// existing one).
+    Install {
+        /// Export directory passed to `apple-notes export --out`.
+        #[arg(long)]
+        out: PathBuf,
+        /// How often to export.
+        #[arg(long, default_value = "daily")]
+        interval: schedule::Interval,
+        /// Time of day to run at (`HH:MM`, 24-hour; only the minute matters for `hourly`).
+        #[arg(long, default_value = "03:00", value_parser = schedule::parse_time_of_day)]
+        at: (u8, u8),
+        /// Extra `export` arguments, after `--` (e.g. `-- --snapshot --format html`).
+        #[arg(last = true)]
+        export_args: Vec<String>,
+    },
+    /// Unload and delete the launch agent.
+    Remove,
+    /// Show whether the launch agent is installed and loaded, and how its last run went.
+    Status,
+}
+
+impl Args {
+    /// Rejects flag combinations clap can't see, involving global flags, as usage errors.
+    pub fn check_usage(&self) -> Result<(), clap::Error> {
+        if let Command::Export(export)
+        | Command::Backup {
+            cmd: BackupCmd::Export(export),
+        } = &self.cmd
+            && export.single_file.is_some()
+            && self.backend == Some(Backend::Db)
+        {
+            return Err(<Args as clap::CommandFactory>::command().error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "--single-file reads notes through Notes.app, so it can't be used with \
+                 --backend db",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Fills in settings the command line left out from `config` (the config file, with
+    /// environment overrides already applied).
+    pub fn apply_config(&mut self, config: &Config) {
+        self.account = self.account.take().or_else(|| config.account.clone());
+        self.backend = self.backend.or(config.backend);
+        match &mut self.cmd {
+            Command::Export(export)
+            | Command::Backup {
+                cmd: BackupCmd::Export(export),
+            } => {
+                export.out = export.out.take().or_else(|| {
+                    let dir = config.export_dir.as_deref()?;
+                    Some(
+                        crate::config::expand_home(dir)
+                            .to_string_lossy()
+                            .into_owned(),
+                    )
+                });
+                export.jobs = export.jobs.or(config.jobs);
+            }
+            // `export --folder` stays a filter: no folder means every folder.
+            Command::Notes {
+                cmd:
+                    NotesCmd::Create { folder, .. }
+                    | NotesCmd::Move { folder, .. }
+                    | NotesCmd::Trash {
+                        cmd: TrashCmd::Restore { folder, .. },
+                    },
+            }
+            | Command::Quick { folder, .. } => {
+                *folder = folder.take().or_else(|| config.default_folder.clone());
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ExportArgs {
+    /// Output directory. Created if it doesn't exist. Defaults to the config file's `export_dir`.
+    #[arg(long)]
+    pub out: Option<String>,
+    /// Stream the export into a `.tar.zst` or `.zip` archive instead of a directory (`-` writes
+    /// a `.tar.zst` stream to stdout).
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["out", "single_file", "snapshot"]
+    )]
+    pub archive: Option<PathBuf>,
+    /// Encrypt the `--archive` with age to every `--recipient`, so it can live on untrusted
+    /// storage.
+    #[arg(long, requires_all = ["archive", "recipient"])]
+    pub encrypt: bool,
+    /// age public key (`age1…`) that can decrypt an `--encrypt`ed archive (repeatable).
+    #[arg(long, value_name = "AGE_KEY", requires = "encrypt")]
+    pub recipient: Vec<String>,
+    /// Write every note into one Markdown file instead, with a table of contents and a heading
+    /// per folder.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["out", "snapshot", "with_html", "html_only", "format", "jobs"]
+    )]
+    pub single_file: Option<PathBuf>,
+    /// Number of export worker threads (decode/render + IO) [default: 4].
+    #[arg(long)]
+    pub jobs: Option<usize>,
+    /// Also write `contents.html` (raw HTML). This is slower and may require Notes.app permissions.
+    #[arg(long, conflicts_with_all = ["no_html", "html_only"])]
+    pub with_html: bool,
+    /// Write `contents.html` only for specific note ids (repeatable).
+    #[arg(long, value_name = "ID", conflicts_with_all = ["no_html", "with_html"])]
+    pub html_only: Vec<String>,
+    /// Do not write `contents.html` (raw HTML). (Deprecated; default is no HTML.)
+    #[arg(long, hide = true)]
+    pub no_html: bool,
+    /// Write into a new dated directory under `--out`, hardlinking files unchanged since the
+    /// previous snapshot and pointing `latest` at the result.
+    #[arg(long)]
+    pub snapshot: bool,
+    /// Output format. `html`/`pdf` also write a standalone `note.html`/`note.pdf` per note;
+    /// `site` adds an `index.html` with search on top of the HTML pages; `epub` writes one
+    /// `.epub` book to `--out`.
+    #[arg(long, value_enum, default_value_t = backup::ExportFormat::Md)]
+    pub format: backup::ExportFormat,
+    /// How `--format html` pages embed images: inline `data:` URIs, or files under `attachments/`.
+    #[arg(long, value_enum, default_value_t = html_export::AttachmentMode::Inline)]
+    pub attachments: html_export::AttachmentMode,
+    /// How `--format epub` splits the book into chapters.
+    #[arg(long, value_enum, default_value_t = epub::Chapters::Note)]
+    pub epub_chapters: epub::Chapters,
+    /// Export every account, each under `<out>/<Account Name>/` (or as `<out>/<Account
+    /// Name>.epub`).
+    #[arg(long, conflicts_with_all = ["single_file", "folder"])]
+    pub all_accounts: bool,
+    /// Only export this folder (e.g. "Personal > Archive") and its subfolders.
+    #[arg(long, value_name = "PATH")]
+    pub folder: Option<String>,
+    /// Only export notes whose title or contents contain this text (case-insensitive).
+    #[arg(long, value_name = "TEXT")]
+    pub query: Option<String>,
+    /// Only export notes modified after this date (`YYYY-MM-DD`, UTC, RFC 3339, or a time ago
+    /// like `7d`).
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    pub modified_after: Option<OffsetDateTime>,
+    /// Only export notes created before this date (`YYYY-MM-DD`, UTC, RFC 3339, or a time ago
+    /// like `7d`).
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    pub created_before: Option<OffsetDateTime>,
+    /// Check that Notes.app can be automated before starting (the default), so a missing
+    /// Automation permission fails fast instead of at the first note.
+    #[arg(long, overrides_with = "no_preflight", hide = true)]
+    pub preflight: bool,
+    /// Skip the Notes.app Automation pre-flight check.
+    #[arg(long)]
+    pub no_preflight: bool,
+    /// Run a shell command when the export ends (successfully or not). It gets
+    /// `APPLE_NOTES_EXPORT_STATUS`, `_EXPORTED`, `_FAILURES`, `_DURATION_MS`, `_OUT` and, on
+    /// failure, `_ERROR` in its environment.
+    #[arg(long, value_name = "COMMAND")]
+    pub on_complete: Option<String>,
+    /// Append a line to this NDJSON file for every note added, updated or removed since the
+    /// previous export that used the same journal.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["single_file", "folder", "query", "modified_after", "created_before"]
+    )]
+    pub journal: Option<PathBuf>,
+    /// Keep running and re-export whenever the Notes database changes, keeping a live mirror in
+    /// `--out` (unchanged notes aren't rewritten; deleted ones are removed). Stop with Ctrl-C.
+    #[arg(long, conflicts_with_all = ["single_file", "archive", "snapshot"])]
+    pub watch: bool,
+    /// How often `--watch` checks the database for changes.
+    #[arg(long, value_name = "SECS", default_value_t = 2, requires = "watch")]
+    pub watch_interval: u64,
 }
 
 fn export_html_mode(with_html: bool, html_only: Vec<String>) -> backup::HtmlExport {
@@ -259,11 +1027,36 @@ fn export_html_mode(with_html: bool, html_only: Vec<String>) -> backup::HtmlExpo
     backup::HtmlExport::None
 }
 
-pub fn dispatch(args: Args, backend: Box<dyn NotesBackend>) -> anyhow::Result<()> {
+/// How long `notes list` buffers before it starts printing rows as they stream in.
+const PROGRESSIVE_LISTING_AFTER: Duration = Duration::from_millis(500);
+
+/// How long a `notes list --preview` snippet may get, in characters.
+const PREVIEW_CHARS: usize = 60;
+
+/// Runs the command. `reads_db` says whether `backend` opened the Notes database, so reads may
+/// go to it directly.
+pub fn dispatch(
+    args: Args,
+    config: Config,
+    backend: Box<dyn NotesBackend>,
+    reads_db: bool,
+) -> anyhow::Result<()> {
     let json = args.json;
-    let account = args.account.clone();
-    let backend_mode = args.backend;
+    let account = args
+        .account
+        .clone()
+        .unwrap_or_else(|| DEFAULT_ACCOUNT.to_string());
+    let backend_mode = args.backend.unwrap_or(Backend::Auto);
     let fixture = args.fixture.clone();
+    let read_only = args.read_only || config.read_only;
+    let mut cancel = CancelToken::new();
+    if let Some(secs) = args.timeout {
+        cancel = cancel.with_timeout(Duration::from_secs(secs));
+    }
+    #[cfg(unix)]
+    if args.cmd.stops_on_cancel() {
+        crate::cancel::cancel_on_interrupt(&cancel);
+    }
     let cmd = args.cmd;
 
     match cmd {
@@ -295,16 +1088,27 @@ pub fn dispatch(args: Args, backend: Box<dyn NotesBackend>) -> anyhow::Result<()
             }
         },
         Command::Folders { cmd } => match cmd {
-            FoldersCmd::List { tree } => {
+            FoldersCmd::List { tree, depth } => {
                 let spinner = progress::spinner("Loading folders…");
                 let folders = backend.list_folders(&account)?;
+                let notes = match tree && !json {
+                    true => backend.list_notes(&account)?,
+                    false => Vec::new(),
+                };
                 if let Some(spinner) = spinner {
                     spinner.finish_and_clear();
                 }
                 if json {
                     print_json(&folders)
                 } else if tree {
-                    print_folder_tree(&folders)
+                    let mut counts = HashMap::new();
+                    for n in &notes {
+                        *counts.entry(n.folder_id.as_str()).or_insert(0) += 1;
+                    }
+                    for line in folder_tree_lines(&folders, &counts, depth) {
+                        println!("{line}");
+                    }
+                    Ok(())
                 } else {
                     print_folders_table(&folders)
                 }
@@ -324,161 +1128,1037 @@ pub fn dispatch(args: Args, backend: Box<dyn NotesBackend>) -> anyhow::Result<()
                 backend.rename_folder(&account, &folder_path, &name)?;
                 Ok(())
             }
-            FoldersCmd::Delete { folder, yes } => {
-                if !yes {
-                    return Err(anyhow!("refusing to delete without --yes"));
+            FoldersCmd::Move { folder, parent } => {
+                let folder_path = split_folder_path(&folder)?;
+                let parent_path = split_folder_path(&parent)?;
+                let index = backup::FolderIndex::new(&backend.list_folders(&account)?)?;
+                let find = |path: &[String]| {
+                    index.find(path).map(|f| f.id.clone()).ok_or_else(|| {
+                        NotesError::NotFound(format!("folder not found: {}", path.join(" > ")))
+                    })
+                };
+                let (folder_id, parent_id) = (find(&folder_path)?, find(&parent_path)?);
+                if index.is_within(&parent_id, &folder_id) {
+                    return Err(anyhow!(
+                        "can't move {} into its own subtree ({})",
+                        folder_path.join(" > "),
+                        parent_path.join(" > ")
+                    ));
+                }
+                backend.move_folder(&account, &folder_path, &parent_path)?;
+                let mut moved = parent_path;
+                moved.extend(folder_path.last().cloned());
+                if json {
+                    print_json(&serde_json::json!({ "folder": moved.join(" > ") }))
+                } else {
+                    println!("Moved to {}.", moved.join(" > "));
+                    Ok(())
                 }
+            }
+            FoldersCmd::Delete { folder, yes } => {
                 let folder_path = split_folder_path(&folder)?;
+                // Notes in subfolders go to Recently Deleted too.
+                let subtree: HashSet<String> = backend
+                    .list_folders(&account)?
+                    .into_iter()
+                    .filter(|f| f.path.starts_with(&folder_path))
+                    .map(|f| f.id)
+                    .collect();
+                if subtree.is_empty() {
+                    return Err(NotesError::NotFound(format!(
+                        "folder not found: {}",
+                        folder_path.join(" > ")
+                    ))
+                    .into());
+                }
+                let subfolders = subtree.len() - 1;
+                let notes = backend
+                    .list_notes(&account)?
+                    .iter()
+                    .filter(|n| subtree.contains(&n.folder_id))
+                    .count();
+                confirm_delete(yes, || {
+                    Ok(format!(
+                        "This deletes the folder {} ({notes} notes, {subfolders} subfolders); \
+                         its notes move to Recently Deleted.",
+                        folder_path.join(" > ")
+                    ))
+                })?;
                 backend.delete_folder(&account, &folder_path)?;
-                Ok(())
+                if json {
+                    print_json(&serde_json::json!({
+                        "folder": folder_path.join(" > "),
+                        "notes_moved_to_recently_deleted": notes,
+                    }))
+                } else {
+                    println!(
+                        "Deleted {}; {notes} notes moved to Recently Deleted.",
+                        folder_path.join(" > ")
+                    );
+                    Ok(())
+                }
             }
         },
-        Command::Notes { cmd } => dispatch_notes(json, &account, backend, cmd),
-        Command::Export {
-            out,
-            jobs,
-            with_html,
-            html_only,
-            no_html,
-        } => {
-            if fixture.is_some() {
-                let html = if no_html {
-                    backup::HtmlExport::None
-                } else {
-                    export_html_mode(with_html, html_only)
-                };
-                return backup::export_all(&*backend, &account, out, jobs, html);
+        Command::Notes { cmd } => {
+            dispatch_notes(json, &account, &config, &cancel, reads_db, backend, cmd)
+        }
+        Command::Quick { text, folder } => {
+            let text = match text {
+                Some(text) if text != "-" => text,
+                _ => read_body(None, None, true, false)?,
+            };
+            let folder_path = target_folder(folder.as_deref())?;
+            let title = quick_title(&text, &local_timestamp()?)?;
+            let spinner = progress::spinner("Capturing note…");
+            let id = backend.create_note_html(
+                &account,
+                &folder_path,
+                &title,
+                &render::text_to_html(text.trim_end()),
+            )?;
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
             }
-            let html = if no_html {
-                backup::HtmlExport::None
+            if json {
+                print_json(&serde_json::json!({ "id": id }))
             } else {
-                export_html_mode(with_html, html_only)
+                println!("{id}");
+                Ok(())
+            }
+        }
+        Command::Todos { folder, open_only } => {
+            let spinner = progress::spinner("Loading notes…");
+            let mut notes = match folder {
+                Some(folder) => {
+                    backend.list_notes_in_folder(&account, &split_folder_path(&folder)?)?
+                }
+                None => backend.list_notes(&account)?,
             };
-            match backend_mode {
-                Backend::Osascript => backup::export_all(&*backend, &account, out, jobs, html),
-                Backend::Db => backup::export_all_db(&account, out, jobs, html),
-                Backend::Auto => backup::export_all_db(&account, out.clone(), jobs, html.clone())
-                    .or_else(|_| backup::export_all(&*backend, &account, out, jobs, html)),
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            sort_notes(&mut notes, ListSort::Title, false, &|_| String::new());
+            let bodies = note_bodies(&*backend, reads_db, &notes, "Reading checklists…", false)?;
+            let todos: Vec<(&NoteSummary, todo::TodoItem)> = notes
+                .iter()
+                .flat_map(|n| {
+                    let items = bodies
+                        .get(&n.id)
+                        .map(|md| todo::markdown_items(md))
+                        .unwrap_or_default();
+                    items.into_iter().map(move |item| (n, item))
+                })
+                .filter(|(_, item)| !(open_only && item.checked))
+                .collect();
+            if json {
+                let todos: Vec<_> = todos
+                    .iter()
+                    .map(|(n, item)| {
+                        serde_json::json!({
+                            "id": n.id,
+                            "title": n.title,
+                            "index": item.index,
+                            "text": item.text,
+                            "checked": item.checked,
+                        })
+                    })
+                    .collect();
+                return print_json(&todos);
+            }
+            struct TodoRow<'a>(&'a NoteSummary, &'a todo::TodoItem);
+            impl tables::TableRow for TodoRow<'_> {
+                const HEADERS: &'static [&'static str] = &["Done", "Task", "Note", "#", "Id"];
+                fn cells(&self) -> Vec<Cell> {
+                    vec![
+                        Cell::new(if self.1.checked { "x" } else { "" }),
+                        Cell::new(self.1.text.as_str()),
+                        Cell::new(self.0.title.as_str()),
+                        Cell::new(self.1.index),
+                        Cell::new(tables::shorten_id_for_table(&self.0.id)),
+                    ]
+                }
             }
+            tables::render_table(todos.iter().map(|(n, item)| TodoRow(n, item)).collect());
+            Ok(())
+        }
+        Command::Tui => tui::run(&*backend, &account, read_only),
+        Command::Serve {
+            http: Some(addr),
+            token,
+            ..
+        } => {
+            let token = token.or_else(|| std::env::var("APPLE_NOTES_SERVE_TOKEN").ok());
+            http::serve(&*backend, &account, read_only, addr, token)
         }
+        Command::Serve { .. } => rpc::serve(&*backend, read_only, io::stdin().lock(), io::stdout()),
+        Command::Export(export) => run_export(
+            json,
+            &account,
+            backend_mode,
+            fixture,
+            cancel,
+            backend,
+            *export,
+        ),
         Command::Backup { cmd } => match cmd {
-            BackupCmd::Export {
-                out,
-                jobs,
-                with_html,
-                html_only,
-                no_html,
-            } => {
-                if fixture.is_some() {
-                    let html = if no_html {
-                        backup::HtmlExport::None
-                    } else {
-                        export_html_mode(with_html, html_only)
-                    };
-                    return backup::export_all(&*backend, &account, out, jobs, html);
+            BackupCmd::Export(export) => run_export(
+                json,
+                &account,
+                backend_mode,
+                fixture,
+                cancel,
+                backend,
+                *export,
+            ),
+            BackupCmd::Verify { dir, contents } => {
+                let exported = backup::scan_export(&dir)?;
+                let live = if fixture.is_some() {
+                    backup::live_notes(&*backend, &account, contents)?
+                } else {
+                    match backend_mode {
+                        Backend::Osascript => backup::live_notes(&*backend, &account, contents)?,
+                        Backend::Db => backup::live_notes_db(&account, contents)?,
+                        Backend::Auto => backup::live_notes_db(&account, contents)
+                            .or_else(|_| backup::live_notes(&*backend, &account, contents))?,
+                    }
+                };
+                let report = verify::compare(&exported, &live)?;
+                if json {
+                    print_json(&report)?;
+                } else {
+                    print_verify_report(&report);
+                }
+                if report.is_ok() {
+                    Ok(())
+                } else {
+                    Err(anyhow!(
+                        "backup does not match the live account ({} missing, {} extra, {} stale)",
+                        report.missing.len(),
+                        report.extra.len(),
+                        report.stale.len()
+                    ))
                 }
-                let html = if no_html {
-                    backup::HtmlExport::None
+            }
+            BackupCmd::Diff { a, b, patch } => {
+                let report = diff::diff_exports(
+                    &backup::scan_export(&a)?,
+                    &backup::scan_export(&b)?,
+                    patch,
+                )?;
+                if json {
+                    print_json(&report)
                 } else {
-                    export_html_mode(with_html, html_only)
+                    print_diff_report(&report);
+                    Ok(())
+                }
+            }
+            BackupCmd::Restore {
+                note_dir,
+                folder,
+                replace,
+            } => {
+                let (metadata, body_html) = backup::read_exported_note(&note_dir)?;
+                // The note goes back to the account it was exported from.
+                let account = metadata.account.as_str();
+                let existing = match replace {
+                    true => match resolve_note(&*backend, account, &metadata.id)
+                        .and_then(|id| backend.get_note(&id))
+                    {
+                        Ok(note) => Some(note.id),
+                        Err(NotesError::NotFound(_)) => None,
+                        Err(e) => return Err(e.into()),
+                    },
+                    false => None,
                 };
-                match backend_mode {
-                    Backend::Osascript => backup::export_all(&*backend, &account, out, jobs, html),
-                    Backend::Db => backup::export_all_db(&account, out, jobs, html),
-                    Backend::Auto => {
-                        backup::export_all_db(&account, out.clone(), jobs, html.clone())
-                            .or_else(|_| backup::export_all(&*backend, &account, out, jobs, html))
+                let folder_path = folder.map(|f| split_folder_path(&f)).transpose()?;
+                let spinner = progress::spinner("Restoring note…");
+                let id = if let Some(id) = existing {
+                    backend.set_note_body_html(&id, &body_html)?;
+                    backend.set_note_title(&id, &metadata.title)?;
+                    if let Some(folder_path) = &folder_path {
+                        backend.move_note(&id, account, folder_path)?;
                     }
+                    id
+                } else {
+                    let folder_path = folder_path.unwrap_or_else(|| metadata.folder_path.clone());
+                    backend.create_note_html(account, &folder_path, &metadata.title, &body_html)?
+                };
+                if let Some(spinner) = spinner {
+                    spinner.finish_and_clear();
+                }
+                if json {
+                    print_json(&serde_json::json!({ "id": id }))
+                } else {
+                    println!("{id}");
+                    Ok(())
                 }
             }
+            BackupCmd::Schedule { cmd } => dispatch_schedule(json, &account, cmd),
         },
     }
 }
 
-fn dispatch_notes(
-    json: bool,
-    account: &str,
-    backend: Box<dyn NotesBackend>,
-    cmd: NotesCmd,
-) -> anyhow::Result<()> {
+fn dispatch_schedule(json: bool, account: &str, cmd: ScheduleCmd) -> anyhow::Result<()> {
     match cmd {
-        NotesCmd::List {
-            folder,
-            query,
-            limit,
+        ScheduleCmd::Install {
+            out,
+            interval,
+            at: (hour, minute),
+            export_args,
         } => {
-            let (mut notes, folder_hint, folder_index) = if let Some(folder) = folder {
-                let folder_path = split_folder_path(&folder)?;
-                let spinner = progress::spinner("Loading notes… 0 loaded");
-                let mut notes = Vec::new();
-                let mut loaded = 0usize;
-                backend.stream_note_summaries(account, Some(&folder_path), &mut |n| {
-                    loaded += 1;
-                    if let Some(spinner) = &spinner
-                        && (loaded == 1 || loaded.is_multiple_of(25))
-                    {
-                        spinner.set_message(format!("Loading notes… {loaded} loaded"));
+            // launchd runs jobs from `/`, so both paths have to be absolute.
+            let out = std::path::absolute(&out).with_context(|| format!("resolve {out:?}"))?;
+            let exe = std::env::current_exe().context("locate the apple-notes binary")?;
+            let mut program = vec![
+                exe.to_string_lossy().to_string(),
+                "--account".to_string(),
+                account.to_string(),
+                "export".to_string(),
+                "--out".to_string(),
+                out.to_string_lossy().to_string(),
+            ];
+            program.extend(export_args);
+            let when = schedule::When {
+                interval,
+                hour,
+                minute,
+            };
+            let plist = schedule::install(&program, when)?;
+            if json {
+                print_json(&serde_json::json!({
+                    "plist": plist,
+                    "out": out,
+                    "interval": format!("{interval:?}").to_lowercase(),
+                    "at": format!("{hour:02}:{minute:02}"),
+                    "command": program,
+                }))
+            } else {
+                println!("{}", plist.display());
+                Ok(())
+            }
+        }
+        ScheduleCmd::Remove => {
+            let removed = schedule::remove()?;
+            if json {
+                print_json(&serde_json::json!({ "removed": removed }))
+            } else {
+                if removed {
+                    println!("Removed {}", schedule::plist_path()?.display());
+                } else {
+                    println!("No scheduled backup installed");
+                }
+                Ok(())
+            }
+        }
+        ScheduleCmd::Status => {
+            let status = schedule::status()?;
+            if json {
+                return print_json(&status);
+            }
+            let yes_no = |b: bool| if b { "yes" } else { "no" };
+            println!(
+                "Installed: {} ({})",
+                yes_no(status.installed),
+                status.plist.display()
+            );
+            println!("Loaded: {}", yes_no(status.loaded));
+            match status.last_exit_status {
+                Some(code) => println!("Last exit status: {code}"),
+                None => println!("Last exit status: -"),
+            }
+            println!("Log: {}", status.log.display());
+            Ok(())
+        }
+    }
+}
+
+fn run_export(
+    json: bool,
+    account: &str,
+    backend_mode: Backend,
+    fixture: Option<PathBuf>,
+    cancel: CancelToken,
+    backend: Box<dyn NotesBackend>,
+    mut args: ExportArgs,
+) -> anyhow::Result<()> {
+    let Some(on_complete) = args.on_complete.take() else {
+        return export_notes(json, account, backend_mode, fixture, cancel, backend, args)
+            .map(|_| ());
+    };
+    let out = args
+        .single_file
+        .clone()
+        .or_else(|| args.archive.clone())
+        .or_else(|| args.out.as_ref().map(PathBuf::from))
+        .unwrap_or_default();
+    let started = Instant::now();
+    let result = export_notes(json, account, backend_mode, fixture, cancel, backend, args);
+    let hook = hooks::run_on_complete(&on_complete, &out, &result, started.elapsed());
+    result?;
+    hook
+}
+
+/// Runs one export and prints where it went. Returns the number of notes exported.
+fn export_notes(
+    json: bool,
+    account: &str,
+    backend_mode: Backend,
+    fixture: Option<PathBuf>,
+    cancel: CancelToken,
+    backend: Box<dyn NotesBackend>,
+    args: ExportArgs,
+) -> anyhow::Result<u64> {
+    let ExportArgs {
+        out,
+        archive,
+        encrypt,
+        recipient,
+        jobs,
+        with_html,
+        html_only,
+        no_html,
+        snapshot,
+        format,
+        attachments,
+        single_file,
+        epub_chapters,
+        all_accounts,
+        folder,
+        query,
+        modified_after,
+        created_before,
+        preflight: _,
+        no_preflight,
+        on_complete: _,
+        journal,
+        watch,
+        watch_interval,
+    } = args;
+    let folder = folder.as_deref().map(split_folder_path).transpose()?;
+    let filter = backup::NoteFilter {
+        query,
+        modified_after,
+        created_before,
+    };
+
+    // Only runs that talk to Notes.app over Apple Events need the Automation permission.
+    let check_notes_access = || -> anyhow::Result<()> {
+        if no_preflight || fixture.is_some() {
+            return Ok(());
+        }
+        let spinner = progress::spinner("Checking Notes.app access…");
+        let res = crate::transport::OsascriptBackend.preflight();
+        if let Some(spinner) = spinner {
+            spinner.finish_and_clear();
+        }
+        res
+    };
+
+    let print_file = |path: &std::path::Path, count: usize| {
+        if json {
+            print_json(&serde_json::json!({ "path": path, "notes": count }))?;
+        } else {
+            println!("{}", path.display());
+        }
+        Ok(count as u64)
+    };
+    if let Some(path) = single_file {
+        check_notes_access()?;
+        let count = crate::single_file::export(
+            &*backend,
+            account,
+            &path,
+            folder.as_deref(),
+            &filter,
+            &cancel,
+        )?;
+        return print_file(&path, count);
+    }
+    if archive.is_some()
+        && !matches!(
+            format,
+            backup::ExportFormat::Md | backup::ExportFormat::Html
+        )
+    {
+        return Err(anyhow!("--archive supports --format md and html only"));
+    }
+    let recipients = if encrypt {
+        recipient
+            .iter()
+            .map(|key| {
+                key.trim()
+                    .parse::<age::x25519::Recipient>()
+                    .map_err(|e| anyhow!("invalid --recipient {key:?}: {e}"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?
+    } else {
+        Vec::new()
+    };
+    // Inside an archive, exports start at its root.
+    let out = match (&archive, out) {
+        (Some(_), _) => String::new(),
+        (None, Some(out)) => out,
+        (None, None) => {
+            return Err(anyhow!(
+                "--out is required (or set export_dir in the config file)"
+            ));
+        }
+    };
+    let accounts = if all_accounts {
+        backend
+            .list_accounts()?
+            .into_iter()
+            .map(|a| a.name)
+            .collect::<Vec<_>>()
+    } else {
+        vec![account.to_string()]
+    };
+    if format == backup::ExportFormat::Epub {
+        if snapshot {
+            return Err(anyhow!("--snapshot can't be combined with --format epub"));
+        }
+        if journal.is_some() {
+            return Err(anyhow!("--journal can't be combined with --format epub"));
+        }
+        if watch {
+            return Err(anyhow!("--watch can't be combined with --format epub"));
+        }
+        check_notes_access()?;
+        let mut exported = 0;
+        for account in &accounts {
+            let path = if all_accounts {
+                let mut path = backup::account_dir(std::path::Path::new(&out), account);
+                path.as_mut_os_string().push(".epub");
+                path
+            } else {
+                PathBuf::from(&out)
+            };
+            let count = epub::export(
+                &*backend,
+                account,
+                &path,
+                epub_chapters,
+                folder.as_deref(),
+                &filter,
+                &cancel,
+            )?;
+            exported += print_file(&path, count)?;
+        }
+        return Ok(exported);
+    }
+
+    let html = if no_html {
+        backup::HtmlExport::None
+    } else {
+        export_html_mode(with_html, html_only)
+    };
+
+    let mut opts = backup::ExportOptions {
+        jobs: jobs.unwrap_or(DEFAULT_JOBS),
+        html,
+        format,
+        attachments,
+        folder,
+        filter,
+        cancel,
+        archive: None,
+        journal: journal.as_ref().map(|_| Arc::default()),
+        incremental: watch,
+    };
+    if watch && (fixture.is_some() || matches!(backend_mode, Backend::Osascript)) {
+        return Err(anyhow!(
+            "--watch needs the Notes database (--backend db or auto)"
+        ));
+    }
+    if matches!(backend_mode, Backend::Osascript) || opts.fetches_html() {
+        check_notes_access()?;
+    }
+    if let Some(path) = &archive {
+        opts.archive = Some(Arc::new(ArchiveWriter::create(path, &recipients)?));
+    }
+
+    let snap = if snapshot {
+        Some(snapshot::Snapshot::begin(PathBuf::from(&out))?)
+    } else {
+        None
+    };
+    let out = match &snap {
+        Some(snap) => snap.dir().to_string_lossy().to_string(),
+        None => out,
+    };
+
+    let export_accounts = || -> anyhow::Result<u64> {
+        let mut exported = 0;
+        for account in &accounts {
+            let out = if all_accounts {
+                backup::account_dir(std::path::Path::new(&out), account)
+                    .to_string_lossy()
+                    .to_string()
+            } else {
+                out.clone()
+            };
+            exported += if fixture.is_some() {
+                backup::export_all(&*backend, account, out, &opts)?
+            } else {
+                match backend_mode {
+                    Backend::Osascript => backup::export_all(&*backend, account, out, &opts)?,
+                    Backend::Db => backup::export_all_db(account, out, &opts)?,
+                    Backend::Auto => {
+                        backup::export_all_db(account, out.clone(), &opts).or_else(|_| {
+                            check_notes_access()?;
+                            backup::export_all(&*backend, account, out, &opts)
+                        })?
                     }
-                    notes.push(n);
-                })?;
-                if let Some(spinner) = spinner {
-                    spinner.finish_and_clear();
                 }
-                (notes, Some(folder), None)
+            };
+        }
+        Ok(exported)
+    };
+    let exported = match &opts.archive {
+        Some(writer) => {
+            let to_stdout = writer.path() == std::path::Path::new("-");
+            let result = export_accounts().and_then(|exported| {
+                writer.finish()?;
+                Ok(exported)
+            });
+            if result.is_err() && !to_stdout {
+                // Don't leave a truncated archive behind.
+                let _ = std::fs::remove_file(writer.path());
+            }
+            let exported = result?;
+            if !to_stdout {
+                print_file(writer.path(), exported as usize)?;
+            }
+            exported
+        }
+        None => export_accounts()?,
+    };
+    let append_journal = || -> anyhow::Result<()> {
+        if let (Some(path), Some(recorder)) = (&journal, &opts.journal) {
+            recorder.append_changes(path, OffsetDateTime::now_utc())?;
+        }
+        Ok(())
+    };
+    append_journal()?;
+
+    if watch {
+        let files = crate::watch::db_files(&crate::db::notes_db_path()?);
+        let mut exported = exported;
+        loop {
+            let spinner = progress::spinner("Watching Notes for changes…");
+            let changed = crate::watch::wait_for_change(
+                &files,
+                Duration::from_secs(watch_interval.max(1)),
+                &opts.cancel,
+            );
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            // Ctrl-C (or `--timeout`) while idle is how a watch ends.
+            if changed.is_err() {
+                return Ok(exported);
+            }
+            exported += export_accounts()?;
+            append_journal()?;
+        }
+    }
+
+    let Some(snap) = snap else {
+        return Ok(exported);
+    };
+    let stats = snap.finish()?;
+    if json {
+        print_json(&stats)?;
+    } else {
+        println!("{}", stats.dir.display());
+    }
+    Ok(exported)
+}
+
+/// `reads_db` says whether reads can go to the Notes database (the backend opened it: no
+/// fixture, not `--backend osascript`, and not `auto` falling back to `osascript`).
+fn dispatch_notes(
+    json: bool,
+    account: &str,
+    config: &Config,
+    cancel: &CancelToken,
+    reads_db: bool,
+    backend: Box<dyn NotesBackend>,
+    cmd: NotesCmd,
+) -> anyhow::Result<()> {
+    match cmd {
+        NotesCmd::List {
+            folder,
+            query,
+            regex,
+            case_sensitive,
+            limit,
+            offset,
+            picker,
+            output,
+            sort,
+            reverse,
+            modified_after,
+            modified_before,
+            created_after,
+            created_before,
+            preview,
+            group_by_folder,
+            pinned,
+        } => {
+            let json = json || output == ListOutput::Json;
+            let dates = DateFilter {
+                modified_after,
+                modified_before,
+                created_after,
+                created_before,
+            };
+            let (folder_path, folder_hint, folder_index) = if let Some(folder) = folder {
+                (Some(split_folder_path(&folder)?), Some(folder), None)
             } else {
                 let spinner = progress::spinner("Loading folders…");
                 let folders = backend.list_folders(account)?;
                 if let Some(spinner) = spinner {
                     spinner.finish_and_clear();
                 }
-                let folder_index = backup::FolderIndex::new(&folders)?;
+                (None, None, Some(backup::FolderIndex::new(&folders)?))
+            };
+            let folder_of = |n: &NoteSummary| match (&folder_hint, &folder_index) {
+                (Some(hint), _) => hint.clone(),
+                (None, Some(index)) => index
+                    .folder_path_string(&n.folder_id)
+                    .unwrap_or_else(|| "?".to_string()),
+                (None, None) => "?".to_string(),
+            };
+            let title_filter = title_filter(query.as_deref(), regex.as_deref(), case_sensitive)?;
 
-                let spinner = progress::spinner("Loading notes… 0 loaded");
-                let mut notes = Vec::new();
-                let mut loaded = 0usize;
-                backend.stream_note_summaries(account, None, &mut |n| {
+            // Fast listings are buffered and printed as one sorted table. If loading takes long
+            // enough to notice, switch to printing rows as they arrive (in arrival order), unless
+            // an order was asked for.
+            let arrival_order = matches!(sort, None | Some(ListSort::Unsorted)) && !reverse;
+            let progressive = !json
+                && !picker
+                && !preview
+                && !group_by_folder
+                && output == ListOutput::Table
+                && arrival_order
+                && io::stdout().is_terminal();
+            let started = Instant::now();
+            let mut spinner = progress::spinner("Loading notes… 0 loaded");
+            let mut notes = Vec::new();
+            let mut loaded = 0usize;
+            let mut live: Option<tables::StreamingTable<io::Stdout>> = None;
+            let mut write_err: Option<io::Error> = None;
+            // Once rows are kept in arrival order, `--limit` rows (after `--offset`) are all we
+            // need, so the listing stops there instead of loading the whole account.
+            let enough = cancel.child();
+            let listed = backend.stream_note_summaries_cancellable(
+                account,
+                folder_path.as_deref(),
+                &enough,
+                &mut |n| {
                     loaded += 1;
                     if let Some(spinner) = &spinner
                         && (loaded == 1 || loaded.is_multiple_of(25))
                     {
                         spinner.set_message(format!("Loading notes… {loaded} loaded"));
                     }
+                    if let Some(filter) = &title_filter
+                        && !filter.is_match(&n.title)
+                    {
+                        return;
+                    }
+                    if !dates.matches(&n) || (pinned && !n.pinned) {
+                        return;
+                    }
                     notes.push(n);
-                })?;
-                if let Some(spinner) = spinner {
-                    spinner.finish_and_clear();
-                }
 
-                (notes, None, Some(folder_index))
-            };
-
-            if let Some(q) = query {
-                let q = q.to_lowercase();
-                notes.retain(|n| n.title.to_lowercase().contains(&q));
+                    if live.is_none()
+                        && write_err.is_none()
+                        && progressive
+                        && started.elapsed() >= PROGRESSIVE_LISTING_AFTER
+                    {
+                        if let Some(spinner) = spinner.take() {
+                            spinner.finish_and_clear();
+                        }
+                        match tables::StreamingTable::new::<NoteRow>(
+                            io::stdout(),
+                            tables::terminal_width().unwrap_or(120),
+                            tables::should_color(),
+                        ) {
+                            Ok(table) => live = Some(table),
+                            Err(e) => write_err = Some(e),
+                        }
+                    }
+                    if let Some(table) = &mut live {
+                        while offset + table.rows() < notes.len()
+                            && limit.is_none_or(|l| table.rows() < l)
+                        {
+                            let n = &notes[offset + table.rows()];
+                            let row = NoteRow {
+                                id: n.display_id().to_string(),
+                                folder: folder_of(n),
+                                title: n.title.clone(),
+                                pinned: n.pinned,
+                                locked: n.locked,
+                                created_at: n.created_at,
+                                modified_at: n.modified_at,
+                            };
+                            if let Err(e) = table.push(&row) {
+                                write_err = Some(e);
+                                live = None;
+                                break;
+                            }
+                        }
+                    }
+                    if (live.is_some() || (sort == Some(ListSort::Unsorted) && !reverse))
+                        && limit.is_some_and(|l| notes.len() >= offset + l)
+                    {
+                        enough.cancel();
+                    }
+                },
+            );
+            match listed {
+                // Stopped because the rows are all in, not by the caller.
+                Err(NotesError::Cancelled(_)) if !cancel.is_cancelled() => {}
+                listed => listed?,
+            }
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            if let Some(e) = write_err {
+                return Err(e).context("write listing");
+            }
+            if let Some(table) = live {
+                return table.finish().context("write listing");
             }
 
-            if json {
-                if let Some(limit) = limit {
-                    notes.truncate(limit);
+            sort_notes(
+                &mut notes,
+                sort.unwrap_or(ListSort::Title),
+                reverse,
+                &folder_of,
+            );
+            let notes: Vec<NoteSummary> = notes
+                .into_iter()
+                .skip(offset)
+                .take(limit.unwrap_or(usize::MAX))
+                .collect();
+            let previews = if preview {
+                note_previews(&*backend, reads_db, &notes)?
+            } else {
+                HashMap::new()
+            };
+            if picker {
+                let mut out = io::stdout().lock();
+                for n in &notes {
+                    writeln!(out, "{}", picker_line(n, &folder_of(n))).context("write listing")?;
                 }
+                Ok(())
+            } else if output == ListOutput::Alfred {
+                let items: Vec<_> = notes
+                    .iter()
+                    .map(|n| alfred_item(n, &folder_of(n)))
+                    .collect();
+                print_json(&serde_json::json!({ "items": items }))
+            } else if json && (preview || group_by_folder) {
+                let values = notes
+                    .iter()
+                    .map(|n| {
+                        let mut value = serde_json::to_value(n)?;
+                        if preview {
+                            value["preview"] =
+                                previews.get(&n.id).cloned().unwrap_or_default().into();
+                        }
+                        Ok((folder_of(n), value))
+                    })
+                    .collect::<serde_json::Result<Vec<_>>>()?;
+                if group_by_folder {
+                    let groups: Vec<_> = group_by_folder_path(values)
+                        .into_iter()
+                        .map(|(folder, notes)| serde_json::json!({ "folder": folder, "notes": notes }))
+                        .collect();
+                    print_json(&groups)
+                } else {
+                    print_json(&values.into_iter().map(|(_, v)| v).collect::<Vec<_>>())
+                }
+            } else if json {
                 print_json(&notes)
-            } else if let Some(folder_hint) = folder_hint {
-                print_note_summaries_folder_hint(&notes, &folder_hint, limit)
             } else {
                 print_note_summaries(
                     &notes,
-                    folder_index.as_ref().expect("folder index missing"),
-                    limit,
+                    &folder_of,
+                    preview.then_some(&previews),
+                    group_by_folder,
                 )
             }
         }
-        NotesCmd::Show { id, markdown, html } => {
+        NotesCmd::Show {
+            ids_from: Some(source),
+            markdown,
+            html,
+            no_pager,
+            ..
+        } => {
+            let ids = resolve_notes(&*backend, account, read_ids(&source)?)?;
+            let pb = progress::bar(ids.len() as u64, "Loading notes…");
+            let mut notes = Vec::new();
+            for id in &ids {
+                notes.push(backend.get_note(id)?);
+                if let Some(pb) = &pb {
+                    pb.inc(1);
+                }
+            }
+            if let Some(pb) = pb {
+                pb.finish_and_clear();
+            }
+            if json {
+                return print_json(&notes);
+            }
+            if html {
+                for note in &notes {
+                    println!("{}", note.body_html);
+                }
+                return Ok(());
+            }
+            let md = notes
+                .iter()
+                .map(render::note_to_markdown)
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            if markdown || !io::stdout().is_terminal() {
+                return pager::page(&format!("{md}\n"), !no_pager);
+            }
+            pager::page(&render::render_markdown(&md), !no_pager)
+        }
+        NotesCmd::Show {
+            id,
+            from_picker_line,
+            meta: true,
+            ..
+        } => {
+            let id = resolve_note(&*backend, account, &show_id(id, from_picker_line)?)?;
+            let spinner = progress::spinner("Loading note…");
+            let meta = note_meta(&*backend, account, reads_db, &id)?;
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            if json {
+                return print_json(&meta);
+            }
+            let date = |d: OffsetDateTime| d.format(&Rfc3339).unwrap_or_default();
+            println!("Title:       {}", meta.title);
+            println!("Folder:      {}", meta.folder);
+            println!("Created:     {}", date(meta.created_at));
+            println!("Modified:    {}", date(meta.modified_at));
+            println!("Attachments: {}", meta.attachments);
+            println!("Words:       {}", meta.words);
+            if meta.shared {
+                println!("Shared:      yes");
+            }
+            if !meta.participants.is_empty() {
+                println!("Shared with: {}", meta.participants.join(", "));
+            }
+            Ok(())
+        }
+        NotesCmd::Show {
+            id,
+            from_picker_line,
+            pdf: Some(path),
+            ..
+        } => {
+            let id = resolve_note(&*backend, account, &show_id(id, from_picker_line)?)?;
+            let spinner = progress::spinner("Rendering PDF…");
+            let note = backend.get_note(&id)?;
+            let page = html_export::Page {
+                title: note.title,
+                body_html: note.body_html,
+                attachments: html_export::AttachmentMode::Inline,
+                index_href: None,
+            };
+            pdf::write_pdf(&page.to_inline_html(), &path)?;
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            if json {
+                print_json(&serde_json::json!({ "path": path }))
+            } else {
+                println!("{}", path.display());
+                Ok(())
+            }
+        }
+        NotesCmd::Show {
+            id,
+            from_picker_line,
+            html,
+            copy: true,
+            ..
+        } => {
+            let id = resolve_note(&*backend, account, &show_id(id, from_picker_line)?)?;
             let spinner = progress::spinner("Loading note…");
             let note = backend.get_note(&id)?;
             if let Some(spinner) = spinner {
                 spinner.finish_and_clear();
             }
+            let (text, what) = if html {
+                (note.body_html, "HTML")
+            } else {
+                (render::note_to_markdown(&note), "Markdown")
+            };
+            clipboard::copy(&text)?;
             if json {
+                print_json(&serde_json::json!({ "id": id, "copied": what.to_lowercase() }))
+            } else {
+                if !progress::quiet() {
+                    eprintln!("Copied the {what} of {:?} to the clipboard.", note.title);
+                }
+                Ok(())
+            }
+        }
+        NotesCmd::Show {
+            id,
+            from_picker_line,
+            markdown,
+            html,
+            no_pager,
+            head,
+            ..
+        } => {
+            let id = resolve_note(&*backend, account, &show_id(id, from_picker_line)?)?;
+            if html && !json {
+                // Raw HTML needs no post-processing, so print it as it arrives.
+                let mut out = io::stdout().lock();
+                let mut res = Ok(());
+                backend.get_note_streaming(&id, &mut |chunk| {
+                    if res.is_ok() {
+                        res = out.write_all(chunk.as_bytes());
+                    }
+                })?;
+                res.and_then(|()| writeln!(out)).context("write stdout")?;
+                return Ok(());
+            }
+            let spinner = progress::spinner("Loading note…");
+            let note = backend.get_note(&id)?;
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            if let Some(n) = head {
+                let body = render::head_lines(&render::html_to_markdown(&note.body_html), n);
+                if json {
+                    return print_json(&serde_json::json!({
+                        "id": note.id,
+                        "title": note.title,
+                        "head": body,
+                    }));
+                }
+                let md = format!("# {}\n\n{}", note.title, body);
+                if markdown || !io::stdout().is_terminal() {
+                    println!("{md}");
+                    return Ok(());
+                }
+                print!("{}", render::render_markdown(&md));
+                Ok(())
+            } else if json {
                 print_json(&note)
             } else if html {
                 println!("{}", note.body_html);
@@ -486,12 +2166,9 @@ fn dispatch_notes(
             } else {
                 let md = render::note_to_markdown(&note);
                 if markdown || !io::stdout().is_terminal() {
-                    println!("{}", md);
-                    return Ok(());
+                    return pager::page(&format!("{md}\n"), !no_pager);
                 }
-                let rendered = render::render_markdown(&md);
-                print!("{rendered}");
-                Ok(())
+                pager::page(&render::render_markdown(&md), !no_pager)
             }
         }
         NotesCmd::Create {
@@ -500,31 +2177,118 @@ fn dispatch_notes(
             body,
             body_file,
             stdin,
+            clipboard,
             markdown,
             html,
+            template,
+            var,
+            attach,
+            open,
         } => {
-            let body = read_body(body, body_file, stdin)?;
-            let body_html = if html {
-                body
-            } else if markdown {
-                render::markdown_to_html(&body)
+            // Catch missing files before creating a note they'd be missing from.
+            let attach = attach
+                .iter()
+                .map(|p| std::fs::canonicalize(p).with_context(|| format!("can't attach {p:?}")))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            // No folder means the account's default folder, like a new note in the Notes UI.
+            let folder_path = folder
+                .as_deref()
+                .map(split_folder_path)
+                .transpose()?
+                .unwrap_or_default();
+            let settings = if folder_path.is_empty() {
+                config.folder_settings(&[DEFAULT_FOLDER.to_string()])
             } else {
-                render::text_to_html(&body)
+                config.folder_settings(&folder_path)
+            };
+            let (body, markdown) = match (&template, &settings.template) {
+                (Some(name), _) => {
+                    let mut vars =
+                        std::collections::BTreeMap::from([("date".to_string(), local_date()?)]);
+                    if let Some(title) = &title {
+                        vars.insert("title".to_string(), title.clone());
+                    }
+                    vars.extend(var);
+                    (templates::fill(&templates::load(name)?, &vars)?, true)
+                }
+                (None, Some(template))
+                    if body.is_none() && body_file.is_none() && !stdin && !clipboard =>
+                {
+                    let path = crate::config::expand_home(template);
+                    let body = std::fs::read_to_string(&path)
+                        .with_context(|| format!("read folder template {path:?}"))?;
+                    (body, markdown)
+                }
+                _ => (read_body(body, body_file, stdin, clipboard)?, markdown),
+            };
+            let title = match title {
+                Some(title) => title,
+                None => title_from_body(&body, html)
+                    .ok_or_else(|| anyhow!("--title is required when the body is empty"))?,
             };
-            let folder_path = split_folder_path(&folder)?;
+            let body_html = body_to_html(&body, html, markdown, &settings);
             let spinner = progress::spinner("Creating note…");
             let id = backend.create_note_html(account, &folder_path, &title, &body_html)?;
+            let mut failed = 0;
+            for path in &attach {
+                if let Err(e) = backend.attach_file(&id, path) {
+                    eprintln!("Couldn't attach {}: {e}", path.display());
+                    failed += 1;
+                }
+            }
             if let Some(spinner) = spinner {
                 spinner.finish_and_clear();
             }
+            if failed > 0 {
+                return Err(PartialFailure(format!(
+                    "created {id}, but {failed} of {} attachments failed",
+                    attach.len()
+                ))
+                .into());
+            }
+            // Print the id first: it's still wanted if Notes can't show the note.
             if json {
-                print_json(&serde_json::json!({ "id": id }))
+                print_json(&serde_json::json!({ "id": id }))?;
             } else {
                 println!("{id}");
-                Ok(())
             }
+            if open {
+                backend.open_note(&id)?;
+            }
+            Ok(())
         }
-        NotesCmd::Rename { id, title } => {
+        NotesCmd::Rename {
+            query: Some(query),
+            replace,
+            folder,
+            yes,
+            ..
+        } => bulk_rename(
+            json,
+            account,
+            &*backend,
+            &regex::Regex::new(&query).with_context(|| format!("invalid --query {query:?}"))?,
+            &replace.unwrap_or_default(),
+            folder.as_deref(),
+            (!yes).then_some("with --yes"),
+        ),
+        NotesCmd::RenameAll {
+            pattern,
+            to,
+            folder,
+            dry_run,
+        } => bulk_rename(
+            json,
+            account,
+            &*backend,
+            &regex::Regex::new(&pattern).with_context(|| format!("invalid --match {pattern:?}"))?,
+            &to,
+            folder.as_deref(),
+            dry_run.then_some("without --dry-run"),
+        ),
+        NotesCmd::Rename { id, title, .. } => {
+            let id = resolve_note(&*backend, account, &id.context("missing note id")?)?;
+            let title = title.context("missing --title")?;
             let spinner = progress::spinner("Renaming note…");
             backend.set_note_title(&id, &title)?;
             if let Some(spinner) = spinner {
@@ -537,49 +2301,87 @@ fn dispatch_notes(
             body,
             body_file,
             stdin,
+            clipboard,
             markdown,
             html,
+            open,
         } => {
-            let body = read_body(body, body_file, stdin)?;
-            let body_html = if html {
-                body
-            } else if markdown {
-                render::markdown_to_html(&body)
-            } else {
-                render::text_to_html(&body)
-            };
+            let id = resolve_note(&*backend, account, &id)?;
+            let body = read_body(body, body_file, stdin, clipboard)?;
+            let settings = note_folder_settings(config, &*backend, account, &id)?;
+            let body_html = body_to_html(&body, html, markdown, &settings);
             let spinner = progress::spinner("Updating note body…");
             backend.set_note_body_html(&id, &body_html)?;
             if let Some(spinner) = spinner {
                 spinner.finish_and_clear();
             }
+            if open {
+                backend.open_note(&id)?;
+            }
             Ok(())
         }
         NotesCmd::Append {
             id,
             body,
+            todo,
             body_file,
             stdin,
+            clipboard,
             markdown,
             html,
+            open,
         } => {
-            let body = read_body(body, body_file, stdin)?;
-            let body_html = if html {
-                body
-            } else if markdown {
-                render::markdown_to_html(&body)
+            let id = resolve_note(&*backend, account, &id)?;
+            let settings = note_folder_settings(config, &*backend, account, &id)?;
+            let mut body_html = if todo.is_empty() {
+                let body = read_body(body, body_file, stdin, clipboard)?;
+                body_to_html(&body, html, markdown, &settings)
             } else {
-                render::text_to_html(&body)
+                todo::checklist_html(&todo)
             };
+            if settings.append_timestamp == Some(true) {
+                body_html = format!("<div><b>{}</b></div>{body_html}", local_timestamp()?);
+            }
             let spinner = progress::spinner("Appending to note…");
             backend.append_note_body_html(&id, &body_html)?;
             if let Some(spinner) = spinner {
                 spinner.finish_and_clear();
             }
+            if open {
+                backend.open_note(&id)?;
+            }
             Ok(())
         }
-        NotesCmd::Move { id, folder } => {
-            let folder_path = split_folder_path(&folder)?;
+        NotesCmd::Move {
+            id: _,
+            ids_from: Some(source),
+            folder,
+        } => {
+            let ids = resolve_notes(&*backend, account, read_ids(&source)?)?;
+            let folder_path = target_folder(folder.as_deref())?;
+            let pb = progress::bar(ids.len() as u64, "Moving notes…");
+            for id in &ids {
+                backend.move_note(id, account, &folder_path)?;
+                if let Some(pb) = &pb {
+                    pb.inc(1);
+                }
+            }
+            if let Some(pb) = pb {
+                pb.finish_and_clear();
+            }
+            if json {
+                print_json(&serde_json::json!({
+                    "ids": ids,
+                    "folder": folder_path.join(" > "),
+                }))
+            } else {
+                println!("Moved {} notes to {}.", ids.len(), folder_path.join(" > "));
+                Ok(())
+            }
+        }
+        NotesCmd::Move { id, folder, .. } => {
+            let id = resolve_note(&*backend, account, &id.context("missing note id")?)?;
+            let folder_path = target_folder(folder.as_deref())?;
             let spinner = progress::spinner("Moving note…");
             backend.move_note(&id, account, &folder_path)?;
             if let Some(spinner) = spinner {
@@ -587,24 +2389,1068 @@ fn dispatch_notes(
             }
             Ok(())
         }
-        NotesCmd::Delete { id, yes } => {
-            if !yes {
-                return Err(anyhow!("refusing to delete without --yes"));
-            }
-            let spinner = progress::spinner("Deleting note…");
-            backend.delete_note(&id)?;
-            if let Some(spinner) = spinner {
-                spinner.finish_and_clear();
-            }
-            Ok(())
+        NotesCmd::Open { id } => Ok(backend.open_note(&resolve_note(&*backend, account, &id)?)?),
+        NotesCmd::Url { id } => {
+            if !reads_db {
+                return Err(anyhow!(
+                    "`notes url` needs the Notes database (--backend db or auto)"
+                ));
+            }
+            let db = crate::db::NotesDb::open_default()?;
+            let id = db.resolve_note_id(&resolve_note(&*backend, account, &id)?)?;
+            let uuid = db.note_identifier(&id)?;
+            let url = format!("notes://showNote?identifier={uuid}");
+            if json {
+                print_json(&serde_json::json!({
+                    "id": id,
+                    "uuid": uuid,
+                    "url": url,
+                    "applenotes_url": format!("applenotes:note/{uuid}"),
+                }))
+            } else {
+                println!("{url}");
+                Ok(())
+            }
+        }
+        NotesCmd::Wordcount { id, folder, all: _ } => {
+            let notes = match (id, folder) {
+                (Some(id), _) => {
+                    let mut id = resolve_note(&*backend, account, &id)?;
+                    if reads_db {
+                        id = crate::db::NotesDb::open_default()?.resolve_note_id(&id)?;
+                    }
+                    let note = backend.get_note(&id)?;
+                    vec![NoteSummary {
+                        id,
+                        title: note.title,
+                        folder_id: note.folder_id,
+                        uuid: None,
+                        created_at: Some(note.created_at),
+                        modified_at: Some(note.modified_at),
+                        pinned: false,
+                        locked: false,
+                    }]
+                }
+                (None, folder) => {
+                    let spinner = progress::spinner("Loading notes…");
+                    let mut notes = match folder {
+                        Some(folder) => {
+                            backend.list_notes_in_folder(account, &split_folder_path(&folder)?)?
+                        }
+                        None => backend.list_notes(account)?,
+                    };
+                    if let Some(spinner) = spinner {
+                        spinner.finish_and_clear();
+                    }
+                    sort_notes(&mut notes, ListSort::Title, false, &|_| String::new());
+                    notes
+                }
+            };
+            let texts = note_texts(&*backend, reads_db, &notes, "Counting words…")?;
+            let counts: Vec<WordCountRow> = notes
+                .iter()
+                .map(|n| {
+                    let text = texts.get(&n.id).map_or("", String::as_str);
+                    WordCountRow {
+                        id: n.display_id().to_string(),
+                        title: n.title.clone(),
+                        words: render::word_count(text),
+                        characters: text.replace('\u{fffc}', "").trim().chars().count(),
+                    }
+                })
+                .collect();
+            let words = counts.iter().map(|c| c.words).sum::<usize>();
+            let characters = counts.iter().map(|c| c.characters).sum::<usize>();
+            if json {
+                return print_json(&serde_json::json!({
+                    "notes": counts,
+                    "total": { "notes": counts.len(), "words": words, "characters": characters },
+                }));
+            }
+            let summary = format!(
+                "Total: {} notes, {words} words, {characters} characters",
+                counts.len()
+            );
+            tables::render_table(counts);
+            println!("{summary}");
+            Ok(())
+        }
+        NotesCmd::Delete {
+            id: _,
+            ids_from: Some(source),
+            yes,
+            permanent,
+        } => {
+            let ids = resolve_notes(&*backend, account, read_ids(&source)?)?;
+            if !permanent {
+                refuse_deleting_deleted_notes(&*backend, &ids)?;
+            }
+            confirm_delete(yes, || {
+                describe_note_deletion(&*backend, account, &ids, permanent)
+            })?;
+            let pb = progress::bar(ids.len() as u64, "Deleting notes…");
+            for id in &ids {
+                if permanent {
+                    backend.purge_note(id)?;
+                } else {
+                    backend.delete_note(id)?;
+                }
+                if let Some(pb) = &pb {
+                    pb.inc(1);
+                }
+            }
+            if let Some(pb) = pb {
+                pb.finish_and_clear();
+            }
+            if json {
+                print_json(&serde_json::json!({ "ids": ids, "permanent": permanent }))
+            } else {
+                if permanent {
+                    println!("Deleted {} notes permanently.", ids.len());
+                } else {
+                    println!(
+                        "Moved {} notes to Recently Deleted; `notes trash restore` brings them back.",
+                        ids.len()
+                    );
+                }
+                Ok(())
+            }
+        }
+        NotesCmd::Delete {
+            id, yes, permanent, ..
+        } => {
+            let id = resolve_note(&*backend, account, &id.context("missing note id")?)?;
+            if !permanent {
+                refuse_deleting_deleted_notes(&*backend, std::slice::from_ref(&id))?;
+            }
+            confirm_delete(yes, || {
+                describe_note_deletion(&*backend, account, std::slice::from_ref(&id), permanent)
+            })?;
+            let spinner = progress::spinner("Deleting note…");
+            if permanent {
+                backend.purge_note(&id)?;
+            } else {
+                backend.delete_note(&id)?;
+            }
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            if json {
+                print_json(&serde_json::json!({ "id": id, "permanent": permanent }))
+            } else {
+                if permanent {
+                    println!("Deleted permanently.");
+                } else {
+                    println!("Moved to Recently Deleted; `notes trash restore` brings it back.");
+                }
+                Ok(())
+            }
+        }
+        NotesCmd::Merge {
+            ids,
+            into,
+            new_title,
+            delete_sources,
+            yes,
+        } => {
+            if delete_sources && !yes {
+                return Err(anyhow!("refusing to delete the merged notes without --yes"));
+            }
+            let target = into
+                .map(|id| resolve_note(&*backend, account, &id))
+                .transpose()?;
+            let mut sources = Vec::new();
+            for id in ids {
+                let id = resolve_note(&*backend, account, &id)?;
+                if target.as_ref() == Some(&id) {
+                    return Err(anyhow!("can't merge {id} into itself"));
+                }
+                if !sources.contains(&id) {
+                    sources.push(id);
+                }
+            }
+            let spinner = progress::spinner("Merging notes…");
+            let notes = sources
+                .iter()
+                .map(|id| backend.get_note(id))
+                .collect::<Result<Vec<_>, _>>()?;
+            // Attachments don't come along in the merged HTML, so deleting their notes would
+            // lose them.
+            if delete_sources {
+                let db_blockers = match reads_db {
+                    true => backup::note_markdown_for_rewrite_db(&sources)?,
+                    false => HashMap::new(),
+                };
+                if let Some(n) = notes.iter().find(|n| {
+                    render::html_has_attachments(&n.body_html)
+                        || db_blockers
+                            .get(&n.id)
+                            .is_some_and(|(_, blocker)| *blocker == Some(backup::HAS_ATTACHMENTS))
+                }) {
+                    return Err(anyhow!(
+                        "refusing to delete {:?} ({}): it has attachments, which merging doesn't \
+                         carry over; merge without --delete-sources",
+                        n.title,
+                        n.id
+                    ));
+                }
+            }
+            let merged = merged_body_html(&notes);
+            let id = match (target, new_title) {
+                (Some(target), _) => {
+                    backend
+                        .append_note_body_html(&target, &format!("{MERGE_SEPARATOR}{merged}"))?;
+                    target
+                }
+                (None, title) => {
+                    let title = title.unwrap_or_default();
+                    let folder_path = backup::FolderIndex::new(&backend.list_folders(account)?)?
+                        .folder_path(&notes[0].folder_id)
+                        .unwrap_or_default();
+                    backend.create_note_html(account, &folder_path, &title, &merged)?
+                }
+            };
+            if delete_sources {
+                for source in &sources {
+                    backend.delete_note(source)?;
+                }
+            }
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            if json {
+                print_json(&serde_json::json!({
+                    "id": id,
+                    "merged": sources,
+                    "deleted_sources": delete_sources,
+                }))
+            } else {
+                println!("Merged {} notes into {id}.", sources.len());
+                if delete_sources {
+                    println!("Moved the merged notes to Recently Deleted.");
+                }
+                Ok(())
+            }
+        }
+        NotesCmd::Dedupe {
+            by,
+            folder,
+            apply,
+            yes,
+            any_body,
+        } => {
+            let spinner = progress::spinner("Loading notes…");
+            let mut notes = match folder {
+                Some(folder) => {
+                    backend.list_notes_in_folder(account, &split_folder_path(&folder)?)?
+                }
+                None => backend.list_notes(account)?,
+            };
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            // Which note is newest decides what's kept, so undated notes can't take part.
+            notes.retain(|n| !n.locked && n.modified_at.is_some());
+            // Same-title notes are only deleted if their bodies match too, unless `--any-body`.
+            let compare_bodies = by == DedupeBy::Title && !any_body;
+            let texts = if by == DedupeBy::Title && !compare_bodies {
+                HashMap::new()
+            } else {
+                note_texts(&*backend, reads_db, &notes, "Reading notes…")?
+            };
+            let clusters = duplicate_clusters(&notes, &texts, by);
+            let mut rows = Vec::new();
+            for cluster in &clusters {
+                for (i, n) in cluster.iter().enumerate() {
+                    let status = match i {
+                        0 => "keep",
+                        _ if compare_bodies && !same_body(&texts, cluster[0], n) => "differs",
+                        _ => "duplicate",
+                    };
+                    rows.push((status, *n));
+                }
+            }
+            let doomed: Vec<&NoteSummary> = rows
+                .iter()
+                .filter(|(status, _)| *status == "duplicate")
+                .map(|(_, n)| *n)
+                .collect();
+            // The plan comes first, so it can be read before answering the prompt.
+            if !json && !clusters.is_empty() {
+                tables::render_table(
+                    rows.iter()
+                        .map(|(status, n)| StatusRow {
+                            status: status.to_string(),
+                            id: n.display_id().to_string(),
+                            title: n.title.clone(),
+                        })
+                        .collect(),
+                );
+            }
+            if apply && !doomed.is_empty() {
+                let ids: Vec<String> = doomed.iter().map(|n| n.id.clone()).collect();
+                confirm_delete(yes, || {
+                    describe_note_deletion(&*backend, account, &ids, false)
+                })?;
+                let pb = progress::bar(doomed.len() as u64, "Deleting duplicates…");
+                for n in &doomed {
+                    cancel.check()?;
+                    backend.delete_note(&n.id)?;
+                    if let Some(pb) = &pb {
+                        pb.inc(1);
+                    }
+                }
+                if let Some(pb) = pb {
+                    pb.finish_and_clear();
+                }
+            }
+            if json {
+                let status_of = |n: &NoteSummary| {
+                    rows.iter()
+                        .find(|(_, r)| r.id == n.id)
+                        .map_or("keep", |(status, _)| *status)
+                };
+                let groups: Vec<_> = clusters
+                    .iter()
+                    .map(|cluster| {
+                        let (duplicates, differing): (Vec<&NoteSummary>, Vec<&NoteSummary>) =
+                            cluster[1..]
+                                .iter()
+                                .partition(|n| status_of(n) == "duplicate");
+                        serde_json::json!({
+                            "keep": cluster[0],
+                            "duplicates": duplicates,
+                            "different_bodies": differing,
+                        })
+                    })
+                    .collect();
+                return print_json(&serde_json::json!({ "applied": apply, "clusters": groups }));
+            }
+            if clusters.is_empty() {
+                println!("No duplicate notes.");
+                return Ok(());
+            }
+            let differing = rows.len() - clusters.len() - doomed.len();
+            if differing > 0 {
+                println!(
+                    "{differing} notes share a title with a kept note but not its body; \
+                     --any-body deletes them too."
+                );
+            }
+            if apply {
+                println!("Deleted {} duplicate notes.", doomed.len());
+            } else {
+                println!(
+                    "{} duplicate notes in {} groups; run with --apply to delete them.",
+                    doomed.len(),
+                    clusters.len()
+                );
+            }
+            Ok(())
+        }
+        NotesCmd::Lint { folder } => {
+            let spinner = progress::spinner("Loading notes…");
+            let notes = match folder {
+                Some(folder) => {
+                    backend.list_notes_in_folder(account, &split_folder_path(&folder)?)?
+                }
+                None => backend.list_notes(account)?,
+            };
+            let folder_ids: HashSet<String> = backend
+                .list_folders(account)?
+                .into_iter()
+                .map(|f| f.id)
+                .collect();
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            let bodies = note_bodies(&*backend, reads_db, &notes, "Checking notes…", false)?;
+            let mut findings: Vec<lint::LintFinding> = notes
+                .iter()
+                .flat_map(|n| {
+                    lint::lint_note(n, bodies.get(&n.id).map(String::as_str), &folder_ids)
+                })
+                .collect();
+
+            // Links may point into any account, so they're checked against every note's UUID.
+            if bodies.values().any(|b| !links::note_links(b).is_empty()) {
+                let mut known_uuids = HashSet::new();
+                for a in backend.list_accounts()? {
+                    known_uuids.extend(
+                        backend
+                            .list_notes(&a.name)?
+                            .into_iter()
+                            .filter_map(|n| n.uuid.map(|u| u.to_ascii_uppercase())),
+                    );
+                }
+                if known_uuids.is_empty() {
+                    eprintln!("Note links not checked: note UUIDs need the Notes database.");
+                } else {
+                    for n in &notes {
+                        if let Some(body) = bodies.get(&n.id) {
+                            findings.extend(lint::broken_links(n, body, &known_uuids));
+                        }
+                    }
+                }
+            }
+            findings.sort_by(|a, b| (&a.title, &a.id).cmp(&(&b.title, &b.id)));
+
+            if json {
+                return print_json(&findings);
+            }
+            if findings.is_empty() {
+                println!("No problems found in {} notes.", notes.len());
+                return Ok(());
+            }
+            let flagged = findings.iter().map(|f| &f.id).collect::<HashSet<_>>().len();
+            let summary = format!("{} problems in {flagged} notes.", findings.len());
+            tables::render_table(findings.into_iter().map(LintRow).collect());
+            println!("{summary}");
+            Ok(())
+        }
+        NotesCmd::Graph { format, folder } => {
+            let spinner = progress::spinner("Loading notes…");
+            let notes = match folder {
+                Some(folder) => {
+                    backend.list_notes_in_folder(account, &split_folder_path(&folder)?)?
+                }
+                None => backend.list_notes(account)?,
+            };
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            if !notes.is_empty() && notes.iter().all(|n| n.uuid.is_none()) {
+                return Err(anyhow!(
+                    "`notes graph` needs note UUIDs from the Notes database (--backend db or auto)"
+                ));
+            }
+            let bodies = note_bodies(&*backend, reads_db, &notes, "Reading links…", false)?;
+            let graph = links::connected(links::link_graph(&notes, &bodies));
+            if json || format == GraphFormat::Json {
+                print_json(&serde_json::json!({ "nodes": graph }))
+            } else {
+                print!("{}", links::to_dot(&graph));
+                Ok(())
+            }
+        }
+        NotesCmd::Backlinks { id } => {
+            let id = resolve_note(&*backend, account, &id)?;
+            let spinner = progress::spinner("Loading notes…");
+            let notes = backend.list_notes(account)?;
+            let folders = backend.list_folders(account)?;
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            let target = notes
+                .iter()
+                .find(|n| n.id == id || n.uuid.as_deref() == Some(id.as_str()))
+                .ok_or_else(|| anyhow!("note {id} is not in account {account:?}"))?;
+            if target.uuid.is_none() {
+                return Err(anyhow!(
+                    "`notes backlinks` needs note UUIDs from the Notes database (--backend db or auto)"
+                ));
+            }
+            let bodies = note_bodies(&*backend, reads_db, &notes, "Reading links…", false)?;
+            let linking: HashSet<String> = links::link_graph(&notes, &bodies)
+                .into_iter()
+                .filter(|n| n.links.contains(&target.id))
+                .map(|n| n.id)
+                .collect();
+            let mut backlinks: Vec<NoteSummary> = notes
+                .iter()
+                .filter(|n| linking.contains(&n.id))
+                .cloned()
+                .collect();
+            let folder_index = backup::FolderIndex::new(&folders)?;
+            let folder_of = |n: &NoteSummary| {
+                folder_index
+                    .folder_path_string(&n.folder_id)
+                    .unwrap_or_else(|| "?".to_string())
+            };
+            sort_notes(&mut backlinks, ListSort::Title, false, &folder_of);
+            if json {
+                print_json(&backlinks)
+            } else if backlinks.is_empty() {
+                println!("No notes link to {:?}.", target.title);
+                Ok(())
+            } else {
+                print_note_summaries(&backlinks, &folder_of, None, false)
+            }
+        }
+        NotesCmd::Replace {
+            query,
+            with,
+            folder,
+            dry_run,
+        } => {
+            let pattern =
+                regex::Regex::new(&query).with_context(|| format!("invalid --query {query:?}"))?;
+            let spinner = progress::spinner("Loading notes…");
+            let notes = match folder {
+                Some(folder) => {
+                    backend.list_notes_in_folder(account, &split_folder_path(&folder)?)?
+                }
+                None => backend.list_notes(account)?,
+            };
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            let mut bodies = HashMap::new();
+            let mut blocked = HashMap::new();
+            for (id, (markdown, blocker)) in rewritable_bodies(&*backend, reads_db, &notes)? {
+                if let Some(blocker) = blocker {
+                    blocked.insert(id.clone(), blocker);
+                }
+                bodies.insert(id, markdown);
+            }
+            let (planned, skipped): (Vec<_>, Vec<_>) =
+                replace::plan(&notes, &bodies, &pattern, &with)
+                    .into_iter()
+                    .partition(|p| !blocked.contains_key(&p.id));
+            let skipped: Vec<replace::SkippedReplace> = skipped
+                .into_iter()
+                .map(|p| replace::SkippedReplace {
+                    reason: blocked[&p.id],
+                    id: p.id,
+                    title: p.title,
+                })
+                .collect();
+            let replacements: usize = planned.iter().map(|p| p.replacements).sum();
+
+            if !json {
+                for s in &skipped {
+                    eprintln!(
+                        "Skipping {} ({}): {}; edit it in Notes instead.",
+                        s.title, s.id, s.reason
+                    );
+                }
+                if planned.is_empty() {
+                    println!("No notes match.");
+                    return match skipped.len() {
+                        0 => Ok(()),
+                        n => Err(anyhow!("skipped all {n} matching notes")),
+                    };
+                }
+                let mut out = io::stdout().lock();
+                for p in &planned {
+                    writeln!(out, "{} ({})", p.title, p.id).context("write diff")?;
+                    write!(out, "{}", p.diff).context("write diff")?;
+                }
+            }
+
+            if !dry_run && !planned.is_empty() {
+                let folder_index = backup::FolderIndex::new(&backend.list_folders(account)?)?;
+                let folder_of: HashMap<&str, &str> = notes
+                    .iter()
+                    .map(|n| (n.id.as_str(), n.folder_id.as_str()))
+                    .collect();
+                let pb = progress::bar(planned.len() as u64, "Updating notes…");
+                for p in &planned {
+                    let folder_path = folder_of
+                        .get(p.id.as_str())
+                        .and_then(|f| folder_index.folder_path(f))
+                        .unwrap_or_default();
+                    let settings = config.folder_settings(&folder_path);
+                    let body_html = body_to_html(&p.new_markdown, false, true, &settings);
+                    backend.set_note_body_html(&p.id, &body_html)?;
+                    if let Some(pb) = &pb {
+                        pb.inc(1);
+                    }
+                }
+                if let Some(pb) = pb {
+                    pb.finish_and_clear();
+                }
+            }
+
+            if json {
+                #[derive(serde::Serialize)]
+                struct Out<'a> {
+                    applied: bool,
+                    replacements: usize,
+                    notes: &'a [replace::PlannedReplace],
+                    skipped: &'a [replace::SkippedReplace],
+                }
+                print_json(&Out {
+                    applied: !dry_run,
+                    replacements,
+                    notes: &planned,
+                    skipped: &skipped,
+                })?;
+            } else if !dry_run {
+                println!(
+                    "Changed {} notes ({replacements} replacements).",
+                    planned.len()
+                );
+            } else if !progress::quiet() {
+                eprintln!(
+                    "{} notes would change ({replacements} replacements); re-run without --dry-run to apply.",
+                    planned.len()
+                );
+            }
+            match skipped.len() {
+                0 => Ok(()),
+                n if planned.is_empty() => Err(anyhow!("skipped all {n} matching notes")),
+                n => Err(PartialFailure(format!(
+                    "skipped {n} of {} matching notes",
+                    n + planned.len()
+                ))
+                .into()),
+            }
+        }
+        NotesCmd::Grep {
+            pattern,
+            context,
+            ignore_case,
+            files_with_matches,
+            folder,
+        } => {
+            let pattern = regex::RegexBuilder::new(&pattern)
+                .case_insensitive(ignore_case)
+                .build()
+                .with_context(|| format!("invalid pattern {pattern:?}"))?;
+            let spinner = progress::spinner("Loading notes…");
+            let mut notes = match folder {
+                Some(folder) => {
+                    backend.list_notes_in_folder(account, &split_folder_path(&folder)?)?
+                }
+                None => backend.list_notes(account)?,
+            };
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            sort_notes(&mut notes, ListSort::Title, false, &|_| String::new());
+            let texts = note_texts(&*backend, reads_db, &notes, "Searching notes…")?;
+            let found: Vec<(&NoteSummary, Vec<grep::GrepLine>)> = notes
+                .iter()
+                .filter_map(|n| {
+                    let lines = grep::grep_lines(texts.get(&n.id)?, &pattern, context);
+                    (!lines.is_empty()).then_some((n, lines))
+                })
+                .collect();
+
+            if json {
+                let found: Vec<_> = found
+                    .iter()
+                    .map(|(n, lines)| {
+                        if files_with_matches {
+                            serde_json::json!({ "id": n.id, "title": n.title })
+                        } else {
+                            serde_json::json!({ "id": n.id, "title": n.title, "lines": lines })
+                        }
+                    })
+                    .collect();
+                return print_json(&found);
+            }
+            let mut out = io::stdout().lock();
+            if files_with_matches {
+                for (n, _) in &found {
+                    writeln!(out, "{}", n.id).context("write matches")?;
+                }
+                return Ok(());
+            }
+            let color = tables::should_color();
+            for (i, (n, lines)) in found.iter().enumerate() {
+                if i > 0 {
+                    writeln!(out).context("write matches")?;
+                }
+                let heading = format!("{}  {}", n.display_id(), n.title);
+                if color {
+                    writeln!(out, "\x1b[1;36m{heading}\x1b[0m").context("write matches")?;
+                } else {
+                    writeln!(out, "{heading}").context("write matches")?;
+                }
+                for (j, line) in lines.iter().enumerate() {
+                    if j > 0 && lines[j - 1].line + 1 < line.line {
+                        writeln!(out, "--").context("write matches")?;
+                    }
+                    let sep = if line.is_match { ':' } else { '-' };
+                    writeln!(out, "{}{sep}{}", line.line, line.text).context("write matches")?;
+                }
+            }
+            Ok(())
+        }
+        NotesCmd::Todo {
+            cmd: TodoCmd::List { id },
+        } => {
+            let id = resolve_note(&*backend, account, &id)?;
+            let spinner = progress::spinner("Loading note…");
+            let note = backend.get_note(&id)?;
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            let items = todo::checklist_items(&note.body_html);
+            if json {
+                return print_json(&items);
+            }
+            for item in &items {
+                let mark = if item.checked { "x" } else { " " };
+                println!("{}. [{mark}] {}", item.index, item.text);
+            }
+            Ok(())
+        }
+        NotesCmd::Todo {
+            cmd: TodoCmd::Toggle { id, item },
+        } => {
+            let id = resolve_note(&*backend, account, &id)?;
+            let spinner = progress::spinner("Updating checklist…");
+            let note = backend.get_note(&id)?;
+            let items = todo::checklist_items(&note.body_html);
+            let index = todo::find_item(&items, &item)?;
+            let body_html = todo::toggle(&note.body_html, index)
+                .ok_or_else(|| anyhow!("no checklist item {index}"))?;
+            backend.set_note_body_html(&id, &body_html)?;
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            let mut item = items[index - 1].clone();
+            item.checked = !item.checked;
+            if json {
+                print_json(&serde_json::json!({ "id": id, "item": item }))
+            } else {
+                let done = if item.checked { "Checked" } else { "Unchecked" };
+                println!("{done} {}. {}", item.index, item.text);
+                Ok(())
+            }
+        }
+        NotesCmd::Trash {
+            cmd: TrashCmd::List,
+        } => {
+            let spinner = progress::spinner("Loading Recently Deleted…");
+            let mut notes = backend.list_deleted_notes(account)?;
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            sort_notes(&mut notes, ListSort::Modified, true, &|_| String::new());
+            if json {
+                return print_json(&notes);
+            }
+            if notes.is_empty() {
+                println!("Recently Deleted is empty.");
+                return Ok(());
+            }
+            tables::render_table(
+                notes
+                    .iter()
+                    .map(|n| TrashRow {
+                        id: n.display_id().to_string(),
+                        title: n.title.clone(),
+                        deleted_at: n.modified_at,
+                    })
+                    .collect(),
+            );
+            Ok(())
+        }
+        NotesCmd::Trash {
+            cmd: TrashCmd::Restore { id, folder },
+        } => {
+            let spinner = progress::spinner("Restoring note…");
+            let deleted = backend.list_deleted_notes(account)?;
+            let id = match resolve::match_note(&deleted, &id) {
+                Err(NotesError::NotFound(_)) => {
+                    return Err(NotesError::NotFound(format!(
+                        "no note in Recently Deleted matches {id:?}"
+                    ))
+                    .into());
+                }
+                found => found?,
+            };
+            let folder_path = split_folder_path(folder.as_deref().unwrap_or(DEFAULT_FOLDER))?;
+            backend.move_note(&id, account, &folder_path)?;
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            if json {
+                print_json(&serde_json::json!({ "id": id, "folder": folder_path.join(" > ") }))
+            } else {
+                println!("Restored to {}.", folder_path.join(" > "));
+                Ok(())
+            }
+        }
+        NotesCmd::Watch { interval } => {
+            if !reads_db {
+                return Err(anyhow!(
+                    "`notes watch` needs the Notes database (--backend db or auto)"
+                ));
+            }
+            let files = crate::watch::db_files(&crate::db::notes_db_path()?);
+            let mut before = backup::live_notes_db(account, false)?;
+            let mut stdout = io::stdout().lock();
+            let poll = Duration::from_secs(interval.max(1));
+            // Whether the last read failed, so the next one shouldn't wait for another change.
+            let mut retry = false;
+            loop {
+                let waited = match retry {
+                    true => crate::watch::sleep(poll, cancel),
+                    false => crate::watch::wait_for_change(&files, poll, cancel),
+                };
+                // Ctrl-C (or `--timeout`) is how a watch ends.
+                if waited.is_err() {
+                    return Ok(());
+                }
+                // Notes may be mid-write (or the database briefly locked); try again next tick.
+                let after = match backup::live_notes_db(account, false) {
+                    Ok(after) => after,
+                    Err(e) => {
+                        eprintln!("Couldn't read the Notes database, retrying: {e:#}");
+                        retry = true;
+                        continue;
+                    }
+                };
+                retry = false;
+                for event in crate::watch::note_events(&before, &after, OffsetDateTime::now_utc()) {
+                    writeln!(stdout, "{}", serde_json::to_string(&event)?)?;
+                }
+                stdout.flush()?;
+                before = after;
+            }
+        }
+    }
+}
+
+/// Renames every note whose title matches `pattern`. With `preview` (how to apply, e.g. "with
+/// --yes"), only prints the planned renames.
+fn bulk_rename(
+    json: bool,
+    account: &str,
+    backend: &dyn NotesBackend,
+    pattern: &regex::Regex,
+    template: &str,
+    folder: Option<&str>,
+    preview: Option<&str>,
+) -> anyhow::Result<()> {
+    let spinner = progress::spinner("Loading notes…");
+    let notes = match folder {
+        Some(folder) => backend.list_notes_in_folder(account, &split_folder_path(folder)?)?,
+        None => backend.list_notes(account)?,
+    };
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+    let planned = rename::plan(&notes, pattern, template)?;
+    let yes = preview.is_none();
+
+    if !json {
+        struct RenameRow<'a>(&'a rename::PlannedRename);
+        impl tables::TableRow for RenameRow<'_> {
+            const HEADERS: &'static [&'static str] = &["Id", "Title", "New title"];
+            fn cells(&self) -> Vec<Cell> {
+                vec![
+                    Cell::new(tables::shorten_id_for_table(&self.0.id)),
+                    Cell::new(self.0.title.as_str()),
+                    Cell::new(self.0.new_title.as_str()),
+                ]
+            }
+        }
+        if planned.is_empty() {
+            println!("No notes to rename.");
+            return Ok(());
+        }
+        tables::render_table(planned.iter().map(RenameRow).collect());
+    }
+
+    if yes {
+        let pb = progress::bar(planned.len() as u64, "Renaming notes…");
+        for p in &planned {
+            backend.set_note_title(&p.id, &p.new_title)?;
+            if let Some(pb) = &pb {
+                pb.inc(1);
+            }
+        }
+        if let Some(pb) = pb {
+            pb.finish_and_clear();
+        }
+    }
+
+    if json {
+        #[derive(serde::Serialize)]
+        struct Out<'a> {
+            applied: bool,
+            renames: &'a [rename::PlannedRename],
+        }
+        print_json(&Out {
+            applied: yes,
+            renames: &planned,
+        })
+    } else {
+        if yes {
+            println!("Renamed {} notes.", planned.len());
+        } else if !progress::quiet() {
+            eprintln!(
+                "{} notes would be renamed; re-run {} to apply.",
+                planned.len(),
+                preview.unwrap_or_default()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A `notes list --picker` line: tabs and line breaks inside fields become spaces so each note
+/// stays one three-field line.
+fn picker_line(note: &NoteSummary, folder: &str) -> String {
+    let clean = |s: &str| s.replace(['\t', '\n', '\r'], " ");
+    format!(
+        "{}\t{}\t{}",
+        clean(note.display_id()),
+        clean(folder),
+        clean(&note.title)
+    )
+}
+
+/// An Alfred Script Filter item: actioning it passes the note id on (e.g. to `notes show`).
+fn alfred_item(note: &NoteSummary, folder: &str) -> serde_json::Value {
+    serde_json::json!({
+        "uid": note.display_id(),
+        "title": note.title,
+        "subtitle": folder,
+        "arg": note.display_id(),
+        "autocomplete": note.title,
+    })
+}
+
+/// The note id in a `notes list --picker` line (its first field; a bare id works too).
+fn picker_line_id(line: &str) -> anyhow::Result<String> {
+    let id = line.split('\t').next().unwrap_or_default().trim();
+    if id.is_empty() {
+        return Err(anyhow!("no note id in picker line {line:?}"));
+    }
+    Ok(id.to_string())
+}
+
+/// The note `notes show` should print: `<ID>`, a picker line read from stdin for `-`, or
+/// `--from-picker-line`.
+fn show_id(id: Option<String>, from_picker_line: Option<String>) -> anyhow::Result<String> {
+    match (id, from_picker_line) {
+        (_, Some(line)) => picker_line_id(&line),
+        (Some(id), None) if id == "-" => {
+            let mut line = String::new();
+            io::stdin().read_line(&mut line).context("read stdin")?;
+            picker_line_id(&line)
+        }
+        (Some(id), None) => Ok(id),
+        (None, None) => Err(anyhow!("missing note id")),
+    }
+}
+
+/// The note ids of an `--ids-from` file (`-` for stdin): one per line, blank lines skipped.
+fn read_ids(source: &str) -> anyhow::Result<Vec<String>> {
+    let text = if source == "-" {
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .context("read stdin")?;
+        text
+    } else {
+        std::fs::read_to_string(source).with_context(|| format!("read {source:?}"))?
+    };
+    let ids: Vec<String> = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect();
+    if ids.is_empty() {
+        return Err(anyhow!("no note ids in {source}"));
+    }
+    Ok(ids)
+}
+
+/// [`resolve_note`] for each of `references`, dropping repeats.
+fn resolve_notes(
+    backend: &dyn NotesBackend,
+    account: &str,
+    references: Vec<String>,
+) -> anyhow::Result<Vec<String>> {
+    let mut ids = Vec::new();
+    for reference in references {
+        let id = resolve_note(backend, account, &reference)?;
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    Ok(ids)
+}
+
+/// How many notes a delete prompt lists by title before summing up the rest.
+const PROMPT_NOTES: usize = 10;
+
+/// Goes ahead with a deletion if `yes` is set, or if stdin is a terminal and the user answers yes
+/// after seeing `summary()`. Refuses otherwise.
+fn confirm_delete(
+    yes: bool,
+    summary: impl FnOnce() -> anyhow::Result<String>,
+) -> anyhow::Result<()> {
+    if yes {
+        return Ok(());
+    }
+    if !io::stdin().is_terminal() {
+        return Err(anyhow!("refusing to delete without --yes"));
+    }
+    eprintln!("{}", summary()?);
+    match ask_yes_no("Delete? [y/N] ", &mut io::stdin().lock())? {
+        true => Ok(()),
+        false => Err(anyhow!("cancelled; nothing was deleted")),
+    }
+}
+
+/// Prints `question` to stderr and reads the answer from `input`: only `y` / `yes` mean yes.
+fn ask_yes_no(question: &str, input: &mut impl io::BufRead) -> anyhow::Result<bool> {
+    eprint!("{question}");
+    io::stderr().flush().ok();
+    let mut answer = String::new();
+    input.read_line(&mut answer).context("read answer")?;
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
+/// Refuses a plain `notes delete` of notes already in Recently Deleted, which Notes would delete
+/// for good; that takes `--permanent`.
+fn refuse_deleting_deleted_notes(backend: &dyn NotesBackend, ids: &[String]) -> anyhow::Result<()> {
+    backend.ensure_not_deleted(ids).map_err(|e| match e {
+        NotesError::AlreadyDeleted(msg) => {
+            NotesError::AlreadyDeleted(format!("{msg}; pass --permanent to delete it for good"))
         }
+        e => e,
+    })?;
+    Ok(())
+}
+
+/// What `notes delete` is about to do to `ids`, listing each note's title and folder.
+fn describe_note_deletion(
+    backend: &dyn NotesBackend,
+    account: &str,
+    ids: &[String],
+    permanent: bool,
+) -> anyhow::Result<String> {
+    let folders = backup::FolderIndex::new(&backend.list_folders(account)?)?;
+    let count = match ids.len() {
+        1 => "1 note".to_string(),
+        n => format!("{n} notes"),
+    };
+    let mut out = match permanent {
+        true => format!("This permanently deletes {count}:"),
+        false => format!("This moves {count} to Recently Deleted:"),
+    };
+    for id in ids.iter().take(PROMPT_NOTES) {
+        let note = backend.get_note(id)?;
+        let folder = folders
+            .folder_path_string(&note.folder_id)
+            .unwrap_or_else(|| "?".to_string());
+        out.push_str(&format!("\n  {:?} in {folder}", note.title));
     }
+    if ids.len() > PROMPT_NOTES {
+        out.push_str(&format!("\n  … and {} more", ids.len() - PROMPT_NOTES));
+    }
+    Ok(out)
 }
 
 fn read_body(
     body: Option<String>,
     body_file: Option<String>,
     stdin: bool,
+    clipboard: bool,
 ) -> anyhow::Result<String> {
     if let Some(body) = body {
         return Ok(body);
@@ -617,10 +3463,222 @@ fn read_body(
         io::stdin().read_to_string(&mut s).context("read stdin")?;
         return Ok(s);
     }
+    if clipboard {
+        return clipboard::paste();
+    }
     Ok(String::new())
 }
 
-fn split_folder_path(path: &str) -> anyhow::Result<Vec<String>> {
+fn body_to_html(body: &str, html: bool, markdown: bool, settings: &FolderSettings) -> String {
+    if html {
+        body.to_string()
+    } else if markdown {
+        render::markdown_to_html_dialect(body, settings.markdown_dialect.unwrap_or_default())
+    } else {
+        render::text_to_html(body)
+    }
+}
+
+/// What `notes show --meta` prints.
+#[derive(Debug, serde::Serialize)]
+struct NoteMeta {
+    id: String,
+    title: String,
+    folder: String,
+    #[serde(with = "time::serde::rfc3339")]
+    created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    modified_at: OffsetDateTime,
+    attachments: usize,
+    words: usize,
+    /// Shared through iCloud collaboration; only the database backend can tell.
+    shared: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    participants: Vec<String>,
+}
+
+/// Reads a note's metadata from the database when it's available, so the body is never fetched
+/// through Notes.app; other backends load the note and count from its HTML.
+fn note_meta(
+    backend: &dyn NotesBackend,
+    account: &str,
+    reads_db: bool,
+    id: &str,
+) -> anyhow::Result<NoteMeta> {
+    let (meta, folder_id) = if reads_db {
+        let id = crate::db::NotesDb::open_default()?.resolve_note_id(id)?;
+        let m = backup::note_meta_db(&id)?;
+        let meta = NoteMeta {
+            id,
+            title: m.title,
+            folder: String::new(),
+            created_at: m.created_at,
+            modified_at: m.modified_at,
+            attachments: m.attachments,
+            words: m.words,
+            shared: m.sharing.is_some(),
+            participants: m.sharing.unwrap_or_default(),
+        };
+        (meta, m.folder_id)
+    } else {
+        let note = backend.get_note(id)?;
+        let meta = NoteMeta {
+            id: note.id,
+            attachments: note.body_html.to_ascii_lowercase().matches("<img").count(),
+            words: render::word_count(&render::html_to_markdown(&note.body_html)),
+            title: note.title,
+            folder: String::new(),
+            created_at: note.created_at,
+            modified_at: note.modified_at,
+            shared: false,
+            participants: Vec::new(),
+        };
+        (meta, note.folder_id)
+    };
+    // The note may live in another account than `account`; look there last.
+    let mut accounts = vec![account.to_string()];
+    accounts.extend(
+        backend
+            .list_accounts()?
+            .into_iter()
+            .map(|a| a.name)
+            .filter(|a| a != account),
+    );
+    for account in accounts {
+        if let Some(folder) = backend
+            .list_folders(&account)?
+            .into_iter()
+            .find(|f| f.id == folder_id)
+        {
+            return Ok(NoteMeta {
+                folder: folder.path_string(),
+                ..meta
+            });
+        }
+    }
+    Ok(meta)
+}
+
+/// Folder settings for an existing note. Only looks the note up when the config has folder
+/// sections, so the common case costs no extra `osascript` round-trips.
+fn note_folder_settings(
+    config: &Config,
+    backend: &dyn NotesBackend,
+    account: &str,
+    id: &str,
+) -> anyhow::Result<FolderSettings> {
+    if config.folders.is_empty() {
+        return Ok(FolderSettings::default());
+    }
+    let note = backend.get_note(id)?;
+    let folders = backend.list_folders(account)?;
+    let folder_path = backup::FolderIndex::new(&folders)?
+        .folder_path(&note.folder_id)
+        .unwrap_or_default();
+    Ok(config.folder_settings(&folder_path))
+}
+
+/// Goes between merged notes, and between a `notes merge --into` target and the first of them.
+const MERGE_SEPARATOR: &str = "<div><br></div><hr><div><br></div>";
+
+/// `notes merge`: the notes' bodies in order, each under an `<h2>` with its title.
+fn merged_body_html(notes: &[Note]) -> String {
+    notes
+        .iter()
+        .map(|n| format!("<h2>{}</h2>{}", render::escape_html(&n.title), n.body_html))
+        .collect::<Vec<_>>()
+        .join(MERGE_SEPARATOR)
+}
+
+fn local_timestamp() -> anyhow::Result<String> {
+    let now = time::OffsetDateTime::now_local().unwrap_or_else(|_| time::OffsetDateTime::now_utc());
+    Ok(now.format(time::macros::format_description!(
+        "[year]-[month]-[day] [hour]:[minute]"
+    ))?)
+}
+
+fn local_date() -> anyhow::Result<String> {
+    let now = time::OffsetDateTime::now_local().unwrap_or_else(|_| time::OffsetDateTime::now_utc());
+    Ok(now.format(time::macros::format_description!("[year]-[month]-[day]"))?)
+}
+
+/// Longest first line `quick` puts in a title, in characters.
+const QUICK_TITLE_CHARS: usize = 60;
+
+/// The title Notes would give a note with this body: its first non-empty line, without
+/// Markdown heading markers.
+fn title_from_body(body: &str, html: bool) -> Option<String> {
+    let text = if html {
+        render::html_to_markdown(body)
+    } else {
+        body.to_string()
+    };
+    text.lines()
+        .map(|l| {
+            let l = l.trim();
+            match l.trim_start_matches('#') {
+                // `#tag` stays: only `# Heading` markers go.
+                rest if rest.is_empty() || rest.starts_with(char::is_whitespace) => rest.trim(),
+                _ => l,
+            }
+        })
+        .find(|l| !l.is_empty())
+        .map(str::to_string)
+}
+
+/// `quick`'s note title: `<timestamp> <first line>`, the line (as [`title_from_body`] finds
+/// it) shortened to [`QUICK_TITLE_CHARS`].
+fn quick_title(text: &str, timestamp: &str) -> anyhow::Result<String> {
+    let first_line = title_from_body(text, false)
+        .ok_or_else(|| anyhow!("nothing to capture: the text is empty"))?;
+    let mut title = format!("{timestamp} ");
+    if first_line.chars().count() > QUICK_TITLE_CHARS {
+        title.extend(first_line.chars().take(QUICK_TITLE_CHARS - 1));
+        title.push('…');
+    } else {
+        title.push_str(&first_line);
+    }
+    Ok(title)
+}
+
+/// Parses a `--modified-after`-style date: `YYYY-MM-DD` (midnight UTC), a full RFC 3339
+/// timestamp, or a time ago like `12h`, `7d` or `2w`.
+fn parse_date(s: &str) -> Result<OffsetDateTime, String> {
+    parse_date_at(s, OffsetDateTime::now_utc())
+}
+
+fn parse_date_at(s: &str, now: OffsetDateTime) -> Result<OffsetDateTime, String> {
+    if let Ok(t) = OffsetDateTime::parse(s, &Rfc3339) {
+        return Ok(t);
+    }
+    if let Some(unit) = s.chars().last()
+        && let Ok(n) = s[..s.len() - unit.len_utf8()].parse::<i64>()
+    {
+        let ago = match unit {
+            'h' => Some(time::Duration::hours(n)),
+            'd' => Some(time::Duration::days(n)),
+            'w' => Some(time::Duration::weeks(n)),
+            _ => None,
+        };
+        if let Some(ago) = ago {
+            return Ok(now - ago);
+        }
+    }
+    time::Date::parse(s, time::macros::format_description!("[year]-[month]-[day]"))
+        .map(|d| d.midnight().assume_utc())
+        .map_err(|_| format!("invalid date {s:?} (expected YYYY-MM-DD, RFC 3339, or e.g. 7d)"))
+}
+
+/// The folder `notes move`/`quick` write to: `--folder`, already defaulted from the
+/// config file by [`Args::apply_config`].
+fn target_folder(folder: Option<&str>) -> anyhow::Result<Vec<String>> {
+    let folder = folder.ok_or_else(|| {
+        anyhow!("--folder is required (or set default_folder in the config file)")
+    })?;
+    split_folder_path(folder)
+}
+
+pub(crate) fn split_folder_path(path: &str) -> anyhow::Result<Vec<String>> {
     let parts: Vec<String> = path
         .split('>')
         .map(|p| p.trim())
@@ -652,7 +3710,10 @@ fn print_folders_table(folders: &[Folder]) -> anyhow::Result<()> {
     let mut rows: Vec<FolderRow> = folders
         .iter()
         .map(|f| FolderRow {
-            path: f.path_string(),
+            path: match f.smart {
+                true => format!("{} (smart)", f.path_string()),
+                false => f.path_string(),
+            },
             id: f.id.clone(),
         })
         .collect();
@@ -662,94 +3723,522 @@ fn print_folders_table(folders: &[Folder]) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn print_folder_tree(folders: &[Folder]) -> anyhow::Result<()> {
-    let mut folders = folders.to_vec();
+/// `folders list --tree`: top-level folders flush left, subfolders under box-drawing
+/// connectors, each with its note count (`counts`, by folder id) and id. `depth` limits the
+/// levels shown.
+fn folder_tree_lines(
+    folders: &[Folder],
+    counts: &HashMap<&str, usize>,
+    depth: Option<usize>,
+) -> Vec<String> {
+    fn walk(
+        folders: &[&Folder],
+        parent: &[String],
+        prefix: &str,
+        counts: &HashMap<&str, usize>,
+        depth: Option<usize>,
+        lines: &mut Vec<String>,
+    ) {
+        if depth.is_some_and(|d| parent.len() >= d) {
+            return;
+        }
+        let children: Vec<&Folder> = folders
+            .iter()
+            .filter(|f| f.path.len() == parent.len() + 1 && f.path.starts_with(parent))
+            .copied()
+            .collect();
+        for (i, f) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            let (connector, indent) = match (parent.is_empty(), last) {
+                (true, _) => ("", ""),
+                (false, false) => ("├── ", "│   "),
+                (false, true) => ("└── ", "    "),
+            };
+            let notes = match f.smart {
+                true => "smart".to_string(),
+                false => format!("{} notes", counts.get(f.id.as_str()).unwrap_or(&0)),
+            };
+            lines.push(format!(
+                "{prefix}{connector}{} ({notes})  {}",
+                f.name,
+                tables::shorten_id_for_table(&f.id)
+            ));
+            walk(
+                folders,
+                &f.path,
+                &format!("{prefix}{indent}"),
+                counts,
+                depth,
+                lines,
+            );
+        }
+    }
+
+    let mut folders: Vec<&Folder> = folders.iter().collect();
     folders.sort_by(|a, b| a.path.cmp(&b.path));
-    for f in folders {
-        let indent = "  ".repeat(f.path.len().saturating_sub(1));
-        println!("{indent}{}", f.name);
+    let mut lines = Vec::new();
+    walk(&folders, &[], "", counts, depth, &mut lines);
+    lines
+}
+
+#[derive(Debug)]
+struct NoteRow {
+    id: String,
+    folder: String,
+    title: String,
+    pinned: bool,
+    locked: bool,
+    created_at: Option<OffsetDateTime>,
+    modified_at: Option<OffsetDateTime>,
+}
+
+impl tables::TableRow for NoteRow {
+    const HEADERS: &'static [&'static str] = &[
+        "Id", "Folder", "Title", "Pinned", "Locked", "Created", "Modified",
+    ];
+    /// Room for a shortened id, "yes", and a `table_date`.
+    const WIDTHS: &'static [Option<usize>] =
+        &[Some(28), None, None, Some(6), Some(6), Some(16), Some(16)];
+    fn cells(&self) -> Vec<Cell> {
+        vec![
+            Cell::new(tables::shorten_id_for_table(self.id.as_str())),
+            Cell::new(self.folder.as_str()),
+            Cell::new(self.title.as_str()),
+            Cell::new(if self.pinned { "yes" } else { "" }),
+            Cell::new(if self.locked { "yes" } else { "" }),
+            Cell::new(table_date(self.created_at)),
+            Cell::new(table_date(self.modified_at)),
+        ]
     }
-    Ok(())
 }
 
-fn print_note_summaries(
-    notes: &[NoteSummary],
-    folder_index: &backup::FolderIndex,
-    limit: Option<usize>,
-) -> anyhow::Result<()> {
-    #[derive(Debug)]
-    struct NoteRow {
-        id: String,
-        folder: String,
-        title: String,
+#[derive(Debug, serde::Serialize)]
+struct WordCountRow {
+    id: String,
+    title: String,
+    words: usize,
+    characters: usize,
+}
+
+impl tables::TableRow for WordCountRow {
+    const HEADERS: &'static [&'static str] = &["Id", "Title", "Words", "Characters"];
+    fn cells(&self) -> Vec<Cell> {
+        vec![
+            Cell::new(tables::shorten_id_for_table(self.id.as_str())),
+            Cell::new(self.title.as_str()),
+            Cell::new(self.words),
+            Cell::new(self.characters),
+        ]
     }
-    impl tables::TableRow for NoteRow {
-        const HEADERS: &'static [&'static str] = &["Id", "Folder", "Title"];
-        fn cells(&self) -> Vec<Cell> {
-            vec![
-                Cell::new(tables::shorten_id_for_table(self.id.as_str())),
-                Cell::new(self.folder.as_str()),
-                Cell::new(self.title.as_str()),
-            ]
-        }
+}
+
+/// A note in Recently Deleted. Deleting a note updates its modification date, which is the
+/// best record of when it was deleted.
+#[derive(Debug)]
+struct TrashRow {
+    id: String,
+    title: String,
+    deleted_at: Option<OffsetDateTime>,
+}
+
+impl tables::TableRow for TrashRow {
+    const HEADERS: &'static [&'static str] = &["Id", "Title", "Deleted"];
+    fn cells(&self) -> Vec<Cell> {
+        vec![
+            Cell::new(tables::shorten_id_for_table(self.id.as_str())),
+            Cell::new(self.title.as_str()),
+            Cell::new(table_date(self.deleted_at)),
+        ]
+    }
+}
+
+#[derive(Debug)]
+struct LintRow(lint::LintFinding);
+
+impl tables::TableRow for LintRow {
+    const HEADERS: &'static [&'static str] = &["Problem", "Id", "Title", "Detail"];
+    fn cells(&self) -> Vec<Cell> {
+        vec![
+            Cell::new(self.0.issue.as_str()),
+            Cell::new(tables::shorten_id_for_table(self.0.id.as_str())),
+            Cell::new(self.0.title.as_str()),
+            Cell::new(self.0.detail.as_deref().unwrap_or("")),
+        ]
+    }
+}
+
+/// A [`NoteRow`] with a body snippet, for `notes list --preview`.
+#[derive(Debug)]
+struct PreviewRow {
+    row: NoteRow,
+    preview: String,
+}
+
+impl tables::TableRow for PreviewRow {
+    const HEADERS: &'static [&'static str] = &[
+        "Id", "Folder", "Title", "Pinned", "Locked", "Created", "Modified", "Preview",
+    ];
+    fn cells(&self) -> Vec<Cell> {
+        let mut cells = self.row.cells();
+        cells.push(Cell::new(self.preview.as_str()));
+        cells
+    }
+}
+
+/// A listing row under a `--group-by-folder` heading, which already names the folder.
+#[derive(Debug)]
+struct InFolder<T>(T);
+
+impl tables::TableRow for InFolder<NoteRow> {
+    const HEADERS: &'static [&'static str] =
+        &["Id", "Title", "Pinned", "Locked", "Created", "Modified"];
+    fn cells(&self) -> Vec<Cell> {
+        let mut cells = self.0.cells();
+        cells.remove(1);
+        cells
+    }
+}
+
+impl tables::TableRow for InFolder<PreviewRow> {
+    const HEADERS: &'static [&'static str] = &[
+        "Id", "Title", "Pinned", "Locked", "Created", "Modified", "Preview",
+    ];
+    fn cells(&self) -> Vec<Cell> {
+        let mut cells = self.0.cells();
+        cells.remove(1);
+        cells
     }
+}
 
-    let mut rows: Vec<NoteRow> = notes
+/// Body snippets for `notes list --preview`, keyed by note id.
+fn note_previews(
+    backend: &dyn NotesBackend,
+    reads_db: bool,
+    notes: &[NoteSummary],
+) -> anyhow::Result<HashMap<String, String>> {
+    let texts = note_texts(backend, reads_db, notes, "Loading previews…")?;
+    Ok(notes
         .iter()
-        .map(|n| NoteRow {
-            id: n.id.clone(),
-            folder: folder_index
-                .folder_path_string(&n.folder_id)
-                .unwrap_or_else(|| "?".to_string()),
-            title: n.title.clone(),
+        .filter_map(|n| {
+            let text = texts.get(&n.id)?;
+            Some((n.id.clone(), render::snippet(text, &n.title, PREVIEW_CHARS)))
         })
-        .collect();
-    rows.sort_by(|a, b| a.title.cmp(&b.title));
-    if let Some(limit) = limit {
-        rows.truncate(limit);
+        .collect())
+}
+
+/// The plain-text bodies of `notes`, keyed by note id. Read from the database when the backend
+/// opened it (`reads_db`), otherwise one backend call per note.
+fn note_texts(
+    backend: &dyn NotesBackend,
+    reads_db: bool,
+    notes: &[NoteSummary],
+    message: &str,
+) -> anyhow::Result<HashMap<String, String>> {
+    note_bodies(backend, reads_db, notes, message, true)
+}
+
+/// [`note_texts`], or the bodies as Markdown when `plain` is false. Locked notes are left out:
+/// their bodies are encrypted.
+fn note_bodies(
+    backend: &dyn NotesBackend,
+    reads_db: bool,
+    notes: &[NoteSummary],
+    message: &str,
+    plain: bool,
+) -> anyhow::Result<HashMap<String, String>> {
+    let notes: Vec<&NoteSummary> = notes.iter().filter(|n| !n.locked).collect();
+    if reads_db {
+        let spinner = progress::spinner(message);
+        let ids: Vec<String> = notes.iter().map(|n| n.id.clone()).collect();
+        let bodies = if plain {
+            backup::note_texts_db(&ids)?
+        } else {
+            backup::note_markdown_db(&ids)?
+        };
+        if let Some(spinner) = spinner {
+            spinner.finish_and_clear();
+        }
+        return Ok(bodies);
+    }
+    let pb = progress::bar(notes.len() as u64, message);
+    let mut bodies = HashMap::new();
+    for n in notes {
+        let note = backend.get_note(&n.id)?;
+        let markdown = render::html_to_markdown(&note.body_html);
+        let body = if plain {
+            render::markdown_to_text(&markdown)
+        } else {
+            markdown
+        };
+        bodies.insert(n.id.clone(), body);
+        if let Some(pb) = &pb {
+            pb.inc(1);
+        }
     }
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
+    }
+    Ok(bodies)
+}
 
-    tables::render_table(rows);
-    Ok(())
+/// Markdown bodies for `notes replace`, which stores them back as HTML, each with why it can't
+/// take that round trip without losing content, if so.
+fn rewritable_bodies(
+    backend: &dyn NotesBackend,
+    reads_db: bool,
+    notes: &[NoteSummary],
+) -> anyhow::Result<HashMap<String, (String, Option<&'static str>)>> {
+    let notes: Vec<&NoteSummary> = notes.iter().filter(|n| !n.locked).collect();
+    if reads_db {
+        let spinner = progress::spinner("Reading notes…");
+        let ids: Vec<String> = notes.iter().map(|n| n.id.clone()).collect();
+        let bodies = backup::note_markdown_for_rewrite_db(&ids)?;
+        if let Some(spinner) = spinner {
+            spinner.finish_and_clear();
+        }
+        return Ok(bodies);
+    }
+    let pb = progress::bar(notes.len() as u64, "Reading notes…");
+    let mut bodies = HashMap::new();
+    for n in notes {
+        let note = backend.get_note(&n.id)?;
+        let blocker =
+            render::html_has_attachments(&note.body_html).then_some(backup::HAS_ATTACHMENTS);
+        bodies.insert(
+            n.id.clone(),
+            (render::html_to_markdown(&note.body_html), blocker),
+        );
+        if let Some(pb) = &pb {
+            pb.inc(1);
+        }
+    }
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
+    }
+    Ok(bodies)
+}
+
+/// A date for a table cell, in local time; empty when unknown.
+fn table_date(date: Option<OffsetDateTime>) -> String {
+    let Some(date) = date else {
+        return String::new();
+    };
+    let offset = time::UtcOffset::current_local_offset().unwrap_or(time::UtcOffset::UTC);
+    date.to_offset(offset)
+        .format(time::macros::format_description!(
+            "[year]-[month]-[day] [hour]:[minute]"
+        ))
+        .unwrap_or_default()
+}
+
+/// `notes list`'s date range filters. A note whose date the backend doesn't know never passes a
+/// filter on that date.
+#[derive(Debug, Default)]
+struct DateFilter {
+    modified_after: Option<OffsetDateTime>,
+    modified_before: Option<OffsetDateTime>,
+    created_after: Option<OffsetDateTime>,
+    created_before: Option<OffsetDateTime>,
+}
+
+impl DateFilter {
+    fn matches(&self, n: &NoteSummary) -> bool {
+        let within = |date: Option<OffsetDateTime>,
+                      after: Option<OffsetDateTime>,
+                      before: Option<OffsetDateTime>| {
+            (after.is_none() && before.is_none())
+                || date.is_some_and(|d| after.is_none_or(|a| d > a) && before.is_none_or(|b| d < b))
+        };
+        within(n.modified_at, self.modified_after, self.modified_before)
+            && within(n.created_at, self.created_after, self.created_before)
+    }
+}
+
+/// The title filter of `notes list`: `--query` as a literal substring or `--regex` as a pattern.
+fn title_filter(
+    query: Option<&str>,
+    pattern: Option<&str>,
+    case_sensitive: bool,
+) -> anyhow::Result<Option<regex::Regex>> {
+    let pattern = match (query, pattern) {
+        (Some(query), _) => regex::escape(query),
+        (None, Some(pattern)) => pattern.to_string(),
+        (None, None) => return Ok(None),
+    };
+    regex::RegexBuilder::new(&pattern)
+        .case_insensitive(!case_sensitive)
+        .build()
+        .map(Some)
+        .with_context(|| format!("invalid --regex {pattern:?}"))
+}
+
+/// Orders a listing for `notes list`; ties are broken by title, then id.
+fn sort_notes(
+    notes: &mut [NoteSummary],
+    sort: ListSort,
+    reverse: bool,
+    folder_of: &dyn Fn(&NoteSummary) -> String,
+) {
+    let by_title =
+        |a: &NoteSummary, b: &NoteSummary| a.title.cmp(&b.title).then_with(|| a.id.cmp(&b.id));
+    match sort {
+        ListSort::Unsorted => {}
+        ListSort::Title => notes.sort_by(by_title),
+        ListSort::Created => {
+            notes.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| by_title(a, b)))
+        }
+        ListSort::Modified => notes.sort_by(|a, b| {
+            a.modified_at
+                .cmp(&b.modified_at)
+                .then_with(|| by_title(a, b))
+        }),
+        ListSort::Folder => {
+            notes.sort_by_cached_key(|n| (folder_of(n), n.title.clone(), n.id.clone()))
+        }
+    }
+    if reverse {
+        notes.reverse();
+    }
 }
 
-fn print_note_summaries_folder_hint(
+fn print_note_summaries(
     notes: &[NoteSummary],
-    folder: &str,
-    limit: Option<usize>,
+    folder_of: &dyn Fn(&NoteSummary) -> String,
+    previews: Option<&HashMap<String, String>>,
+    group_by_folder: bool,
 ) -> anyhow::Result<()> {
-    #[derive(Debug)]
-    struct NoteRow {
-        id: String,
-        folder: String,
-        title: String,
+    let row = |n: &NoteSummary| NoteRow {
+        id: n.display_id().to_string(),
+        folder: folder_of(n),
+        title: n.title.clone(),
+        pinned: n.pinned,
+        locked: n.locked,
+        created_at: n.created_at,
+        modified_at: n.modified_at,
+    };
+    let preview_row = |n: &NoteSummary, previews: &HashMap<String, String>| PreviewRow {
+        row: row(n),
+        preview: previews.get(&n.id).cloned().unwrap_or_default(),
+    };
+    if !group_by_folder {
+        match previews {
+            Some(previews) => tables::render_table(
+                notes
+                    .iter()
+                    .map(|n| preview_row(n, previews))
+                    .collect::<Vec<_>>(),
+            ),
+            None => tables::render_table(notes.iter().map(row).collect::<Vec<_>>()),
+        }
+        return Ok(());
     }
-    impl tables::TableRow for NoteRow {
-        const HEADERS: &'static [&'static str] = &["Id", "Folder", "Title"];
-        fn cells(&self) -> Vec<Cell> {
-            vec![
-                Cell::new(tables::shorten_id_for_table(self.id.as_str())),
-                Cell::new(self.folder.as_str()),
-                Cell::new(self.title.as_str()),
-            ]
+    let groups = group_by_folder_path(notes.iter().map(|n| (folder_of(n), n)));
+    for (i, (folder, notes)) in groups.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{folder} ({})", notes.len());
+        match previews {
+            Some(previews) => tables::render_table(
+                notes
+                    .iter()
+                    .map(|n| InFolder(preview_row(n, previews)))
+                    .collect::<Vec<_>>(),
+            ),
+            None => tables::render_table(notes.iter().map(|n| InFolder(row(n))).collect()),
         }
     }
+    Ok(())
+}
 
-    let mut rows: Vec<NoteRow> = notes
-        .iter()
-        .map(|n| NoteRow {
+/// Items grouped by folder path, folders in path order; items keep their order within a folder.
+fn group_by_folder_path<T>(items: impl IntoIterator<Item = (String, T)>) -> Vec<(String, Vec<T>)> {
+    let mut groups: std::collections::BTreeMap<String, Vec<T>> = Default::default();
+    for (folder, item) in items {
+        groups.entry(folder).or_default().push(item);
+    }
+    groups.into_iter().collect()
+}
+
+#[derive(Debug)]
+struct StatusRow {
+    status: String,
+    id: String,
+    title: String,
+}
+
+impl tables::TableRow for StatusRow {
+    const HEADERS: &'static [&'static str] = &["Status", "Id", "Title"];
+    fn cells(&self) -> Vec<Cell> {
+        vec![
+            Cell::new(self.status.as_str()),
+            Cell::new(tables::shorten_id_for_table(self.id.as_str())),
+            Cell::new(self.title.as_str()),
+        ]
+    }
+}
+
+impl StatusRow {
+    fn new(status: impl Into<String>, note: &verify::NoteRef) -> Self {
+        Self {
+            status: status.into(),
+            id: note.id.clone(),
+            title: note.title.clone(),
+        }
+    }
+}
+
+fn print_verify_report(report: &verify::VerifyReport) {
+    if report.is_ok() {
+        if progress::quiet() {
+            return;
+        }
+        println!(
+            "OK: {} exported notes match the live account",
+            report.exported
+        );
+        return;
+    }
+
+    let mut rows = Vec::new();
+    rows.extend(report.missing.iter().map(|n| StatusRow::new("missing", n)));
+    rows.extend(report.extra.iter().map(|n| StatusRow::new("extra", n)));
+    for n in &report.stale {
+        rows.push(StatusRow {
+            status: match n.reason {
+                verify::StaleReason::Modified => "stale (modified)".to_string(),
+                verify::StaleReason::Contents => "stale (contents)".to_string(),
+            },
             id: n.id.clone(),
-            folder: folder.to_string(),
             title: n.title.clone(),
-        })
-        .collect();
-    rows.sort_by(|a, b| a.title.cmp(&b.title));
-    if let Some(limit) = limit {
-        rows.truncate(limit);
+        });
+    }
+    tables::render_table(rows);
+}
+
+fn print_diff_report(report: &diff::DiffReport) {
+    if report.added.is_empty() && report.removed.is_empty() && report.modified.is_empty() {
+        println!("No differences");
+        return;
     }
 
+    let mut rows = Vec::new();
+    rows.extend(report.added.iter().map(|n| StatusRow::new("added", n)));
+    rows.extend(report.removed.iter().map(|n| StatusRow::new("removed", n)));
+    for n in &report.modified {
+        rows.push(StatusRow {
+            status: format!("modified ({})", n.changes.join(", ")),
+            id: n.id.clone(),
+            title: n.title.clone(),
+        });
+    }
     tables::render_table(rows);
-    Ok(())
+
+    for n in &report.modified {
+        if let Some(patch) = &n.patch {
+            print!("{patch}");
+        }
+    }
 }
 
 fn print_json<T: serde::Serialize>(value: &T) -> anyhow::Result<()> {
@@ -760,6 +4249,42 @@ fn print_json<T: serde::Serialize>(value: &T) -> anyhow::Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tables::TableRow;
+
+    #[test]
+    fn streaming_note_rows_fit_every_column() {
+        assert_eq!(NoteRow::WIDTHS.len(), NoteRow::HEADERS.len());
+        let date = Some(time::macros::datetime!(2024-03-04 05:06 UTC));
+        let mut buf = Vec::new();
+        let mut table = tables::StreamingTable::new::<NoteRow>(&mut buf, 140, false).unwrap();
+        table
+            .push(&NoteRow {
+                id: "n1".into(),
+                folder: "Personal".into(),
+                title: "Alpha".into(),
+                pinned: true,
+                locked: true,
+                created_at: date,
+                modified_at: date,
+            })
+            .unwrap();
+        table.finish().unwrap();
+
+        let out = String::from_utf8(buf).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        let cells = |line: &str| -> Vec<String> {
+            line.trim_matches(|c| c == '│' || c == ' ')
+                .split('┆')
+                .map(|c| c.trim().to_string())
+                .collect()
+        };
+        assert_eq!(cells(lines[1]), NoteRow::HEADERS);
+        let row = cells(lines[3]);
+        assert_eq!(&row[..5], ["n1", "Personal", "Alpha", "yes", "yes"]);
+        assert_eq!(row[5], table_date(date));
+        assert_eq!(row[6], table_date(date));
+        assert!(!out.contains('…'), "{out}");
+    }
 
     #[test]
     fn split_folder_path_parses_and_trims() {
@@ -773,16 +4298,184 @@ mod tests {
         );
     }
 
+    #[test]
+    fn folder_tree_lines_draw_connectors_counts_and_ids() {
+        let folder = |id: &str, path: &[&str]| Folder {
+            id: id.into(),
+            name: path.last().unwrap().to_string(),
+            account: "iCloud".into(),
+            path: path.iter().map(|p| p.to_string()).collect(),
+            created_at: None,
+            modified_at: None,
+            smart: false,
+        };
+        let folders = [
+            folder("f4", &["Work"]),
+            folder("f1", &["Personal"]),
+            folder("f3", &["Personal", "Trips"]),
+            folder("f2", &["Personal", "Archive"]),
+            folder("f5", &["Personal", "Archive", "2024"]),
+        ];
+        let counts = HashMap::from([("f1", 1), ("f2", 2)]);
+        assert_eq!(
+            folder_tree_lines(&folders, &counts, None),
+            [
+                "Personal (1 notes)  f1",
+                "├── Archive (2 notes)  f2",
+                "│   └── 2024 (0 notes)  f5",
+                "└── Trips (0 notes)  f3",
+                "Work (0 notes)  f4",
+            ]
+        );
+        assert_eq!(
+            folder_tree_lines(&folders, &counts, Some(1)),
+            ["Personal (1 notes)  f1", "Work (0 notes)  f4"]
+        );
+    }
+
+    #[test]
+    fn merged_body_html_heads_each_note_with_its_title() {
+        let note = |title: &str, body: &str| Note {
+            id: title.into(),
+            title: title.into(),
+            folder_id: "f".into(),
+            created_at: OffsetDateTime::UNIX_EPOCH,
+            modified_at: OffsetDateTime::UNIX_EPOCH,
+            body_html: body.into(),
+        };
+        assert_eq!(
+            merged_body_html(&[note("A & B", "<div>one</div>"), note("C", "<div>two</div>")]),
+            format!("<h2>A &amp; B</h2><div>one</div>{MERGE_SEPARATOR}<h2>C</h2><div>two</div>")
+        );
+    }
+
+    #[test]
+    fn delete_prompts_name_the_notes_and_take_only_yes() {
+        let backend = crate::fixture::FixtureBackend::from_path(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/basic.json"),
+        )
+        .unwrap();
+        let ids = ["n1".to_string(), "n2".to_string()];
+        assert_eq!(
+            describe_note_deletion(&backend, "iCloud", &ids, false).unwrap(),
+            "This moves 2 notes to Recently Deleted:\n  \"Alpha\" in Personal\n  \"Beta\" in Personal > Archive"
+        );
+        let answer = |text: &str| ask_yes_no("", &mut io::Cursor::new(text.to_string())).unwrap();
+        assert!(answer("y\n") && answer(" YES\n"));
+        assert!(!answer("\n") && !answer("no\n") && !answer(""));
+    }
+
+    #[test]
+    fn parse_date_accepts_day_or_timestamp() {
+        assert_eq!(
+            parse_date("2025-03-04").unwrap(),
+            time::macros::datetime!(2025-03-04 00:00 UTC)
+        );
+        assert_eq!(
+            parse_date("2025-03-04T05:06:07+02:00").unwrap(),
+            time::macros::datetime!(2025-03-04 05:06:07 +2)
+        );
+        assert!(parse_date("last tuesday").is_err());
+
+        let now = time::macros::datetime!(2025-03-04 12:00 UTC);
+        assert_eq!(
+            parse_date_at("7d", now).unwrap(),
+            time::macros::datetime!(2025-02-25 12:00 UTC)
+        );
+        assert_eq!(
+            parse_date_at("2w", now).unwrap(),
+            time::macros::datetime!(2025-02-18 12:00 UTC)
+        );
+        assert_eq!(
+            parse_date_at("36h", now).unwrap(),
+            time::macros::datetime!(2025-03-03 00:00 UTC)
+        );
+        assert!(parse_date_at("7y", now).is_err());
+    }
+
+    #[test]
+    fn title_filters_escape_queries_and_honor_case() {
+        let matches = |query, pattern, case_sensitive, title| {
+            title_filter(query, pattern, case_sensitive)
+                .unwrap()
+                .unwrap()
+                .is_match(title)
+        };
+        assert!(matches(Some("a.b"), None, false, "A.B notes"));
+        assert!(!matches(Some("a.b"), None, false, "axb"));
+        assert!(!matches(Some("alpha"), None, true, "Alpha"));
+        assert!(matches(None, Some(r"^\d{4}-"), false, "2025-01 plan"));
+        assert!(!matches(None, Some("^plan"), true, "Plan"));
+        assert!(title_filter(None, None, true).unwrap().is_none());
+        assert!(title_filter(None, Some("("), false).is_err());
+    }
+
     #[test]
     fn split_folder_path_rejects_empty() {
         assert!(split_folder_path("   ").is_err());
         assert!(split_folder_path(" > > ").is_err());
     }
 
+    #[test]
+    fn picker_lines_round_trip_to_the_note_id() {
+        let note = NoteSummary {
+            id: "x-coredata://UUID/ICNote/p1".into(),
+            title: "Tabs\tand\nbreaks".into(),
+            folder_id: "f1".into(),
+            uuid: None,
+            created_at: None,
+            modified_at: None,
+            pinned: false,
+            locked: false,
+        };
+        let line = picker_line(&note, "Personal > Archive");
+        assert_eq!(
+            line,
+            "x-coredata://UUID/ICNote/p1\tPersonal > Archive\tTabs and breaks"
+        );
+        assert_eq!(picker_line_id(&format!("{line}\n")).unwrap(), note.id);
+        assert_eq!(picker_line_id("n1").unwrap(), "n1");
+        assert!(picker_line_id("\n").is_err());
+    }
+
+    #[test]
+    fn titles_come_from_the_first_nonblank_body_line() {
+        assert_eq!(
+            title_from_body("\n\n## Trip to Rome \nDay 1", false).as_deref(),
+            Some("Trip to Rome")
+        );
+        assert_eq!(
+            title_from_body("<div><br></div><div>Groceries</div>", true).as_deref(),
+            Some("Groceries")
+        );
+        assert_eq!(
+            title_from_body("#work stuff", false).as_deref(),
+            Some("#work stuff")
+        );
+        assert_eq!(title_from_body(" \n#\n", false), None);
+    }
+
+    #[test]
+    fn quick_titles_use_the_first_nonblank_line() {
+        let stamp = "2026-10-17 09:41";
+        assert_eq!(
+            quick_title("\n  Buy milk \nand eggs", stamp).unwrap(),
+            "2026-10-17 09:41 Buy milk"
+        );
+        assert_eq!(
+            quick_title("# Ideas\n- one", stamp).unwrap(),
+            "2026-10-17 09:41 Ideas"
+        );
+        let long = quick_title(&"é".repeat(100), stamp).unwrap();
+        assert_eq!(long.chars().count(), stamp.len() + 1 + QUICK_TITLE_CHARS);
+        assert!(long.ends_with('…'));
+        assert!(quick_title(" \n\t", stamp).is_err());
+    }
+
     #[test]
     fn read_body_prefers_inline() {
         assert_eq!(
-            read_body(Some("x".into()), Some("y".into()), true).unwrap(),
+            read_body(Some("x".into()), Some("y".into()), true, true).unwrap(),
             "x"
         );
     }
@@ -0,0 +1,54 @@
+//! The macOS pasteboard, through `pbpaste` and `pbcopy`.
+
+use anyhow::{Context, anyhow};
+use std::ffi::OsString;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn pbpaste_bin() -> OsString {
+    std::env::var_os("APPLE_NOTES_PBPASTE_BIN").unwrap_or_else(|| OsString::from("pbpaste"))
+}
+
+fn pbcopy_bin() -> OsString {
+    std::env::var_os("APPLE_NOTES_PBCOPY_BIN").unwrap_or_else(|| OsString::from("pbcopy"))
+}
+
+/// The clipboard's text contents.
+pub fn paste() -> anyhow::Result<String> {
+    let out = Command::new(pbpaste_bin())
+        .stdin(Stdio::null())
+        .output()
+        .context("failed to spawn pbpaste (are you on macOS?)")?;
+    if !out.status.success() {
+        return Err(anyhow!(
+            "pbpaste failed ({}): {}",
+            out.status,
+            String::from_utf8_lossy(&out.stderr)
+        ));
+    }
+    String::from_utf8(out.stdout).context("clipboard text isn't UTF-8")
+}
+
+/// Replaces the clipboard's contents with `text`.
+pub fn copy(text: &str) -> anyhow::Result<()> {
+    let mut child = Command::new(pbcopy_bin())
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to spawn pbcopy (are you on macOS?)")?;
+    {
+        let mut stdin = child.stdin.take().context("stdin was not piped")?;
+        stdin
+            .write_all(text.as_bytes())
+            .context("failed writing pbcopy stdin")?;
+    }
+    let out = child.wait_with_output().context("pbcopy failed")?;
+    if !out.status.success() {
+        return Err(anyhow!(
+            "pbcopy failed ({}): {}",
+            out.status,
+            String::from_utf8_lossy(&out.stderr)
+        ));
+    }
+    Ok(())
+}
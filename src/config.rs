@@ -0,0 +1,293 @@
+use crate::transport::Backend;
+use anyhow::{Context, anyhow};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// User configuration, read from `config.toml`.
+///
+/// Location: `--config`, else `$APPLE_NOTES_CONFIG`, else
+/// `$XDG_CONFIG_HOME/apple-notes/config.toml`, else `~/.config/apple-notes/config.toml`. A
+/// missing file means "all defaults". Environment variables override the file (see
+/// [`Config::apply_env`]), and command-line flags override both.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Same as `--read-only`: refuse commands that modify notes or folders.
+    pub read_only: bool,
+    /// Default for `--account`.
+    pub account: Option<String>,
+    /// Default for `--backend`.
+    pub backend: Option<Backend>,
+    /// Default for `--folder` in `notes create` and `notes move`: where quick captures land.
+    pub default_folder: Option<String>,
+    /// Default for `export --out`.
+    pub export_dir: Option<PathBuf>,
+    /// Default for `export --jobs`.
+    pub jobs: Option<usize>,
+    /// Whether tables use color.
+    pub color: ColorChoice,
+    pub progress: ProgressConfig,
+    /// `[folder."Work > Meetings"]` sections, keyed by folder path.
+    #[serde(rename = "folder")]
+    pub folders: BTreeMap<String, FolderSettings>,
+}
+
+impl Config {
+    /// Settings for notes in `folder_path`: sections for ancestor folders apply too, with the most
+    /// specific folder winning per key.
+    pub fn folder_settings(&self, folder_path: &[String]) -> FolderSettings {
+        let mut out = FolderSettings::default();
+        let mut matches: Vec<(usize, &FolderSettings)> = self
+            .folders
+            .iter()
+            .filter_map(|(key, settings)| {
+                let key: Vec<&str> = key
+                    .split('>')
+                    .map(str::trim)
+                    .filter(|p| !p.is_empty())
+                    .collect();
+                let is_prefix = key.len() <= folder_path.len()
+                    && key.iter().zip(folder_path).all(|(a, b)| *a == b);
+                is_prefix.then_some((key.len(), settings))
+            })
+            .collect();
+        matches.sort_by_key(|(depth, _)| *depth);
+        for (_, settings) in matches {
+            out.markdown_dialect = settings.markdown_dialect.or(out.markdown_dialect);
+            out.template = settings.template.clone().or(out.template);
+            out.append_timestamp = settings.append_timestamp.or(out.append_timestamp);
+        }
+        out
+    }
+
+    /// Overrides file values with `APPLE_NOTES_ACCOUNT`, `APPLE_NOTES_BACKEND`,
+    /// `APPLE_NOTES_FOLDER`, `APPLE_NOTES_EXPORT_DIR` and `APPLE_NOTES_JOBS`, as looked up by
+    /// `var`.
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> anyhow::Result<()> {
+        if let Some(account) = var("APPLE_NOTES_ACCOUNT") {
+            self.account = Some(account);
+        }
+        if let Some(backend) = var("APPLE_NOTES_BACKEND") {
+            self.backend = Some(
+                <Backend as clap::ValueEnum>::from_str(&backend, true)
+                    .map_err(|_| anyhow!("invalid APPLE_NOTES_BACKEND {backend:?}"))?,
+            );
+        }
+        if let Some(folder) = var("APPLE_NOTES_FOLDER") {
+            self.default_folder = Some(folder);
+        }
+        if let Some(dir) = var("APPLE_NOTES_EXPORT_DIR") {
+            self.export_dir = Some(PathBuf::from(dir));
+        }
+        if let Some(jobs) = var("APPLE_NOTES_JOBS") {
+            self.jobs = Some(
+                jobs.parse()
+                    .map_err(|_| anyhow!("invalid APPLE_NOTES_JOBS {jobs:?}"))?,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// `color = "auto" | "always" | "never"`. `auto` colors when stdout is a terminal; `NO_COLOR`
+/// turns color off regardless.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Per-folder defaults, consulted by commands that write notes in that folder.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FolderSettings {
+    /// Markdown flavor for `--markdown` bodies.
+    pub markdown_dialect: Option<MarkdownDialect>,
+    /// Body template for `notes create` when no body is given.
+    pub template: Option<PathBuf>,
+    /// Prefix `notes append` content with the current date and time.
+    pub append_timestamp: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarkdownDialect {
+    #[default]
+    Commonmark,
+    /// GitHub Flavored Markdown: tables, task lists, strikethrough, autolinks.
+    Gfm,
+}
+
+/// `[progress]`: whether to show spinners and progress bars, their look and refresh rate.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ProgressConfig {
+    /// `false` hides progress output (like `NO_PROGRESS`).
+    pub enabled: bool,
+    /// Spinner animation frames (the last one is shown when finished).
+    pub spinner_chars: String,
+    /// indicatif template for spinners.
+    pub spinner_template: String,
+    /// indicatif template for progress bars.
+    pub bar_template: String,
+    /// Redraw interval in milliseconds.
+    pub tick_ms: u64,
+}
+
+impl Default for ProgressConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            spinner_chars: "⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏".to_string(),
+            spinner_template: "{spinner:.cyan} {msg}".to_string(),
+            bar_template: "{spinner:.cyan} {msg} {wide_bar} {pos}/{len}".to_string(),
+            tick_ms: 80,
+        }
+    }
+}
+
+/// Expands a leading `~/` to `$HOME`.
+pub fn expand_home(path: &std::path::Path) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn config_path(explicit: Option<&Path>) -> Option<(PathBuf, bool)> {
+    if let Some(path) = explicit {
+        return Some((path.to_path_buf(), true));
+    }
+    if let Some(path) = std::env::var_os("APPLE_NOTES_CONFIG") {
+        return Some((PathBuf::from(path), true));
+    }
+    Some((config_dir()?.join("config.toml"), false))
+}
+
+/// `$XDG_CONFIG_HOME/apple-notes`, else `~/.config/apple-notes`.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("apple-notes"))
+}
+
+/// Reads the config file (`explicit` is `--config`) and applies environment overrides.
+pub fn load(explicit: Option<&Path>) -> anyhow::Result<Config> {
+    let mut config = match config_path(explicit) {
+        Some((path, explicit)) if explicit || path.exists() => {
+            let data =
+                std::fs::read_to_string(&path).with_context(|| format!("read config {path:?}"))?;
+            parse(&data).with_context(|| format!("parse config {path:?}"))?
+        }
+        _ => Config::default(),
+    };
+    config.apply_env(|name| std::env::var(name).ok())?;
+    Ok(config)
+}
+
+fn parse(data: &str) -> anyhow::Result<Config> {
+    Ok(toml::from_str(data)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_fills_missing_progress_keys_with_defaults() {
+        let config = parse("[progress]\nspinner_chars = \"-\\\\|/ \"\ntick_ms = 250\n").unwrap();
+        assert_eq!(config.progress.spinner_chars, "-\\|/ ");
+        assert_eq!(config.progress.tick_ms, 250);
+        assert_eq!(
+            config.progress.bar_template,
+            ProgressConfig::default().bar_template
+        );
+
+        assert_eq!(parse("").unwrap().progress.tick_ms, 80);
+    }
+
+    #[test]
+    fn defaults_come_from_the_file_then_the_environment() {
+        let mut config = parse(
+            r#"
+            account = "On My Mac"
+            backend = "osascript"
+            default_folder = "Personal > Inbox"
+            export_dir = "~/Backups/Notes"
+            jobs = 8
+            color = "never"
+
+            [progress]
+            enabled = false
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.account.as_deref(), Some("On My Mac"));
+        assert_eq!(config.backend, Some(Backend::Osascript));
+        assert_eq!(config.color, ColorChoice::Never);
+        assert!(!config.progress.enabled);
+
+        let env = |name: &str| match name {
+            "APPLE_NOTES_ACCOUNT" => Some("iCloud".to_string()),
+            "APPLE_NOTES_JOBS" => Some("2".to_string()),
+            "APPLE_NOTES_FOLDER" => Some("Work".to_string()),
+            _ => None,
+        };
+        assert_eq!(config.default_folder.as_deref(), Some("Personal > Inbox"));
+        config.apply_env(env).unwrap();
+        assert_eq!(config.account.as_deref(), Some("iCloud"));
+        assert_eq!(config.default_folder.as_deref(), Some("Work"));
+        assert_eq!(config.jobs, Some(2));
+        assert_eq!(
+            config.export_dir,
+            Some(PathBuf::from("~/Backups/Notes")),
+            "unset variables leave file values alone"
+        );
+        assert!(
+            config
+                .apply_env(|_| Some("nope".to_string()))
+                .unwrap_err()
+                .to_string()
+                .contains("APPLE_NOTES_BACKEND")
+        );
+        assert!(parse("").unwrap().progress.enabled);
+    }
+
+    #[test]
+    fn folder_settings_inherit_from_ancestors() {
+        let config = parse(
+            r#"
+            [folder.Work]
+            markdown_dialect = "gfm"
+            append_timestamp = true
+
+            [folder."Work > Meetings"]
+            append_timestamp = false
+            template = "~/meeting.md"
+            "#,
+        )
+        .unwrap();
+
+        let path = |p: &[&str]| p.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let meetings = config.folder_settings(&path(&["Work", "Meetings", "2025"]));
+        assert_eq!(meetings.markdown_dialect, Some(MarkdownDialect::Gfm));
+        assert_eq!(meetings.append_timestamp, Some(false));
+        assert_eq!(meetings.template, Some(PathBuf::from("~/meeting.md")));
+
+        let work = config.folder_settings(&path(&["Work"]));
+        assert_eq!(work.append_timestamp, Some(true));
+        assert_eq!(work.template, None);
+        assert!(
+            config
+                .folder_settings(&path(&["Workshop"]))
+                .markdown_dialect
+                .is_none()
+        );
+    }
+}
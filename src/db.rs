@@ -1,16 +1,23 @@
+use crate::error::NotesError;
 use crate::model::{Account, Folder, NoteSummary};
+use crate::transport::DEFAULT_FOLDER;
 use anyhow::{Context, anyhow};
-use rusqlite::{Connection, OpenFlags, Row};
+use rusqlite::{Connection, OpenFlags, OptionalExtension, Row};
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+use time::OffsetDateTime;
 
 #[derive(Debug, Clone)]
 struct DbFolderRow {
     pk: i64,
     name: String,
     parent_pk: Option<i64>,
+    smart: bool,
 }
 
+/// Read-only access to the Notes database (`NoteStore.sqlite`).
 #[derive(Debug, Clone)]
 pub struct NotesDb {
     path: PathBuf,
@@ -18,27 +25,33 @@ pub struct NotesDb {
 }
 
 impl NotesDb {
+    /// Opens the database at [`use_db_path`]'s path, `$APPLE_NOTES_DB_PATH`, or where Notes
+    /// keeps it.
     pub fn open_default() -> anyhow::Result<Self> {
-        if let Some(p) = std::env::var_os("APPLE_NOTES_DB_PATH") {
-            return Self::open(PathBuf::from(p));
-        }
-        let path = default_notes_db_path().ok_or_else(|| anyhow!("HOME not set"))?;
-        Self::open(path)
+        Self::open(notes_db_path()?)
     }
 
     pub fn open(path: PathBuf) -> anyhow::Result<Self> {
+        let store_uuid = read_store_uuid(&path).map_err(|err| match diagnose(&path) {
+            Some(cause) => err.context(cause),
+            None => err,
+        })?;
         let conn = open_readonly(&path)?;
-        let store_uuid: String = conn
+        let accounts: i64 = conn
             .query_row(
-                "SELECT Z_UUID FROM Z_METADATA WHERE Z_VERSION = 1",
+                "SELECT COUNT(*) FROM ZICCLOUDSYNCINGOBJECT WHERE Z_ENT = 14",
                 [],
                 |row| row.get(0),
             )
-            .with_context(|| format!("read Z_METADATA from {}", path.display()))?;
+            .with_context(|| format!("count accounts in {}", path.display()))?;
+        if accounts == 0 {
+            return Err(DbUnavailable::NoAccounts.into());
+        }
 
         Ok(Self { path, store_uuid })
     }
 
+    #[tracing::instrument(name = "db", skip(self), fields(call = "list_accounts"))]
     pub fn list_accounts(&self) -> anyhow::Result<Vec<Account>> {
         let conn = open_readonly(&self.path)?;
         let mut stmt = conn
@@ -52,10 +65,12 @@ impl NotesDb {
         Ok(out)
     }
 
+    #[tracing::instrument(name = "db", skip(self), fields(call = "list_folders"))]
     pub fn list_folders(&self, account: &str) -> anyhow::Result<Vec<Folder>> {
         let conn = open_readonly(&self.path)?;
         let account_pk = account_pk(&conn, account)?;
         let rows = folder_rows(&conn, account_pk)?;
+        let dates = folder_dates(&conn, account_pk)?;
         let mut by_pk: HashMap<i64, DbFolderRow> = HashMap::new();
         for r in rows {
             by_pk.insert(r.pk, r);
@@ -64,31 +79,69 @@ impl NotesDb {
         let mut out = Vec::new();
         for r in by_pk.values() {
             let path = folder_path(&by_pk, r.pk)?;
+            let (created, modified) = dates.get(&r.pk).copied().unwrap_or_default();
             out.push(Folder {
                 id: self.folder_id(r.pk),
                 name: r.name.clone(),
                 account: account.to_string(),
                 path,
+                created_at: created.map(apple_epoch_seconds),
+                modified_at: modified.map(apple_epoch_seconds),
+                smart: r.smart,
             });
         }
         out.sort_by(|a, b| a.path.cmp(&b.path));
         Ok(out)
     }
 
+    #[tracing::instrument(name = "db", skip(self), fields(call = "list_notes"))]
     pub fn list_notes(&self, account: &str) -> anyhow::Result<Vec<NoteSummary>> {
+        self.list_account_notes(account, "1")
+    }
+
+    /// Notes in the account's Recently Deleted folder (`ZFOLDERTYPE` 1, or by name in schemas
+    /// without that column).
+    #[tracing::instrument(name = "db", skip(self), fields(call = "list_deleted_notes"))]
+    pub fn list_deleted_notes(&self, account: &str) -> anyhow::Result<Vec<NoteSummary>> {
+        let conn = open_readonly(&self.path)?;
+        let columns = table_columns(&conn, "ZICCLOUDSYNCINGOBJECT")?;
+        if columns.contains("ZFOLDERTYPE") {
+            self.list_account_notes(account, "f.ZFOLDERTYPE = 1")
+        } else {
+            self.list_account_notes(
+                account,
+                &format!(
+                    "COALESCE(f.ZNAME, f.ZTITLE2) = '{}'",
+                    crate::transport::RECENTLY_DELETED
+                ),
+            )
+        }
+    }
+
+    /// The account's notes whose folder `f` matches `folder_condition` (SQL).
+    fn list_account_notes(
+        &self,
+        account: &str,
+        folder_condition: &str,
+    ) -> anyhow::Result<Vec<NoteSummary>> {
         let conn = open_readonly(&self.path)?;
         let account_pk = account_pk(&conn, account)?;
-        let mut stmt = conn.prepare(
+        let identifier = identifier_column(&conn, "n.")?;
+        let (created, modified) = note_date_columns(&conn, "n.")?;
+        let pinned = flag_column(&conn, "n.", "ZISPINNED")?;
+        let locked = flag_column(&conn, "n.", "ZISPASSWORDPROTECTED")?;
+        let mut stmt = conn.prepare(&format!(
             r#"
-SELECT n.Z_PK, n.ZTITLE1, n.ZFOLDER
+SELECT n.Z_PK, n.ZTITLE1, n.ZFOLDER, {identifier}, {created}, {modified}, {pinned}, {locked}
 FROM ZICCLOUDSYNCINGOBJECT n
 JOIN ZICCLOUDSYNCINGOBJECT f ON f.Z_PK = n.ZFOLDER
 WHERE n.Z_ENT = 12
   AND IFNULL(n.ZMARKEDFORDELETION, 0) = 0
   AND f.Z_ENT = 15
   AND f.ZACCOUNT8 = ?
-"#,
-        )?;
+  AND {folder_condition}
+"#
+        ))?;
 
         let iter = stmt.query_map([account_pk], |row| note_summary_row(self, row))?;
         let mut out = Vec::new();
@@ -98,6 +151,7 @@ WHERE n.Z_ENT = 12
         Ok(out)
     }
 
+    #[tracing::instrument(name = "db", skip(self), fields(call = "list_notes_in_folder"))]
     pub fn list_notes_in_folder(
         &self,
         account: &str,
@@ -105,24 +159,32 @@ WHERE n.Z_ENT = 12
     ) -> anyhow::Result<Vec<NoteSummary>> {
         let folders = self.list_folders(account)?;
         let want = folder_path.join(" > ");
-        let folder = folders
-            .iter()
-            .find(|f| f.path_string() == want)
-            .ok_or_else(|| anyhow!("folder not found: {want}"))?;
-
         let conn = open_readonly(&self.path)?;
-        let folder_pk = parse_coredata_pk(&folder.id)
-            .with_context(|| format!("unexpected folder id format: {}", folder.id))?;
+        let folder_pk = match folders.iter().find(|f| f.path_string() == want) {
+            Some(folder) if folder.smart => {
+                let pk = parse_coredata_pk(&folder.id)?;
+                return self.list_smart_folder_notes(&conn, account, pk);
+            }
+            Some(folder) => parse_coredata_pk(&folder.id)
+                .with_context(|| format!("unexpected folder id format: {}", folder.id))?,
+            None if want == DEFAULT_FOLDER => default_folder_pk(&conn, account)?
+                .ok_or_else(|| NotesError::NotFound(format!("folder not found: {want}")))?,
+            None => return Err(NotesError::NotFound(format!("folder not found: {want}")).into()),
+        };
 
-        let mut stmt = conn.prepare(
+        let identifier = identifier_column(&conn, "")?;
+        let (created, modified) = note_date_columns(&conn, "")?;
+        let pinned = flag_column(&conn, "", "ZISPINNED")?;
+        let locked = flag_column(&conn, "", "ZISPASSWORDPROTECTED")?;
+        let mut stmt = conn.prepare(&format!(
             r#"
-SELECT Z_PK, ZTITLE1, ZFOLDER
+SELECT Z_PK, ZTITLE1, ZFOLDER, {identifier}, {created}, {modified}, {pinned}, {locked}
 FROM ZICCLOUDSYNCINGOBJECT
 WHERE Z_ENT = 12
   AND IFNULL(ZMARKEDFORDELETION, 0) = 0
   AND ZFOLDER = ?
-"#,
-        )?;
+"#
+        ))?;
         let iter = stmt.query_map([folder_pk], |row| note_summary_row(self, row))?;
         let mut out = Vec::new();
         for n in iter {
@@ -131,6 +193,73 @@ WHERE Z_ENT = 12
         Ok(out)
     }
 
+    /// The notes a smart folder shows: the account's notes (outside Recently Deleted) whose tags
+    /// match the folder's query. Queries without tags (dates, checklists, attachments, …) aren't
+    /// understood and are an error, rather than a folder that shows every note.
+    fn list_smart_folder_notes(
+        &self,
+        conn: &Connection,
+        account: &str,
+        folder_pk: i64,
+    ) -> anyhow::Result<Vec<NoteSummary>> {
+        let query: String = conn.query_row(
+            &format!("SELECT {SMART_QUERY_COLUMN} FROM ZICCLOUDSYNCINGOBJECT WHERE Z_PK = ?"),
+            [folder_pk],
+            |row| row.get(0),
+        )?;
+        let query = SmartQuery::parse(&query)
+            .with_context(|| format!("unreadable smart folder query: {query}"))?;
+        if query.tags.is_empty() {
+            return Err(anyhow!(
+                "unsupported smart folder query (only tag filters are understood): {query:?}"
+            ));
+        }
+        let tags = note_tags(conn)?;
+        let live = match table_columns(conn, "ZICCLOUDSYNCINGOBJECT")?.contains("ZFOLDERTYPE") {
+            true => "IFNULL(f.ZFOLDERTYPE, 0) != 1",
+            false => "1",
+        };
+        let mut notes = self.list_account_notes(account, live)?;
+        notes.retain(|n| {
+            let note_tags = parse_coredata_pk(&n.id).ok().and_then(|pk| tags.get(&pk));
+            query.matches(note_tags.unwrap_or(&HashSet::new()))
+        });
+        Ok(notes)
+    }
+
+    /// The note's `ZIDENTIFIER`: the UUID Notes uses in links, which (unlike the
+    /// `x-coredata://` id) is the same on every device.
+    pub fn note_identifier(&self, id: &str) -> anyhow::Result<String> {
+        let pk = parse_coredata_pk(id)?;
+        let conn = open_readonly(&self.path)?;
+        let identifier: Option<String> = conn
+            .query_row(
+                "SELECT ZIDENTIFIER FROM ZICCLOUDSYNCINGOBJECT WHERE Z_ENT = 12 AND Z_PK = ?",
+                [pk],
+                |row| row.get(0),
+            )
+            .map_err(|_| NotesError::NotFound(format!("note not found: {id}")))?;
+        identifier.ok_or_else(|| anyhow!("note {id} has no identifier"))
+    }
+
+    /// The `x-coredata://` id of the note `id`, which may be either that id already or the note's
+    /// UUID.
+    #[tracing::instrument(name = "db", skip(self), fields(call = "resolve_note_id"))]
+    pub fn resolve_note_id(&self, id: &str) -> anyhow::Result<String> {
+        if id.starts_with("x-coredata://") {
+            return Ok(id.to_string());
+        }
+        let conn = open_readonly(&self.path)?;
+        let pk: i64 = conn
+            .query_row(
+                "SELECT Z_PK FROM ZICCLOUDSYNCINGOBJECT WHERE Z_ENT = 12 AND ZIDENTIFIER = ?",
+                [id],
+                |row| row.get(0),
+            )
+            .map_err(|_| NotesError::NotFound(format!("note not found: {id}")))?;
+        Ok(self.note_id(pk))
+    }
+
     pub fn note_id(&self, pk: i64) -> String {
         format!("x-coredata://{}/ICNote/p{}", self.store_uuid, pk)
     }
@@ -148,9 +277,167 @@ fn note_summary_row(db: &NotesDb, row: &Row<'_>) -> rusqlite::Result<NoteSummary
         id: db.note_id(pk),
         title: title.unwrap_or_else(|| "Untitled".to_string()),
         folder_id: db.folder_id(folder_pk),
+        uuid: row.get(3)?,
+        created_at: row.get::<_, Option<f64>>(4)?.map(apple_epoch_seconds),
+        modified_at: row.get::<_, Option<f64>>(5)?.map(apple_epoch_seconds),
+        pinned: row.get(6)?,
+        locked: row.get(7)?,
+    })
+}
+
+/// A boolean note column such as `ZISPINNED` (with `prefix`), or `0` if this schema lacks it.
+fn flag_column(conn: &Connection, prefix: &str, column: &str) -> anyhow::Result<String> {
+    let columns = table_columns(conn, "ZICCLOUDSYNCINGOBJECT")?;
+    Ok(match columns.contains(column) {
+        true => format!("IFNULL({prefix}{column}, 0) != 0"),
+        false => "0".to_string(),
+    })
+}
+
+/// SQL matching the account's default folder, whose `ZIDENTIFIER` is `DefaultFolder-…`.
+const DEFAULT_FOLDER_IDENTIFIER: &str = "ZIDENTIFIER LIKE 'DefaultFolder%'";
+
+/// The pk of the account's default folder, if this schema can tell which it is.
+fn default_folder_pk(conn: &Connection, account: &str) -> anyhow::Result<Option<i64>> {
+    if !table_columns(conn, "ZICCLOUDSYNCINGOBJECT")?.contains("ZIDENTIFIER") {
+        return Ok(None);
+    }
+    let account_pk = account_pk(conn, account)?;
+    Ok(conn
+        .query_row(
+            &format!(
+                "SELECT Z_PK FROM ZICCLOUDSYNCINGOBJECT \
+                 WHERE Z_ENT = 15 AND ZACCOUNT8 = ? AND {DEFAULT_FOLDER_IDENTIFIER}"
+            ),
+            [account_pk],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+/// Where Notes keeps a smart folder's query (JSON); `NULL` for ordinary folders.
+const SMART_QUERY_COLUMN: &str = "ZSMARTFOLDERQUERYJSON";
+
+/// Type of the inline attachments Notes uses for hashtags; `ZALTTEXT` holds the `#tag` text and
+/// `ZNOTE1` the note.
+const HASHTAG_UTI: &str = "com.apple.notes.inlinetextattachment.hashtag";
+
+/// The tags a smart folder's notes must have. Read best-effort from the query JSON: tag names are
+/// the strings under any key mentioning "tag", and the notes need all of them unless an
+/// operator/mode key says "any" or "or".
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct SmartQuery {
+    tags: Vec<String>,
+    any: bool,
+}
+
+impl SmartQuery {
+    fn parse(json: &str) -> anyhow::Result<Self> {
+        fn strings(value: &serde_json::Value, out: &mut Vec<String>) {
+            match value {
+                serde_json::Value::String(s) => out.push(normalize_tag(s)),
+                serde_json::Value::Array(items) => items.iter().for_each(|v| strings(v, out)),
+                serde_json::Value::Object(map) => map.values().for_each(|v| strings(v, out)),
+                _ => {}
+            }
+        }
+        fn walk(value: &serde_json::Value, query: &mut SmartQuery) {
+            match value {
+                serde_json::Value::Object(map) => {
+                    for (key, value) in map {
+                        let key = key.to_ascii_lowercase();
+                        if key.contains("tag") {
+                            strings(value, &mut query.tags);
+                        } else if ["operator", "mode", "match"]
+                            .iter()
+                            .any(|k| key.contains(k))
+                        {
+                            query.any |= value.as_str().is_some_and(|v| {
+                                matches!(v.to_ascii_lowercase().as_str(), "any" | "or")
+                            });
+                        } else {
+                            walk(value, query);
+                        }
+                    }
+                }
+                serde_json::Value::Array(items) => items.iter().for_each(|v| walk(v, query)),
+                _ => {}
+            }
+        }
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        let mut query = SmartQuery::default();
+        walk(&value, &mut query);
+        query.tags.sort();
+        query.tags.dedup();
+        Ok(query)
+    }
+
+    fn matches(&self, tags: &HashSet<String>) -> bool {
+        match self.any {
+            true => self.tags.iter().any(|t| tags.contains(t)),
+            false => self.tags.iter().all(|t| tags.contains(t)),
+        }
+    }
+}
+
+fn normalize_tag(tag: &str) -> String {
+    tag.trim().trim_start_matches('#').to_lowercase()
+}
+
+/// Every note's tags (normalized), keyed by note pk. Empty in schemas without hashtags.
+fn note_tags(conn: &Connection) -> anyhow::Result<HashMap<i64, HashSet<String>>> {
+    let columns = table_columns(conn, "ZICCLOUDSYNCINGOBJECT")?;
+    let mut out: HashMap<i64, HashSet<String>> = HashMap::new();
+    if !["ZTYPEUTI1", "ZALTTEXT", "ZNOTE1"]
+        .iter()
+        .all(|c| columns.contains(*c))
+    {
+        return Ok(out);
+    }
+    let mut stmt = conn.prepare(
+        "SELECT ZNOTE1, ZALTTEXT FROM ZICCLOUDSYNCINGOBJECT \
+         WHERE ZTYPEUTI1 = ? AND ZNOTE1 IS NOT NULL AND ZALTTEXT IS NOT NULL",
+    )?;
+    let rows = stmt.query_map([HASHTAG_UTI], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+    })?;
+    for r in rows {
+        let (pk, tag) = r?;
+        out.entry(pk).or_default().insert(normalize_tag(&tag));
+    }
+    Ok(out)
+}
+
+/// `ZIDENTIFIER` (with `prefix`, e.g. a table alias), or `NULL` if this schema lacks it.
+fn identifier_column(conn: &Connection, prefix: &str) -> anyhow::Result<String> {
+    let columns = table_columns(conn, "ZICCLOUDSYNCINGOBJECT")?;
+    Ok(match columns.contains("ZIDENTIFIER") {
+        true => format!("{prefix}ZIDENTIFIER"),
+        false => "NULL".to_string(),
     })
 }
 
+/// Expressions for a note's creation and modification dates (Apple epoch seconds), from
+/// whichever of the columns different schema versions use are present.
+fn note_date_columns(conn: &Connection, prefix: &str) -> anyhow::Result<(String, String)> {
+    let columns = table_columns(conn, "ZICCLOUDSYNCINGOBJECT")?;
+    let coalesce = |names: &[&str]| {
+        let present: Vec<String> = names
+            .iter()
+            .filter(|c| columns.contains(**c))
+            .map(|c| format!("{prefix}{c}"))
+            .collect();
+        match present.len() {
+            0 => "NULL".to_string(),
+            _ => format!("COALESCE({}, NULL)", present.join(", ")),
+        }
+    };
+    Ok((
+        coalesce(&["ZCREATIONDATE3", "ZCREATIONDATE2", "ZCREATIONDATE1"]),
+        coalesce(&["ZMODIFICATIONDATE1", "ZMODIFICATIONDATEATIMPORT"]),
+    ))
+}
+
 fn account_pk(conn: &Connection, account: &str) -> anyhow::Result<i64> {
     conn.query_row(
         "SELECT Z_PK FROM ZICCLOUDSYNCINGOBJECT WHERE Z_ENT = 14 AND ZNAME = ?",
@@ -161,20 +448,31 @@ fn account_pk(conn: &Connection, account: &str) -> anyhow::Result<i64> {
 }
 
 fn folder_rows(conn: &Connection, account_pk: i64) -> anyhow::Result<Vec<DbFolderRow>> {
-    let mut stmt = conn.prepare(
+    let columns = table_columns(conn, "ZICCLOUDSYNCINGOBJECT")?;
+    let smart = match columns.contains(SMART_QUERY_COLUMN) {
+        true => format!("{SMART_QUERY_COLUMN} IS NOT NULL"),
+        false => "0".to_string(),
+    };
+    // The default folder may have no title of its own.
+    let default_name = match columns.contains("ZIDENTIFIER") {
+        true => format!("CASE WHEN {DEFAULT_FOLDER_IDENTIFIER} THEN '{DEFAULT_FOLDER}' END"),
+        false => "NULL".to_string(),
+    };
+    let mut stmt = conn.prepare(&format!(
         r#"
-SELECT Z_PK, COALESCE(ZNAME, ZTITLE2, 'Untitled'), ZPARENT
+SELECT Z_PK, COALESCE(ZNAME, ZTITLE2, {default_name}, 'Untitled'), ZPARENT, {smart}
 FROM ZICCLOUDSYNCINGOBJECT
 WHERE Z_ENT = 15
   AND ZACCOUNT8 = ?
-"#,
-    )?;
+"#
+    ))?;
 
     let iter = stmt.query_map([account_pk], |row| {
         Ok(DbFolderRow {
             pk: row.get(0)?,
             name: row.get(1)?,
             parent_pk: row.get(2)?,
+            smart: row.get(3)?,
         })
     })?;
 
@@ -185,6 +483,60 @@ WHERE Z_ENT = 15
     Ok(out)
 }
 
+type FolderDates = (Option<f64>, Option<f64>);
+
+/// Folder dates (Apple epoch seconds), keyed by folder pk, read from the folder rows
+/// themselves: "created" is the folder's creation date and "modified" the later of its
+/// modification date and its last rename. Columns missing from this schema version, or left
+/// empty by Notes, come back as `None`.
+fn folder_dates(conn: &Connection, account_pk: i64) -> anyhow::Result<HashMap<i64, FolderDates>> {
+    let columns = table_columns(conn, "ZICCLOUDSYNCINGOBJECT")?;
+    let present = |names: &[&'static str]| -> Vec<&'static str> {
+        names
+            .iter()
+            .copied()
+            .filter(|c| columns.contains(*c))
+            .collect()
+    };
+    let created = match present(&["ZCREATIONDATE3", "ZCREATIONDATE2", "ZCREATIONDATE1"])[..] {
+        [] => "NULL".to_string(),
+        ref cols => format!("COALESCE({}, NULL)", cols.join(", ")),
+    };
+    // SQLite's multi-argument MAX() is NULL if any argument is, so skip the NULLs first.
+    let modified = match present(&["ZMODIFICATIONDATE1", "ZDATEFORLASTTITLEMODIFICATION"])[..] {
+        [] => "NULL".to_string(),
+        [col] => col.to_string(),
+        ref cols => format!(
+            "(SELECT MAX(d) FROM (SELECT {}))",
+            cols.iter()
+                .map(|c| format!("{c} AS d"))
+                .collect::<Vec<_>>()
+                .join(" UNION ALL SELECT ")
+        ),
+    };
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT Z_PK, {created}, {modified} FROM ZICCLOUDSYNCINGOBJECT \
+         WHERE Z_ENT = 15 AND ZACCOUNT8 = ?"
+    ))?;
+    let rows = stmt.query_map([account_pk], |row| {
+        Ok((row.get::<_, i64>(0)?, (row.get(1)?, row.get(2)?)))
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+pub(crate) fn table_columns(conn: &Connection, table: &str) -> anyhow::Result<HashSet<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({table})"))?;
+    let names = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    Ok(names.collect::<Result<_, _>>()?)
+}
+
+/// Converts Apple's Core Data timestamps (seconds since 2001-01-01 UTC).
+pub(crate) fn apple_epoch_seconds(secs: f64) -> OffsetDateTime {
+    let base = OffsetDateTime::from_unix_timestamp(978307200).unwrap(); // 2001-01-01T00:00:00Z
+    base + time::Duration::milliseconds((secs * 1000.0) as i64)
+}
+
 fn folder_path(by_pk: &HashMap<i64, DbFolderRow>, pk: i64) -> anyhow::Result<Vec<String>> {
     let mut parts = Vec::new();
     let mut current = pk;
@@ -211,21 +563,119 @@ fn folder_path(by_pk: &HashMap<i64, DbFolderRow>, pk: i64) -> anyhow::Result<Vec
     Ok(parts)
 }
 
-fn open_readonly(path: &Path) -> anyhow::Result<Connection> {
-    Connection::open_with_flags(
+fn read_store_uuid(path: &Path) -> anyhow::Result<String> {
+    let conn = open_readonly(path)?;
+    conn.query_row(
+        "SELECT Z_UUID FROM Z_METADATA WHERE Z_VERSION = 1",
+        [],
+        |row| row.get(0),
+    )
+    .with_context(|| format!("read Z_METADATA from {}", path.display()))
+}
+
+/// Why the Notes database can't be used, with what to do about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DbUnavailable {
+    /// The group container doesn't exist: Notes.app has never run for this user.
+    NeverLaunched(PathBuf),
+    /// The container exists but can't be read (macOS privacy protection / sandbox).
+    PermissionDenied(PathBuf),
+    /// The container exists but holds no usable `NoteStore.sqlite`.
+    NoDatabase(PathBuf),
+    /// The database has no accounts: iCloud Notes is off and "On My Mac" is disabled.
+    NoAccounts,
+}
+
+impl std::fmt::Display for DbUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DbUnavailable::NeverLaunched(dir) => write!(
+                f,
+                "Notes has never been opened on this Mac ({} does not exist). \
+                 Open Notes.app once, then retry (or use --backend osascript).",
+                dir.display()
+            ),
+            DbUnavailable::PermissionDenied(dir) => write!(
+                f,
+                "macOS is blocking access to {}. Grant your terminal Full Disk Access \
+                 (System Settings > Privacy & Security > Full Disk Access) and restart it, \
+                 or use --backend osascript.",
+                dir.display()
+            ),
+            DbUnavailable::NoDatabase(db) => write!(
+                f,
+                "no Notes database at {}. Notes may still be setting up; open Notes.app and \
+                 let it finish syncing, then retry (or use --backend osascript).",
+                db.display()
+            ),
+            DbUnavailable::NoAccounts => write!(
+                f,
+                "the Notes database has no accounts: iCloud Notes is turned off and the \
+                 \"On My Mac\" account is disabled. Enable Notes in System Settings > Apple \
+                 Account > iCloud, or turn on Notes > Settings > \"Enable the On My Mac account\"."
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DbUnavailable {}
+
+/// Works out which common setup problem keeps the database at `db_path` from opening, if any.
+fn diagnose(db_path: &Path) -> Option<DbUnavailable> {
+    let container = db_path.parent()?;
+    match std::fs::read_dir(container) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Some(DbUnavailable::NeverLaunched(container.to_path_buf()));
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            return Some(DbUnavailable::PermissionDenied(container.to_path_buf()));
+        }
+        _ => {}
+    }
+    match std::fs::metadata(db_path) {
+        Ok(meta) if meta.len() > 0 => None,
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            Some(DbUnavailable::PermissionDenied(db_path.to_path_buf()))
+        }
+        _ => Some(DbUnavailable::NoDatabase(db_path.to_path_buf())),
+    }
+}
+
+/// Opens the database read-only, logging every statement it runs (at `-vv`).
+pub(crate) fn open_readonly(path: &Path) -> anyhow::Result<Connection> {
+    let mut conn = Connection::open_with_flags(
         path,
         OpenFlags::SQLITE_OPEN_READ_ONLY
             | OpenFlags::SQLITE_OPEN_NO_MUTEX
             | OpenFlags::SQLITE_OPEN_SHARED_CACHE,
     )
-    .with_context(|| format!("open notes db {}", path.display()))
+    .with_context(|| format!("open notes db {}", path.display()))?;
+    conn.profile(Some(log_statement));
+    Ok(conn)
 }
 
-fn default_notes_db_path() -> Option<PathBuf> {
-    let home = std::env::var_os("HOME")?;
-    Some(
-        PathBuf::from(home).join("Library/Group Containers/group.com.apple.notes/NoteStore.sqlite"),
-    )
+fn log_statement(sql: &str, elapsed: Duration) {
+    tracing::debug!(elapsed_ms = elapsed.as_secs_f64() * 1000.0, sql, "sql");
+}
+
+static DB_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Reads the Notes database at `path` (`--db-path`) instead of the default location, e.g. a
+/// `NoteStore.sqlite` copied off a Mac.
+pub fn use_db_path(path: PathBuf) {
+    let _ = DB_PATH.set(path);
+}
+
+/// `--db-path`, then `APPLE_NOTES_DB_PATH`, then the Notes group container under `$HOME`.
+pub(crate) fn notes_db_path() -> anyhow::Result<PathBuf> {
+    if let Some(p) = DB_PATH.get() {
+        return Ok(p.clone());
+    }
+    if let Some(p) = std::env::var_os("APPLE_NOTES_DB_PATH") {
+        return Ok(PathBuf::from(p));
+    }
+    let home = std::env::var_os("HOME").ok_or_else(|| anyhow!("HOME not set"))?;
+    Ok(PathBuf::from(home).join("Library/Group Containers/group.com.apple.notes/NoteStore.sqlite"))
 }
 
 fn parse_coredata_pk(coredata_id: &str) -> anyhow::Result<i64> {
@@ -245,6 +695,64 @@ mod tests {
     use super::*;
     use tempfile::tempdir;
 
+    #[test]
+    fn open_diagnoses_missing_container_database_and_accounts() {
+        let dir = tempdir().unwrap();
+        let container = dir.path().join("group.com.apple.notes");
+        let db_path = container.join("NoteStore.sqlite");
+
+        let err = NotesDb::open(db_path.clone()).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<DbUnavailable>(),
+            Some(&DbUnavailable::NeverLaunched(container.clone()))
+        );
+
+        std::fs::create_dir(&container).unwrap();
+        let err = NotesDb::open(db_path.clone()).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<DbUnavailable>(),
+            Some(&DbUnavailable::NoDatabase(db_path.clone()))
+        );
+
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE Z_METADATA (Z_VERSION INTEGER PRIMARY KEY, Z_UUID VARCHAR(255));
+             INSERT INTO Z_METADATA VALUES (1, 'UUID');
+             CREATE TABLE ZICCLOUDSYNCINGOBJECT (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER);",
+        )
+        .unwrap();
+        let err = NotesDb::open(db_path).unwrap_err();
+        assert!(
+            err.to_string().contains("iCloud Notes is turned off"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn folder_dates_come_from_the_folder_rows() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE ZICCLOUDSYNCINGOBJECT (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZFOLDER INTEGER, ZACCOUNT8 INTEGER, ZMARKEDFORDELETION INTEGER, ZCREATIONDATE1 REAL, ZCREATIONDATE3 REAL, ZMODIFICATIONDATE1 REAL, ZDATEFORLASTTITLEMODIFICATION REAL);
+             INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZACCOUNT8, ZCREATIONDATE1, ZMODIFICATIONDATE1, ZDATEFORLASTTITLEMODIFICATION) VALUES
+                 (10, 15, 1, 40, 300, 500),
+                 (11, 15, 1, NULL, 9, NULL),
+                 (12, 15, 2, 1, 2, 3);
+             INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZFOLDER, ZCREATIONDATE1, ZCREATIONDATE3, ZMODIFICATIONDATE1) VALUES
+                 (20, 12, 10, 1, 100, 900),
+                 (22, 12, 11, 7, NULL, 9);",
+        )
+        .unwrap();
+
+        let dates = folder_dates(&conn, 1).unwrap();
+        assert_eq!(dates.len(), 2);
+        assert_eq!(dates[&10], (Some(40.0), Some(500.0)));
+        assert_eq!(dates[&11], (None, Some(9.0)));
+        assert_eq!(
+            apple_epoch_seconds(86400.0).date(),
+            time::macros::date!(2001 - 01 - 02)
+        );
+    }
+
     #[test]
     fn db_lists_folders_and_notes() {
         let dir = tempdir().unwrap();
@@ -311,6 +819,7 @@ mod tests {
         assert_eq!(folders.len(), 2);
         assert_eq!(folders[0].path_string(), "Personal");
         assert_eq!(folders[1].path_string(), "Personal > Archive");
+        assert_eq!(folders[0].created_at, None);
 
         let notes = db.list_notes("iCloud").unwrap();
         assert_eq!(notes.len(), 2);
@@ -326,6 +835,194 @@ mod tests {
         assert_eq!(pk, 21);
     }
 
+    #[test]
+    fn notes_are_listed_and_found_by_uuid() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("NoteStore.sqlite");
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE Z_METADATA (Z_VERSION INTEGER PRIMARY KEY, Z_UUID VARCHAR(255));
+                 INSERT INTO Z_METADATA VALUES (1, 'STORE');
+                 CREATE TABLE ZICCLOUDSYNCINGOBJECT (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZNAME VARCHAR, ZTITLE1 VARCHAR, ZFOLDER INTEGER, ZPARENT INTEGER, ZACCOUNT8 INTEGER, ZMARKEDFORDELETION INTEGER, ZIDENTIFIER VARCHAR);
+                 INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZNAME) VALUES (1, 14, 'iCloud');
+                 INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZNAME, ZACCOUNT8) VALUES (10, 15, 'Notes', 1);
+                 INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZTITLE1, ZFOLDER, ZIDENTIFIER) VALUES (20, 12, 'A', 10, 'E1F2');",
+            )
+            .unwrap();
+        let db = NotesDb::open(db_path).unwrap();
+
+        let notes = db.list_notes("iCloud").unwrap();
+        assert_eq!(notes[0].uuid.as_deref(), Some("E1F2"));
+        assert_eq!(notes[0].display_id(), "E1F2");
+        assert_eq!(
+            db.resolve_note_id("E1F2").unwrap(),
+            "x-coredata://STORE/ICNote/p20"
+        );
+        assert_eq!(
+            db.resolve_note_id("x-coredata://STORE/ICNote/p99").unwrap(),
+            "x-coredata://STORE/ICNote/p99"
+        );
+        let missing = NotesError::from(db.resolve_note_id("nope").unwrap_err());
+        assert!(matches!(missing, NotesError::NotFound(_)));
+        assert_eq!(
+            db.note_identifier("x-coredata://STORE/ICNote/p20").unwrap(),
+            "E1F2"
+        );
+    }
+
+    #[test]
+    fn deleted_notes_come_from_the_recently_deleted_folder() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("NoteStore.sqlite");
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE Z_METADATA (Z_VERSION INTEGER PRIMARY KEY, Z_UUID VARCHAR(255));
+                 INSERT INTO Z_METADATA VALUES (1, 'STORE');
+                 CREATE TABLE ZICCLOUDSYNCINGOBJECT (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZNAME VARCHAR, ZTITLE1 VARCHAR, ZTITLE2 VARCHAR, ZFOLDER INTEGER, ZPARENT INTEGER, ZACCOUNT8 INTEGER, ZMARKEDFORDELETION INTEGER, ZFOLDERTYPE INTEGER);
+                 INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZNAME) VALUES (1, 14, 'iCloud');
+                 INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZNAME, ZACCOUNT8, ZFOLDERTYPE) VALUES (10, 15, 'Notes', 1, 0), (11, 15, NULL, 1, 1);
+                 INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZTITLE1, ZFOLDER, ZMARKEDFORDELETION) VALUES (20, 12, 'Live', 10, 0), (21, 12, 'Deleted', 11, 0), (22, 12, 'Purged', 11, 1);",
+            )
+            .unwrap();
+        let db = NotesDb::open(db_path).unwrap();
+
+        let deleted = db.list_deleted_notes("iCloud").unwrap();
+        let titles: Vec<&str> = deleted.iter().map(|n| n.title.as_str()).collect();
+        assert_eq!(titles, ["Deleted"]);
+        assert_eq!(deleted[0].folder_id, "x-coredata://STORE/ICFolder/p11");
+    }
+
+    #[test]
+    fn pinned_and_password_protected_notes_are_flagged() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("NoteStore.sqlite");
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE Z_METADATA (Z_VERSION INTEGER PRIMARY KEY, Z_UUID VARCHAR(255));
+                 INSERT INTO Z_METADATA VALUES (1, 'STORE');
+                 CREATE TABLE ZICCLOUDSYNCINGOBJECT (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZNAME VARCHAR, ZTITLE1 VARCHAR, ZTITLE2 VARCHAR, ZFOLDER INTEGER, ZPARENT INTEGER, ZACCOUNT8 INTEGER, ZMARKEDFORDELETION INTEGER, ZISPINNED INTEGER, ZISPASSWORDPROTECTED INTEGER);
+                 INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZNAME) VALUES (1, 14, 'iCloud');
+                 INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZNAME, ZACCOUNT8) VALUES (10, 15, 'Notes', 1);
+                 INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZTITLE1, ZFOLDER, ZISPASSWORDPROTECTED) VALUES (20, 12, 'Open', 10, 0), (21, 12, 'Secret', 10, 1), (22, 12, 'Old', 10, NULL);
+                 UPDATE ZICCLOUDSYNCINGOBJECT SET ZISPINNED = 1 WHERE Z_PK = 20;",
+            )
+            .unwrap();
+        let db = NotesDb::open(db_path).unwrap();
+
+        let mut notes = db.list_notes("iCloud").unwrap();
+        notes.sort_by(|a, b| a.title.cmp(&b.title));
+        let locked: Vec<(&str, bool)> =
+            notes.iter().map(|n| (n.title.as_str(), n.locked)).collect();
+        assert_eq!(locked, [("Old", false), ("Open", false), ("Secret", true)]);
+        let pinned: Vec<&str> = notes
+            .iter()
+            .filter(|n| n.pinned)
+            .map(|n| n.title.as_str())
+            .collect();
+        assert_eq!(pinned, ["Open"]);
+        let in_folder = db
+            .list_notes_in_folder("iCloud", &["Notes".into()])
+            .unwrap();
+        assert_eq!(in_folder.iter().filter(|n| n.locked).count(), 1);
+    }
+
+    #[test]
+    fn smart_folders_list_the_notes_matching_their_tags() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("NoteStore.sqlite");
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch(
+                r##"CREATE TABLE Z_METADATA (Z_VERSION INTEGER PRIMARY KEY, Z_UUID VARCHAR(255));
+                 INSERT INTO Z_METADATA VALUES (1, 'STORE');
+                 CREATE TABLE ZICCLOUDSYNCINGOBJECT (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZNAME VARCHAR, ZTITLE1 VARCHAR, ZTITLE2 VARCHAR, ZFOLDER INTEGER, ZPARENT INTEGER, ZACCOUNT8 INTEGER, ZMARKEDFORDELETION INTEGER, ZFOLDERTYPE INTEGER, ZSMARTFOLDERQUERYJSON VARCHAR, ZTYPEUTI1 VARCHAR, ZALTTEXT VARCHAR, ZNOTE1 INTEGER);
+                 INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZNAME) VALUES (1, 14, 'iCloud');
+                 INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZNAME, ZACCOUNT8, ZFOLDERTYPE, ZSMARTFOLDERQUERYJSON) VALUES
+                   (10, 15, 'Notes', 1, 0, NULL),
+                   (11, 15, NULL, 1, 1, NULL),
+                   (12, 15, 'Work', 1, 3, '{"tags":{"include":["#Work","urgent"]}}'),
+                   (13, 15, 'Either', 1, 3, '{"tags":["work","urgent"],"operator":"any"}'),
+                   (14, 15, 'Recent', 1, 3, '{"dateCreated":{"within":"7d"}}');
+                 INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZTITLE1, ZFOLDER) VALUES (20, 12, 'Both', 10), (21, 12, 'Work only', 10), (22, 12, 'Deleted', 11);
+                 INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZTYPEUTI1, ZALTTEXT, ZNOTE1) VALUES
+                   (30, 9, 'com.apple.notes.inlinetextattachment.hashtag', '#work', 20),
+                   (31, 9, 'com.apple.notes.inlinetextattachment.hashtag', '#URGENT', 20),
+                   (32, 9, 'com.apple.notes.inlinetextattachment.hashtag', '#work', 21),
+                   (33, 9, 'com.apple.notes.inlinetextattachment.hashtag', '#work', 22);"##,
+            )
+            .unwrap();
+        let db = NotesDb::open(db_path).unwrap();
+
+        let smart: Vec<String> = db
+            .list_folders("iCloud")
+            .unwrap()
+            .into_iter()
+            .filter(|f| f.smart)
+            .map(|f| f.name)
+            .collect();
+        assert_eq!(smart, ["Either", "Recent", "Work"]);
+        let titles = |folder: &str| -> Vec<String> {
+            let mut titles: Vec<String> = db
+                .list_notes_in_folder("iCloud", &[folder.to_string()])
+                .unwrap()
+                .into_iter()
+                .map(|n| n.title)
+                .collect();
+            titles.sort();
+            titles
+        };
+        assert_eq!(titles("Work"), ["Both"]);
+        assert_eq!(titles("Either"), ["Both", "Work only"]);
+        let err = db
+            .list_notes_in_folder("iCloud", &["Recent".to_string()])
+            .unwrap_err();
+        assert!(
+            format!("{err:#}").contains("unsupported smart folder query"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn the_default_folder_answers_to_notes() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("NoteStore.sqlite");
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE Z_METADATA (Z_VERSION INTEGER PRIMARY KEY, Z_UUID VARCHAR(255));
+                 INSERT INTO Z_METADATA VALUES (1, 'STORE');
+                 CREATE TABLE ZICCLOUDSYNCINGOBJECT (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZNAME VARCHAR, ZTITLE1 VARCHAR, ZTITLE2 VARCHAR, ZFOLDER INTEGER, ZPARENT INTEGER, ZACCOUNT8 INTEGER, ZMARKEDFORDELETION INTEGER, ZIDENTIFIER VARCHAR);
+                 INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZNAME) VALUES (1, 14, 'iCloud'), (2, 14, 'Lokal');
+                 INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZTITLE2, ZACCOUNT8, ZIDENTIFIER) VALUES
+                   (10, 15, NULL, 1, 'DefaultFolder-CloudKit'),
+                   (11, 15, 'Notizen', 2, 'DefaultFolder-Local'),
+                   (12, 15, 'Work', 1, 'F12');
+                 INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZTITLE1, ZFOLDER) VALUES (20, 12, 'A', 10), (21, 12, 'B', 11), (22, 12, 'C', 12);",
+            )
+            .unwrap();
+        let db = NotesDb::open(db_path).unwrap();
+
+        let names: Vec<String> = db
+            .list_folders("iCloud")
+            .unwrap()
+            .into_iter()
+            .map(|f| f.name)
+            .collect();
+        assert_eq!(names, ["Notes", "Work"]);
+        let titles = |account: &str| -> Vec<String> {
+            db.list_notes_in_folder(account, &[DEFAULT_FOLDER.to_string()])
+                .unwrap()
+                .into_iter()
+                .map(|n| n.title)
+                .collect()
+        };
+        assert_eq!(titles("iCloud"), ["A"]);
+        assert_eq!(titles("Lokal"), ["B"]);
+    }
+
     #[test]
     fn parse_coredata_pk_parses() {
         assert_eq!(
@@ -0,0 +1,144 @@
+//! Finding duplicate notes for `notes dedupe`: notes whose titles and/or bodies match once case
+//! and whitespace are ignored.
+
+use crate::checksums;
+use crate::model::NoteSummary;
+use clap::ValueEnum;
+use std::collections::HashMap;
+
+/// What two notes must share to count as duplicates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DedupeBy {
+    Title,
+    /// The body text, apart from the title line.
+    Content,
+    /// Title and body text.
+    Both,
+}
+
+/// Lowercases and collapses runs of whitespace.
+fn normalize(s: &str) -> String {
+    s.split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `text` without attachment placeholders and without a first line repeating `title`.
+fn body_text(text: &str, title: &str) -> String {
+    let text = text.replace('\u{fffc}', "");
+    let mut lines = text.lines().skip_while(|l| l.trim().is_empty()).peekable();
+    if lines
+        .peek()
+        .is_some_and(|first| normalize(first.trim_start_matches('#')) == normalize(title))
+    {
+        lines.next();
+    }
+    normalize(&lines.collect::<Vec<_>>().join("\n"))
+}
+
+/// Whether notes `a` and `b` have the same body text (ignoring case, whitespace and a first line
+/// repeating the title), by their bodies in `texts`. Notes without a body in `texts` never do.
+pub fn same_body(texts: &HashMap<String, String>, a: &NoteSummary, b: &NoteSummary) -> bool {
+    match (texts.get(&a.id), texts.get(&b.id)) {
+        (Some(x), Some(y)) => body_text(x, &a.title) == body_text(y, &b.title),
+        _ => false,
+    }
+}
+
+/// Groups of two or more duplicate notes, newest (by modification date) first within each group.
+/// `texts` holds note bodies by id; notes with an empty title or body (whichever `by` compares)
+/// are never duplicates.
+pub fn duplicate_clusters<'a>(
+    notes: &'a [NoteSummary],
+    texts: &HashMap<String, String>,
+    by: DedupeBy,
+) -> Vec<Vec<&'a NoteSummary>> {
+    let mut groups: HashMap<(Option<String>, Option<String>), Vec<&NoteSummary>> = HashMap::new();
+    for n in notes {
+        let title = (by != DedupeBy::Content).then(|| normalize(&n.title));
+        let content = (by != DedupeBy::Title).then(|| {
+            let body = texts.get(&n.id).map(|t| body_text(t, &n.title));
+            body.unwrap_or_default()
+        });
+        if title.as_ref().is_some_and(String::is_empty)
+            || content.as_ref().is_some_and(String::is_empty)
+        {
+            continue;
+        }
+        let content = content.map(|c| checksums::sha256_bytes(c.as_bytes()));
+        groups.entry((title, content)).or_default().push(n);
+    }
+    let mut clusters: Vec<Vec<&NoteSummary>> = groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|mut group| {
+            group.sort_by(|a, b| b.modified_at.cmp(&a.modified_at).then(a.id.cmp(&b.id)));
+            group
+        })
+        .collect();
+    clusters.sort_by(|a, b| (&a[0].title, &a[0].id).cmp(&(&b[0].title, &b[0].id)));
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    fn note(id: &str, title: &str, modified: time::OffsetDateTime) -> NoteSummary {
+        NoteSummary {
+            id: id.to_string(),
+            title: title.to_string(),
+            folder_id: "f".to_string(),
+            uuid: None,
+            created_at: None,
+            modified_at: Some(modified),
+            pinned: false,
+            locked: false,
+        }
+    }
+
+    #[test]
+    fn clusters_match_normalized_titles_and_bodies() {
+        let notes = [
+            note("a", "Groceries", datetime!(2025-01-01 0:00 UTC)),
+            note("b", "groceries ", datetime!(2025-03-01 0:00 UTC)),
+            note("c", "Groceries", datetime!(2025-02-01 0:00 UTC)),
+            note("d", "Other", datetime!(2025-02-01 0:00 UTC)),
+        ];
+        let texts: HashMap<String, String> = [
+            ("a", "Groceries\nMilk  and eggs"),
+            ("b", "groceries\nmilk and eggs\n"),
+            ("c", "Groceries\nBread"),
+            ("d", "Other\nMilk and eggs"),
+        ]
+        .into_iter()
+        .map(|(id, text)| (id.to_string(), text.to_string()))
+        .collect();
+        let ids = |by| -> Vec<Vec<&str>> {
+            duplicate_clusters(&notes, &texts, by)
+                .into_iter()
+                .map(|c| c.iter().map(|n| n.id.as_str()).collect())
+                .collect()
+        };
+        assert_eq!(ids(DedupeBy::Title), [["b", "c", "a"]]);
+        assert_eq!(ids(DedupeBy::Both), [["b", "a"]]);
+        assert_eq!(ids(DedupeBy::Content), [["b", "d", "a"]]);
+        assert!(same_body(&texts, &notes[0], &notes[1]));
+        assert!(!same_body(&texts, &notes[0], &notes[2]));
+    }
+
+    #[test]
+    fn empty_bodies_are_not_duplicates() {
+        let notes = [
+            note("a", "One", datetime!(2025-01-01 0:00 UTC)),
+            note("b", "Two", datetime!(2025-01-01 0:00 UTC)),
+        ];
+        let texts = HashMap::from([
+            ("a".to_string(), "One\n".to_string()),
+            ("b".to_string(), "Two".to_string()),
+        ]);
+        assert!(duplicate_clusters(&notes, &texts, DedupeBy::Content).is_empty());
+    }
+}
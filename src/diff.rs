@@ -0,0 +1,175 @@
+use crate::backup::ExportedNote;
+use crate::verify::NoteRef;
+use anyhow::Context;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModifiedNote {
+    pub id: String,
+    pub title: String,
+    /// Which aspects changed: `title`, `folder`, `modified_at`, `contents`.
+    pub changes: Vec<&'static str>,
+    /// Unified diff of `contents.md` (only when requested and contents changed).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub patch: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffReport {
+    pub added: Vec<NoteRef>,
+    pub removed: Vec<NoteRef>,
+    pub modified: Vec<ModifiedNote>,
+}
+
+/// Compares two export trees note-by-note (matched by note id).
+pub fn diff_exports(
+    a: &[ExportedNote],
+    b: &[ExportedNote],
+    with_patch: bool,
+) -> anyhow::Result<DiffReport> {
+    let a_by_id: HashMap<&str, &ExportedNote> =
+        a.iter().map(|n| (n.metadata.id.as_str(), n)).collect();
+    let b_by_id: HashMap<&str, &ExportedNote> =
+        b.iter().map(|n| (n.metadata.id.as_str(), n)).collect();
+
+    let mut report = DiffReport {
+        added: Vec::new(),
+        removed: Vec::new(),
+        modified: Vec::new(),
+    };
+
+    for old in a {
+        if !b_by_id.contains_key(old.metadata.id.as_str()) {
+            report.removed.push(note_ref(old));
+        }
+    }
+
+    for new in b {
+        let Some(old) = a_by_id.get(new.metadata.id.as_str()) else {
+            report.added.push(note_ref(new));
+            continue;
+        };
+
+        let mut changes = Vec::new();
+        if old.metadata.title != new.metadata.title {
+            changes.push("title");
+        }
+        if old.metadata.folder_path != new.metadata.folder_path {
+            changes.push("folder");
+        }
+        if old.metadata.modified_at != new.metadata.modified_at {
+            changes.push("modified_at");
+        }
+        let old_md = read_contents(&old.dir)?;
+        let new_md = read_contents(&new.dir)?;
+        let mut patch = None;
+        if old_md != new_md {
+            changes.push("contents");
+            if with_patch {
+                patch = Some(unified_diff(
+                    &old_md,
+                    &new_md,
+                    &old.dir.join("contents.md"),
+                    &new.dir.join("contents.md"),
+                ));
+            }
+        }
+
+        if !changes.is_empty() {
+            report.modified.push(ModifiedNote {
+                id: new.metadata.id.clone(),
+                title: new.metadata.title.clone(),
+                changes,
+                patch,
+            });
+        }
+    }
+
+    report.added.sort_by(|a, b| a.title.cmp(&b.title));
+    report.removed.sort_by(|a, b| a.title.cmp(&b.title));
+    report.modified.sort_by(|a, b| a.title.cmp(&b.title));
+    Ok(report)
+}
+
+fn note_ref(n: &ExportedNote) -> NoteRef {
+    NoteRef {
+        id: n.metadata.id.clone(),
+        title: n.metadata.title.clone(),
+    }
+}
+
+fn read_contents(note_dir: &Path) -> anyhow::Result<String> {
+    let path = note_dir.join("contents.md");
+    if !path.is_file() {
+        return Ok(String::new());
+    }
+    std::fs::read_to_string(&path).with_context(|| format!("read {path:?}"))
+}
+
+fn unified_diff(old: &str, new: &str, old_path: &Path, new_path: &Path) -> String {
+    similar::TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(
+            &old_path.display().to_string(),
+            &new_path.display().to_string(),
+        )
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::BackupNoteMetadata;
+    use tempfile::tempdir;
+    use time::OffsetDateTime;
+
+    fn note(root: &Path, id: &str, title: &str, contents: &str) -> ExportedNote {
+        let dir = root.join(id);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("contents.md"), contents).unwrap();
+        ExportedNote {
+            dir,
+            metadata: BackupNoteMetadata {
+                id: id.into(),
+                title: title.into(),
+                account: "iCloud".into(),
+                folder_path: vec!["Personal".into()],
+                created_at: OffsetDateTime::UNIX_EPOCH,
+                modified_at: OffsetDateTime::UNIX_EPOCH,
+                shared: false,
+                participants: Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn diff_exports_classifies_notes_and_renders_patch() {
+        let a = tempdir().unwrap();
+        let b = tempdir().unwrap();
+        let report = diff_exports(
+            &[
+                note(a.path(), "n1", "Same", "same"),
+                note(a.path(), "n2", "Edited", "line one\nline two\n"),
+                note(a.path(), "n3", "Gone", "bye"),
+            ],
+            &[
+                note(b.path(), "n1", "Same", "same"),
+                note(b.path(), "n2", "Edited!", "line one\nline 2\n"),
+                note(b.path(), "n4", "New", "hi"),
+            ],
+            true,
+        )
+        .unwrap();
+
+        assert_eq!(report.added[0].id, "n4");
+        assert_eq!(report.removed[0].id, "n3");
+        assert_eq!(report.modified.len(), 1);
+        assert_eq!(report.modified[0].changes, vec!["title", "contents"]);
+        let patch = report.modified[0].patch.as_deref().unwrap();
+        assert!(patch.contains("-line two"));
+        assert!(patch.contains("+line 2"));
+    }
+}
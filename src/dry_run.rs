@@ -0,0 +1,220 @@
+//! `--dry-run`: a backend that reads through to the real one and reports writes instead of
+//! making them.
+
+use crate::cancel::CancelToken;
+use crate::error::Result;
+use crate::model::{Account, Folder, Note, NoteSummary};
+use crate::transport::NotesBackend;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Wraps a backend so every write prints `Would …` to stderr and succeeds without touching Notes.
+/// Created notes and folders get `dry-run://` ids.
+pub struct DryRunBackend {
+    inner: Box<dyn NotesBackend>,
+    next_id: AtomicUsize,
+}
+
+impl DryRunBackend {
+    pub fn new(inner: Box<dyn NotesBackend>) -> Self {
+        Self {
+            inner,
+            next_id: AtomicUsize::new(1),
+        }
+    }
+
+    fn report(&self, change: String) -> Result<()> {
+        eprintln!("Would {change}");
+        Ok(())
+    }
+
+    fn placeholder_id(&self, kind: &str) -> String {
+        let n = self.next_id.fetch_add(1, Ordering::Relaxed);
+        format!("dry-run://{kind}/{n}")
+    }
+}
+
+/// `account > path`, the way `--folder` spells it.
+fn place(account: &str, path: &[String]) -> String {
+    std::iter::once(account)
+        .chain(path.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(" > ")
+}
+
+impl NotesBackend for DryRunBackend {
+    fn list_accounts(&self) -> Result<Vec<Account>> {
+        self.inner.list_accounts()
+    }
+
+    fn list_folders(&self, account: &str) -> Result<Vec<Folder>> {
+        self.inner.list_folders(account)
+    }
+
+    fn list_notes(&self, account: &str) -> Result<Vec<NoteSummary>> {
+        self.inner.list_notes(account)
+    }
+
+    fn list_notes_in_folder(
+        &self,
+        account: &str,
+        folder_path: &[String],
+    ) -> Result<Vec<NoteSummary>> {
+        self.inner.list_notes_in_folder(account, folder_path)
+    }
+
+    fn stream_note_summaries(
+        &self,
+        account: &str,
+        folder_path: Option<&[String]>,
+        on_note: &mut dyn FnMut(NoteSummary),
+    ) -> Result<()> {
+        self.inner
+            .stream_note_summaries(account, folder_path, on_note)
+    }
+
+    fn stream_note_summaries_cancellable(
+        &self,
+        account: &str,
+        folder_path: Option<&[String]>,
+        cancel: &CancelToken,
+        on_note: &mut dyn FnMut(NoteSummary),
+    ) -> Result<()> {
+        self.inner
+            .stream_note_summaries_cancellable(account, folder_path, cancel, on_note)
+    }
+
+    fn list_deleted_notes(&self, account: &str) -> Result<Vec<NoteSummary>> {
+        self.inner.list_deleted_notes(account)
+    }
+
+    fn deleted_note_ids(&self, ids: &[String]) -> Result<HashSet<String>> {
+        self.inner.deleted_note_ids(ids)
+    }
+
+    fn is_note_deleted(&self, id: &str) -> Result<bool> {
+        self.inner.is_note_deleted(id)
+    }
+
+    fn get_note(&self, id: &str) -> Result<Note> {
+        self.inner.get_note(id)
+    }
+
+    fn get_note_streaming(&self, id: &str, on_chunk: &mut dyn FnMut(&str)) -> Result<Note> {
+        self.inner.get_note_streaming(id, on_chunk)
+    }
+
+    fn create_note_html(
+        &self,
+        account: &str,
+        folder_path: &[String],
+        title: &str,
+        body_html: &str,
+    ) -> Result<String> {
+        let folder = match folder_path.is_empty() {
+            true => format!("the default folder of {account}"),
+            false => place(account, folder_path),
+        };
+        self.report(format!(
+            "create note {title:?} in {folder} ({} bytes of HTML)",
+            body_html.len()
+        ))?;
+        Ok(self.placeholder_id("note"))
+    }
+
+    fn set_note_title(&self, id: &str, title: &str) -> Result<()> {
+        self.report(format!("rename {id} to {title:?}"))
+    }
+
+    fn set_note_body_html(&self, id: &str, body_html: &str) -> Result<()> {
+        self.report(format!(
+            "replace the body of {id} ({} bytes of HTML)",
+            body_html.len()
+        ))
+    }
+
+    fn append_note_body_html(&self, id: &str, body_html: &str) -> Result<()> {
+        self.report(format!("append {} bytes of HTML to {id}", body_html.len()))
+    }
+
+    fn delete_note(&self, id: &str) -> Result<()> {
+        self.report(format!("move {id} to Recently Deleted"))
+    }
+
+    fn purge_note(&self, id: &str) -> Result<()> {
+        self.report(format!("permanently delete {id}"))
+    }
+
+    fn move_note(&self, id: &str, account: &str, folder_path: &[String]) -> Result<()> {
+        self.report(format!("move {id} to {}", place(account, folder_path)))
+    }
+
+    fn open_note(&self, id: &str) -> Result<()> {
+        self.report(format!("open {id} in Notes"))
+    }
+
+    fn attach_file(&self, id: &str, path: &Path) -> Result<()> {
+        self.report(format!("attach {} to {id}", path.display()))
+    }
+
+    fn create_folder(&self, account: &str, parent_path: &[String], name: &str) -> Result<String> {
+        self.report(format!(
+            "create folder {name:?} in {}",
+            place(account, parent_path)
+        ))?;
+        Ok(self.placeholder_id("folder"))
+    }
+
+    fn rename_folder(&self, account: &str, folder_path: &[String], name: &str) -> Result<()> {
+        self.report(format!(
+            "rename folder {} to {name:?}",
+            place(account, folder_path)
+        ))
+    }
+
+    fn move_folder(
+        &self,
+        account: &str,
+        folder_path: &[String],
+        parent_path: &[String],
+    ) -> Result<()> {
+        self.report(format!(
+            "move folder {} into {}",
+            place(account, folder_path),
+            place(account, parent_path)
+        ))
+    }
+
+    fn delete_folder(&self, account: &str, folder_path: &[String]) -> Result<()> {
+        self.report(format!("delete folder {}", place(account, folder_path)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::FixtureBackend;
+
+    #[test]
+    fn reads_go_through_and_writes_get_placeholder_ids() {
+        let fixture = FixtureBackend::from_path(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/basic.json"),
+        )
+        .unwrap();
+        let backend = DryRunBackend::new(Box::new(fixture));
+        assert_eq!(backend.get_note("n1").unwrap().title, "Alpha");
+        let path = ["Personal".to_string()];
+        assert_eq!(
+            backend
+                .create_note_html("iCloud", &path, "Hi", "<div>Hi</div>")
+                .unwrap(),
+            "dry-run://note/1"
+        );
+        assert_eq!(
+            backend.create_folder("iCloud", &path, "New").unwrap(),
+            "dry-run://folder/2"
+        );
+        assert_eq!(place("iCloud", &path), "iCloud > Personal");
+    }
+}
@@ -0,0 +1,230 @@
+use crate::backup::NoteFilter;
+use crate::cancel::CancelToken;
+use crate::checksums;
+use crate::config::MarkdownDialect;
+use crate::model::Note;
+use crate::render::{self, escape_html};
+use crate::single_file;
+use crate::transport::NotesBackend;
+use anyhow::Context;
+use std::io::Write;
+use std::path::Path;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use zip::CompressionMethod;
+use zip::write::SimpleFileOptions;
+
+/// How `export --format epub` splits notes into chapters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Chapters {
+    /// One chapter per note.
+    #[default]
+    Note,
+    /// One chapter per folder, with a section per note.
+    Folder,
+}
+
+struct Chapter {
+    title: String,
+    body: String,
+}
+
+/// Writes every note in `account` into a single EPUB 3 book at `out`. Returns the note count.
+pub fn export(
+    backend: &dyn NotesBackend,
+    account: &str,
+    out: &Path,
+    chapters: Chapters,
+    folder: Option<&[String]>,
+    filter: &NoteFilter,
+    cancel: &CancelToken,
+) -> anyhow::Result<usize> {
+    let notes = single_file::load_notes(backend, account, folder, filter, cancel)?;
+    if let Some(parent) = out.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).with_context(|| format!("create {parent:?}"))?;
+    }
+    let file = std::fs::File::create(out).with_context(|| format!("create {out:?}"))?;
+    write_book(file, account, &notes, chapters).with_context(|| format!("write {out:?}"))?;
+    Ok(notes.len())
+}
+
+fn write_book<W: Write + std::io::Seek>(
+    w: W,
+    account: &str,
+    notes: &[(Vec<String>, Note)],
+    chapters: Chapters,
+) -> anyhow::Result<()> {
+    let chapters = match chapters {
+        Chapters::Note => notes
+            .iter()
+            .map(|(path, note)| Chapter {
+                title: note_title(note).to_string(),
+                body: format!(
+                    "<h1>{}</h1>\n<p class=\"folder\">{}</p>\n{}",
+                    escape_html(note_title(note)),
+                    escape_html(&path.join(" > ")),
+                    note_xhtml(note)
+                ),
+            })
+            .collect::<Vec<_>>(),
+        Chapters::Folder => {
+            let mut out: Vec<Chapter> = Vec::new();
+            let mut prev: Option<&[String]> = None;
+            for (path, note) in notes {
+                if prev != Some(path.as_slice()) {
+                    let title = path.join(" > ");
+                    out.push(Chapter {
+                        body: format!("<h1>{}</h1>\n", escape_html(&title)),
+                        title,
+                    });
+                    prev = Some(path);
+                }
+                let chapter = out.last_mut().expect("chapter was pushed");
+                chapter.body.push_str(&format!(
+                    "<h2>{}</h2>\n{}",
+                    escape_html(note_title(note)),
+                    note_xhtml(note)
+                ));
+            }
+            out
+        }
+    };
+
+    let modified = notes
+        .iter()
+        .map(|(_, n)| n.modified_at)
+        .max()
+        .unwrap_or(OffsetDateTime::UNIX_EPOCH);
+    let ids: Vec<&str> = notes.iter().map(|(_, n)| n.id.as_str()).collect();
+    let identifier = format!(
+        "urn:apple-notes:{}",
+        &checksums::sha256_bytes(ids.join("\n").as_bytes())[..32]
+    );
+
+    let mut zip = zip::ZipWriter::new(w);
+    // The `mimetype` entry must come first and be stored uncompressed.
+    zip.start_file(
+        "mimetype",
+        SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    zip.write_all(b"application/epub+zip")?;
+
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file("META-INF/container.xml", deflated)?;
+    zip.write_all(CONTAINER_XML.as_bytes())?;
+
+    zip.start_file("OEBPS/content.opf", deflated)?;
+    zip.write_all(content_opf(account, &identifier, modified, chapters.len()).as_bytes())?;
+
+    zip.start_file("OEBPS/nav.xhtml", deflated)?;
+    let nav: String = chapters
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            format!(
+                "<li><a href=\"{}\">{}</a></li>\n",
+                chapter_file(i),
+                escape_html(&c.title)
+            )
+        })
+        .collect();
+    zip.write_all(
+        xhtml(
+            "Contents",
+            &format!(
+                "<nav epub:type=\"toc\" id=\"toc\">\n<h1>Contents</h1>\n<ol>\n{nav}</ol>\n</nav>\n"
+            ),
+        )
+        .as_bytes(),
+    )?;
+
+    for (i, chapter) in chapters.iter().enumerate() {
+        zip.start_file(format!("OEBPS/{}", chapter_file(i)), deflated)?;
+        zip.write_all(xhtml(&chapter.title, &chapter.body).as_bytes())?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+fn note_title(note: &Note) -> &str {
+    match note.title.trim() {
+        "" => "Untitled",
+        title => title,
+    }
+}
+
+/// Note bodies go through Markdown so the chapter is well-formed XHTML (raw HTML is dropped).
+fn note_xhtml(note: &Note) -> String {
+    let md = render::html_to_markdown(&note.body_html);
+    render::markdown_to_html_dialect(&md, MarkdownDialect::Gfm)
+}
+
+fn chapter_file(index: usize) -> String {
+    format!("chapter-{:04}.xhtml", index + 1)
+}
+
+fn xhtml(title: &str, body: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head>
+<title>{}</title>
+</head>
+<body>
+{body}
+</body>
+</html>
+"#,
+        escape_html(title)
+    )
+}
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+fn content_opf(
+    account: &str,
+    identifier: &str,
+    modified: OffsetDateTime,
+    chapters: usize,
+) -> String {
+    let modified = modified
+        .to_offset(time::UtcOffset::UTC)
+        .replace_nanosecond(0)
+        .unwrap_or(modified)
+        .format(&Rfc3339)
+        .unwrap_or_default();
+    let mut manifest = String::new();
+    let mut spine = String::new();
+    for i in 0..chapters {
+        manifest.push_str(&format!(
+            "    <item id=\"c{i}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>\n",
+            chapter_file(i)
+        ));
+        spine.push_str(&format!("    <itemref idref=\"c{i}\"/>\n"));
+    }
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="book-id">{identifier}</dc:identifier>
+    <dc:title>{title}</dc:title>
+    <dc:language>en</dc:language>
+    <meta property="dcterms:modified">{modified}</meta>
+  </metadata>
+  <manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+{manifest}  </manifest>
+  <spine>
+{spine}  </spine>
+</package>
+"#,
+        title = escape_html(&format!("{account} Notes")),
+    )
+}
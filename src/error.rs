@@ -0,0 +1,156 @@
+use crate::cancel::Cancelled;
+use crate::db::DbUnavailable;
+
+/// Errors returned by [`NotesBackend`](crate::transport::NotesBackend) methods, so callers can
+/// tell a missing note from a denied permission without parsing messages.
+#[derive(Debug)]
+pub enum NotesError {
+    /// No account, folder or note matches what was asked for.
+    NotFound(String),
+    /// A folder path or note reference matches more than one folder or note.
+    Ambiguous(String),
+    /// macOS denied access: Automation consent for Notes.app, or the Notes database.
+    PermissionDenied(String),
+    /// The backend can't run here (no `osascript`, no Notes database, …).
+    BackendUnavailable(String),
+    /// `osascript` ran, but the script failed.
+    ScriptError(String),
+    /// The note is in Recently Deleted already, where deleting it again would delete it for good.
+    AlreadyDeleted(String),
+    /// The operation was cancelled or hit `--timeout`.
+    Cancelled(Cancelled),
+    /// Anything else (I/O, unexpected output, …).
+    Other(anyhow::Error),
+}
+
+pub type Result<T, E = NotesError> = std::result::Result<T, E>;
+
+impl NotesError {
+    /// Stable snake_case name of the variant, for machine-readable error output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            NotesError::NotFound(_) => "not_found",
+            NotesError::Ambiguous(_) => "ambiguous",
+            NotesError::PermissionDenied(_) => "permission_denied",
+            NotesError::BackendUnavailable(_) => "backend_unavailable",
+            NotesError::ScriptError(_) => "script_error",
+            NotesError::AlreadyDeleted(_) => "already_deleted",
+            NotesError::Cancelled(_) => "cancelled",
+            NotesError::Other(_) => "other",
+        }
+    }
+
+    /// The `apple-notes` exit status for a command that failed with this error: 2 not found,
+    /// 3 ambiguous, 4 permission denied, 5 backend unavailable, 124 timed out, 130 cancelled,
+    /// and 1 for anything else.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            NotesError::NotFound(_) => 2,
+            NotesError::Ambiguous(_) => 3,
+            NotesError::PermissionDenied(_) => 4,
+            NotesError::BackendUnavailable(_) => 5,
+            NotesError::Cancelled(Cancelled::DeadlineExceeded) => 124,
+            NotesError::Cancelled(Cancelled::Cancelled) => 130,
+            NotesError::ScriptError(_) | NotesError::AlreadyDeleted(_) | NotesError::Other(_) => 1,
+        }
+    }
+
+    /// Classifies a failed `osascript` run by the error it printed.
+    pub(crate) fn from_script_failure(message: String) -> Self {
+        // errAEEventNotPermitted: Automation consent was declined (or never answered).
+        if message.contains("(-1743)") {
+            NotesError::PermissionDenied(message)
+        // errAENoSuchObject, or our own JXA `throw new Error("… not found: …")`.
+        } else if message.contains("(-1728)") || message.contains("not found: ") {
+            NotesError::NotFound(message)
+        } else {
+            NotesError::ScriptError(message)
+        }
+    }
+}
+
+impl std::fmt::Display for NotesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotesError::NotFound(msg)
+            | NotesError::Ambiguous(msg)
+            | NotesError::PermissionDenied(msg)
+            | NotesError::BackendUnavailable(msg)
+            | NotesError::ScriptError(msg)
+            | NotesError::AlreadyDeleted(msg) => f.write_str(msg),
+            NotesError::Cancelled(c) => c.fmt(f),
+            NotesError::Other(e) => write!(f, "{e:#}"),
+        }
+    }
+}
+
+impl std::error::Error for NotesError {}
+
+impl From<Cancelled> for NotesError {
+    fn from(c: Cancelled) -> Self {
+        NotesError::Cancelled(c)
+    }
+}
+
+/// Recovers the kind of errors that were passed along as `anyhow::Error`.
+impl From<anyhow::Error> for NotesError {
+    fn from(e: anyhow::Error) -> Self {
+        let e = match e.downcast::<NotesError>() {
+            Ok(n) => return n,
+            Err(e) => e,
+        };
+        let e = match e.downcast::<Cancelled>() {
+            Ok(c) => return NotesError::Cancelled(c),
+            Err(e) => e,
+        };
+        match e.downcast_ref::<DbUnavailable>() {
+            Some(DbUnavailable::PermissionDenied(_)) => {
+                NotesError::PermissionDenied(format!("{e:#}"))
+            }
+            Some(_) => NotesError::BackendUnavailable(format!("{e:#}")),
+            None => NotesError::Other(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn kinds_survive_a_round_trip_through_anyhow() {
+        let wrapped = Err::<(), _>(NotesError::NotFound("note x".into()))
+            .context("show note")
+            .unwrap_err();
+        assert!(matches!(NotesError::from(wrapped), NotesError::NotFound(_)));
+
+        let cancelled = anyhow::Error::from(Cancelled::DeadlineExceeded);
+        assert!(matches!(
+            NotesError::from(cancelled),
+            NotesError::Cancelled(Cancelled::DeadlineExceeded)
+        ));
+        assert!(matches!(
+            NotesError::from(anyhow::anyhow!("disk full")),
+            NotesError::Other(_)
+        ));
+    }
+
+    #[test]
+    fn script_failures_are_classified() {
+        let denied = "osascript failed (exit status: 1): Not authorized to send Apple events to Notes. (-1743)";
+        assert!(matches!(
+            NotesError::from_script_failure(denied.into()),
+            NotesError::PermissionDenied(_)
+        ));
+        let missing = "osascript failed (exit status: 1): Notes got an error: Can’t get note id \"x\". (-1728)";
+        assert!(matches!(
+            NotesError::from_script_failure(missing.into()),
+            NotesError::NotFound(_)
+        ));
+        assert!(matches!(
+            NotesError::from_script_failure("osascript failed: syntax error".into()),
+            NotesError::ScriptError(_)
+        ));
+    }
+}
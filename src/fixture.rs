@@ -1,9 +1,11 @@
+use crate::error::{NotesError, Result};
 use crate::model::{Account, Folder, Note, NoteSummary};
-use crate::transport::NotesBackend;
-use anyhow::{Context, anyhow};
+use crate::transport::{NotesBackend, RECENTLY_DELETED};
+use anyhow::Context;
 use serde::Deserialize;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 #[derive(Debug, Clone, Deserialize)]
@@ -14,10 +16,15 @@ struct FixtureData {
     notes_by_id: HashMap<String, Note>,
 }
 
+/// Serves notes from a JSON fixture file (see `tests/fixtures/basic.json`). Writes succeed
+/// without changing anything, except deletions: a deleted note moves to Recently Deleted, and
+/// deleting it from there makes it disappear.
 #[derive(Debug)]
 pub struct FixtureBackend {
     data: FixtureData,
     next_id: AtomicUsize,
+    trashed: Mutex<HashSet<String>>,
+    purged: Mutex<HashSet<String>>,
 }
 
 impl FixtureBackend {
@@ -32,36 +39,64 @@ impl FixtureBackend {
         Ok(Self {
             data,
             next_id: AtomicUsize::new(1),
+            trashed: Mutex::new(HashSet::new()),
+            purged: Mutex::new(HashSet::new()),
         })
     }
 
-    fn folders(&self, account: &str) -> anyhow::Result<Vec<Folder>> {
+    fn is_purged(&self, id: &str) -> bool {
+        self.purged.lock().unwrap().contains(id)
+    }
+
+    /// Whether the note is in its account's Recently Deleted folder, from the fixture or because
+    /// it was deleted since.
+    fn in_trash(&self, id: &str) -> Result<bool> {
+        if self.trashed.lock().unwrap().contains(id) {
+            return Ok(true);
+        }
+        let note = self.get_note(id)?;
+        Ok(self
+            .data
+            .folders_by_account
+            .values()
+            .flatten()
+            .any(|f| f.id == note.folder_id && f.path == [RECENTLY_DELETED]))
+    }
+
+    fn folders(&self, account: &str) -> Result<Vec<Folder>> {
         self.data
             .folders_by_account
             .get(account)
             .cloned()
-            .ok_or_else(|| anyhow!("fixture missing folders for account {account:?}"))
+            .ok_or_else(|| {
+                NotesError::NotFound(format!("fixture missing folders for account {account:?}"))
+            })
     }
 
-    fn note_summaries(&self, account: &str) -> anyhow::Result<Vec<NoteSummary>> {
-        self.data
+    fn note_summaries(&self, account: &str) -> Result<Vec<NoteSummary>> {
+        let mut notes = self
+            .data
             .note_summaries_by_account
             .get(account)
             .cloned()
-            .ok_or_else(|| anyhow!("fixture missing notes for account {account:?}"))
+            .ok_or_else(|| {
+                NotesError::NotFound(format!("fixture missing notes for account {account:?}"))
+            })?;
+        notes.retain(|n| !self.is_purged(&n.id));
+        Ok(notes)
     }
 }
 
 impl NotesBackend for FixtureBackend {
-    fn list_accounts(&self) -> anyhow::Result<Vec<Account>> {
+    fn list_accounts(&self) -> Result<Vec<Account>> {
         Ok(self.data.accounts.clone())
     }
 
-    fn list_folders(&self, account: &str) -> anyhow::Result<Vec<Folder>> {
+    fn list_folders(&self, account: &str) -> Result<Vec<Folder>> {
         self.folders(account)
     }
 
-    fn list_notes(&self, account: &str) -> anyhow::Result<Vec<NoteSummary>> {
+    fn list_notes(&self, account: &str) -> Result<Vec<NoteSummary>> {
         self.note_summaries(account)
     }
 
@@ -69,13 +104,13 @@ impl NotesBackend for FixtureBackend {
         &self,
         account: &str,
         folder_path: &[String],
-    ) -> anyhow::Result<Vec<NoteSummary>> {
+    ) -> Result<Vec<NoteSummary>> {
         let folders = self.folders(account)?;
         let want = folder_path.join(" > ");
         let folder = folders
             .into_iter()
             .find(|f| f.path.join(" > ") == want)
-            .ok_or_else(|| anyhow!("fixture missing folder {want:?}"))?;
+            .ok_or_else(|| NotesError::NotFound(format!("fixture missing folder {want:?}")))?;
 
         let mut notes = self.note_summaries(account)?;
         notes.retain(|n| n.folder_id == folder.id);
@@ -87,7 +122,7 @@ impl NotesBackend for FixtureBackend {
         account: &str,
         folder_path: Option<&[String]>,
         on_note: &mut dyn FnMut(NoteSummary),
-    ) -> anyhow::Result<()> {
+    ) -> Result<()> {
         let mut notes = if let Some(folder_path) = folder_path {
             self.list_notes_in_folder(account, folder_path)?
         } else {
@@ -101,12 +136,20 @@ impl NotesBackend for FixtureBackend {
         Ok(())
     }
 
-    fn get_note(&self, id: &str) -> anyhow::Result<Note> {
+    fn list_deleted_notes(&self, account: &str) -> Result<Vec<NoteSummary>> {
+        let trashed = self.trashed.lock().unwrap().clone();
+        let mut notes = self.note_summaries(account)?;
+        notes.retain(|n| trashed.contains(&n.id) || self.in_trash(&n.id).unwrap_or(false));
+        Ok(notes)
+    }
+
+    fn get_note(&self, id: &str) -> Result<Note> {
         self.data
             .notes_by_id
             .get(id)
+            .filter(|_| !self.is_purged(id))
             .cloned()
-            .ok_or_else(|| anyhow!("fixture missing note id {id:?}"))
+            .ok_or_else(|| NotesError::NotFound(format!("fixture missing note id {id:?}")))
     }
 
     fn create_note_html(
@@ -115,28 +158,41 @@ impl NotesBackend for FixtureBackend {
         _folder_path: &[String],
         _title: &str,
         _body_html: &str,
-    ) -> anyhow::Result<String> {
+    ) -> Result<String> {
         let id = self.next_id.fetch_add(1, Ordering::Relaxed);
         Ok(format!("fixture://note/{id}"))
     }
 
-    fn set_note_title(&self, _id: &str, _title: &str) -> anyhow::Result<()> {
+    fn set_note_title(&self, _id: &str, _title: &str) -> Result<()> {
         Ok(())
     }
 
-    fn set_note_body_html(&self, _id: &str, _body_html: &str) -> anyhow::Result<()> {
+    fn set_note_body_html(&self, _id: &str, _body_html: &str) -> Result<()> {
         Ok(())
     }
 
-    fn append_note_body_html(&self, _id: &str, _body_html: &str) -> anyhow::Result<()> {
+    fn append_note_body_html(&self, _id: &str, _body_html: &str) -> Result<()> {
         Ok(())
     }
 
-    fn delete_note(&self, _id: &str) -> anyhow::Result<()> {
+    fn delete_note(&self, id: &str) -> Result<()> {
+        if self.in_trash(id)? {
+            self.purged.lock().unwrap().insert(id.to_string());
+        } else {
+            self.trashed.lock().unwrap().insert(id.to_string());
+        }
         Ok(())
     }
 
-    fn move_note(&self, _id: &str, _account: &str, _folder_path: &[String]) -> anyhow::Result<()> {
+    fn move_note(&self, _id: &str, _account: &str, _folder_path: &[String]) -> Result<()> {
+        Ok(())
+    }
+
+    fn open_note(&self, id: &str) -> Result<()> {
+        self.get_note(id).map(|_| ())
+    }
+
+    fn attach_file(&self, _id: &str, _path: &Path) -> Result<()> {
         Ok(())
     }
 
@@ -145,21 +201,25 @@ impl NotesBackend for FixtureBackend {
         _account: &str,
         _parent_path: &[String],
         _name: &str,
-    ) -> anyhow::Result<String> {
+    ) -> Result<String> {
         let id = self.next_id.fetch_add(1, Ordering::Relaxed);
         Ok(format!("fixture://folder/{id}"))
     }
 
-    fn rename_folder(
+    fn rename_folder(&self, _account: &str, _folder_path: &[String], _name: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn move_folder(
         &self,
         _account: &str,
         _folder_path: &[String],
-        _name: &str,
-    ) -> anyhow::Result<()> {
+        _parent_path: &[String],
+    ) -> Result<()> {
         Ok(())
     }
 
-    fn delete_folder(&self, _account: &str, _folder_path: &[String]) -> anyhow::Result<()> {
+    fn delete_folder(&self, _account: &str, _folder_path: &[String]) -> Result<()> {
         Ok(())
     }
 }
@@ -196,4 +256,26 @@ mod tests {
         assert_eq!(backend.list_notes("iCloud").unwrap().len(), 1);
         assert_eq!(backend.get_note("n1").unwrap().title, "Hello");
     }
+    #[test]
+    fn deleting_twice_purges_and_purging_a_deleted_note_deletes_it_once() {
+        let backend = FixtureBackend::from_path(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/basic.json"),
+        )
+        .unwrap();
+        backend.delete_note("n1").unwrap();
+        assert!(backend.is_note_deleted("n1").unwrap());
+        assert_eq!(backend.list_deleted_notes("iCloud").unwrap()[0].id, "n1");
+        backend.purge_note("n1").unwrap();
+        assert!(matches!(
+            backend.get_note("n1"),
+            Err(NotesError::NotFound(_))
+        ));
+
+        backend.purge_note("n2").unwrap();
+        assert!(matches!(
+            backend.get_note("n2"),
+            Err(NotesError::NotFound(_))
+        ));
+        assert!(backend.list_deleted_notes("iCloud").unwrap().is_empty());
+    }
 }
@@ -0,0 +1,71 @@
+//! Line matching for `notes grep`.
+
+use regex::Regex;
+use serde::Serialize;
+
+/// A line printed for a match: the matching line itself or one of its context lines.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GrepLine {
+    /// 1-based line number in the note's text.
+    pub line: usize,
+    pub text: String,
+    #[serde(rename = "match")]
+    pub is_match: bool,
+}
+
+/// The lines of `text` matching `pattern`, each with up to `context` lines before and after.
+/// Overlapping context is printed once, in line order.
+pub fn grep_lines(text: &str, pattern: &Regex, context: usize) -> Vec<GrepLine> {
+    let lines: Vec<&str> = text.lines().collect();
+    let matches: Vec<usize> = (0..lines.len())
+        .filter(|&i| pattern.is_match(lines[i]))
+        .collect();
+    let mut out: Vec<GrepLine> = Vec::new();
+    for &m in &matches {
+        let start = m.saturating_sub(context);
+        let end = (m + context).min(lines.len() - 1);
+        for (i, text) in lines.iter().enumerate().take(end + 1).skip(start) {
+            if out.last().is_some_and(|l| l.line > i) {
+                continue;
+            }
+            out.push(GrepLine {
+                line: i + 1,
+                text: text.to_string(),
+                is_match: matches.binary_search(&i).is_ok(),
+            });
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_come_with_merged_context() {
+        let text = "one\ntwo match\nthree\nfour\nfive match\nsix\nseven\neight\nnine match";
+        let pattern = Regex::new("match").unwrap();
+        let shown = |context| -> Vec<(usize, bool)> {
+            grep_lines(text, &pattern, context)
+                .iter()
+                .map(|l| (l.line, l.is_match))
+                .collect()
+        };
+        assert_eq!(shown(0), [(2, true), (5, true), (9, true)]);
+        assert_eq!(
+            shown(1),
+            [
+                (1, false),
+                (2, true),
+                (3, false),
+                (4, false),
+                (5, true),
+                (6, false),
+                (8, false),
+                (9, true)
+            ]
+        );
+        assert!(grep_lines("", &pattern, 2).is_empty());
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::{Context, anyhow};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Environment passed to an `--on-complete` command.
+fn env_vars(
+    out: &Path,
+    result: &anyhow::Result<u64>,
+    elapsed: Duration,
+) -> Vec<(&'static str, String)> {
+    let mut vars = vec![
+        ("APPLE_NOTES_EXPORT_OUT", out.display().to_string()),
+        (
+            "APPLE_NOTES_EXPORT_DURATION_MS",
+            elapsed.as_millis().to_string(),
+        ),
+    ];
+    match result {
+        Ok(exported) => vars.extend([
+            ("APPLE_NOTES_EXPORT_STATUS", "ok".to_string()),
+            ("APPLE_NOTES_EXPORT_EXPORTED", exported.to_string()),
+            ("APPLE_NOTES_EXPORT_FAILURES", "0".to_string()),
+        ]),
+        Err(err) => vars.extend([
+            ("APPLE_NOTES_EXPORT_STATUS", "failed".to_string()),
+            ("APPLE_NOTES_EXPORT_EXPORTED", "0".to_string()),
+            ("APPLE_NOTES_EXPORT_FAILURES", "1".to_string()),
+            ("APPLE_NOTES_EXPORT_ERROR", format!("{err:#}")),
+        ]),
+    }
+    vars
+}
+
+/// Runs `command` through the shell (`sh -c`, or `cmd /C` on Windows) once an export has finished, successfully or not. `result`
+/// carries the number of notes exported.
+///
+/// The command's stdout is forwarded to our stderr so it can't corrupt `--json` output. A
+/// command that exits non-zero is an error.
+pub fn run_on_complete(
+    command: &str,
+    out: &Path,
+    result: &anyhow::Result<u64>,
+    elapsed: Duration,
+) -> anyhow::Result<()> {
+    let output = shell(command)
+        .envs(env_vars(out, result, elapsed))
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .context("failed to spawn --on-complete command")?;
+    std::io::stderr().write_all(&output.stdout).ok();
+    if !output.status.success() {
+        return Err(anyhow!("--on-complete command failed ({})", output.status));
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command);
+    cmd
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/C").arg(command);
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_vars_report_status_and_counts() {
+        let ok = Ok(3);
+        let vars = env_vars(Path::new("/tmp/out"), &ok, Duration::from_millis(1500));
+        assert!(vars.contains(&("APPLE_NOTES_EXPORT_STATUS", "ok".to_string())));
+        assert!(vars.contains(&("APPLE_NOTES_EXPORT_EXPORTED", "3".to_string())));
+        assert!(vars.contains(&("APPLE_NOTES_EXPORT_DURATION_MS", "1500".to_string())));
+
+        let failed = Err(anyhow!("disk full"));
+        let vars = env_vars(Path::new("/tmp/out"), &failed, Duration::ZERO);
+        assert!(vars.contains(&("APPLE_NOTES_EXPORT_STATUS", "failed".to_string())));
+        assert!(vars.contains(&("APPLE_NOTES_EXPORT_FAILURES", "1".to_string())));
+        assert!(vars.contains(&("APPLE_NOTES_EXPORT_ERROR", "disk full".to_string())));
+    }
+}
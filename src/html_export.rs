@@ -0,0 +1,242 @@
+use crate::render;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use std::path::{Path, PathBuf};
+
+/// File name of the standalone page written by `export --format html`.
+pub const NOTE_HTML: &str = "note.html";
+const ATTACHMENTS_DIR: &str = "attachments";
+
+/// Where `export --format html` puts images referenced by a note.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum AttachmentMode {
+    /// Inline every image as a `data:` URI (one self-contained file per note).
+    #[default]
+    Inline,
+    /// Copy images into an `attachments/` directory next to the page and link to them.
+    Copy,
+}
+
+/// A standalone HTML page for one note.
+#[derive(Debug, Clone)]
+pub struct Page {
+    pub title: String,
+    pub body_html: String,
+    pub attachments: AttachmentMode,
+    /// Link back to the site index (`export --format site`), relative to the page.
+    pub index_href: Option<String>,
+}
+
+impl Page {
+    /// `note.html` and, in copy mode, its attachments, as paths relative to the note directory.
+    pub fn files(&self) -> Vec<(PathBuf, Vec<u8>)> {
+        let (body, attachments) = rewrite_images(&self.body_html, self.attachments);
+        let mut files: Vec<(PathBuf, Vec<u8>)> = attachments
+            .into_iter()
+            .map(|(name, data)| (Path::new(ATTACHMENTS_DIR).join(name), data))
+            .collect();
+        files.push((PathBuf::from(NOTE_HTML), self.document(&body).into_bytes()));
+        files
+    }
+
+    /// The page as one self-contained document, with every local image inlined.
+    pub fn to_inline_html(&self) -> String {
+        let (body, _) = rewrite_images(&self.body_html, AttachmentMode::Inline);
+        self.document(&body)
+    }
+
+    fn document(&self, body_html: &str) -> String {
+        let nav = match &self.index_href {
+            Some(href) => format!("<nav><a href=\"{href}\">← All notes</a></nav>\n"),
+            None => String::new(),
+        };
+        format!(
+            r#"<!doctype html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+body {{ font: 15px/1.5 -apple-system, BlinkMacSystemFont, "Helvetica Neue", sans-serif; max-width: 46em; margin: 2em auto; padding: 0 1em; }}
+img {{ max-width: 100%; height: auto; }}
+table {{ border-collapse: collapse; }}
+td, th {{ border: 1px solid #ccc; padding: 0.25em 0.5em; }}
+</style>
+</head>
+<body>
+{nav}{body_html}
+</body>
+</html>
+"#,
+            title = render::escape_html(&self.title),
+        )
+    }
+}
+
+/// Rewrites `<img src>` values so the page works without Notes.app.
+///
+/// Local files (`file://` URLs and absolute paths) are inlined or copied; `data:` URIs stay inline
+/// or get copied out; remote URLs are left alone. Returns the new HTML and files to write under
+/// `attachments/`.
+fn rewrite_images(html: &str, mode: AttachmentMode) -> (String, Vec<(String, Vec<u8>)>) {
+    let mut out = String::with_capacity(html.len());
+    let mut files = Vec::new();
+    let mut rest = html;
+    while let Some(start) = find_ascii_ci(rest, "<img") {
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start..=start + end];
+        out.push_str(&rest[..start]);
+        rest = &rest[start + end + 1..];
+
+        let Some((src_start, src_end)) = src_range(tag) else {
+            out.push_str(tag);
+            continue;
+        };
+        let src = &tag[src_start..src_end];
+        let replacement = load_image(src).and_then(|(mime, data)| match mode {
+            AttachmentMode::Inline if src.starts_with("data:") => None,
+            AttachmentMode::Inline => Some(format!("data:{mime};base64,{}", BASE64.encode(&data))),
+            AttachmentMode::Copy => {
+                let name = format!("{}.{}", files.len() + 1, extension_for(mime));
+                let link = format!("{ATTACHMENTS_DIR}/{name}");
+                files.push((name, data));
+                Some(link)
+            }
+        });
+        match replacement {
+            Some(new_src) => {
+                out.push_str(&tag[..src_start]);
+                out.push_str(&new_src);
+                out.push_str(&tag[src_end..]);
+            }
+            None => out.push_str(tag),
+        }
+    }
+    out.push_str(rest);
+    (out, files)
+}
+
+fn find_ascii_ci(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// Byte range of the (quoted) `src` attribute value within an `<img …>` tag.
+fn src_range(tag: &str) -> Option<(usize, usize)> {
+    let attr = find_ascii_ci(tag, " src=").or_else(|| find_ascii_ci(tag, "\nsrc="))?;
+    let value_start = attr + " src=".len();
+    let quote = tag[value_start..].chars().next()?;
+    if quote != '"' && quote != '\'' {
+        return None;
+    }
+    let start = value_start + 1;
+    let len = tag[start..].find(quote)?;
+    Some((start, start + len))
+}
+
+fn load_image(src: &str) -> Option<(&'static str, Vec<u8>)> {
+    if let Some(data_uri) = src.strip_prefix("data:") {
+        let (meta, payload) = data_uri.split_once(',')?;
+        let mime = meta.strip_suffix(";base64")?;
+        let data = BASE64.decode(payload.trim()).ok()?;
+        return Some((known_mime(mime), data));
+    }
+    let path = match src.strip_prefix("file://") {
+        Some(p) => PathBuf::from(percent_decode(p)),
+        None if src.starts_with('/') => PathBuf::from(src),
+        None => return None,
+    };
+    let data = std::fs::read(&path).ok()?;
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some((mime_for_extension(&ext), data))
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(hex) = s.get(i + 1..i + 3)
+            && let Ok(b) = u8::from_str_radix(hex, 16)
+        {
+            out.push(b);
+            i += 3;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+const MIME_TYPES: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("heic", "image/heic"),
+    ("tiff", "image/tiff"),
+    ("webp", "image/webp"),
+    ("svg", "image/svg+xml"),
+    ("pdf", "application/pdf"),
+];
+
+fn mime_for_extension(ext: &str) -> &'static str {
+    let ext = match ext {
+        "jpeg" => "jpg",
+        "tif" => "tiff",
+        other => other,
+    };
+    MIME_TYPES
+        .iter()
+        .find(|(e, _)| *e == ext)
+        .map_or("application/octet-stream", |(_, m)| m)
+}
+
+fn known_mime(mime: &str) -> &'static str {
+    MIME_TYPES
+        .iter()
+        .find(|(_, m)| m.eq_ignore_ascii_case(mime))
+        .map_or("application/octet-stream", |(_, m)| m)
+}
+
+fn extension_for(mime: &str) -> &'static str {
+    MIME_TYPES
+        .iter()
+        .find(|(_, m)| *m == mime)
+        .map_or("bin", |(e, _)| e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn rewrite_images_inlines_local_files_and_copies_data_uris() {
+        let dir = tempdir().unwrap();
+        let png = dir.path().join("pic one.png");
+        std::fs::write(&png, b"PNGDATA").unwrap();
+        let file_url = format!("file://{}", png.display()).replace(' ', "%20");
+
+        let html = format!(
+            r#"<div><IMG src="{file_url}" alt="x"></div><img src="https://example.com/a.png">"#
+        );
+        let (inlined, files) = rewrite_images(&html, AttachmentMode::Inline);
+        assert!(files.is_empty());
+        assert!(inlined.contains(&format!(
+            r#"src="data:image/png;base64,{}""#,
+            BASE64.encode(b"PNGDATA")
+        )));
+        assert!(inlined.contains(r#"<img src="https://example.com/a.png">"#));
+
+        let (copied, files) = rewrite_images(&inlined, AttachmentMode::Copy);
+        assert_eq!(files, vec![("1.png".to_string(), b"PNGDATA".to_vec())]);
+        assert!(copied.contains(r#"src="attachments/1.png""#));
+    }
+}
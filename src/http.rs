@@ -0,0 +1,519 @@
+//! `apple-notes serve --http`: a small local REST API over the [`NotesBackend`], for other apps
+//! on the machine. Responses are the same JSON as `--json` output (the model types).
+
+use crate::cli::split_folder_path;
+use crate::error::NotesError;
+use crate::transport::NotesBackend;
+use anyhow::anyhow;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::net::SocketAddr;
+
+/// A response: status code plus JSON body (`None` for 204s).
+#[derive(Debug, PartialEq)]
+struct Reply {
+    status: u16,
+    body: Option<Value>,
+}
+
+impl Reply {
+    fn json(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            body: Some(body),
+        }
+    }
+
+    fn no_content() -> Self {
+        Self {
+            status: 204,
+            body: None,
+        }
+    }
+
+    fn error(status: u16, kind: &str, message: impl Into<String>) -> Self {
+        Self::json(status, json!({ "error": message.into(), "kind": kind }))
+    }
+}
+
+impl From<NotesError> for Reply {
+    fn from(e: NotesError) -> Self {
+        let status = match e {
+            NotesError::NotFound(_) => 404,
+            NotesError::Ambiguous(_) | NotesError::AlreadyDeleted(_) => 409,
+            NotesError::PermissionDenied(_) => 403,
+            NotesError::BackendUnavailable(_) => 503,
+            NotesError::Cancelled(_) => 504,
+            NotesError::ScriptError(_) | NotesError::Other(_) => 500,
+        };
+        Reply::error(status, e.kind(), e.to_string())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateNote {
+    folder_path: Vec<String>,
+    title: String,
+    #[serde(default)]
+    body_html: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdateNote {
+    title: Option<String>,
+    body_html: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AppendNote {
+    body_html: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MoveNote {
+    folder_path: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateFolder {
+    parent_path: Vec<String>,
+    name: String,
+}
+
+/// The API's state: which backend and account requests go to, and who may call it.
+struct Api<'a> {
+    backend: &'a dyn NotesBackend,
+    account: String,
+    read_only: bool,
+    token: String,
+    /// Only accept `Host: localhost` / `127.0.0.1` / `[::1]` (with any port), so a web page
+    /// can't reach a loopback server through DNS rebinding.
+    loopback_host: bool,
+}
+
+/// The request headers the API looks at.
+#[derive(Debug, Default, Clone, Copy)]
+struct Headers<'a> {
+    authorization: Option<&'a str>,
+    host: Option<&'a str>,
+    origin: Option<&'a str>,
+    content_type: Option<&'a str>,
+}
+
+/// Whether `host` (a `Host` header) names this machine's loopback interface.
+fn is_loopback_host(host: &str) -> bool {
+    let name = match host.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().map(|ip| format!("[{ip}]")),
+        None => host.split(':').next().map(str::to_string),
+    };
+    matches!(
+        name.as_deref().map(str::to_ascii_lowercase).as_deref(),
+        Some("localhost" | "127.0.0.1" | "[::1]")
+    )
+}
+
+/// A random bearer token for servers started without `--token`.
+fn generate_token() -> anyhow::Result<String> {
+    use std::io::Read;
+    let mut bytes = [0u8; 16];
+    std::fs::File::open("/dev/urandom")
+        .and_then(|mut f| f.read_exact(&mut bytes))
+        .map_err(|e| anyhow!("generate a token: {e}"))?;
+    Ok(bytes.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+/// Compares tokens in time that depends only on their lengths, so response timing doesn't give
+/// away how much of a guess was right.
+fn tokens_match(given: &str, token: &str) -> bool {
+    let (given, token) = (given.as_bytes(), token.as_bytes());
+    given.len() == token.len() && given.iter().zip(token).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+fn parse_body<T: DeserializeOwned>(body: &str) -> Result<T, Reply> {
+    serde_json::from_str(body).map_err(|e| Reply::error(400, "bad_request", e.to_string()))
+}
+
+fn ok<T: serde::Serialize>(status: u16, value: crate::error::Result<T>) -> Reply {
+    match value {
+        Ok(value) => Reply::json(status, serde_json::to_value(value).unwrap_or(Value::Null)),
+        Err(e) => e.into(),
+    }
+}
+
+fn done(value: crate::error::Result<()>) -> Reply {
+    match value {
+        Ok(()) => Reply::no_content(),
+        Err(e) => e.into(),
+    }
+}
+
+/// Decodes `%XX` escapes (and `+` as a space, when `plus` is set).
+fn percent_decode(s: &str, plus: bool) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len()
+                && bytes[i + 1].is_ascii_hexdigit()
+                && bytes[i + 2].is_ascii_hexdigit() =>
+            {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).expect("ascii hex digits");
+                out.push(u8::from_str_radix(hex, 16).expect("ascii hex digits"));
+                i += 3;
+                continue;
+            }
+            b'+' if plus => out.push(b' '),
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+impl Api<'_> {
+    fn handle(&self, method: &str, url: &str, headers: &Headers<'_>, body: &str) -> Reply {
+        // Browsers send `Origin` on cross-origin requests; other local programs don't need it.
+        if headers.origin.is_some() {
+            return Reply::error(403, "forbidden", "requests from web pages are not allowed");
+        }
+        if self.loopback_host && !headers.host.is_some_and(is_loopback_host) {
+            return Reply::error(403, "forbidden", "Host must be localhost or 127.0.0.1");
+        }
+        if !headers
+            .authorization
+            .and_then(|a| a.strip_prefix("Bearer "))
+            .is_some_and(|t| tokens_match(t, &self.token))
+        {
+            return Reply::error(401, "unauthorized", "missing or wrong bearer token");
+        }
+        // A page can only send `text/plain` and form bodies without a preflight.
+        if matches!(method, "POST" | "PUT" | "PATCH")
+            && !headers
+                .content_type
+                .and_then(|c| c.split(';').next())
+                .is_some_and(|c| c.trim().eq_ignore_ascii_case("application/json"))
+        {
+            return Reply::error(
+                415,
+                "unsupported_media_type",
+                "send the body as Content-Type: application/json",
+            );
+        }
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let query: Vec<(String, String)> = query
+            .split('&')
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                let (k, v) = p.split_once('=').unwrap_or((p, ""));
+                (percent_decode(k, true), percent_decode(v, true))
+            })
+            .collect();
+        let param = |name: &str| {
+            query
+                .iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.as_str())
+        };
+        let account = param("account").unwrap_or(&self.account);
+        let segments: Vec<String> = path
+            .trim_matches('/')
+            .split('/')
+            .map(|s| percent_decode(s, false))
+            .collect();
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+        let mutation = !matches!(method, "GET" | "HEAD");
+        if mutation && self.read_only {
+            return Reply::error(
+                403,
+                "read_only",
+                "this server is read-only; notes can't be changed",
+            );
+        }
+        let backend = self.backend;
+        let result = match (method, segments.as_slice()) {
+            ("GET", ["accounts"]) => Ok(ok(200, backend.list_accounts())),
+            ("GET", ["folders"]) => Ok(ok(200, backend.list_folders(account))),
+            ("POST", ["folders"]) => parse_body::<CreateFolder>(body).map(|f| {
+                ok(
+                    201,
+                    backend
+                        .create_folder(account, &f.parent_path, &f.name)
+                        .map(|id| json!({ "id": id })),
+                )
+            }),
+            ("GET", ["notes"]) => match param("folder") {
+                Some(folder) => match split_folder_path(folder) {
+                    Ok(path) => Ok(ok(200, backend.list_notes_in_folder(account, &path))),
+                    Err(e) => Err(Reply::error(400, "bad_request", e.to_string())),
+                },
+                None => Ok(ok(200, backend.list_notes(account))),
+            },
+            ("POST", ["notes"]) => parse_body::<CreateNote>(body).map(|n| {
+                ok(
+                    201,
+                    backend
+                        .create_note_html(account, &n.folder_path, &n.title, &n.body_html)
+                        .map(|id| json!({ "id": id })),
+                )
+            }),
+            ("GET", ["notes", id]) => Ok(ok(200, backend.get_note(id))),
+            ("PATCH", ["notes", id]) => parse_body::<UpdateNote>(body).map(|u| {
+                done((|| {
+                    if let Some(title) = &u.title {
+                        backend.set_note_title(id, title)?;
+                    }
+                    if let Some(body_html) = &u.body_html {
+                        backend.set_note_body_html(id, body_html)?;
+                    }
+                    Ok(())
+                })())
+            }),
+            ("DELETE", ["notes", id]) => Ok(done(backend.trash_note(id))),
+            ("POST", ["notes", id, "append"]) => parse_body::<AppendNote>(body)
+                .map(|a| done(backend.append_note_body_html(id, &a.body_html))),
+            ("POST", ["notes", id, "move"]) => parse_body::<MoveNote>(body)
+                .map(|m| done(backend.move_note(id, account, &m.folder_path))),
+            _ => Err(Reply::error(
+                404,
+                "not_found",
+                format!("no route for {method} {path}"),
+            )),
+        };
+        result.unwrap_or_else(|reply| reply)
+    }
+}
+
+/// Serves the API on `addr` until the process is stopped. Requests are handled one at a time.
+/// Without `token`, a random one is generated and printed.
+pub fn serve(
+    backend: &dyn NotesBackend,
+    account: &str,
+    read_only: bool,
+    addr: SocketAddr,
+    token: Option<String>,
+) -> anyhow::Result<()> {
+    if !addr.ip().is_loopback() && token.is_none() {
+        return Err(anyhow!(
+            "refusing to serve on non-loopback address {addr} without --token"
+        ));
+    }
+    let token = match token {
+        Some(token) => token,
+        None => {
+            let token = generate_token()?;
+            eprintln!("Token: {token}");
+            token
+        }
+    };
+    let server = tiny_http::Server::http(addr).map_err(|e| anyhow!("listen on {addr}: {e}"))?;
+    if let Some(addr) = server.server_addr().to_ip() {
+        eprintln!("Listening on http://{addr}");
+    }
+    let api = Api {
+        backend,
+        account: account.to_string(),
+        read_only,
+        token,
+        loopback_host: addr.ip().is_loopback(),
+    };
+    for mut request in server.incoming_requests() {
+        let mut body = String::new();
+        let reply = match request.as_reader().read_to_string(&mut body) {
+            Ok(_) => {
+                let header = |name: &'static str| {
+                    request
+                        .headers()
+                        .iter()
+                        .find(|h| h.field.equiv(name))
+                        .map(|h| h.value.as_str())
+                };
+                let headers = Headers {
+                    authorization: header("Authorization"),
+                    host: header("Host"),
+                    origin: header("Origin"),
+                    content_type: header("Content-Type"),
+                };
+                api.handle(request.method().as_str(), request.url(), &headers, &body)
+            }
+            Err(e) => Reply::error(400, "bad_request", format!("read request body: {e}")),
+        };
+        let response = match reply.body {
+            Some(body) => tiny_http::Response::from_string(body.to_string())
+                .with_header(
+                    "Content-Type: application/json"
+                        .parse::<tiny_http::Header>()
+                        .expect("valid header"),
+                )
+                .with_status_code(reply.status),
+            None => tiny_http::Response::from_string(String::new()).with_status_code(204),
+        };
+        // A client that hung up doesn't stop the server.
+        let _ = request.respond(response);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::FixtureBackend;
+
+    fn backend() -> FixtureBackend {
+        FixtureBackend::from_path(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/basic.json"),
+        )
+        .unwrap()
+    }
+
+    fn api(backend: &FixtureBackend, read_only: bool) -> Api<'_> {
+        Api {
+            backend,
+            account: "iCloud".to_string(),
+            read_only,
+            token: "secret".to_string(),
+            loopback_host: true,
+        }
+    }
+
+    const AUTH: &Headers = &Headers {
+        authorization: Some("Bearer secret"),
+        host: Some("127.0.0.1:7878"),
+        origin: None,
+        content_type: Some("application/json"),
+    };
+
+    #[test]
+    fn routes_map_onto_backend_calls() {
+        let backend = backend();
+        let api = api(&backend, false);
+
+        let reply = api.handle("GET", "/notes?folder=Personal+%3E+Archive", AUTH, "");
+        let titles: Vec<&str> = reply
+            .body
+            .as_ref()
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|n| n["title"].as_str().unwrap())
+            .collect();
+        assert_eq!((reply.status, titles), (200, vec!["Beta", "Gamma"]));
+
+        let reply = api.handle("GET", "/notes/n4?account=On%20My%20Mac", AUTH, "");
+        assert_eq!(reply.body.unwrap()["title"], "Delta");
+
+        let reply = api.handle(
+            "POST",
+            "/notes",
+            AUTH,
+            r#"{"folder_path":["Personal"],"title":"Hi","body_html":"<p>x</p>"}"#,
+        );
+        assert_eq!(reply.status, 201);
+        assert!(reply.body.unwrap()["id"].is_string());
+        assert_eq!(
+            api.handle("PATCH", "/notes/n1", AUTH, r#"{"title":"New"}"#),
+            Reply::no_content()
+        );
+        assert_eq!(
+            api.handle("DELETE", "/notes/n1", AUTH, ""),
+            Reply::no_content()
+        );
+        // A second delete would purge the note; that's refused rather than done.
+        let reply = api.handle("DELETE", "/notes/n1", AUTH, "");
+        assert_eq!(reply.status, 409);
+        assert_eq!(reply.body.unwrap()["kind"], "already_deleted");
+        assert!(backend.get_note("n1").is_ok());
+    }
+
+    #[test]
+    fn errors_have_statuses_and_kinds() {
+        let backend = backend();
+        let read_only = api(&backend, true);
+        let status = |method, url, auth, body| read_only.handle(method, url, auth, body).status;
+        let no_auth = Headers {
+            authorization: None,
+            ..*AUTH
+        };
+        assert_eq!(status("GET", "/notes", &no_auth, ""), 401);
+        let wrong = Headers {
+            authorization: Some("Bearer nope"),
+            ..*AUTH
+        };
+        assert_eq!(status("GET", "/notes", &wrong, ""), 401);
+        assert_eq!(status("GET", "/notes/missing", AUTH, ""), 404);
+        assert_eq!(status("GET", "/nowhere", AUTH, ""), 404);
+        assert_eq!(status("DELETE", "/notes/n1", AUTH, ""), 403);
+
+        let writable = api(&backend, false);
+        let reply = writable.handle("POST", "/notes", AUTH, "{");
+        assert_eq!(reply.status, 400);
+        assert_eq!(reply.body.unwrap()["kind"], "bad_request");
+        assert_eq!(
+            percent_decode("x-coredata%3A%2F%2FU%2FICNote%2Fp1", false),
+            "x-coredata://U/ICNote/p1"
+        );
+        assert_eq!(percent_decode("100%", false), "100%");
+    }
+
+    #[test]
+    fn tokens_match_only_exactly() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secreT", "secret"));
+        assert!(!tokens_match("secret2", "secret"));
+        assert!(!tokens_match("", "secret"));
+    }
+
+    #[test]
+    fn browser_requests_are_rejected() {
+        let backend = backend();
+        let api = api(&backend, false);
+        let create = r#"{"folder_path":["Personal"],"title":"Hi"}"#;
+
+        let from_page = Headers {
+            origin: Some("https://evil.example"),
+            ..*AUTH
+        };
+        assert_eq!(api.handle("GET", "/notes", &from_page, "").status, 403);
+
+        // DNS rebinding: the page's own host name, resolved to 127.0.0.1.
+        let rebound = Headers {
+            host: Some("evil.example:7878"),
+            ..*AUTH
+        };
+        assert_eq!(api.handle("GET", "/notes", &rebound, "").status, 403);
+        let no_host = Headers {
+            host: None,
+            ..*AUTH
+        };
+        assert_eq!(api.handle("GET", "/notes", &no_host, "").status, 403);
+        for host in ["localhost", "LOCALHOST:80", "[::1]:7878"] {
+            let ok = Headers {
+                host: Some(host),
+                ..*AUTH
+            };
+            assert_eq!(api.handle("GET", "/notes", &ok, "").status, 200, "{host}");
+        }
+
+        let plain = Headers {
+            content_type: Some("text/plain"),
+            ..*AUTH
+        };
+        assert_eq!(api.handle("POST", "/notes", &plain, create).status, 415);
+        let untyped = Headers {
+            content_type: None,
+            ..*AUTH
+        };
+        assert_eq!(api.handle("POST", "/notes", &untyped, create).status, 415);
+        let charset = Headers {
+            content_type: Some("application/json; charset=utf-8"),
+            ..*AUTH
+        };
+        assert_eq!(api.handle("POST", "/notes", &charset, create).status, 201);
+        // Reads and deletes carry no body.
+        assert_eq!(api.handle("GET", "/notes", &untyped, "").status, 200);
+        assert_eq!(api.handle("DELETE", "/notes/n1", &untyped, "").status, 204);
+    }
+}
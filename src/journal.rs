@@ -0,0 +1,202 @@
+use crate::checksums;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use time::OffsetDateTime;
+
+/// What happened to a note since the previous journaled export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Added,
+    Updated,
+    Removed,
+}
+
+/// One line of an `export --journal` file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// When the export that noticed the change ran.
+    #[serde(with = "time::serde::rfc3339")]
+    pub at: OffsetDateTime,
+    pub action: Action,
+    pub id: String,
+    pub title: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub modified_at: OffsetDateTime,
+    /// SHA-256 of the note's `contents.md` (the last known one, for removed notes).
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct NoteState {
+    title: String,
+    modified_at: OffsetDateTime,
+    hash: String,
+}
+
+/// Collects every note an export writes, from any number of export workers.
+#[derive(Debug, Default)]
+pub struct Recorder {
+    notes: Mutex<HashMap<String, NoteState>>,
+}
+
+impl Recorder {
+    pub fn record(&self, id: &str, title: &str, modified_at: OffsetDateTime, contents_md: &str) {
+        let state = NoteState {
+            title: title.to_string(),
+            modified_at,
+            hash: checksums::sha256_bytes(contents_md.as_bytes()),
+        };
+        self.notes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id.to_string(), state);
+    }
+
+    /// Appends an entry to the journal at `path` for every note added, updated or removed since
+    /// the state the journal already describes. Returns the number of entries appended.
+    ///
+    /// The recorded notes are then forgotten, so the same recorder can follow the next export.
+    pub fn append_changes(&self, path: &Path, at: OffsetDateTime) -> anyhow::Result<usize> {
+        let before = replay(path)?;
+        let after = std::mem::take(&mut *self.notes.lock().unwrap_or_else(|e| e.into_inner()));
+        let entries = changes(&before, &after, at);
+        if entries.is_empty() {
+            return Ok(0);
+        }
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).with_context(|| format!("create {parent:?}"))?;
+        }
+        let mut lines = String::new();
+        for entry in &entries {
+            lines.push_str(&serde_json::to_string(entry)?);
+            lines.push('\n');
+        }
+        // One write, so an interrupted run can't leave half a batch behind.
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut f| f.write_all(lines.as_bytes()))
+            .with_context(|| format!("append to {path:?}"))?;
+        Ok(entries.len())
+    }
+}
+
+/// The account as of the last entry for each note in the journal; empty if there's no journal yet.
+fn replay(path: &Path) -> anyhow::Result<HashMap<String, NoteState>> {
+    let file = match std::fs::File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(e).with_context(|| format!("open {path:?}")),
+    };
+    let mut notes = HashMap::new();
+    for (idx, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.with_context(|| format!("read {path:?}"))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: Entry = serde_json::from_str(&line)
+            .with_context(|| format!("invalid journal entry on line {} of {path:?}", idx + 1))?;
+        match entry.action {
+            Action::Removed => {
+                notes.remove(&entry.id);
+            }
+            Action::Added | Action::Updated => {
+                notes.insert(
+                    entry.id,
+                    NoteState {
+                        title: entry.title,
+                        modified_at: entry.modified_at,
+                        hash: entry.hash,
+                    },
+                );
+            }
+        }
+    }
+    Ok(notes)
+}
+
+/// Entries turning `before` into `after`, ordered by title.
+fn changes(
+    before: &HashMap<String, NoteState>,
+    after: &HashMap<String, NoteState>,
+    at: OffsetDateTime,
+) -> Vec<Entry> {
+    let entry = |action, id: &str, state: &NoteState| Entry {
+        at,
+        action,
+        id: id.to_string(),
+        title: state.title.clone(),
+        modified_at: state.modified_at,
+        hash: state.hash.clone(),
+    };
+    let mut entries = Vec::new();
+    for (id, new) in after {
+        match before.get(id) {
+            None => entries.push(entry(Action::Added, id, new)),
+            Some(old) if old != new => entries.push(entry(Action::Updated, id, new)),
+            Some(_) => {}
+        }
+    }
+    for (id, old) in before {
+        if !after.contains_key(id) {
+            entries.push(entry(Action::Removed, id, old));
+        }
+    }
+    entries.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.id.cmp(&b.id)));
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn journal_records_only_changes_since_the_last_run() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("journal.ndjson");
+        let t0 = OffsetDateTime::from_unix_timestamp(1_766_188_800).unwrap();
+        let t1 = OffsetDateTime::from_unix_timestamp(1_766_275_200).unwrap();
+
+        let first = Recorder::default();
+        first.record("n1", "Alpha", t0, "a");
+        first.record("n2", "Beta", t0, "b");
+        assert_eq!(first.append_changes(&path, t0).unwrap(), 2);
+        // Nothing changed: nothing appended.
+        first.record("n1", "Alpha", t0, "a");
+        first.record("n2", "Beta", t0, "b");
+        assert_eq!(first.append_changes(&path, t1).unwrap(), 0);
+
+        let second = Recorder::default();
+        second.record("n1", "Alpha", t1, "a, edited");
+        second.record("n3", "Gamma", t1, "c");
+        assert_eq!(second.append_changes(&path, t1).unwrap(), 3);
+
+        let entries: Vec<Entry> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        let summary: Vec<(Action, &str)> =
+            entries.iter().map(|e| (e.action, e.id.as_str())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Action::Added, "n1"),
+                (Action::Added, "n2"),
+                (Action::Updated, "n1"),
+                (Action::Removed, "n2"),
+                (Action::Added, "n3"),
+            ]
+        );
+        assert_eq!(entries[3].hash, checksums::sha256_bytes(b"b"));
+        assert_eq!(entries[4].at, t1);
+    }
+}
@@ -1,19 +1,220 @@
+//! Apple Notes access from Rust, plus the `apple-notes` command line built on it.
+//!
+//! The [`backend`] module is the library: the [`backend::NotesBackend`] trait, the model types it
+//! returns, and the `osascript`, database and fixture implementations. Building with
+//! `default-features = false` leaves out the `cli` feature (and clap, comfy-table, indicatif and
+//! the other dependencies only the binary needs).
+
+pub mod backend;
+
+#[cfg(feature = "cli")]
+mod archive;
+#[cfg(feature = "async")]
+mod async_backend;
+#[cfg(feature = "cli")]
 mod backup;
+mod cancel;
+#[cfg(feature = "cli")]
+mod checksums;
+#[cfg(feature = "cli")]
 mod cli;
+#[cfg(feature = "cli")]
+mod clipboard;
+#[cfg(feature = "cli")]
+mod config;
 mod db;
+#[cfg(feature = "cli")]
+mod dedupe;
+#[cfg(feature = "cli")]
+mod diff;
+#[cfg(feature = "cli")]
+mod dry_run;
+#[cfg(feature = "cli")]
+mod epub;
+mod error;
 mod fixture;
+#[cfg(feature = "cli")]
+mod grep;
+#[cfg(feature = "cli")]
+mod hooks;
+#[cfg(feature = "cli")]
+mod html_export;
+#[cfg(feature = "cli")]
+mod http;
+#[cfg(feature = "cli")]
+mod journal;
+#[cfg(feature = "cli")]
+mod links;
+#[cfg(feature = "cli")]
+mod lint;
+#[cfg(feature = "cli")]
+mod logging;
+#[cfg(feature = "cli")]
+mod manifest;
 mod model;
+#[cfg(feature = "cli")]
+mod notestore;
+#[cfg(feature = "cli")]
+mod notetable;
+#[cfg(feature = "cli")]
+mod pager;
+#[cfg(feature = "cli")]
+mod pdf;
+#[cfg(feature = "cli")]
 mod progress;
+#[cfg(feature = "cli")]
+mod rename;
+#[cfg(feature = "cli")]
 mod render;
+#[cfg(feature = "cli")]
+mod replace;
+#[cfg(feature = "cli")]
+mod resolve;
+#[cfg(feature = "cli")]
+mod rpc;
+#[cfg(feature = "cli")]
+mod schedule;
+#[cfg(feature = "cli")]
+mod single_file;
+#[cfg(feature = "cli")]
+mod site;
+#[cfg(feature = "cli")]
+mod snapshot;
+#[cfg(feature = "cli")]
 mod tables;
+#[cfg(feature = "cli")]
+mod templates;
+#[cfg(feature = "cli")]
+mod timing;
+#[cfg(feature = "cli")]
+mod todo;
 mod transport;
+#[cfg(feature = "cli")]
+mod tui;
+#[cfg(feature = "cli")]
+mod verify;
+#[cfg(feature = "cli")]
+mod watch;
 
-use anyhow::Context;
+#[cfg(feature = "cli")]
+use anyhow::{Context, anyhow};
+#[cfg(feature = "cli")]
 use clap::Parser;
 
+/// Runs the `apple-notes` command line with the process arguments.
+#[cfg(feature = "cli")]
 pub fn run() -> anyhow::Result<()> {
-    let args = cli::Args::parse();
-    let backend = transport::make_backend(args.fixture.clone(), args.backend)?;
+    // clap exits with 2 on bad usage, which `exit_code` gives to "not found"; use EX_USAGE.
+    let parsed = cli::Args::try_parse().and_then(|args| args.check_usage().map(|()| args));
+    let mut args = parsed.unwrap_or_else(|e| match e.use_stderr() {
+        true => {
+            let _ = e.print();
+            std::process::exit(64)
+        }
+        false => e.exit(),
+    });
+    JSON_ERRORS.store(args.json, std::sync::atomic::Ordering::Relaxed);
+    logging::init(args.verbose, args.log_format, args.timing);
+    let config = config::load(args.config.as_deref())?;
+    args.apply_config(&config);
+    progress::configure(args.quiet, &config.progress)?;
+    tables::configure_color(config.color);
+    if args.dry_run {
+        args.cmd.preview_only();
+    } else if (args.read_only || config.read_only)
+        && let Some(name) = args.cmd.mutation()
+    {
+        return Err(anyhow!(
+            "`{name}` modifies notes; refusing to run in read-only mode"
+        ));
+    }
+    if let Some(path) = args.db_path.clone() {
+        db::use_db_path(path);
+        if matches!(args.backend, None | Some(transport::Backend::Auto)) {
+            args.backend = Some(transport::Backend::Db);
+        }
+    }
+    let backend_mode = args.backend.unwrap_or(transport::Backend::Auto);
+    let (mut backend, reads_db) = transport::open_backend(args.fixture.clone(), backend_mode)?;
+    if args.dry_run {
+        backend = Box::new(dry_run::DryRunBackend::new(backend));
+    }
+    if args.timing || args.verbose > 0 {
+        backend = Box::new(timing::TimedBackend::new(backend));
+    }
+
+    let timing = args.timing;
+    let result =
+        tracing::info_span!("command").in_scope(|| cli::dispatch(args, config, backend, reads_db));
+    if timing {
+        eprintln!("{}", timing::summary());
+    }
+    result.context("command failed")
+}
+
+/// Whether [`report_error`] prints JSON (`--json`).
+#[cfg(feature = "cli")]
+static JSON_ERRORS: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// Why a command failed, from the first error in the chain that says: its kind (as in
+/// [`backend::NotesError::kind`], plus `partial_failure`) and exit status.
+#[cfg(feature = "cli")]
+fn failure_cause(err: &anyhow::Error) -> (&'static str, i32) {
+    for cause in err.chain() {
+        if cause.is::<cli::PartialFailure>() {
+            return ("partial_failure", 6);
+        }
+        if let Some(e) = cause.downcast_ref::<error::NotesError>() {
+            return (e.kind(), e.exit_code());
+        }
+        if let Some(c) = cause.downcast_ref::<cancel::Cancelled>() {
+            let e = error::NotesError::Cancelled(*c);
+            return (e.kind(), e.exit_code());
+        }
+        match cause.downcast_ref::<db::DbUnavailable>() {
+            Some(db::DbUnavailable::PermissionDenied(_)) => return ("permission_denied", 4),
+            Some(_) => return ("backend_unavailable", 5),
+            None => {}
+        }
+    }
+    ("other", 1)
+}
+
+/// The exit status for an error [`run`] returned: the first error in its chain that says why the
+/// command failed picks it (see [`backend::NotesError::exit_code`]; 6 is a partial failure),
+/// otherwise 1. Bad command lines exit with 64 before [`run`] returns.
+#[cfg(feature = "cli")]
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    failure_cause(err).1
+}
 
-    cli::dispatch(args, backend).context("command failed")
+/// Prints an error [`run`] returned to stderr: the message chain, or with `--json` one line of
+/// `{"error": {"kind", "message", "hint", "exit_code"}}`.
+#[cfg(feature = "cli")]
+pub fn report_error(err: &anyhow::Error) {
+    if !JSON_ERRORS.load(std::sync::atomic::Ordering::Relaxed) {
+        eprintln!("{err:#}");
+        return;
+    }
+    let (kind, exit_code) = failure_cause(err);
+    let hint = match (kind, exit_code) {
+        ("not_found", _) => Some("`apple-notes folders list` and `notes list` show what exists."),
+        ("ambiguous", _) => Some("Pass more of the id, or the full id."),
+        ("permission_denied", _) => Some(
+            "Allow your terminal to control Notes in System Settings > Privacy & Security > \
+             Automation; the database backend also needs Full Disk Access.",
+        ),
+        ("backend_unavailable", _) => Some("Try `--backend osascript`."),
+        ("cancelled", 124) => Some("Raise `--timeout`."),
+        _ => None,
+    };
+    let error = serde_json::json!({
+        "error": {
+            "kind": kind,
+            "message": format!("{err:#}"),
+            "hint": hint,
+            "exit_code": exit_code,
+        }
+    });
+    eprintln!("{error}");
 }
@@ -0,0 +1,157 @@
+//! Links between notes: finding `applenotes:note/<uuid>` and `notes://showNote?identifier=<uuid>`
+//! links in note bodies, and the graph they form (`notes graph`, `notes backlinks`).
+
+use crate::model::NoteSummary;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::LazyLock;
+
+static NOTE_LINK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?:applenotes:note/|notes://showNote\?identifier=)([0-9A-Fa-f]{8}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{12})",
+    )
+    .expect("valid regex")
+});
+
+/// The UUIDs (uppercase) of the notes `markdown` links to, in order of appearance.
+pub fn note_links(markdown: &str) -> Vec<String> {
+    NOTE_LINK
+        .captures_iter(markdown)
+        .map(|c| c[1].to_ascii_uppercase())
+        .collect()
+}
+
+/// A note in the link graph, with the ids of the notes it links to.
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphNode {
+    pub id: String,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    pub links: Vec<String>,
+}
+
+/// The links between `notes`, whose bodies (Markdown) are in `bodies` by id. Links to notes
+/// outside `notes` and links from a note to itself are left out. Nodes are in `notes` order;
+/// each note's links are sorted and listed once.
+pub fn link_graph(notes: &[NoteSummary], bodies: &HashMap<String, String>) -> Vec<GraphNode> {
+    let by_uuid: HashMap<String, &str> = notes
+        .iter()
+        .filter_map(|n| Some((n.uuid.as_ref()?.to_ascii_uppercase(), n.id.as_str())))
+        .collect();
+    notes
+        .iter()
+        .map(|n| {
+            let links: BTreeSet<String> = bodies
+                .get(&n.id)
+                .map(|body| note_links(body))
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|uuid| by_uuid.get(uuid))
+                .filter(|id| **id != n.id)
+                .map(|id| id.to_string())
+                .collect();
+            GraphNode {
+                id: n.id.clone(),
+                title: n.title.clone(),
+                uuid: n.uuid.clone(),
+                links: links.into_iter().collect(),
+            }
+        })
+        .collect()
+}
+
+/// The nodes that link somewhere or are linked to.
+pub fn connected(nodes: Vec<GraphNode>) -> Vec<GraphNode> {
+    let linked: BTreeSet<String> = nodes.iter().flat_map(|n| n.links.clone()).collect();
+    nodes
+        .into_iter()
+        .filter(|n| !n.links.is_empty() || linked.contains(&n.id))
+        .collect()
+}
+
+/// The graph in Graphviz DOT, with notes labelled by title.
+pub fn to_dot(nodes: &[GraphNode]) -> String {
+    fn quote(s: &str) -> String {
+        format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+    let mut out = String::from("digraph notes {\n");
+    for n in nodes {
+        out.push_str(&format!(
+            "  {} [label={}];\n",
+            quote(&n.id),
+            quote(&n.title)
+        ));
+    }
+    for n in nodes {
+        for target in &n.links {
+            out.push_str(&format!("  {} -> {};\n", quote(&n.id), quote(target)));
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(id: &str, uuid: &str, title: &str) -> NoteSummary {
+        NoteSummary {
+            id: id.to_string(),
+            title: title.to_string(),
+            folder_id: "f".to_string(),
+            uuid: Some(uuid.to_string()),
+            created_at: None,
+            modified_at: None,
+            pinned: false,
+            locked: false,
+        }
+    }
+
+    const A: &str = "AAAAAAAA-0000-0000-0000-000000000001";
+    const B: &str = "BBBBBBBB-0000-0000-0000-000000000002";
+
+    #[test]
+    fn links_are_found_in_both_url_forms() {
+        let markdown = "[a](applenotes:note/3f2504e0-4f89-11d3-9a0c-0305e82c3301) \
+             [b](notes://showNote?identifier=11111111-2222-3333-4444-555555555555)";
+        assert_eq!(
+            note_links(markdown),
+            [
+                "3F2504E0-4F89-11D3-9A0C-0305E82C3301",
+                "11111111-2222-3333-4444-555555555555"
+            ]
+        );
+    }
+
+    #[test]
+    fn graphs_keep_links_between_known_notes() {
+        let notes = [
+            note("a", A, "Alpha \"one\""),
+            note("b", B, "Beta"),
+            note("c", "CCCCCCCC-0000-0000-0000-000000000003", "Lonely"),
+        ];
+        let bodies = HashMap::from([
+            (
+                "a".to_string(),
+                format!(
+                    "[b](applenotes:note/{B}) [b again](notes://showNote?identifier={B}) \
+                     [self](applenotes:note/{A}) [gone](applenotes:note/DDDDDDDD-0000-0000-0000-000000000004)"
+                ),
+            ),
+            ("b".to_string(), "no links".to_string()),
+        ]);
+        let graph = connected(link_graph(&notes, &bodies));
+        let edges: Vec<(&str, &[String])> = graph
+            .iter()
+            .map(|n| (n.id.as_str(), n.links.as_slice()))
+            .collect();
+        assert_eq!(edges, [("a", &["b".to_string()][..]), ("b", &[][..])]);
+        assert_eq!(
+            to_dot(&graph),
+            "digraph notes {\n  \"a\" [label=\"Alpha \\\"one\\\"\"];\n  \"b\" [label=\"Beta\"];\n  \"a\" -> \"b\";\n}\n"
+        );
+    }
+}
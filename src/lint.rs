@@ -0,0 +1,158 @@
+//! Checks for `notes lint`: empty notes, notes outside any known folder, titles repeated as the
+//! first body line, and links to notes that don't exist.
+
+use crate::links::note_links;
+use crate::model::NoteSummary;
+use crate::render;
+use serde::Serialize;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintIssue {
+    /// Nothing in the body but the title.
+    Empty,
+    /// The note's folder isn't one of the account's folders.
+    MissingFolder,
+    /// The first line after the title repeats the title.
+    DuplicateTitle,
+    /// A link to a note UUID that no note has.
+    BrokenLink,
+}
+
+impl LintIssue {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LintIssue::Empty => "empty",
+            LintIssue::MissingFolder => "missing folder",
+            LintIssue::DuplicateTitle => "duplicate title",
+            LintIssue::BrokenLink => "broken link",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LintFinding {
+    pub id: String,
+    pub title: String,
+    pub issue: LintIssue,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Everything wrong with one note, apart from broken links (which need every note's UUID; see
+/// [`broken_links`]). `markdown` is the note's body (`None` if it couldn't be read, which skips
+/// the body checks), `folder_ids` the account's folders.
+pub fn lint_note(
+    note: &NoteSummary,
+    markdown: Option<&str>,
+    folder_ids: &HashSet<String>,
+) -> Vec<LintFinding> {
+    let finding = |issue, detail: Option<String>| LintFinding {
+        id: note.id.clone(),
+        title: note.title.clone(),
+        issue,
+        detail,
+    };
+    let mut findings = Vec::new();
+    if !folder_ids.contains(&note.folder_id) {
+        findings.push(finding(
+            LintIssue::MissingFolder,
+            Some(note.folder_id.clone()),
+        ));
+    }
+    let Some(markdown) = markdown else {
+        return findings;
+    };
+    let title = note.title.trim().to_lowercase();
+    let mut lines = markdown
+        .lines()
+        .map(|l| render::markdown_to_text(&l.replace('\u{fffc}', "")).to_lowercase())
+        .filter(|l| !l.trim().is_empty())
+        .peekable();
+    // Notes stores the title as the body's first line.
+    if lines.peek().is_some_and(|first| first.trim() == title) {
+        lines.next();
+    }
+    match lines.next() {
+        None => findings.push(finding(LintIssue::Empty, None)),
+        Some(line) if line.trim() == title => {
+            findings.push(finding(LintIssue::DuplicateTitle, None))
+        }
+        Some(_) => {}
+    }
+    findings
+}
+
+/// Findings for the links in `markdown` to UUIDs missing from `known_uuids` (uppercase).
+pub fn broken_links(
+    note: &NoteSummary,
+    markdown: &str,
+    known_uuids: &HashSet<String>,
+) -> Vec<LintFinding> {
+    note_links(markdown)
+        .into_iter()
+        .filter(|uuid| !known_uuids.contains(uuid))
+        .map(|uuid| LintFinding {
+            id: note.id.clone(),
+            title: note.title.clone(),
+            issue: LintIssue::BrokenLink,
+            detail: Some(uuid),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(title: &str, folder_id: &str) -> NoteSummary {
+        NoteSummary {
+            id: "n".to_string(),
+            title: title.to_string(),
+            folder_id: folder_id.to_string(),
+            uuid: None,
+            created_at: None,
+            modified_at: None,
+            pinned: false,
+            locked: false,
+        }
+    }
+
+    fn issues(note: &NoteSummary, markdown: &str) -> Vec<LintIssue> {
+        let folders = HashSet::from(["f".to_string()]);
+        lint_note(note, Some(markdown), &folders)
+            .into_iter()
+            .map(|f| f.issue)
+            .collect()
+    }
+
+    #[test]
+    fn notes_are_checked_for_body_and_folder_problems() {
+        let plan = note("Plan", "f");
+        assert_eq!(issues(&plan, "# Plan\n\nStep one"), []);
+        assert_eq!(issues(&plan, "# Plan\n\n\u{fffc}\n"), [LintIssue::Empty]);
+        assert_eq!(issues(&plan, ""), [LintIssue::Empty]);
+        assert_eq!(
+            issues(&plan, "# Plan\n**Plan**\nStep one"),
+            [LintIssue::DuplicateTitle]
+        );
+        assert_eq!(
+            issues(&note("Plan", "gone"), "Plan\nStep one"),
+            [LintIssue::MissingFolder]
+        );
+    }
+
+    #[test]
+    fn links_to_unknown_uuids_are_broken() {
+        let markdown = "[a](applenotes:note/3f2504e0-4f89-11d3-9a0c-0305e82c3301) \
+             [b](notes://showNote?identifier=11111111-2222-3333-4444-555555555555)";
+        let known = HashSet::from(["3F2504E0-4F89-11D3-9A0C-0305E82C3301".to_string()]);
+        let broken = broken_links(&note("Plan", "f"), markdown, &known);
+        assert_eq!(broken.len(), 1);
+        assert_eq!(
+            broken[0].detail.as_deref(),
+            Some("11111111-2222-3333-4444-555555555555")
+        );
+    }
+}
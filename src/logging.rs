@@ -0,0 +1,48 @@
+//! `-v` / `--log-format`: `tracing` output on stderr, and the `--timing` span totals.
+
+use crate::timing::TimingLayer;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::Layer;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// How log lines are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum LogFormat {
+    /// One human-readable line per event.
+    #[default]
+    Text,
+    /// One JSON object per event.
+    Json,
+}
+
+/// Installs the stderr logger for `-v` (backend calls and their timing) or `-vv` (also the
+/// `osascript` scripts and SQL statements they run), and the `--timing` totals. Does nothing
+/// without either.
+pub fn init(verbose: u8, format: LogFormat, timing: bool) {
+    let level = match verbose {
+        0 => None,
+        1 => Some(LevelFilter::INFO),
+        2 => Some(LevelFilter::DEBUG),
+        _ => Some(LevelFilter::TRACE),
+    };
+    if level.is_none() && !timing {
+        return;
+    }
+    let log = level.map(|level| {
+        let layer = tracing_subscriber::fmt::layer()
+            .with_writer(std::io::stderr)
+            .with_ansi(false)
+            .with_target(false)
+            .with_span_events(FmtSpan::CLOSE);
+        match format {
+            LogFormat::Text => layer.with_filter(level).boxed(),
+            LogFormat::Json => layer.json().with_filter(level).boxed(),
+        }
+    });
+    let _ = tracing_subscriber::registry()
+        .with(log)
+        .with(timing.then_some(TimingLayer))
+        .try_init();
+}
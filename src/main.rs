@@ -6,7 +6,7 @@ fn main() {
     }
 
     if let Err(err) = apple_notes_cli::run() {
-        eprintln!("{err:#}");
-        std::process::exit(1);
+        apple_notes_cli::report_error(&err);
+        std::process::exit(apple_notes_cli::exit_code(&err));
     }
 }
@@ -0,0 +1,261 @@
+use crate::checksums;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use time::OffsetDateTime;
+
+/// Note listing written at the root of every directory export (and archive).
+pub const MANIFEST_JSON: &str = "manifest.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub account: String,
+    /// Set when the export stopped early (Ctrl-C, an `osascript` error, …); `notes` then lists
+    /// only what it got through, so a re-run can pick up from there.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+    pub notes: Vec<ManifestNote>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestNote {
+    pub id: String,
+    /// Note directory, relative to the manifest, with `/` separators.
+    pub path: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub modified_at: OffsetDateTime,
+    /// SHA-256 of the note's `contents.md`.
+    pub sha256: String,
+}
+
+/// Collects the notes an export writes, from any number of export workers.
+#[derive(Debug)]
+pub struct Collector {
+    root: PathBuf,
+    notes: Mutex<Vec<ManifestNote>>,
+}
+
+impl Collector {
+    /// Collects notes for the export rooted at `root`.
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            notes: Mutex::default(),
+        }
+    }
+
+    /// Records a note written to `note_dir`, somewhere under the export root.
+    pub fn record(
+        &self,
+        id: &str,
+        note_dir: &Path,
+        modified_at: OffsetDateTime,
+        contents_md: &str,
+    ) {
+        self.push(ManifestNote {
+            id: id.to_string(),
+            path: relative_path(&self.root, note_dir),
+            modified_at,
+            sha256: checksums::sha256_bytes(contents_md.as_bytes()),
+        });
+    }
+
+    /// Records a note an interrupted export already wrote (see [`Resume`]).
+    pub fn push(&self, note: ManifestNote) {
+        self.notes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(note);
+    }
+
+    /// The manifest for everything recorded so far, sorted by path.
+    pub fn manifest(&self, account: &str) -> Manifest {
+        let mut notes = self.notes.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        notes.sort_by(|a, b| a.path.cmp(&b.path).then_with(|| a.id.cmp(&b.id)));
+        Manifest {
+            account: account.to_string(),
+            partial: false,
+            notes,
+        }
+    }
+}
+
+/// A note a previous export already wrote, found by [`Resume::written`].
+#[derive(Debug, Clone)]
+pub struct Written {
+    pub note: ManifestNote,
+    pub contents_md: String,
+}
+
+/// The notes a previous export into the same directory wrote, read from its `manifest.json`:
+/// what an interrupted export got through or, for incremental exports (`export --watch`), what
+/// the last finished one wrote. Empty when there's nothing to pick up.
+#[derive(Debug, Default)]
+pub struct Resume {
+    root: PathBuf,
+    notes: HashMap<String, ManifestNote>,
+    /// Whether `notes` come from an interrupted export.
+    partial: bool,
+    reused: AtomicUsize,
+}
+
+impl Resume {
+    /// Reads the manifest at `root`, if it's for `account` and partial (or `incremental`). A
+    /// missing or unreadable manifest just means starting over.
+    pub fn load(root: &Path, account: &str, incremental: bool) -> Self {
+        let manifest = std::fs::read_to_string(root.join(MANIFEST_JSON))
+            .ok()
+            .and_then(|s| serde_json::from_str::<Manifest>(&s).ok())
+            .filter(|m| (m.partial || incremental) && m.account == account);
+        let partial = manifest.as_ref().is_some_and(|m| m.partial);
+        let notes = manifest
+            .map(|m| m.notes.into_iter().map(|n| (n.id.clone(), n)).collect())
+            .unwrap_or_default();
+        Self {
+            root: root.to_path_buf(),
+            notes,
+            partial,
+            reused: AtomicUsize::new(0),
+        }
+    }
+
+    /// The note `id`, if the previous export wrote it to `note_dir` and its `contents.md` is
+    /// still intact. With `modified_at` (when it's cheap to know up front), the note must also
+    /// be unchanged since; without it, only an interrupted export's notes are trusted.
+    pub fn written(
+        &self,
+        id: &str,
+        note_dir: &Path,
+        modified_at: Option<OffsetDateTime>,
+    ) -> Option<Written> {
+        let note = self.notes.get(id)?;
+        let unchanged = match modified_at {
+            Some(t) => t == note.modified_at,
+            None => self.partial,
+        };
+        if !unchanged || note.path != relative_path(&self.root, note_dir) {
+            return None;
+        }
+        let contents_md = std::fs::read_to_string(note_dir.join("contents.md")).ok()?;
+        if checksums::sha256_bytes(contents_md.as_bytes()) != note.sha256 {
+            return None;
+        }
+        self.reused.fetch_add(1, Ordering::Relaxed);
+        Some(Written {
+            note: note.clone(),
+            contents_md,
+        })
+    }
+
+    /// How many notes [`Resume::written`] has handed back.
+    pub fn reused(&self) -> usize {
+        self.reused.load(Ordering::Relaxed)
+    }
+
+    /// Note directories the previous export wrote that `current` no longer has (deleted or
+    /// moved notes).
+    pub fn removed(&self, current: &Manifest) -> Vec<PathBuf> {
+        let kept: HashSet<(&str, &str)> = current
+            .notes
+            .iter()
+            .map(|n| (n.id.as_str(), n.path.as_str()))
+            .collect();
+        let mut removed: Vec<PathBuf> = self
+            .notes
+            .values()
+            .filter(|n| !kept.contains(&(n.id.as_str(), n.path.as_str())))
+            .map(|n| self.root.join(&n.path))
+            .collect();
+        removed.sort();
+        removed
+    }
+}
+
+/// `note_dir` relative to `root`, with `/` separators.
+fn relative_path(root: &Path, note_dir: &Path) -> String {
+    note_dir
+        .strip_prefix(root)
+        .unwrap_or(note_dir)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_lists_recorded_notes_by_path() {
+        let collector = Collector::new(Path::new("/backup"));
+        let t = OffsetDateTime::UNIX_EPOCH;
+        collector.record("n2", Path::new("/backup/Personal/Archive/Beta-n2"), t, "b");
+        collector.record("n1", Path::new("/backup/Personal/Alpha-n1"), t, "a");
+
+        let manifest = collector.manifest("iCloud");
+        let paths: Vec<&str> = manifest.notes.iter().map(|n| n.path.as_str()).collect();
+        assert_eq!(paths, ["Personal/Alpha-n1", "Personal/Archive/Beta-n2"]);
+        assert_eq!(manifest.notes[0].sha256, checksums::sha256_bytes(b"a"));
+    }
+
+    #[test]
+    fn resume_reuses_intact_notes_from_a_partial_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let t = OffsetDateTime::UNIX_EPOCH;
+        let alpha = root.join("Personal/Alpha-n1");
+        let beta = root.join("Personal/Beta-n2");
+        for (note_dir, md) in [(&alpha, "a"), (&beta, "b")] {
+            std::fs::create_dir_all(note_dir).unwrap();
+            std::fs::write(note_dir.join("contents.md"), md).unwrap();
+        }
+        let collector = Collector::new(root);
+        collector.record("n1", &alpha, t, "a");
+        collector.record("n2", &beta, t, "b");
+        let mut manifest = collector.manifest("iCloud");
+        manifest.partial = true;
+        std::fs::write(
+            root.join(MANIFEST_JSON),
+            serde_json::to_string(&manifest).unwrap(),
+        )
+        .unwrap();
+        // Truncated after the manifest was written.
+        std::fs::write(beta.join("contents.md"), "").unwrap();
+
+        let resume = Resume::load(root, "iCloud", false);
+        assert_eq!(resume.written("n1", &alpha, None).unwrap().contents_md, "a");
+        assert!(resume.written("n2", &beta, None).is_none());
+        let later = OffsetDateTime::from_unix_timestamp(60).unwrap();
+        assert!(resume.written("n1", &alpha, Some(later)).is_none());
+        assert!(resume.written("n1", &beta, None).is_none());
+        assert_eq!(resume.reused(), 1);
+
+        // Once finished, only notes known to be unchanged are reused, and only incrementally.
+        manifest.partial = false;
+        std::fs::write(
+            root.join(MANIFEST_JSON),
+            serde_json::to_string(&manifest).unwrap(),
+        )
+        .unwrap();
+        assert!(
+            Resume::load(root, "iCloud", false)
+                .written("n1", &alpha, Some(t))
+                .is_none()
+        );
+        let incremental = Resume::load(root, "iCloud", true);
+        assert!(incremental.written("n1", &alpha, None).is_none());
+        assert!(incremental.written("n1", &alpha, Some(t)).is_some());
+
+        let current = Collector::new(root);
+        current.record("n1", &alpha, t, "a");
+        assert_eq!(incremental.removed(&current.manifest("iCloud")), [beta]);
+        assert!(
+            Resume::load(root, "On My Mac", false)
+                .written("n1", &alpha, None)
+                .is_none()
+        );
+    }
+}
@@ -1,17 +1,37 @@
 use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 
+/// A Notes account ("iCloud", "On My Mac", …).
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Account {
     pub name: String,
 }
 
+/// A folder; `path` runs from the top-level folder down to this one.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Folder {
     pub id: String,
     pub name: String,
     pub account: String,
     pub path: Vec<String>,
+    /// Only known with the DB backend.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "time::serde::rfc3339::option"
+    )]
+    pub created_at: Option<OffsetDateTime>,
+    /// Only known with the DB backend.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "time::serde::rfc3339::option"
+    )]
+    pub modified_at: Option<OffsetDateTime>,
+    /// A smart folder: instead of holding notes, it shows the notes whose tags match its query.
+    /// Read-only, and only known with the DB backend.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub smart: bool,
 }
 
 impl Folder {
@@ -20,13 +40,48 @@ impl Folder {
     }
 }
 
+/// What note listings return: enough to show a note and fetch it with `get_note`.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct NoteSummary {
     pub id: String,
     pub title: String,
     pub folder_id: String,
+    /// The note's UUID (`ZIDENTIFIER`), when read from the Notes database. Unlike `id`, it
+    /// survives database migrations and is the same on every device.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    /// Unknown when the backend can't tell (e.g. fixtures without dates).
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "time::serde::rfc3339::option"
+    )]
+    pub created_at: Option<OffsetDateTime>,
+    /// Unknown when the backend can't tell (e.g. fixtures without dates).
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "time::serde::rfc3339::option"
+    )]
+    pub modified_at: Option<OffsetDateTime>,
+    /// Pinned to the top of its folder. Only the database backend can tell; elsewhere this is
+    /// always false.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    /// Password-protected: the body is encrypted until the note is unlocked in Notes. Only the
+    /// database backend can tell; elsewhere this is always false.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub locked: bool,
 }
 
+impl NoteSummary {
+    /// The id to show people: the UUID when known, else `id`. Both are accepted as note ids.
+    pub fn display_id(&self) -> &str {
+        self.uuid.as_deref().unwrap_or(&self.id)
+    }
+}
+
+/// A whole note, with its body as the HTML Notes stores.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Note {
     pub id: String,
@@ -39,6 +94,7 @@ pub struct Note {
     pub body_html: String,
 }
 
+#[cfg(feature = "cli")]
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BackupNoteMetadata {
     pub id: String,
@@ -49,4 +105,10 @@ pub struct BackupNoteMetadata {
     pub created_at: OffsetDateTime,
     #[serde(with = "time::serde::rfc3339")]
     pub modified_at: OffsetDateTime,
+    /// Shared through iCloud collaboration (only known for database exports).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub shared: bool,
+    /// Email addresses of the people a shared note is shared with, owner included.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub participants: Vec<String>,
 }
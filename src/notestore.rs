@@ -0,0 +1,622 @@
+//! Decoder for the protobuf note bodies stored in `ZICNOTEDATA.ZDATA`.
+//!
+//! The (gunzipped) blob is a `NoteStoreProto`:
+//!
+//! ```text
+//! NoteStoreProto { 2: Document }
+//! Document       { 2: version, 3: Note }
+//! Note           { 2: note_text (string), 5: repeated AttributeRun }
+//! AttributeRun   { 1: length, 2: ParagraphStyle, 5: font_weight, 6: underlined,
+//!                  7: strikethrough, 9: link, 12: AttachmentInfo }
+//! ParagraphStyle { 1: style_type, 2: alignment, 4: indent_amount, 5: Checklist, 8: block_quote }
+//! Checklist      { 1: uuid, 2: done }
+//! ```
+//!
+//! Run lengths are counted in UTF-16 code units (the note text is an `NSAttributedString`).
+//! Attachments (tables, images, …) appear in the text as U+FFFC with an `AttachmentInfo`
+//! (`1: identifier, 2: type_uti`) on their run; their contents live in separate DB rows and are
+//! resolved by the caller.
+
+use anyhow::anyhow;
+
+/// Replacement character Notes puts in the text where an attachment sits.
+const ATTACHMENT_CHAR: char = '\u{FFFC}';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StyleType {
+    Body,
+    Title,
+    Heading,
+    Subheading,
+    Monospaced,
+    DottedList,
+    DashedList,
+    NumberedList,
+    Checklist,
+}
+
+impl StyleType {
+    fn from_proto(v: i64) -> Self {
+        match v {
+            0 => StyleType::Title,
+            1 => StyleType::Heading,
+            2 => StyleType::Subheading,
+            4 => StyleType::Monospaced,
+            100 => StyleType::DottedList,
+            101 => StyleType::DashedList,
+            102 => StyleType::NumberedList,
+            103 => StyleType::Checklist,
+            _ => StyleType::Body,
+        }
+    }
+
+    fn is_list(self) -> bool {
+        matches!(
+            self,
+            StyleType::DottedList
+                | StyleType::DashedList
+                | StyleType::NumberedList
+                | StyleType::Checklist
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParagraphStyle {
+    pub style: StyleType,
+    pub indent: u32,
+    pub block_quote: bool,
+    /// Checked state of a checklist item.
+    pub done: bool,
+}
+
+impl Default for ParagraphStyle {
+    fn default() -> Self {
+        Self {
+            style: StyleType::Body,
+            indent: 0,
+            block_quote: false,
+            done: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Attachment {
+    pub identifier: String,
+    pub type_uti: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AttributeRun {
+    /// Length in UTF-16 code units.
+    pub length: usize,
+    pub paragraph: ParagraphStyle,
+    pub bold: bool,
+    pub italic: bool,
+    pub strikethrough: bool,
+    pub link: Option<String>,
+    pub attachment: Option<Attachment>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NoteBody {
+    pub text: String,
+    pub runs: Vec<AttributeRun>,
+}
+
+/// Parses a decompressed `NoteStoreProto`.
+pub fn parse(data: &[u8]) -> anyhow::Result<NoteBody> {
+    let document = find_message(data, 2)?.ok_or_else(|| anyhow!("missing document"))?;
+    let note = find_message(document, 3)?.ok_or_else(|| anyhow!("missing note"))?;
+
+    let mut body = NoteBody::default();
+    for field in Fields::new(note) {
+        match field? {
+            (2, Value::Bytes(b)) => {
+                body.text = std::str::from_utf8(b)
+                    .map_err(|_| anyhow!("note text is not UTF-8"))?
+                    .to_string();
+            }
+            (5, Value::Bytes(b)) => body.runs.push(parse_run(b)?),
+            _ => {}
+        }
+    }
+    Ok(body)
+}
+
+fn parse_run(data: &[u8]) -> anyhow::Result<AttributeRun> {
+    let mut run = AttributeRun::default();
+    for field in Fields::new(data) {
+        match field? {
+            (1, Value::Varint(v)) => run.length = v as usize,
+            (2, Value::Bytes(b)) => run.paragraph = parse_paragraph_style(b)?,
+            (5, Value::Varint(v)) => {
+                run.bold = v == 1 || v == 3;
+                run.italic = v == 2 || v == 3;
+            }
+            (7, Value::Varint(v)) => run.strikethrough = v != 0,
+            (9, Value::Bytes(b)) => run.link = Some(String::from_utf8_lossy(b).into_owned()),
+            (12, Value::Bytes(b)) => {
+                let mut attachment = Attachment::default();
+                for field in Fields::new(b) {
+                    match field? {
+                        (1, Value::Bytes(b)) => {
+                            attachment.identifier = String::from_utf8_lossy(b).into_owned();
+                        }
+                        (2, Value::Bytes(b)) => {
+                            attachment.type_uti = String::from_utf8_lossy(b).into_owned();
+                        }
+                        _ => {}
+                    }
+                }
+                run.attachment = Some(attachment);
+            }
+            _ => {}
+        }
+    }
+    Ok(run)
+}
+
+fn parse_paragraph_style(data: &[u8]) -> anyhow::Result<ParagraphStyle> {
+    let mut style = ParagraphStyle::default();
+    for field in Fields::new(data) {
+        match field? {
+            (1, Value::Varint(v)) => style.style = StyleType::from_proto(v as i64),
+            (4, Value::Varint(v)) => style.indent = v as u32,
+            (5, Value::Bytes(b)) => {
+                for field in Fields::new(b) {
+                    if let (2, Value::Varint(v)) = field? {
+                        style.done = v != 0;
+                    }
+                }
+            }
+            (8, Value::Varint(v)) => style.block_quote = v != 0,
+            _ => {}
+        }
+    }
+    Ok(style)
+}
+
+/// Decodes a decompressed note blob straight to Markdown, with `resolve` turning attachments
+/// into Markdown blocks (or `None` to drop them).
+pub fn to_markdown_with(
+    data: &[u8],
+    resolve: &mut dyn FnMut(&Attachment) -> Option<String>,
+) -> anyhow::Result<String> {
+    let _span = tracing::debug_span!("render").entered();
+    Ok(render_markdown(&parse(data)?, resolve))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Span<'a> {
+    text: String,
+    run: &'a AttributeRun,
+}
+
+struct Paragraph<'a> {
+    style: ParagraphStyle,
+    spans: Vec<Span<'a>>,
+    attachments: Vec<&'a Attachment>,
+}
+
+/// Splits the text into paragraphs (one per line), each carrying its styled spans.
+fn paragraphs(body: &NoteBody) -> Vec<Paragraph<'_>> {
+    static PLAIN: AttributeRun = AttributeRun {
+        length: 0,
+        paragraph: ParagraphStyle {
+            style: StyleType::Body,
+            indent: 0,
+            block_quote: false,
+            done: false,
+        },
+        bold: false,
+        italic: false,
+        strikethrough: false,
+        link: None,
+        attachment: None,
+    };
+
+    let mut out = Vec::new();
+    let mut current = Paragraph {
+        style: ParagraphStyle::default(),
+        spans: Vec::new(),
+        attachments: Vec::new(),
+    };
+    let mut runs = body.runs.iter();
+    let mut run = runs.next();
+    let mut left = run.map_or(usize::MAX, |r| r.length);
+
+    for ch in body.text.chars() {
+        while left == 0 {
+            run = runs.next();
+            left = run.map_or(usize::MAX, |r| r.length);
+        }
+        let attrs = run.unwrap_or(&PLAIN);
+        left = left.saturating_sub(ch.len_utf16());
+
+        // The paragraph style lives on the run covering the paragraph's text (and its newline).
+        current.style = attrs.paragraph;
+        if ch == '\n' {
+            out.push(std::mem::replace(
+                &mut current,
+                Paragraph {
+                    style: ParagraphStyle::default(),
+                    spans: Vec::new(),
+                    attachments: Vec::new(),
+                },
+            ));
+            continue;
+        }
+        if ch == ATTACHMENT_CHAR {
+            current.attachments.extend(&attrs.attachment);
+            continue;
+        }
+        match current.spans.last_mut() {
+            Some(span) if same_inline(span.run, attrs) => span.text.push(ch),
+            _ => current.spans.push(Span {
+                text: ch.to_string(),
+                run: attrs,
+            }),
+        }
+    }
+    if !current.spans.is_empty() || !current.attachments.is_empty() {
+        out.push(current);
+    }
+    out
+}
+
+fn same_inline(a: &AttributeRun, b: &AttributeRun) -> bool {
+    a.bold == b.bold
+        && a.italic == b.italic
+        && a.strikethrough == b.strikethrough
+        && a.link == b.link
+}
+
+pub fn render_markdown(
+    body: &NoteBody,
+    resolve: &mut dyn FnMut(&Attachment) -> Option<String>,
+) -> String {
+    let mut blocks: Vec<String> = Vec::new();
+    let mut prev: Option<ParagraphStyle> = None;
+    let mut numbers: Vec<u32> = Vec::new();
+
+    for p in paragraphs(body) {
+        let attachments: Vec<String> = p.attachments.iter().filter_map(|a| resolve(a)).collect();
+        let inline = render_inline(&p.spans);
+        if inline.trim().is_empty() && p.style.style != StyleType::Monospaced {
+            prev = None;
+            numbers.clear();
+            blocks.extend(attachments);
+            continue;
+        }
+
+        let indent = p.style.indent as usize;
+        let mut line = match p.style.style {
+            StyleType::Title => format!("# {}", inline.trim()),
+            StyleType::Heading => format!("## {}", inline.trim()),
+            StyleType::Subheading => format!("### {}", inline.trim()),
+            StyleType::Monospaced => plain_text(&p.spans),
+            StyleType::DottedList | StyleType::DashedList => {
+                format!("{}- {}", "  ".repeat(indent), inline.trim())
+            }
+            StyleType::Checklist => format!(
+                "{}- [{}] {}",
+                "  ".repeat(indent),
+                if p.style.done { 'x' } else { ' ' },
+                inline.trim()
+            ),
+            StyleType::NumberedList => {
+                numbers.resize(indent + 1, 0);
+                numbers[indent] += 1;
+                format!(
+                    "{}{}. {}",
+                    "   ".repeat(indent),
+                    numbers[indent],
+                    inline.trim()
+                )
+            }
+            StyleType::Body => inline.trim_end().to_string(),
+        };
+        if p.style.style != StyleType::NumberedList {
+            numbers.truncate(indent);
+        }
+        if p.style.block_quote {
+            line = format!("> {line}");
+        }
+
+        let continues = prev.is_some_and(|prev| {
+            prev.block_quote == p.style.block_quote
+                && ((prev.style.is_list() && p.style.style.is_list())
+                    || (prev.style == StyleType::Monospaced
+                        && p.style.style == StyleType::Monospaced))
+        });
+        match blocks.last_mut() {
+            Some(block) if continues => {
+                if p.style.style == StyleType::Monospaced {
+                    // Re-open the fence we closed after the previous line.
+                    block.truncate(block.len() - "\n```".len());
+                }
+                block.push('\n');
+                block.push_str(&line);
+                if p.style.style == StyleType::Monospaced {
+                    block.push_str("\n```");
+                }
+            }
+            _ if p.style.style == StyleType::Monospaced => {
+                blocks.push(format!("```\n{line}\n```"));
+            }
+            _ => blocks.push(line),
+        }
+        prev = Some(p.style);
+        if !attachments.is_empty() {
+            blocks.extend(attachments);
+            prev = None;
+        }
+    }
+
+    let mut out = blocks.join("\n\n");
+    out.push('\n');
+    out
+}
+
+fn plain_text(spans: &[Span<'_>]) -> String {
+    spans.iter().map(|s| s.text.as_str()).collect()
+}
+
+fn render_inline(spans: &[Span<'_>]) -> String {
+    let mut out = String::new();
+    for span in spans {
+        let mut s = span.text.clone();
+        if span.run.strikethrough {
+            s = wrap(&s, "~~");
+        }
+        if span.run.italic {
+            s = wrap(&s, "*");
+        }
+        if span.run.bold {
+            s = wrap(&s, "**");
+        }
+        if let Some(link) = &span.run.link {
+            s = format!("[{s}]({link})");
+        }
+        out.push_str(&s);
+    }
+    out
+}
+
+/// Wraps `s` in `marker`, keeping surrounding whitespace outside (`** x**` is not emphasis).
+fn wrap(s: &str, marker: &str) -> String {
+    let trimmed = s.trim();
+    if trimmed.is_empty() {
+        return s.to_string();
+    }
+    let start = s.len() - s.trim_start().len();
+    let end = start + trimmed.len();
+    format!("{}{marker}{trimmed}{marker}{}", &s[..start], &s[end..])
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+/// Minimal protobuf wire-format reader yielding `(field_number, value)` pairs.
+pub(crate) struct Fields<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Fields<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn varint(&mut self) -> anyhow::Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| anyhow!("truncated varint"))?;
+            self.pos += 1;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(anyhow!("varint too long"))
+    }
+
+    fn take(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| anyhow!("truncated field"))?;
+        let out = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(out)
+    }
+
+    fn field(&mut self) -> anyhow::Result<(u64, Value<'a>)> {
+        let key = self.varint()?;
+        let number = key >> 3;
+        if number == 0 {
+            return Err(anyhow!("invalid field number 0"));
+        }
+        let value = match key & 7 {
+            0 => Value::Varint(self.varint()?),
+            1 => {
+                self.take(8)?;
+                Value::Fixed
+            }
+            2 => {
+                let len = self.varint()? as usize;
+                Value::Bytes(self.take(len)?)
+            }
+            5 => {
+                self.take(4)?;
+                Value::Fixed
+            }
+            wire => return Err(anyhow!("unsupported wire type {wire}")),
+        };
+        Ok((number, value))
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = anyhow::Result<(u64, Value<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.data.len() {
+            return None;
+        }
+        let field = self.field();
+        if field.is_err() {
+            self.pos = self.data.len();
+        }
+        Some(field)
+    }
+}
+
+pub(crate) fn find_message(data: &[u8], number: u64) -> anyhow::Result<Option<&[u8]>> {
+    for field in Fields::new(data) {
+        if let (n, Value::Bytes(b)) = field?
+            && n == number
+        {
+            return Ok(Some(b));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Decodes a decompressed note blob straight to Markdown, dropping attachments.
+    fn to_markdown(data: &[u8]) -> anyhow::Result<String> {
+        to_markdown_with(data, &mut |_| None)
+    }
+
+    fn varint(mut v: u64, out: &mut Vec<u8>) {
+        loop {
+            let byte = (v & 0x7f) as u8;
+            v >>= 7;
+            if v == 0 {
+                out.push(byte);
+                return;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+
+    pub(crate) fn field_varint(number: u64, v: u64) -> Vec<u8> {
+        let mut out = Vec::new();
+        varint(number << 3, &mut out);
+        varint(v, &mut out);
+        out
+    }
+
+    pub(crate) fn field_bytes(number: u64, b: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        varint((number << 3) | 2, &mut out);
+        varint(b.len() as u64, &mut out);
+        out.extend_from_slice(b);
+        out
+    }
+
+    /// Builds a `NoteStoreProto` from `(text, style_type, font_weight, link)` runs.
+    pub(crate) fn note_proto(runs: &[(&str, Option<u64>, u64, Option<&str>)]) -> Vec<u8> {
+        let text: String = runs.iter().map(|r| r.0).collect();
+        let mut note = field_bytes(2, text.as_bytes());
+        for (s, style, weight, link) in runs {
+            let mut run = field_varint(1, s.encode_utf16().count() as u64);
+            if let Some(style) = style {
+                run.extend(field_bytes(2, &field_varint(1, *style)));
+            }
+            if *weight != 0 {
+                run.extend(field_varint(5, *weight));
+            }
+            if let Some(link) = link {
+                run.extend(field_bytes(9, link.as_bytes()));
+            }
+            note.extend(field_bytes(5, &run));
+        }
+        let mut document = field_varint(2, 0);
+        document.extend(field_bytes(3, &note));
+        field_bytes(2, &document)
+    }
+
+    #[test]
+    fn to_markdown_renders_styles_lists_and_links() {
+        let proto = note_proto(&[
+            ("Groceries 🛒\n", Some(0), 0, None),
+            ("Buy ", None, 0, None),
+            ("fresh", None, 1, None),
+            (" milk, see ", None, 0, None),
+            ("store", None, 0, Some("https://example.com")),
+            ("\n", None, 0, None),
+            ("one\ntwo\n", Some(102), 0, None),
+            ("eggs\n", Some(100), 0, None),
+            ("let x = 1;\n", Some(4), 0, None),
+        ]);
+
+        assert_eq!(
+            to_markdown(&proto).unwrap(),
+            "# Groceries 🛒\n\n\
+             Buy **fresh** milk, see [store](https://example.com)\n\n\
+             1. one\n2. two\n- eggs\n\n\
+             ```\nlet x = 1;\n```\n"
+        );
+    }
+
+    #[test]
+    fn to_markdown_renders_checklists_as_task_lists() {
+        let item = |text: &str, done: u64| {
+            let mut checklist = field_bytes(1, b"uuid");
+            checklist.extend(field_varint(2, done));
+            let mut style = field_varint(1, 103);
+            style.extend(field_bytes(5, &checklist));
+            let mut run = field_varint(1, text.encode_utf16().count() as u64);
+            run.extend(field_bytes(2, &style));
+            field_bytes(5, &run)
+        };
+        let mut note = field_bytes(2, "Milk\nEggs\n".as_bytes());
+        note.extend(item("Milk\n", 1));
+        note.extend(item("Eggs\n", 0));
+        let proto = field_bytes(2, &field_bytes(3, &note));
+
+        assert_eq!(to_markdown(&proto).unwrap(), "- [x] Milk\n- [ ] Eggs\n");
+    }
+
+    #[test]
+    fn to_markdown_with_places_resolved_attachments_as_blocks() {
+        let text = "Before\n\u{FFFC}\nAfter";
+        let mut note = field_bytes(2, text.as_bytes());
+        note.extend(field_bytes(5, &field_varint(1, 7)));
+        let info = [
+            field_bytes(1, b"TABLE-1"),
+            field_bytes(2, b"com.apple.notes.table"),
+        ]
+        .concat();
+        let run = [field_varint(1, 1), field_bytes(12, &info)].concat();
+        note.extend(field_bytes(5, &run));
+        note.extend(field_bytes(5, &field_varint(1, 6)));
+        let proto = field_bytes(2, &field_bytes(3, &note));
+
+        let md = to_markdown_with(&proto, &mut |a| {
+            (a.identifier == "TABLE-1").then(|| "| a |\n| --- |".to_string())
+        })
+        .unwrap();
+        assert_eq!(md, "Before\n\n| a |\n| --- |\n\nAfter\n");
+    }
+
+    #[test]
+    fn parse_rejects_non_protobuf() {
+        assert!(parse(b"\0\0Title\0\0").is_err());
+    }
+}
@@ -0,0 +1,386 @@
+//! Decoder for embedded Notes tables (`com.apple.notes.table` attachments).
+//!
+//! A table is stored in its attachment's `ZICCLOUDSYNCINGOBJECT.ZMERGEABLEDATA1` as a gzipped CRDT
+//! document (`MergableDataProto { 2: { 3: MergeableDataObjectData } }`). That data is a flat list
+//! of object entries plus key/type/UUID string tables; the `com.apple.notes.ICTable` map entry
+//! points at the `crRows`/`crColumns` ordered sets and at `cellColumns`, a column → row → cell
+//! dictionary whose cells are small `Note` messages.
+
+use crate::notestore::{Fields, Value, find_message};
+use anyhow::anyhow;
+use std::collections::HashMap;
+
+pub const TABLE_UTI: &str = "com.apple.notes.table";
+
+#[derive(Debug, Clone, Default)]
+struct ObjectId {
+    unsigned: Option<u64>,
+    object_index: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+enum Entry<'a> {
+    Map {
+        type_index: Option<usize>,
+        entries: Vec<(usize, ObjectId)>,
+    },
+    Dictionary(Vec<(ObjectId, ObjectId)>),
+    OrderedSet {
+        /// UUIDs in display order.
+        order: Vec<&'a [u8]>,
+        /// Later additions: the element at `key` now lives at `value`'s position.
+        contents: Vec<(ObjectId, ObjectId)>,
+    },
+    Note(&'a [u8]),
+    Other,
+}
+
+struct Document<'a> {
+    entries: Vec<Entry<'a>>,
+    keys: Vec<String>,
+    types: Vec<String>,
+    uuids: Vec<&'a [u8]>,
+}
+
+/// Decodes a decompressed `MergableDataProto` table into rows of plain-text cells.
+pub fn decode(data: &[u8]) -> anyhow::Result<Vec<Vec<String>>> {
+    let object = find_message(data, 2)?.ok_or_else(|| anyhow!("missing mergeable object"))?;
+    let object_data = find_message(object, 3)?.ok_or_else(|| anyhow!("missing object data"))?;
+
+    let mut doc = Document {
+        entries: Vec::new(),
+        keys: Vec::new(),
+        types: Vec::new(),
+        uuids: Vec::new(),
+    };
+    for field in Fields::new(object_data) {
+        match field? {
+            (3, Value::Bytes(b)) => doc.entries.push(parse_entry(b)?),
+            (4, Value::Bytes(b)) => doc.keys.push(String::from_utf8_lossy(b).into_owned()),
+            (5, Value::Bytes(b)) => doc.types.push(String::from_utf8_lossy(b).into_owned()),
+            (6, Value::Bytes(b)) => doc.uuids.push(b),
+            _ => {}
+        }
+    }
+    doc.table()
+}
+
+impl<'a> Document<'a> {
+    fn table(&self) -> anyhow::Result<Vec<Vec<String>>> {
+        let table = self
+            .entries
+            .iter()
+            .find_map(|e| match e {
+                Entry::Map {
+                    type_index: Some(t),
+                    entries,
+                } if self.types.get(*t).map(String::as_str) == Some("com.apple.notes.ICTable") => {
+                    Some(entries)
+                }
+                _ => None,
+            })
+            .ok_or_else(|| anyhow!("no ICTable object"))?;
+
+        let mut rows = HashMap::new();
+        let mut columns = HashMap::new();
+        let mut cells = None;
+        for (key, value) in table {
+            let Some(entry) = value.object_index.and_then(|i| self.entries.get(i)) else {
+                continue;
+            };
+            match self.keys.get(*key).map(String::as_str) {
+                Some("crRows") => rows = self.ordering(entry),
+                Some("crColumns") => columns = self.ordering(entry),
+                Some("cellColumns") => cells = Some(entry),
+                _ => {}
+            }
+        }
+
+        let n_rows = rows.values().max().map_or(0, |m| m + 1);
+        let n_cols = columns.values().max().map_or(0, |m| m + 1);
+        let mut grid = vec![vec![String::new(); n_cols]; n_rows];
+
+        let Some(Entry::Dictionary(cell_columns)) = cells else {
+            return Ok(grid);
+        };
+        for (column_key, column_value) in cell_columns {
+            let Some(col) = self.uuid_ref(column_key).and_then(|u| columns.get(&u)) else {
+                continue;
+            };
+            let Some(Entry::Dictionary(column)) = self.entry(column_value) else {
+                continue;
+            };
+            for (row_key, cell) in column {
+                let Some(row) = self.uuid_ref(row_key).and_then(|u| rows.get(&u)) else {
+                    continue;
+                };
+                if let Some(Entry::Note(note)) = self.entry(cell) {
+                    grid[*row][*col] = note_text(note)?;
+                }
+            }
+        }
+        Ok(grid)
+    }
+
+    fn entry(&self, id: &ObjectId) -> Option<&Entry<'a>> {
+        id.object_index.and_then(|i| self.entries.get(i))
+    }
+
+    /// Resolves an object that wraps a UUID reference to its index in the UUID table.
+    fn uuid_ref(&self, id: &ObjectId) -> Option<usize> {
+        match self.entry(id)? {
+            Entry::Map { entries, .. } => entries.first()?.1.unsigned.map(|u| u as usize),
+            _ => None,
+        }
+    }
+
+    /// Maps UUID-table indices of an ordered set's members to their display positions.
+    fn ordering(&self, entry: &Entry<'a>) -> HashMap<usize, usize> {
+        let mut positions = HashMap::new();
+        let Entry::OrderedSet { order, contents } = entry else {
+            return positions;
+        };
+        for (pos, uuid) in order.iter().enumerate() {
+            if let Some(i) = self.uuids.iter().position(|u| u == uuid) {
+                positions.insert(i, pos);
+            }
+        }
+        for (key, value) in contents {
+            if let (Some(k), Some(v)) = (self.uuid_ref(key), self.uuid_ref(value))
+                && let Some(pos) = positions.get(&k).copied()
+            {
+                positions.insert(v, pos);
+            }
+        }
+        positions
+    }
+}
+
+fn parse_entry(data: &[u8]) -> anyhow::Result<Entry<'_>> {
+    for field in Fields::new(data) {
+        match field? {
+            (6, Value::Bytes(b)) => return Ok(Entry::Dictionary(parse_dictionary(b)?)),
+            (10, Value::Bytes(b)) => return Ok(Entry::Note(b)),
+            (13, Value::Bytes(b)) => {
+                let mut type_index = None;
+                let mut entries = Vec::new();
+                for field in Fields::new(b) {
+                    match field? {
+                        (1, Value::Varint(v)) => type_index = Some(v as usize),
+                        (3, Value::Bytes(b)) => {
+                            let mut key = 0;
+                            let mut value = ObjectId::default();
+                            for field in Fields::new(b) {
+                                match field? {
+                                    (1, Value::Varint(v)) => key = v as usize,
+                                    (2, Value::Bytes(b)) => value = parse_object_id(b)?,
+                                    _ => {}
+                                }
+                            }
+                            entries.push((key, value));
+                        }
+                        _ => {}
+                    }
+                }
+                return Ok(Entry::Map {
+                    type_index,
+                    entries,
+                });
+            }
+            (16, Value::Bytes(b)) => return parse_ordered_set(b),
+            _ => {}
+        }
+    }
+    Ok(Entry::Other)
+}
+
+fn parse_ordered_set(data: &[u8]) -> anyhow::Result<Entry<'_>> {
+    let mut order = Vec::new();
+    let mut contents = Vec::new();
+    if let Some(ordering) = find_message(data, 1)? {
+        for field in Fields::new(ordering) {
+            match field? {
+                (1, Value::Bytes(array)) => {
+                    for field in Fields::new(array) {
+                        if let (2, Value::Bytes(attachment)) = field?
+                            && let Some(uuid) = find_message(attachment, 2)?
+                        {
+                            order.push(uuid);
+                        }
+                    }
+                }
+                (2, Value::Bytes(b)) => contents = parse_dictionary(b)?,
+                _ => {}
+            }
+        }
+    }
+    Ok(Entry::OrderedSet { order, contents })
+}
+
+fn parse_dictionary(data: &[u8]) -> anyhow::Result<Vec<(ObjectId, ObjectId)>> {
+    let mut out = Vec::new();
+    for field in Fields::new(data) {
+        if let (1, Value::Bytes(element)) = field? {
+            let mut key = ObjectId::default();
+            let mut value = ObjectId::default();
+            for field in Fields::new(element) {
+                match field? {
+                    (1, Value::Bytes(b)) => key = parse_object_id(b)?,
+                    (2, Value::Bytes(b)) => value = parse_object_id(b)?,
+                    _ => {}
+                }
+            }
+            out.push((key, value));
+        }
+    }
+    Ok(out)
+}
+
+fn parse_object_id(data: &[u8]) -> anyhow::Result<ObjectId> {
+    let mut id = ObjectId::default();
+    for field in Fields::new(data) {
+        match field? {
+            (2, Value::Varint(v)) => id.unsigned = Some(v),
+            (6, Value::Varint(v)) => id.object_index = Some(v as usize),
+            _ => {}
+        }
+    }
+    Ok(id)
+}
+
+fn note_text(note: &[u8]) -> anyhow::Result<String> {
+    for field in Fields::new(note) {
+        if let (2, Value::Bytes(b)) = field? {
+            return Ok(String::from_utf8_lossy(b).into_owned());
+        }
+    }
+    Ok(String::new())
+}
+
+/// Renders rows as a GitHub-style table; the first row becomes the header.
+pub fn to_markdown(rows: &[Vec<String>]) -> Option<String> {
+    let header = rows.first().filter(|r| !r.is_empty())?;
+    let cell = |s: &String| {
+        s.trim()
+            .replace('|', "\\|")
+            .replace("\r\n", "<br>")
+            .replace('\n', "<br>")
+    };
+    let line = |row: &Vec<String>| {
+        let cells: Vec<String> = row.iter().map(cell).collect();
+        format!("| {} |", cells.join(" | "))
+    };
+
+    let mut out = vec![line(header)];
+    out.push(format!("|{}", " --- |".repeat(header.len())));
+    out.extend(rows[1..].iter().map(line));
+    Some(out.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notestore::tests::{field_bytes, field_varint};
+
+    fn object_index(i: u64) -> Vec<u8> {
+        field_varint(6, i)
+    }
+
+    fn uuid_ref(uuid_index: u64) -> Vec<u8> {
+        let map_entry = [
+            field_varint(1, 0),
+            field_bytes(2, &field_varint(2, uuid_index)),
+        ]
+        .concat();
+        let map = [field_varint(1, 1), field_bytes(3, &map_entry)].concat();
+        field_bytes(13, &map)
+    }
+
+    fn dictionary(pairs: &[(u64, u64)]) -> Vec<u8> {
+        let mut dict = Vec::new();
+        for (k, v) in pairs {
+            let element = [
+                field_bytes(1, &object_index(*k)),
+                field_bytes(2, &object_index(*v)),
+            ]
+            .concat();
+            dict.extend(field_bytes(1, &element));
+        }
+        field_bytes(6, &dict)
+    }
+
+    fn ordered_set(uuids: &[&[u8]]) -> Vec<u8> {
+        let mut array = Vec::new();
+        for (i, uuid) in uuids.iter().enumerate() {
+            let attachment = [field_varint(1, i as u64), field_bytes(2, uuid)].concat();
+            array.extend(field_bytes(2, &attachment));
+        }
+        let ordering = field_bytes(1, &array);
+        field_bytes(16, &field_bytes(1, &ordering))
+    }
+
+    fn cell(text: &str) -> Vec<u8> {
+        field_bytes(10, &field_bytes(2, text.as_bytes()))
+    }
+
+    /// A 2x2 table: rows R0/R1 and columns C0/C1, with rows listed in reverse UUID order.
+    fn table_proto() -> Vec<u8> {
+        let table_map = [
+            field_varint(1, 0),
+            field_bytes(
+                3,
+                &[field_varint(1, 0), field_bytes(2, &object_index(1))].concat(),
+            ),
+            field_bytes(
+                3,
+                &[field_varint(1, 1), field_bytes(2, &object_index(2))].concat(),
+            ),
+            field_bytes(
+                3,
+                &[field_varint(1, 2), field_bytes(2, &object_index(3))].concat(),
+            ),
+        ]
+        .concat();
+        let entries = [
+            field_bytes(13, &table_map),
+            ordered_set(&[b"R1", b"R0"]),
+            ordered_set(&[b"C0", b"C1"]),
+            dictionary(&[(4, 6), (5, 7)]),
+            uuid_ref(2),
+            uuid_ref(3),
+            dictionary(&[(8, 10), (9, 11)]),
+            dictionary(&[(8, 12), (9, 13)]),
+            uuid_ref(0),
+            uuid_ref(1),
+            cell("Milk"),
+            cell("Name"),
+            cell("2"),
+            cell("Qty|n"),
+        ];
+
+        let mut data = Vec::new();
+        for e in &entries {
+            data.extend(field_bytes(3, e));
+        }
+        for k in ["crRows", "crColumns", "cellColumns"] {
+            data.extend(field_bytes(4, k.as_bytes()));
+        }
+        for t in ["com.apple.notes.ICTable", "com.apple.CRDT.NSUUID"] {
+            data.extend(field_bytes(5, t.as_bytes()));
+        }
+        for u in [b"R0", b"R1", b"C0", b"C1"] {
+            data.extend(field_bytes(6, u));
+        }
+        field_bytes(2, &field_bytes(3, &data))
+    }
+
+    #[test]
+    fn decode_reconstructs_table_in_display_order() {
+        let rows = decode(&table_proto()).unwrap();
+        assert_eq!(rows, vec![vec!["Name", "Qty|n"], vec!["Milk", "2"]]);
+        assert_eq!(
+            to_markdown(&rows).unwrap(),
+            "| Name | Qty\\|n |\n| --- | --- |\n| Milk | 2 |"
+        );
+    }
+}
@@ -0,0 +1,85 @@
+//! Paging long output through `$PAGER` (or `less`), the way git does.
+
+use anyhow::Context;
+use std::ffi::OsString;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+use terminal_size::{Height, terminal_size};
+
+/// `less` flags used when `$LESS` is unset: quit if the text fits on one screen, pass colors
+/// through, and leave the text on screen afterwards.
+const DEFAULT_LESS: &str = "FRX";
+
+/// The pager to run: `APPLE_NOTES_PAGER`, then `PAGER`, then `less`. `None` when paging is off
+/// (an empty value or `cat`).
+fn pager_command(configured: Option<OsString>) -> Option<Vec<String>> {
+    let configured = configured.map(|c| c.to_string_lossy().into_owned());
+    let words: Vec<String> = configured
+        .as_deref()
+        .unwrap_or("less")
+        .split_whitespace()
+        .map(str::to_string)
+        .collect();
+    match words.first().map(String::as_str) {
+        None | Some("cat") => None,
+        Some(_) => Some(words),
+    }
+}
+
+fn terminal_height() -> Option<usize> {
+    if let Ok(lines) = std::env::var("LINES")
+        && let Ok(v) = lines.parse::<usize>()
+    {
+        return Some(v);
+    }
+    terminal_size().map(|(_, Height(h))| h as usize)
+}
+
+/// Prints `text` to stdout, through the pager when stdout is a terminal, `enabled` is set, and
+/// the text is taller than the screen. Falls back to printing if the pager can't start.
+pub fn page(text: &str, enabled: bool) -> anyhow::Result<()> {
+    let too_tall = || terminal_height().is_some_and(|h| text.lines().count() >= h);
+    let pager = std::env::var_os("APPLE_NOTES_PAGER").or_else(|| std::env::var_os("PAGER"));
+    if enabled
+        && io::stdout().is_terminal()
+        && too_tall()
+        && let Some(cmd) = pager_command(pager)
+    {
+        let mut command = Command::new(&cmd[0]);
+        command.args(&cmd[1..]).stdin(Stdio::piped());
+        if std::env::var_os("LESS").is_none() {
+            command.env("LESS", DEFAULT_LESS);
+        }
+        if let Ok(mut child) = command.spawn() {
+            let mut stdin = child.stdin.take().context("stdin was not piped")?;
+            // Quitting the pager early closes the pipe; that's not an error.
+            if let Err(e) = stdin.write_all(text.as_bytes())
+                && e.kind() != io::ErrorKind::BrokenPipe
+            {
+                return Err(e).context("write to pager");
+            }
+            drop(stdin);
+            child.wait().context("wait for pager")?;
+            return Ok(());
+        }
+    }
+    let mut out = io::stdout().lock();
+    out.write_all(text.as_bytes()).context("write stdout")?;
+    out.flush().context("write stdout")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pager_command_defaults_to_less_and_can_be_disabled() {
+        assert_eq!(pager_command(None), Some(vec!["less".to_string()]));
+        assert_eq!(
+            pager_command(Some("most -s".into())),
+            Some(vec!["most".to_string(), "-s".to_string()])
+        );
+        assert_eq!(pager_command(Some("".into())), None);
+        assert_eq!(pager_command(Some("cat".into())), None);
+    }
+}
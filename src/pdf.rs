@@ -0,0 +1,83 @@
+use anyhow::{Context, anyhow};
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// File name of the per-note PDF written by `export --format pdf`.
+pub const NOTE_PDF: &str = "note.pdf";
+
+fn textutil_bin() -> OsString {
+    std::env::var_os("APPLE_NOTES_TEXTUTIL_BIN").unwrap_or_else(|| OsString::from("textutil"))
+}
+
+fn cupsfilter_bin() -> OsString {
+    std::env::var_os("APPLE_NOTES_CUPSFILTER_BIN").unwrap_or_else(|| OsString::from("cupsfilter"))
+}
+
+/// Renders a standalone HTML page to a PDF at `out`.
+///
+/// Uses the tools that ship with macOS: `textutil` lays out the HTML as RTF, and `cupsfilter`
+/// prints that to PDF. The intermediate RTF lives next to `out` and is removed afterwards.
+pub fn write_pdf(page_html: &str, out: &Path) -> anyhow::Result<()> {
+    let rtf = html_to_rtf(page_html)?;
+
+    let mut tmp = out.as_os_str().to_owned();
+    tmp.push(".rtf.tmp");
+    let tmp = Path::new(&tmp);
+    std::fs::write(tmp, rtf).with_context(|| format!("write {tmp:?}"))?;
+    let res = rtf_to_pdf(tmp, out);
+    let _ = std::fs::remove_file(tmp);
+    res
+}
+
+fn html_to_rtf(html: &str) -> anyhow::Result<Vec<u8>> {
+    let mut child = Command::new(textutil_bin())
+        .args([
+            "-stdin",
+            "-stdout",
+            "-format",
+            "html",
+            "-convert",
+            "rtf",
+            "-inputencoding",
+            "UTF-8",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to spawn textutil (are you on macOS?)")?;
+    {
+        let mut stdin = child.stdin.take().context("stdin was not piped")?;
+        stdin
+            .write_all(html.as_bytes())
+            .context("failed writing textutil stdin")?;
+    }
+    let out = child.wait_with_output().context("textutil failed")?;
+    if !out.status.success() {
+        return Err(anyhow!(
+            "textutil failed ({}): {}",
+            out.status,
+            String::from_utf8_lossy(&out.stderr)
+        ));
+    }
+    Ok(out.stdout)
+}
+
+fn rtf_to_pdf(rtf: &Path, out: &Path) -> anyhow::Result<()> {
+    let pdf = Command::new(cupsfilter_bin())
+        .args(["-m", "application/pdf"])
+        .arg(rtf)
+        .stdin(Stdio::null())
+        .output()
+        .context("failed to spawn cupsfilter (are you on macOS?)")?;
+    if !pdf.status.success() || pdf.stdout.is_empty() {
+        return Err(anyhow!(
+            "cupsfilter failed ({}): {}",
+            pdf.status,
+            String::from_utf8_lossy(&pdf.stderr)
+        ));
+    }
+    std::fs::write(out, pdf.stdout).with_context(|| format!("write {out:?}"))
+}
@@ -1,12 +1,65 @@
+use crate::config::ProgressConfig;
+use anyhow::Context;
 use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 use std::io::IsTerminal;
+use std::sync::OnceLock;
 use std::time::Duration;
 
+struct Settings {
+    quiet: bool,
+    /// `[progress] enabled`.
+    enabled: bool,
+    spinner: ProgressStyle,
+    bar: ProgressStyle,
+    tick: Duration,
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// Applies `--quiet` and the `[progress]` config section. Call once at startup; without it the
+/// built-in styles are used.
+pub fn configure(quiet: bool, config: &ProgressConfig) -> anyhow::Result<()> {
+    let settings = build(quiet, config)?;
+    let _ = SETTINGS.set(settings);
+    Ok(())
+}
+
+fn build(quiet: bool, config: &ProgressConfig) -> anyhow::Result<Settings> {
+    if config.spinner_chars.chars().count() < 2 {
+        anyhow::bail!("progress.spinner_chars needs at least two characters");
+    }
+    let spinner = ProgressStyle::with_template(&config.spinner_template)
+        .context("invalid progress.spinner_template")?
+        .tick_chars(&config.spinner_chars);
+    let bar = ProgressStyle::with_template(&config.bar_template)
+        .context("invalid progress.bar_template")?
+        .tick_chars(&config.spinner_chars);
+    Ok(Settings {
+        quiet,
+        enabled: config.enabled,
+        spinner,
+        bar,
+        tick: Duration::from_millis(config.tick_ms.max(1)),
+    })
+}
+
+fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(|| build(false, &ProgressConfig::default()).expect("default styles"))
+}
+
+/// True when `--quiet` was given: callers should skip non-essential output.
+pub fn quiet() -> bool {
+    settings().quiet
+}
+
 fn enabled() -> bool {
+    if quiet() {
+        return false;
+    }
     if std::env::var_os("APPLE_NOTES_FORCE_PROGRESS").is_some() {
         return true;
     }
-    if std::env::var_os("NO_PROGRESS").is_some() {
+    if std::env::var_os("NO_PROGRESS").is_some() || !settings().enabled {
         return false;
     }
     std::io::stderr().is_terminal()
@@ -16,14 +69,11 @@ pub fn spinner(msg: &str) -> Option<ProgressBar> {
     if !enabled() {
         return None;
     }
+    let settings = settings();
     let pb = ProgressBar::new_spinner();
     pb.set_draw_target(ProgressDrawTarget::stderr());
-    pb.enable_steady_tick(Duration::from_millis(80));
-    pb.set_style(
-        ProgressStyle::with_template("{spinner:.cyan} {msg}")
-            .unwrap()
-            .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏"),
-    );
+    pb.enable_steady_tick(settings.tick);
+    pb.set_style(settings.spinner.clone());
     pb.set_message(msg.to_string());
     Some(pb)
 }
@@ -32,14 +82,28 @@ pub fn bar(len: u64, msg: &str) -> Option<ProgressBar> {
     if !enabled() {
         return None;
     }
+    let settings = settings();
     let pb = ProgressBar::new(len);
     pb.set_draw_target(ProgressDrawTarget::stderr());
-    pb.enable_steady_tick(Duration::from_millis(80));
-    pb.set_style(
-        ProgressStyle::with_template("{spinner:.cyan} {msg} {wide_bar} {pos}/{len}")
-            .unwrap()
-            .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏"),
-    );
+    pb.enable_steady_tick(settings.tick);
+    pb.set_style(settings.bar.clone());
     pb.set_message(msg.to_string());
     Some(pb)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_rejects_bad_templates() {
+        assert!(build(false, &ProgressConfig::default()).is_ok());
+
+        let config = ProgressConfig {
+            bar_template: "{msg:?}".to_string(),
+            ..ProgressConfig::default()
+        };
+        let err = build(false, &config).err().unwrap();
+        assert!(format!("{err:#}").contains("progress.bar_template"));
+    }
+}
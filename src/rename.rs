@@ -0,0 +1,91 @@
+use crate::model::NoteSummary;
+use anyhow::anyhow;
+use regex::Regex;
+use serde::Serialize;
+
+/// One title change planned by `notes rename --query/--replace`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlannedRename {
+    pub id: String,
+    pub title: String,
+    pub new_title: String,
+}
+
+/// Applies `pattern` → `template` to every matching title.
+///
+/// Only the first match in a title is replaced (anchor the pattern to be explicit); `$1`, `${1}`
+/// and `${name}` in the template refer to capture groups. Notes whose title would not change are
+/// skipped, and a replacement that leaves a title empty is an error.
+pub fn plan(
+    notes: &[NoteSummary],
+    pattern: &Regex,
+    template: &str,
+) -> anyhow::Result<Vec<PlannedRename>> {
+    let mut out = Vec::new();
+    for n in notes {
+        if !pattern.is_match(&n.title) {
+            continue;
+        }
+        let new_title = pattern.replace(&n.title, template).trim().to_string();
+        if new_title.is_empty() {
+            return Err(anyhow!(
+                "replacement leaves note {} ({:?}) without a title",
+                n.id,
+                n.title
+            ));
+        }
+        if new_title != n.title {
+            out.push(PlannedRename {
+                id: n.id.clone(),
+                title: n.title.clone(),
+                new_title,
+            });
+        }
+    }
+    out.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.id.cmp(&b.id)));
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(id: &str, title: &str) -> NoteSummary {
+        NoteSummary {
+            id: id.to_string(),
+            title: title.to_string(),
+            folder_id: "f".to_string(),
+            uuid: None,
+            created_at: None,
+            modified_at: None,
+            pinned: false,
+            locked: false,
+        }
+    }
+
+    #[test]
+    fn plan_uses_capture_groups_and_skips_unchanged() {
+        let notes = [
+            note("1", "WIP: Roadmap"),
+            note("2", "WIP: Budget 2025"),
+            note("3", "Groceries"),
+        ];
+        let pattern = Regex::new(r"^WIP: (?<name>.*)$").unwrap();
+        let planned = plan(&notes, &pattern, "${name} (draft)").unwrap();
+        assert_eq!(
+            planned
+                .iter()
+                .map(|p| p.new_title.as_str())
+                .collect::<Vec<_>>(),
+            ["Budget 2025 (draft)", "Roadmap (draft)"]
+        );
+
+        let strip_all = Regex::new(".*").unwrap();
+        assert!(plan(&notes, &strip_all, "").is_err());
+        assert!(
+            plan(&notes, &Regex::new("Groceries").unwrap(), "$0")
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
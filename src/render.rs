@@ -1,14 +1,197 @@
+use crate::config::MarkdownDialect;
 use crate::model::Note;
 use html2md::parse_html;
+use regex::Regex;
+use std::sync::LazyLock;
 use termimad::MadSkin;
 
+/// Where a list's first item is a comrak task item (matched right after its `<ul>`).
+static FIRST_TASK_ITEM: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^\s*<li><input type="checkbox" "#).unwrap());
+/// A list tag (group 1 is `/` on closing tags) or a comrak task item (group 2 if checked).
+static LIST_TAG_OR_TASK_ITEM: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"<(/?)[uo]l\b[^>]*>|<li><input type="checkbox" (checked="" )?disabled="" /> ?"#)
+        .unwrap()
+});
+
 pub fn note_to_markdown(note: &Note) -> String {
     let body_md = html_to_markdown(&note.body_html);
     format!("# {}\n\n{}", note.title, body_md.trim())
 }
 
+/// Counts words: whitespace-separated runs with at least one letter or digit, so Markdown
+/// markers like `-` or `#` don't count.
+pub fn word_count(text: &str) -> usize {
+    text.split_whitespace()
+        .filter(|w| w.chars().any(char::is_alphanumeric))
+        .count()
+}
+
+/// The first `n` non-blank lines of `markdown`, keeping the blank lines between them.
+pub fn head_lines(markdown: &str, n: usize) -> String {
+    let mut out = Vec::new();
+    let mut kept = 0;
+    for line in markdown.trim().lines() {
+        if kept == n {
+            break;
+        }
+        if !line.trim().is_empty() {
+            kept += 1;
+        }
+        out.push(line);
+    }
+    out.join("\n")
+}
+
+/// A one-line snippet of a note's body for listings: the first lines after the title, with
+/// whitespace collapsed and cut at `max_chars` with `…`.
+pub fn snippet(text: &str, title: &str, max_chars: usize) -> String {
+    let mut lines = text
+        .lines()
+        .map(|l| l.replace('\u{fffc}', "").trim().to_string())
+        .filter(|l| !l.is_empty())
+        .peekable();
+    if lines
+        .peek()
+        .is_some_and(|first| first.trim_start_matches('#').trim() == title.trim())
+    {
+        lines.next();
+    }
+    let line = lines.collect::<Vec<_>>().join(" ");
+    let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() <= max_chars {
+        return line;
+    }
+    let cut: String = line.chars().take(max_chars.saturating_sub(1)).collect();
+    format!("{}…", cut.trim_end())
+}
+
+/// The text of `markdown` without its markup: one line per block, inline formatting dropped.
+pub fn markdown_to_text(markdown: &str) -> String {
+    use comrak::nodes::NodeValue;
+    let arena = comrak::Arena::new();
+    let root = comrak::parse_document(&arena, markdown, &comrak::Options::default());
+    let mut out = String::new();
+    for node in root.descendants() {
+        match &node.data.borrow().value {
+            NodeValue::Paragraph | NodeValue::Heading(_) | NodeValue::Item(_)
+                if !out.is_empty() && !out.ends_with('\n') =>
+            {
+                out.push('\n');
+            }
+            NodeValue::Text(text) => out.push_str(text),
+            NodeValue::Code(code) => out.push_str(&code.literal),
+            NodeValue::CodeBlock(block) => {
+                if !out.is_empty() && !out.ends_with('\n') {
+                    out.push('\n');
+                }
+                out.push_str(&block.literal);
+            }
+            NodeValue::SoftBreak | NodeValue::LineBreak => out.push('\n'),
+            _ => {}
+        }
+    }
+    out.trim_end().to_string()
+}
+
 pub fn html_to_markdown(html: &str) -> String {
-    parse_html(html)
+    let _span = tracing::debug_span!("render").entered();
+    task_list_bullets(&parse_html(&mark_checklist_items(html)))
+}
+
+/// Whether Notes HTML embeds something (an image or other attachment) that Markdown can't carry.
+pub fn html_has_attachments(html: &str) -> bool {
+    let html = html.to_ascii_lowercase();
+    ["<img", "<object", "<embed", "<video", "<audio"]
+        .iter()
+        .any(|tag| html.contains(tag))
+}
+
+/// Inserts `[ ] ` / `[x] ` text markers for checklist items so they survive HTML→Markdown.
+///
+/// Handles `<ul class="checklist">` lists (checked items carry a `checked` class) and
+/// `<input type="checkbox">` elements.
+fn mark_checklist_items(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut lists: Vec<bool> = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start..=start + len];
+        rest = &rest[start + len + 1..];
+
+        let lower = tag.to_ascii_lowercase();
+        let name = lower[1..]
+            .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .next()
+            .unwrap_or("");
+        let closing = lower.starts_with("</");
+        match name {
+            "ul" | "ol" if !closing => lists.push(html_class(&lower).contains("checklist")),
+            "" if closing && (lower.starts_with("</ul") || lower.starts_with("</ol")) => {
+                lists.pop();
+            }
+            "li" if lists.last() == Some(&true) => {
+                out.push_str(tag);
+                let class = html_class(&lower);
+                let checked = class
+                    .split_whitespace()
+                    .any(|c| c == "checked" || c == "done");
+                out.push_str(if checked { "[x] " } else { "[ ] " });
+                continue;
+            }
+            "input" if lower.contains("checkbox") => {
+                let checked = lower.contains("checked");
+                // A checklist `<li>` already got a marker; let the checkbox state win.
+                for marker in ["[ ] ", "[x] "] {
+                    if out.ends_with(marker) {
+                        out.truncate(out.len() - marker.len());
+                    }
+                }
+                out.push_str(if checked { "[x] " } else { "[ ] " });
+                continue;
+            }
+            _ => {}
+        }
+        out.push_str(tag);
+    }
+    out.push_str(rest);
+    out
+}
+
+fn html_class(tag: &str) -> &str {
+    let Some(i) = tag.find("class=") else {
+        return "";
+    };
+    let value = &tag[i + "class=".len()..];
+    let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+        return value.split_whitespace().next().unwrap_or("");
+    };
+    let value = &value[1..];
+    &value[..value.find(quote).unwrap_or(value.len())]
+}
+
+/// Normalizes `* [ ] item` bullets to GitHub-style `- [ ] item` task list items.
+fn task_list_bullets(md: &str) -> String {
+    md.lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            let indent = &line[..line.len() - trimmed.len()];
+            match trimmed
+                .strip_prefix("* ")
+                .or_else(|| trimmed.strip_prefix("+ "))
+            {
+                Some(item) if item.starts_with("[ ] ") || item.starts_with("[x] ") => {
+                    format!("{indent}- {item}")
+                }
+                _ => line.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 pub fn render_markdown(markdown: &str) -> String {
@@ -32,12 +215,62 @@ pub fn text_to_html(text: &str) -> String {
 }
 
 pub fn markdown_to_html(markdown: &str) -> String {
+    markdown_to_html_dialect(markdown, MarkdownDialect::Commonmark)
+}
+
+pub fn markdown_to_html_dialect(markdown: &str, dialect: MarkdownDialect) -> String {
+    let _span = tracing::debug_span!("render").entered();
     // Keep it simple and reliable: render markdown to HTML and wrap in a container.
-    let html = comrak::markdown_to_html(markdown, &comrak::Options::default());
-    format!("<div>{}</div>", html)
+    let mut options = comrak::Options::default();
+    // `- [ ]` items become Notes checklists in every dialect.
+    options.extension.tasklist = true;
+    if dialect == MarkdownDialect::Gfm {
+        options.extension.table = true;
+        options.extension.strikethrough = true;
+        options.extension.autolink = true;
+    }
+    let html = comrak::markdown_to_html(markdown, &options);
+    format!("<div>{}</div>", notes_checklists(&html))
+}
+
+/// Rewrites comrak's task lists (disabled checkboxes) to the `<ul class="checklist">` markup
+/// Notes shows as interactive checklists. A list is one when its first item is a task; task
+/// items in any other list keep their `[ ]` / `[x]` as text, since a Notes list can't mix both.
+fn notes_checklists(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    // Whether each open list became a checklist.
+    let mut lists = Vec::new();
+    let mut last = 0;
+    for c in LIST_TAG_OR_TASK_ITEM.captures_iter(html) {
+        let m = c.get(0).expect("whole match");
+        out.push_str(&html[last..m.start()]);
+        last = m.end();
+        match (c.get(1).map(|close| close.as_str()), c.get(2).is_some()) {
+            (Some("/"), _) => {
+                lists.pop();
+                out.push_str(m.as_str());
+            }
+            (Some(_), _) => {
+                let checklist = m.as_str() == "<ul>" && FIRST_TASK_ITEM.is_match(&html[m.end()..]);
+                lists.push(checklist);
+                out.push_str(match checklist {
+                    true => r#"<ul class="checklist">"#,
+                    false => m.as_str(),
+                });
+            }
+            (None, checked) => out.push_str(match (lists.last() == Some(&true), checked) {
+                (true, true) => r#"<li class="checked">"#,
+                (true, false) => "<li>",
+                (false, true) => "<li>[x] ",
+                (false, false) => "<li>[ ] ",
+            }),
+        }
+    }
+    out.push_str(&html[last..]);
+    out
 }
 
-fn escape_html(s: &str) -> String {
+pub(crate) fn escape_html(s: &str) -> String {
     s.replace('&', "&amp;")
         .replace('<', "&lt;")
         .replace('>', "&gt;")
@@ -49,6 +282,23 @@ fn escape_html(s: &str) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn markdown_to_text_drops_markup() {
+        assert_eq!(
+            markdown_to_text("# Title\n\n**Hello** `x` [link](https://a.b)\n\n- one\n- two"),
+            "Title\nHello x link\none\ntwo"
+        );
+    }
+
+    #[test]
+    fn snippet_skips_the_title_and_truncates() {
+        let text = "Groceries\n\n- milk\n- eggs   and\tbread\n";
+        assert_eq!(snippet(text, "Groceries", 60), "- milk - eggs and bread");
+        assert_eq!(snippet("# Plan\nStep one", "Plan", 60), "Step one");
+        assert_eq!(snippet(text, "Groceries", 10), "- milk -…");
+        assert_eq!(snippet("Groceries", "Groceries", 10), "");
+    }
+
     #[test]
     fn text_to_html_wraps_lines_and_escapes() {
         let html = text_to_html("a<b\nc&d");
@@ -57,6 +307,77 @@ mod tests {
         assert!(html.contains("<div>"));
     }
 
+    #[test]
+    fn head_lines_counts_non_blank_lines() {
+        let md = "one\n\ntwo\nthree\n\nfour";
+        assert_eq!(head_lines(md, 2), "one\n\ntwo");
+        assert_eq!(head_lines(md, 9), md);
+        assert_eq!(head_lines(md, 0), "");
+    }
+
+    #[test]
+    fn word_count_skips_markup() {
+        assert_eq!(word_count("# Title\n\n- [ ] buy milk, eggs\n\n---\n"), 4);
+        assert_eq!(word_count("  "), 0);
+    }
+
+    #[test]
+    fn html_to_markdown_converts_checklists_to_task_lists() {
+        let md = html_to_markdown(
+            "<ul class=\"checklist\"><li class=\"checked\">Milk</li><li>Eggs</li></ul>\
+             <ul><li><input type=\"checkbox\" checked>Bread</li></ul>",
+        );
+        assert_eq!(md, "- [x] Milk\n- [ ] Eggs\n\n- [x] Bread");
+    }
+
+    #[test]
+    fn html_to_markdown_converts_tables() {
+        let md = html_to_markdown(
+            "<table><tbody><tr><td><div>Name</div></td><td><div>Qty</div></td></tr>\
+             <tr><td><div>Milk</div></td><td><div>2</div></td></tr></tbody></table>",
+        );
+        assert!(md.contains("|Name|Qty|"), "{md}");
+        assert!(md.contains("|Milk|"), "{md}");
+    }
+
+    #[test]
+    fn markdown_to_html_gfm_enables_tables() {
+        let md = "| a |\n| --- |\n| b |";
+        assert!(!markdown_to_html(md).contains("<table>"));
+        assert!(markdown_to_html_dialect(md, MarkdownDialect::Gfm).contains("<table>"));
+    }
+
+    #[test]
+    fn task_items_in_a_plain_list_keep_their_markers() {
+        let html = markdown_to_html("- plain\n- [x] done\n- [ ] todo");
+        assert!(!html.contains("checklist"), "{html}");
+        assert!(
+            html.contains("<ul>\n<li>plain</li>\n<li>[x] done</li>\n<li>[ ] todo</li>\n</ul>"),
+            "{html}"
+        );
+
+        let nested = markdown_to_html("- [ ] outer\n  - inner\n  - [x] deep");
+        assert!(nested.contains(r#"<ul class="checklist">"#), "{nested}");
+        assert!(nested.contains("<li>[x] deep</li>"), "{nested}");
+    }
+
+    #[test]
+    fn markdown_task_lists_become_notes_checklists() {
+        let html = markdown_to_html("Shopping:\n\n- [x] Milk\n- [ ] Eggs\n\nThen:\n\n- plain");
+        assert!(
+            html.contains(
+                "<ul class=\"checklist\">\n<li class=\"checked\">Milk</li>\n<li>Eggs</li>\n</ul>"
+            ),
+            "{html}"
+        );
+        assert!(html.contains("<ul>\n<li>plain</li>"), "{html}");
+        assert!(!html.contains("[ ]"), "{html}");
+        assert_eq!(
+            html_to_markdown(&html),
+            "Shopping:\n\n- [x] Milk\n- [ ] Eggs\n\nThen:\n\n* plain"
+        );
+    }
+
     #[test]
     fn html_to_markdown_basic() {
         let md = html_to_markdown("<div>Hello</div>");
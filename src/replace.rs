@@ -0,0 +1,110 @@
+use crate::model::NoteSummary;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// One body change planned by `notes replace`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlannedReplace {
+    pub id: String,
+    pub title: String,
+    pub replacements: usize,
+    /// Unified diff of the note's Markdown.
+    pub diff: String,
+    #[serde(skip)]
+    pub new_markdown: String,
+}
+
+/// A note `notes replace` would change but leaves alone, because storing its Markdown back as
+/// HTML would lose content.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SkippedReplace {
+    pub id: String,
+    pub title: String,
+    pub reason: &'static str,
+}
+
+/// Replaces every match of `pattern` in each note's Markdown body (`bodies`, by note id) with
+/// `template`, where `$1`, `${1}` and `${name}` refer to capture groups.
+///
+/// Notes without a body in `bodies` or whose body would not change are skipped.
+pub fn plan(
+    notes: &[NoteSummary],
+    bodies: &HashMap<String, String>,
+    pattern: &Regex,
+    template: &str,
+) -> Vec<PlannedReplace> {
+    let mut out = Vec::new();
+    for n in notes {
+        let Some(markdown) = bodies.get(&n.id) else {
+            continue;
+        };
+        let replacements = pattern.find_iter(markdown).count();
+        if replacements == 0 {
+            continue;
+        }
+        let new_markdown = pattern.replace_all(markdown, template).into_owned();
+        if new_markdown == *markdown {
+            continue;
+        }
+        let diff = similar::TextDiff::from_lines(markdown.as_str(), new_markdown.as_str())
+            .unified_diff()
+            .context_radius(1)
+            .header(&n.title, &n.title)
+            .to_string();
+        out.push(PlannedReplace {
+            id: n.id.clone(),
+            title: n.title.clone(),
+            replacements,
+            diff,
+            new_markdown,
+        });
+    }
+    out.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.id.cmp(&b.id)));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(id: &str, title: &str) -> NoteSummary {
+        NoteSummary {
+            id: id.to_string(),
+            title: title.to_string(),
+            folder_id: "f".to_string(),
+            uuid: None,
+            created_at: None,
+            modified_at: None,
+            pinned: false,
+            locked: false,
+        }
+    }
+
+    #[test]
+    fn plan_replaces_every_match_and_diffs_the_markdown() {
+        let notes = [
+            note("1", "Trip"),
+            note("2", "Groceries"),
+            note("3", "Locked"),
+        ];
+        let bodies = HashMap::from([
+            (
+                "1".to_string(),
+                "# Trip\n\nACME flight\nACME hotel\n".to_string(),
+            ),
+            ("2".to_string(), "# Groceries\n\nmilk\n".to_string()),
+        ]);
+        let pattern = Regex::new(r"ACME (\w+)").unwrap();
+        let planned = plan(&notes, &bodies, &pattern, "Globex $1");
+        assert_eq!(planned.len(), 1);
+        let p = &planned[0];
+        assert_eq!((p.id.as_str(), p.replacements), ("1", 2));
+        assert_eq!(p.new_markdown, "# Trip\n\nGlobex flight\nGlobex hotel\n");
+        assert!(p.diff.contains("-ACME flight\n"), "{}", p.diff);
+        assert!(p.diff.contains("+Globex hotel\n"), "{}", p.diff);
+
+        let unchanged = Regex::new("milk").unwrap();
+        assert!(plan(&notes, &bodies, &unchanged, "$0").is_empty());
+    }
+}
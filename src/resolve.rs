@@ -0,0 +1,135 @@
+//! Turning what people type for a note (an id, a UUID or id prefix, or part of its title) into
+//! the note's id.
+
+use crate::error::{NotesError, Result};
+use crate::model::NoteSummary;
+use crate::transport::NotesBackend;
+
+/// How many candidates an "ambiguous" error lists.
+const MAX_SUGGESTIONS: usize = 10;
+
+/// Whether `reference` is a complete note id that can go to the backend without a listing.
+fn is_full_id(reference: &str) -> bool {
+    let is_uuid = reference.len() == 36
+        && reference.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        });
+    is_uuid || (reference.starts_with("x-coredata://") && reference.contains("/ICNote/p"))
+}
+
+/// The id of the note in `account` that `reference` names: a full id or UUID, an unambiguous
+/// prefix of one, or a unique (case-insensitive) title or title substring.
+pub fn resolve_note(backend: &dyn NotesBackend, account: &str, reference: &str) -> Result<String> {
+    if is_full_id(reference) {
+        return Ok(reference.to_string());
+    }
+    match match_note(&backend.list_notes(account)?, reference) {
+        // Exact ids from another account still work; otherwise the backend says what's missing.
+        Err(NotesError::NotFound(_)) => backend.get_note(reference).map(|_| reference.to_string()),
+        found => found,
+    }
+}
+
+/// [`resolve_note`] over an already loaded listing.
+pub fn match_note(notes: &[NoteSummary], reference: &str) -> Result<String> {
+    let reference = reference.trim();
+    if reference.is_empty() {
+        return Err(NotesError::NotFound("empty note reference".to_string()));
+    }
+    fn ids(n: &NoteSummary) -> [Option<&str>; 2] {
+        [Some(n.id.as_str()), n.uuid.as_deref()]
+    }
+    if let Some(n) = notes.iter().find(|n| ids(n).contains(&Some(reference))) {
+        return Ok(n.id.clone());
+    }
+    let by_prefix: Vec<&NoteSummary> = notes
+        .iter()
+        .filter(|n| ids(n).iter().flatten().any(|id| id.starts_with(reference)))
+        .collect();
+    let lower = reference.to_lowercase();
+    let by_exact_title: Vec<&NoteSummary> = notes
+        .iter()
+        .filter(|n| n.title.to_lowercase() == lower)
+        .collect();
+    let by_title: Vec<&NoteSummary> = notes
+        .iter()
+        .filter(|n| n.title.to_lowercase().contains(&lower))
+        .collect();
+    let candidates = [by_prefix, by_exact_title, by_title]
+        .into_iter()
+        .find(|matches| !matches.is_empty())
+        .ok_or_else(|| {
+            NotesError::NotFound(format!(
+                "no note has the id, id prefix or title {reference:?}"
+            ))
+        })?;
+    match candidates.as_slice() {
+        [only] => Ok(only.id.clone()),
+        many => {
+            let mut msg = format!("ambiguous note {reference:?}, did you mean:");
+            for n in many.iter().take(MAX_SUGGESTIONS) {
+                msg.push_str(&format!("\n  {}  {}", n.display_id(), n.title));
+            }
+            if many.len() > MAX_SUGGESTIONS {
+                msg.push_str(&format!("\n  … and {} more", many.len() - MAX_SUGGESTIONS));
+            }
+            Err(NotesError::Ambiguous(msg))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(id: &str, uuid: Option<&str>, title: &str) -> NoteSummary {
+        NoteSummary {
+            id: id.to_string(),
+            title: title.to_string(),
+            folder_id: "f".to_string(),
+            uuid: uuid.map(str::to_string),
+            created_at: None,
+            modified_at: None,
+            pinned: false,
+            locked: false,
+        }
+    }
+
+    #[test]
+    fn references_match_ids_prefixes_then_titles() {
+        let notes = [
+            note("x-coredata://S/ICNote/p1", Some("AB12"), "Meeting notes"),
+            note("x-coredata://S/ICNote/p2", Some("CD34"), "Meeting"),
+            note("x-coredata://S/ICNote/p3", Some("AB99"), "Groceries"),
+        ];
+        let id = |r| match_note(&notes, r).unwrap();
+        assert_eq!(id("CD34"), "x-coredata://S/ICNote/p2");
+        assert_eq!(id("AB1"), "x-coredata://S/ICNote/p1");
+        assert_eq!(id("groc"), "x-coredata://S/ICNote/p3");
+        assert_eq!(
+            id("meeting"),
+            "x-coredata://S/ICNote/p2",
+            "exact titles win"
+        );
+
+        let err = match_note(&notes, "AB").unwrap_err();
+        assert!(matches!(err, NotesError::Ambiguous(_)));
+        assert_eq!(
+            err.to_string(),
+            "ambiguous note \"AB\", did you mean:\n  AB12  Meeting notes\n  AB99  Groceries"
+        );
+        assert!(matches!(
+            match_note(&notes, "nothing"),
+            Err(NotesError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn full_ids_skip_the_listing() {
+        assert!(is_full_id("x-coredata://S/ICNote/p12"));
+        assert!(is_full_id("3F2504E0-4F89-11D3-9A0C-0305E82C3301"));
+        assert!(!is_full_id("3F2504E0"));
+        assert!(!is_full_id("x-coredata://S"));
+    }
+}
@@ -0,0 +1,413 @@
+//! `apple-notes serve --stdio`: newline-delimited JSON-RPC 2.0 over stdin/stdout, one method per
+//! [`NotesBackend`] method, so editor plugins can keep one process (and its warm `osascript`/DB
+//! state) around instead of starting the CLI for every call.
+
+use crate::error::NotesError;
+use crate::transport::NotesBackend;
+use anyhow::Context;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::io::{BufRead, Write};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Backend failures; `error.data.kind` says which [`NotesError`] it was.
+const BACKEND_ERROR: i64 = -32000;
+/// A mutating method called in read-only mode.
+const READ_ONLY: i64 = -32001;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    /// Absent for notifications, which get no response.
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// A failed call, as a JSON-RPC error object.
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+    kind: Option<&'static str>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            kind: None,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let mut error = json!({ "code": self.code, "message": self.message });
+        if let Some(kind) = self.kind {
+            error["data"] = json!({ "kind": kind });
+        }
+        error
+    }
+}
+
+impl From<NotesError> for RpcError {
+    fn from(e: NotesError) -> Self {
+        Self {
+            code: BACKEND_ERROR,
+            message: e.to_string(),
+            kind: Some(e.kind()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Account {
+    account: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct InFolder {
+    account: String,
+    folder_path: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MaybeInFolder {
+    account: String,
+    #[serde(default)]
+    folder_path: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Id {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AttachFile {
+    id: String,
+    path: std::path::PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateNote {
+    account: String,
+    folder_path: Vec<String>,
+    title: String,
+    body_html: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SetTitle {
+    id: String,
+    title: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SetBody {
+    id: String,
+    body_html: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MoveNote {
+    id: String,
+    account: String,
+    folder_path: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateFolder {
+    account: String,
+    parent_path: Vec<String>,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RenameFolder {
+    account: String,
+    folder_path: Vec<String>,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MoveFolder {
+    account: String,
+    folder_path: Vec<String>,
+    parent_path: Vec<String>,
+}
+
+fn params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn ok<T: serde::Serialize>(value: crate::error::Result<T>) -> Result<Value, RpcError> {
+    let value = value?;
+    serde_json::to_value(value).map_err(|e| RpcError::new(BACKEND_ERROR, e.to_string()))
+}
+
+/// Methods that change notes or folders, refused in read-only mode.
+const MUTATIONS: &[&str] = &[
+    "create_note_html",
+    "set_note_title",
+    "set_note_body_html",
+    "append_note_body_html",
+    "delete_note",
+    "purge_note",
+    "move_note",
+    "attach_file",
+    "create_folder",
+    "rename_folder",
+    "move_folder",
+    "delete_folder",
+];
+
+/// Runs one call. Parameters are objects named like the trait method's arguments.
+fn call(
+    backend: &dyn NotesBackend,
+    read_only: bool,
+    method: &str,
+    p: Value,
+) -> Result<Value, RpcError> {
+    if read_only && MUTATIONS.contains(&method) {
+        return Err(RpcError::new(
+            READ_ONLY,
+            format!("`{method}` modifies notes; refusing in read-only mode"),
+        ));
+    }
+    match method {
+        "list_accounts" => ok(backend.list_accounts()),
+        "list_folders" => ok(backend.list_folders(&params::<Account>(p)?.account)),
+        "list_notes" => ok(backend.list_notes(&params::<Account>(p)?.account)),
+        "list_notes_in_folder" => {
+            let p: InFolder = params(p)?;
+            ok(backend.list_notes_in_folder(&p.account, &p.folder_path))
+        }
+        // Streamed server-side; the client gets the whole list.
+        "stream_note_summaries" => {
+            let p: MaybeInFolder = params(p)?;
+            let mut notes = Vec::new();
+            let streamed =
+                backend.stream_note_summaries(&p.account, p.folder_path.as_deref(), &mut |n| {
+                    notes.push(n)
+                });
+            ok(streamed.map(|()| notes))
+        }
+        "list_deleted_notes" => ok(backend.list_deleted_notes(&params::<Account>(p)?.account)),
+        "is_note_deleted" => ok(backend.is_note_deleted(&params::<Id>(p)?.id)),
+        "get_note" => ok(backend.get_note(&params::<Id>(p)?.id)),
+        "create_note_html" => {
+            let p: CreateNote = params(p)?;
+            ok(backend
+                .create_note_html(&p.account, &p.folder_path, &p.title, &p.body_html)
+                .map(|id| json!({ "id": id })))
+        }
+        "set_note_title" => {
+            let p: SetTitle = params(p)?;
+            ok(backend.set_note_title(&p.id, &p.title))
+        }
+        "set_note_body_html" => {
+            let p: SetBody = params(p)?;
+            ok(backend.set_note_body_html(&p.id, &p.body_html))
+        }
+        "append_note_body_html" => {
+            let p: SetBody = params(p)?;
+            ok(backend.append_note_body_html(&p.id, &p.body_html))
+        }
+        "delete_note" => ok(backend.trash_note(&params::<Id>(p)?.id)),
+        "purge_note" => ok(backend.purge_note(&params::<Id>(p)?.id)),
+        "move_note" => {
+            let p: MoveNote = params(p)?;
+            ok(backend.move_note(&p.id, &p.account, &p.folder_path))
+        }
+        "open_note" => ok(backend.open_note(&params::<Id>(p)?.id)),
+        "attach_file" => {
+            let p: AttachFile = params(p)?;
+            ok(backend.attach_file(&p.id, &p.path))
+        }
+        "create_folder" => {
+            let p: CreateFolder = params(p)?;
+            ok(backend
+                .create_folder(&p.account, &p.parent_path, &p.name)
+                .map(|id| json!({ "id": id })))
+        }
+        "rename_folder" => {
+            let p: RenameFolder = params(p)?;
+            ok(backend.rename_folder(&p.account, &p.folder_path, &p.name))
+        }
+        "move_folder" => {
+            let p: MoveFolder = params(p)?;
+            ok(backend.move_folder(&p.account, &p.folder_path, &p.parent_path))
+        }
+        "delete_folder" => {
+            let p: InFolder = params(p)?;
+            ok(backend.delete_folder(&p.account, &p.folder_path))
+        }
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("unknown method {method:?}"),
+        )),
+    }
+}
+
+/// The response to one request line, or `None` for notifications.
+fn handle_line(backend: &dyn NotesBackend, read_only: bool, line: &str) -> Option<Value> {
+    let value: Value = match serde_json::from_str(line) {
+        Ok(v) => v,
+        Err(e) => {
+            return Some(response(
+                Value::Null,
+                Err(RpcError::new(PARSE_ERROR, e.to_string())),
+            ));
+        }
+    };
+    let request: Request = match serde_json::from_value(value) {
+        Ok(r) => r,
+        Err(e) => {
+            return Some(response(
+                Value::Null,
+                Err(RpcError::new(INVALID_REQUEST, e.to_string())),
+            ));
+        }
+    };
+    let result = call(backend, read_only, &request.method, request.params);
+    request.id.map(|id| response(id, result))
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => json!({ "jsonrpc": "2.0", "id": id, "error": e.to_json() }),
+    }
+}
+
+/// Answers requests from `input`, one per line, until it ends. Calls run one at a time, in order.
+pub fn serve(
+    backend: &dyn NotesBackend,
+    read_only: bool,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> anyhow::Result<()> {
+    for line in input.lines() {
+        let line = line.context("read request")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle_line(backend, read_only, &line) {
+            writeln!(output, "{response}").context("write response")?;
+            output.flush().context("write response")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::FixtureBackend;
+
+    fn backend() -> FixtureBackend {
+        FixtureBackend::from_path(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/basic.json"),
+        )
+        .unwrap()
+    }
+
+    fn session(read_only: bool, requests: &[&str]) -> Vec<Value> {
+        let mut out = Vec::new();
+        serve(
+            &backend(),
+            read_only,
+            requests.join("\n").as_bytes(),
+            &mut out,
+        )
+        .unwrap();
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn calls_map_onto_backend_methods() {
+        let responses = session(
+            false,
+            &[
+                r#"{"jsonrpc":"2.0","id":1,"method":"list_notes_in_folder","params":{"account":"iCloud","folder_path":["Personal","Archive"]}}"#,
+                r#"{"jsonrpc":"2.0","method":"delete_note","params":{"id":"n1"}}"#,
+                r#"{"jsonrpc":"2.0","id":"b","method":"get_note","params":{"id":"n2"}}"#,
+                r#"{"jsonrpc":"2.0","id":3,"method":"create_folder","params":{"account":"iCloud","parent_path":["Personal"],"name":"New"}}"#,
+            ],
+        );
+        assert_eq!(responses.len(), 3, "notifications get no response");
+        let titles: Vec<&str> = responses[0]["result"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|n| n["title"].as_str().unwrap())
+            .collect();
+        assert_eq!(titles, ["Beta", "Gamma"]);
+        assert_eq!(responses[1]["id"], "b");
+        assert_eq!(responses[1]["result"]["title"], "Beta");
+        assert!(responses[2]["result"]["id"].is_string());
+    }
+
+    #[test]
+    fn trash_methods_are_exposed() {
+        let responses = session(
+            false,
+            &[
+                r#"{"jsonrpc":"2.0","id":1,"method":"delete_note","params":{"id":"n1"}}"#,
+                r#"{"jsonrpc":"2.0","id":2,"method":"is_note_deleted","params":{"id":"n1"}}"#,
+                r#"{"jsonrpc":"2.0","id":3,"method":"list_deleted_notes","params":{"account":"iCloud"}}"#,
+                r#"{"jsonrpc":"2.0","id":4,"method":"delete_note","params":{"id":"n1"}}"#,
+                r#"{"jsonrpc":"2.0","id":5,"method":"purge_note","params":{"id":"n1"}}"#,
+                r#"{"jsonrpc":"2.0","id":6,"method":"get_note","params":{"id":"n1"}}"#,
+            ],
+        );
+        assert_eq!(responses[1]["result"], true);
+        assert_eq!(responses[2]["result"][0]["id"], "n1");
+        assert_eq!(responses[3]["error"]["data"]["kind"], "already_deleted");
+        assert!(responses[4]["error"].is_null(), "{}", responses[4]);
+        assert_eq!(responses[5]["error"]["data"]["kind"], "not_found");
+    }
+
+    #[test]
+    fn errors_follow_json_rpc() {
+        let responses = session(
+            true,
+            &[
+                "not json",
+                r#"{"jsonrpc":"2.0","id":1,"method":"nope"}"#,
+                r#"{"jsonrpc":"2.0","id":2,"method":"get_note","params":{}}"#,
+                r#"{"jsonrpc":"2.0","id":3,"method":"get_note","params":{"id":"missing"}}"#,
+                r#"{"jsonrpc":"2.0","id":4,"method":"delete_note","params":{"id":"n1"}}"#,
+                r#"{"jsonrpc":"2.0","id":5,"method":"purge_note","params":{"id":"n1"}}"#,
+                r#"{"jsonrpc":"2.0","id":6,"method":"attach_file","params":{"id":"n1","path":"a.png"}}"#,
+            ],
+        );
+        let codes: Vec<i64> = responses
+            .iter()
+            .map(|r| r["error"]["code"].as_i64().unwrap())
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                PARSE_ERROR,
+                METHOD_NOT_FOUND,
+                INVALID_PARAMS,
+                BACKEND_ERROR,
+                READ_ONLY,
+                READ_ONLY,
+                READ_ONLY
+            ]
+        );
+        assert_eq!(responses[3]["error"]["data"]["kind"], "not_found");
+    }
+}
@@ -0,0 +1,235 @@
+//! `backup schedule`: periodic exports through a launchd user agent.
+
+use anyhow::{Context, anyhow};
+use serde::Serialize;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// launchd label of the scheduled export (also the plist's file name).
+pub const LABEL: &str = "com.javisoto.apple-notes.backup";
+
+fn launchctl_bin() -> OsString {
+    std::env::var_os("APPLE_NOTES_LAUNCHCTL_BIN").unwrap_or_else(|| OsString::from("launchctl"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Interval {
+    /// Every hour, at the minute of `--at`.
+    Hourly,
+    /// Every day at `--at`.
+    Daily,
+    /// Every Sunday at `--at`.
+    Weekly,
+}
+
+/// When the export runs: an [`Interval`] plus a wall-clock time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct When {
+    pub interval: Interval,
+    pub hour: u8,
+    pub minute: u8,
+}
+
+/// Parses `--at` (`HH:MM`, 24-hour).
+pub fn parse_time_of_day(s: &str) -> Result<(u8, u8), String> {
+    let (h, m) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected HH:MM, got {s:?}"))?;
+    match (h.parse::<u8>(), m.parse::<u8>()) {
+        (Ok(h), Ok(m)) if h < 24 && m < 60 => Ok((h, m)),
+        _ => Err(format!("expected HH:MM (00:00–23:59), got {s:?}")),
+    }
+}
+
+/// `~/Library/LaunchAgents/<LABEL>.plist`.
+pub fn plist_path() -> anyhow::Result<PathBuf> {
+    let home = std::env::var_os("HOME").ok_or_else(|| anyhow!("HOME is not set"))?;
+    Ok(PathBuf::from(home)
+        .join("Library/LaunchAgents")
+        .join(format!("{LABEL}.plist")))
+}
+
+/// `~/Library/Logs/apple-notes-backup.log`, where the scheduled export's output goes.
+pub fn log_path() -> anyhow::Result<PathBuf> {
+    let home = std::env::var_os("HOME").ok_or_else(|| anyhow!("HOME is not set"))?;
+    Ok(PathBuf::from(home).join("Library/Logs/apple-notes-backup.log"))
+}
+
+/// The launch agent plist running `program` (an argv) on `when`'s schedule.
+pub fn render_plist(program: &[String], when: When, log: &Path) -> String {
+    let mut calendar = String::new();
+    if when.interval == Interval::Weekly {
+        calendar.push_str("        <key>Weekday</key>\n        <integer>0</integer>\n");
+    }
+    if when.interval != Interval::Hourly {
+        calendar.push_str(&format!(
+            "        <key>Hour</key>\n        <integer>{}</integer>\n",
+            when.hour
+        ));
+    }
+    calendar.push_str(&format!(
+        "        <key>Minute</key>\n        <integer>{}</integer>\n",
+        when.minute
+    ));
+    let arguments: String = program
+        .iter()
+        .map(|arg| format!("        <string>{}</string>\n", xml_escape(arg)))
+        .collect();
+    let log = xml_escape(&log.to_string_lossy());
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{LABEL}</string>
+    <key>ProgramArguments</key>
+    <array>
+{arguments}    </array>
+    <key>StartCalendarInterval</key>
+    <dict>
+{calendar}    </dict>
+    <key>StandardOutPath</key>
+    <string>{log}</string>
+    <key>StandardErrorPath</key>
+    <string>{log}</string>
+</dict>
+</plist>
+"#
+    )
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn launchctl(args: &[&str]) -> anyhow::Result<std::process::Output> {
+    Command::new(launchctl_bin())
+        .args(args)
+        .output()
+        .context("failed to run launchctl (are you on macOS?)")
+}
+
+/// Writes the plist and (re)loads it with launchd. Returns the plist path.
+pub fn install(program: &[String], when: When) -> anyhow::Result<PathBuf> {
+    let path = plist_path()?;
+    let log = log_path()?;
+    for dir in [path.parent(), log.parent()].into_iter().flatten() {
+        std::fs::create_dir_all(dir).with_context(|| format!("create {dir:?}"))?;
+    }
+    let path_str = path.to_string_lossy();
+    if path.exists() {
+        // Replacing an existing schedule: launchd keeps the old definition until it's unloaded.
+        let _ = launchctl(&["unload", &path_str]);
+    }
+    std::fs::write(&path, render_plist(program, when, &log))
+        .with_context(|| format!("write {path:?}"))?;
+    let out = launchctl(&["load", "-w", &path_str])?;
+    if !out.status.success() {
+        return Err(anyhow!(
+            "launchctl load failed ({}): {}",
+            out.status,
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+    Ok(path)
+}
+
+/// Unloads and deletes the plist. Returns whether there was one.
+pub fn remove() -> anyhow::Result<bool> {
+    let path = plist_path()?;
+    if !path.exists() {
+        return Ok(false);
+    }
+    // Already unloaded is fine; the plist goes either way.
+    let _ = launchctl(&["unload", "-w", &path.to_string_lossy()]);
+    std::fs::remove_file(&path).with_context(|| format!("remove {path:?}"))?;
+    Ok(true)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Status {
+    pub plist: PathBuf,
+    pub installed: bool,
+    pub loaded: bool,
+    /// Exit status of the last scheduled run, if launchd has one.
+    pub last_exit_status: Option<i64>,
+    pub log: PathBuf,
+}
+
+pub fn status() -> anyhow::Result<Status> {
+    let plist = plist_path()?;
+    let out = launchctl(&["list", LABEL])?;
+    let loaded = out.status.success();
+    let last_exit_status = loaded
+        .then(|| last_exit_status(&String::from_utf8_lossy(&out.stdout)))
+        .flatten();
+    Ok(Status {
+        installed: plist.exists(),
+        plist,
+        loaded,
+        last_exit_status,
+        log: log_path()?,
+    })
+}
+
+/// `"LastExitStatus" = 0;` from `launchctl list <label>`.
+fn last_exit_status(listing: &str) -> Option<i64> {
+    listing.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix("\"LastExitStatus\" = ")?
+            .trim_end_matches(';')
+            .parse()
+            .ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plist_runs_the_export_on_the_calendar() {
+        let program = ["/usr/local/bin/apple-notes", "export", "--out", "/b&u/<x>"]
+            .map(String::from)
+            .to_vec();
+        let when = When {
+            interval: Interval::Weekly,
+            hour: 3,
+            minute: 30,
+        };
+        let plist = render_plist(&program, when, Path::new("/Users/me/backup.log"));
+        assert!(plist.contains("<string>com.javisoto.apple-notes.backup</string>"));
+        assert!(
+            plist.contains("<string>/b&amp;u/&lt;x&gt;</string>"),
+            "{plist}"
+        );
+        assert!(plist.contains("<key>Weekday</key>\n        <integer>0</integer>"));
+        assert!(plist.contains("<key>Hour</key>\n        <integer>3</integer>"));
+        assert!(plist.contains("<key>Minute</key>\n        <integer>30</integer>"));
+
+        let hourly = render_plist(
+            &program,
+            When {
+                interval: Interval::Hourly,
+                ..when
+            },
+            Path::new("/tmp/log"),
+        );
+        assert!(!hourly.contains("<key>Hour</key>"));
+        assert!(!hourly.contains("<key>Weekday</key>"));
+    }
+
+    #[test]
+    fn time_of_day_and_exit_status_parse() {
+        assert_eq!(parse_time_of_day("03:05"), Ok((3, 5)));
+        assert!(parse_time_of_day("24:00").is_err());
+        assert!(parse_time_of_day("3pm").is_err());
+        let listing = "{\n\t\"Label\" = \"x\";\n\t\"LastExitStatus\" = 256;\n};\n";
+        assert_eq!(last_exit_status(listing), Some(256));
+        assert_eq!(last_exit_status("{}"), None);
+    }
+}
@@ -0,0 +1,233 @@
+use crate::backup::{self, FolderIndex, NoteFilter};
+use crate::cancel::CancelToken;
+use crate::model::Note;
+use crate::progress;
+use crate::render;
+use crate::transport::NotesBackend;
+use anyhow::{Context, anyhow};
+use std::collections::HashMap;
+use std::path::Path;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+/// Note headings are `###`; headings inside a note body are pushed down by this many levels.
+const BODY_HEADING_SHIFT: usize = 3;
+
+/// Writes every note in `account` into one Markdown document at `out`: a table of contents,
+/// a heading per folder, and each note with a metadata line. `folder` limits it to that folder
+/// and its subfolders, `filter` to the notes it matches. Returns the number of notes written.
+pub fn export(
+    backend: &dyn NotesBackend,
+    account: &str,
+    out: &Path,
+    folder: Option<&[String]>,
+    filter: &NoteFilter,
+    cancel: &CancelToken,
+) -> anyhow::Result<usize> {
+    let notes = load_notes(backend, account, folder, filter, cancel)?;
+    let count = notes.len();
+    let doc = render_document(account, &notes);
+    if let Some(parent) = out.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).with_context(|| format!("create {parent:?}"))?;
+    }
+    std::fs::write(out, doc).with_context(|| format!("write {out:?}"))?;
+    Ok(count)
+}
+
+/// Fetches every note in `account` (or under `folder`) that matches `filter`, with its folder
+/// path, sorted by folder and then title.
+pub(crate) fn load_notes(
+    backend: &dyn NotesBackend,
+    account: &str,
+    folder: Option<&[String]>,
+    filter: &NoteFilter,
+    cancel: &CancelToken,
+) -> anyhow::Result<Vec<(Vec<String>, Note)>> {
+    let spinner = progress::spinner("Loading folders…");
+    let mut folders = backend.list_folders(account)?;
+    if let Some(root) = folder {
+        folders = backup::folder_subtree(folders, root)?;
+    }
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+    let folder_index = FolderIndex::new(&folders)?;
+
+    let spinner = progress::spinner("Indexing notes…");
+    let mut summaries = Vec::new();
+    backend.stream_note_summaries_cancellable(account, None, cancel, &mut |n| {
+        if folder.is_none() || folder_index.contains(&n.folder_id) {
+            summaries.push(n)
+        }
+    })?;
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+    backup::skip_locked(&mut summaries);
+
+    let pb = progress::bar(summaries.len() as u64, "Exporting notes…");
+    let mut notes = Vec::with_capacity(summaries.len());
+    for n in summaries {
+        cancel.check()?;
+        let folder_path = folder_index
+            .folder_path(&n.folder_id)
+            .ok_or_else(|| anyhow!("note {} references unknown folder id {}", n.id, n.folder_id))?;
+        let note = backend.get_note(&n.id)?;
+        if filter.matches_dates(note.created_at, note.modified_at)
+            && (filter.query.is_none()
+                || filter.matches_text(&note.title, &render::note_to_markdown(&note)))
+        {
+            notes.push((folder_path, note));
+        }
+        if let Some(pb) = &pb {
+            pb.inc(1);
+        }
+    }
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
+    }
+
+    notes.sort_by(|(a_path, a), (b_path, b)| {
+        a_path
+            .cmp(b_path)
+            .then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    Ok(notes)
+}
+
+fn render_document(account: &str, notes: &[(Vec<String>, Note)]) -> String {
+    let mut slugs = Slugger::default();
+    slugs.slug(account);
+    slugs.slug("Contents");
+
+    let mut toc = String::new();
+    let mut body = String::new();
+    let mut prev_path: &[String] = &[];
+    for (path, note) in notes {
+        if path != prev_path {
+            let common = path
+                .iter()
+                .zip(prev_path)
+                .take_while(|(a, b)| a == b)
+                .count();
+            for (depth, name) in path.iter().enumerate().skip(common) {
+                toc.push_str(&format!("{}- **{name}**\n", "  ".repeat(depth)));
+            }
+            let heading = path.join(" > ");
+            slugs.slug(&heading);
+            body.push_str(&format!("## {heading}\n\n"));
+            prev_path = path;
+        }
+
+        let title = if note.title.trim().is_empty() {
+            "Untitled"
+        } else {
+            note.title.trim()
+        };
+        let anchor = slugs.slug(title);
+        toc.push_str(&format!(
+            "{}- [{title}](#{anchor})\n",
+            "  ".repeat(path.len())
+        ));
+
+        body.push_str(&format!("### {title}\n\n"));
+        body.push_str(&format!(
+            "> Id: `{}` · Created: {} · Modified: {}\n\n",
+            note.id,
+            rfc3339(note.created_at),
+            rfc3339(note.modified_at)
+        ));
+        let md = demote_headings(render::html_to_markdown(&note.body_html).trim());
+        for line in md.lines() {
+            if let Some(text) = heading_text(line) {
+                slugs.slug(text);
+            }
+        }
+        if !md.is_empty() {
+            body.push_str(&md);
+            body.push_str("\n\n");
+        }
+    }
+
+    format!("# {account}\n\n## Contents\n\n{toc}\n{}\n", body.trim_end())
+}
+
+fn rfc3339(t: OffsetDateTime) -> String {
+    t.format(&Rfc3339).unwrap_or_else(|_| t.to_string())
+}
+
+/// Pushes ATX headings down [`BODY_HEADING_SHIFT`] levels (capped at `######`), leaving fenced
+/// code blocks alone, so note bodies nest under their note heading.
+fn demote_headings(md: &str) -> String {
+    let mut in_fence = false;
+    md.lines()
+        .map(|line| {
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+            }
+            if in_fence || heading_text(line).is_none() {
+                return line.to_string();
+            }
+            let level = line.chars().take_while(|c| *c == '#').count();
+            let new_level = (level + BODY_HEADING_SHIFT).min(6);
+            format!("{}{}", "#".repeat(new_level), &line[level..])
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn heading_text(line: &str) -> Option<&str> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    line[level..].strip_prefix(' ').map(str::trim)
+}
+
+/// GitHub-style heading anchors, with `-1`, `-2`… suffixes for repeats.
+#[derive(Debug, Default)]
+struct Slugger {
+    seen: HashMap<String, usize>,
+}
+
+impl Slugger {
+    fn slug(&mut self, heading: &str) -> String {
+        let base: String = heading
+            .trim()
+            .to_lowercase()
+            .chars()
+            .filter_map(|c| match c {
+                ' ' => Some('-'),
+                c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+                _ => None,
+            })
+            .collect();
+        let count = self.seen.entry(base.clone()).or_default();
+        let slug = if *count == 0 {
+            base.clone()
+        } else {
+            format!("{base}-{count}")
+        };
+        *count += 1;
+        slug
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demote_headings_skips_code_and_dedupes_anchors() {
+        assert_eq!(
+            demote_headings("# A\n```\n# not a heading\n```\n##### Deep\n#hashtag"),
+            "#### A\n```\n# not a heading\n```\n###### Deep\n#hashtag"
+        );
+
+        let mut slugs = Slugger::default();
+        assert_eq!(slugs.slug("Hello, World!"), "hello-world");
+        assert_eq!(slugs.slug("hello world"), "hello-world-1");
+        assert_eq!(slugs.slug("Personal > Archive"), "personal--archive");
+    }
+}
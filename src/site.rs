@@ -0,0 +1,156 @@
+use crate::backup;
+use crate::html_export::NOTE_HTML;
+use crate::render::escape_html;
+use anyhow::Context;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Landing page of `export --format site`.
+pub const INDEX_HTML: &str = "index.html";
+/// Search index of `export --format site` (also embedded in `index.html` so the site works from
+/// `file://` URLs, where browsers refuse to `fetch` local files).
+pub const SEARCH_INDEX_JSON: &str = "search-index.json";
+
+#[derive(Debug, Clone, Serialize)]
+struct IndexEntry {
+    title: String,
+    folder: String,
+    href: String,
+    text: String,
+}
+
+/// Writes `index.html` and `search-index.json` for the note pages already exported under `root`.
+pub fn write_index(root: &Path) -> anyhow::Result<()> {
+    let mut entries = Vec::new();
+    let mut account = String::new();
+    for note in backup::scan_export(root)? {
+        let rel = note.dir.strip_prefix(root).unwrap_or(&note.dir);
+        let href = rel
+            .components()
+            .map(|c| url_encode(&c.as_os_str().to_string_lossy()))
+            .chain([NOTE_HTML.to_string()])
+            .collect::<Vec<_>>()
+            .join("/");
+        let md_path = note.dir.join("contents.md");
+        let text =
+            std::fs::read_to_string(&md_path).with_context(|| format!("read {md_path:?}"))?;
+        account = note.metadata.account.clone();
+        entries.push(IndexEntry {
+            title: note.metadata.title,
+            folder: note.metadata.folder_path.join(" > "),
+            href,
+            text,
+        });
+    }
+    entries.sort_by(|a, b| {
+        a.folder
+            .cmp(&b.folder)
+            .then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+    });
+
+    let json = serde_json::to_string(&entries)?;
+    let path = root.join(SEARCH_INDEX_JSON);
+    std::fs::write(&path, &json).with_context(|| format!("write {path:?}"))?;
+
+    let path = root.join(INDEX_HTML);
+    std::fs::write(&path, index_page(&account, &entries, &json))
+        .with_context(|| format!("write {path:?}"))
+}
+
+fn index_page(account: &str, entries: &[IndexEntry], json: &str) -> String {
+    let mut by_folder: BTreeMap<&str, Vec<&IndexEntry>> = BTreeMap::new();
+    for e in entries {
+        by_folder.entry(&e.folder).or_default().push(e);
+    }
+    let mut listing = String::new();
+    for (folder, notes) in by_folder {
+        listing.push_str(&format!("<h2>{}</h2>\n<ul>\n", escape_html(folder)));
+        for n in notes {
+            listing.push_str(&format!(
+                "<li><a href=\"{}\">{}</a></li>\n",
+                n.href,
+                escape_html(&n.title)
+            ));
+        }
+        listing.push_str("</ul>\n");
+    }
+
+    let title = escape_html(if account.is_empty() { "Notes" } else { account });
+    // `</` can't appear inside a <script> element; JSON allows escaping the slash.
+    let json = json.replace("</", "<\\/");
+    format!(
+        r#"<!doctype html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+body {{ font: 15px/1.5 -apple-system, BlinkMacSystemFont, "Helvetica Neue", sans-serif; max-width: 46em; margin: 2em auto; padding: 0 1em; }}
+#search {{ width: 100%; font-size: 1.1em; padding: 0.4em; box-sizing: border-box; }}
+.folder {{ color: #888; font-size: 0.85em; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<input id="search" type="search" placeholder="Search notes…" autofocus>
+<ul id="results" hidden></ul>
+<div id="listing">
+{listing}</div>
+<script id="search-index" type="application/json">{json}</script>
+<script>
+const notes = JSON.parse(document.getElementById("search-index").textContent);
+const search = document.getElementById("search");
+const results = document.getElementById("results");
+const listing = document.getElementById("listing");
+search.addEventListener("input", () => {{
+  const terms = search.value.toLowerCase().split(/\s+/).filter(Boolean);
+  listing.hidden = terms.length > 0;
+  results.hidden = terms.length === 0;
+  results.replaceChildren();
+  for (const note of notes) {{
+    const haystack = (note.title + "\n" + note.folder + "\n" + note.text).toLowerCase();
+    if (!terms.every((t) => haystack.includes(t))) continue;
+    const li = document.createElement("li");
+    const a = document.createElement("a");
+    a.href = note.href;
+    a.textContent = note.title;
+    const folder = document.createElement("span");
+    folder.className = "folder";
+    folder.textContent = " — " + note.folder;
+    li.append(a, folder);
+    results.append(li);
+  }}
+}});
+</script>
+</body>
+</html>
+"#
+    )
+}
+
+/// Percent-encodes a path segment for use in an `href`.
+fn url_encode(segment: &str) -> String {
+    let mut out = String::with_capacity(segment.len());
+    for b in segment.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{b:02X}")),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url_encode_escapes_reserved_and_non_ascii() {
+        assert_eq!(url_encode("Café Notes-n1"), "Caf%C3%A9%20Notes-n1");
+        assert_eq!(url_encode("a#b?c"), "a%23b%3Fc");
+    }
+}
@@ -0,0 +1,200 @@
+use crate::checksums;
+use anyhow::{Context, anyhow};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+use time::macros::format_description;
+
+const LATEST: &str = "latest";
+
+/// A dated export directory under a snapshot root (Time Machine style).
+///
+/// Files that are byte-identical to the previous snapshot are replaced by hardlinks, so
+/// unchanged notes cost no extra disk space.
+#[derive(Debug)]
+pub struct Snapshot {
+    root: PathBuf,
+    dir: PathBuf,
+    previous: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotStats {
+    pub dir: PathBuf,
+    pub previous: Option<PathBuf>,
+    pub files: usize,
+    pub linked: usize,
+}
+
+impl Snapshot {
+    pub fn begin(root: PathBuf) -> anyhow::Result<Self> {
+        Self::begin_at(root, OffsetDateTime::now_utc())
+    }
+
+    fn begin_at(root: PathBuf, now: OffsetDateTime) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(&root).with_context(|| format!("create {root:?}"))?;
+        let previous = previous_snapshot(&root)?;
+
+        let stamp = now
+            .format(format_description!(
+                "[year]-[month]-[day]T[hour][minute][second]Z"
+            ))
+            .context("format snapshot timestamp")?;
+        let mut dir = root.join(&stamp);
+        let mut n = 2;
+        while dir.exists() {
+            dir = root.join(format!("{stamp}-{n}"));
+            n += 1;
+        }
+        std::fs::create_dir_all(&dir).with_context(|| format!("create {dir:?}"))?;
+
+        Ok(Self {
+            root,
+            dir,
+            previous,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Hardlinks unchanged files against the previous snapshot and repoints `latest`.
+    pub fn finish(self) -> anyhow::Result<SnapshotStats> {
+        let mut files = 0usize;
+        let mut linked = 0usize;
+        for entry in walkdir::WalkDir::new(&self.dir) {
+            let entry = entry.context("walk snapshot")?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            files += 1;
+            let Some(previous) = &self.previous else {
+                continue;
+            };
+            let rel = entry.path().strip_prefix(&self.dir)?;
+            let old = previous.join(rel);
+            if same_contents(&old, entry.path())? {
+                std::fs::remove_file(entry.path())
+                    .with_context(|| format!("remove {:?}", entry.path()))?;
+                std::fs::hard_link(&old, entry.path())
+                    .with_context(|| format!("hardlink {old:?} -> {:?}", entry.path()))?;
+                linked += 1;
+            }
+        }
+
+        update_latest(&self.root, &self.dir)?;
+
+        Ok(SnapshotStats {
+            dir: self.dir,
+            previous: self.previous,
+            files,
+            linked,
+        })
+    }
+}
+
+fn previous_snapshot(root: &Path) -> anyhow::Result<Option<PathBuf>> {
+    let latest = root.join(LATEST);
+    if let Ok(target) = std::fs::read_link(&latest) {
+        let target = root.join(target);
+        if target.is_dir() {
+            return Ok(Some(target));
+        }
+    }
+
+    // Fall back to the newest dated directory (names sort chronologically).
+    let mut dirs = Vec::new();
+    for entry in std::fs::read_dir(root).with_context(|| format!("read {root:?}"))? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.file_type()?.is_dir() && looks_like_snapshot_name(&name) {
+            dirs.push(name);
+        }
+    }
+    dirs.sort();
+    Ok(dirs.pop().map(|name| root.join(name)))
+}
+
+fn looks_like_snapshot_name(name: &str) -> bool {
+    let b = name.as_bytes();
+    b.len() >= 18
+        && b[..4].iter().all(u8::is_ascii_digit)
+        && b[4] == b'-'
+        && b[10] == b'T'
+        && b[17] == b'Z'
+}
+
+fn update_latest(root: &Path, dir: &Path) -> anyhow::Result<()> {
+    let name = dir
+        .file_name()
+        .ok_or_else(|| anyhow!("snapshot dir has no name: {dir:?}"))?;
+    let latest = root.join(LATEST);
+    let tmp = root.join(format!("{LATEST}.tmp"));
+    let _ = std::fs::remove_file(&tmp);
+    symlink_dir(Path::new(name), &tmp).with_context(|| format!("symlink {tmp:?}"))?;
+    std::fs::rename(&tmp, &latest).with_context(|| format!("update {latest:?}"))?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn symlink_dir(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink_dir(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_dir(target, link)
+}
+
+fn same_contents(a: &Path, b: &Path) -> anyhow::Result<bool> {
+    let (Ok(ma), Ok(mb)) = (std::fs::metadata(a), std::fs::metadata(b)) else {
+        return Ok(false);
+    };
+    if !ma.is_file() || ma.len() != mb.len() {
+        return Ok(false);
+    }
+    Ok(checksums::sha256_file(a)? == checksums::sha256_file(b)?)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::MetadataExt;
+    use tempfile::tempdir;
+
+    #[test]
+    fn snapshots_hardlink_unchanged_files_and_update_latest() {
+        let root = tempdir().unwrap();
+        let t0 = OffsetDateTime::from_unix_timestamp(1_736_935_200).unwrap();
+
+        let first = Snapshot::begin_at(root.path().to_path_buf(), t0).unwrap();
+        std::fs::write(first.dir().join("same.md"), "same").unwrap();
+        std::fs::write(first.dir().join("changed.md"), "v1").unwrap();
+        let first = first.finish().unwrap();
+        assert_eq!(first.linked, 0);
+        assert!(first.dir.ends_with("2025-01-15T100000Z"));
+
+        let second = Snapshot::begin_at(root.path().to_path_buf(), t0).unwrap();
+        assert!(second.dir().ends_with("2025-01-15T100000Z-2"));
+        std::fs::write(second.dir().join("same.md"), "same").unwrap();
+        std::fs::write(second.dir().join("changed.md"), "v2").unwrap();
+        let second = second.finish().unwrap();
+        assert_eq!(second.previous.as_deref(), Some(first.dir.as_path()));
+        assert_eq!((second.files, second.linked), (2, 1));
+
+        let ino = |p: PathBuf| std::fs::metadata(p).unwrap().ino();
+        assert_eq!(
+            ino(first.dir.join("same.md")),
+            ino(second.dir.join("same.md"))
+        );
+        assert_ne!(
+            ino(first.dir.join("changed.md")),
+            ino(second.dir.join("changed.md"))
+        );
+        assert_eq!(
+            std::fs::read_link(root.path().join(LATEST)).unwrap(),
+            PathBuf::from("2025-01-15T100000Z-2")
+        );
+    }
+}
@@ -1,12 +1,17 @@
+use crate::config::ColorChoice;
 use comfy_table::modifiers::UTF8_ROUND_CORNERS;
 use comfy_table::presets::UTF8_FULL;
 use comfy_table::{Attribute, Cell, Color, ContentArrangement, Row as ComfyRow, Table};
-use std::io::IsTerminal;
+use std::io::{self, IsTerminal, Write};
+use std::sync::OnceLock;
 use terminal_size::{Width as TermWidth, terminal_size};
-use unicode_width::UnicodeWidthStr;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 pub trait TableRow {
     const HEADERS: &'static [&'static str];
+    /// Content width of each column in a [`StreamingTable`], in [`Self::HEADERS`] order; `None`
+    /// columns (and any past the end) share the remaining space.
+    const WIDTHS: &'static [Option<usize>] = &[];
     fn cells(&self) -> Vec<Cell>;
 }
 
@@ -62,6 +67,117 @@ pub fn render_table<T: TableRow>(rows: Vec<T>) {
     println!("{out}");
 }
 
+/// A fixed-width table that prints rows as they arrive, for listings too slow to buffer.
+///
+/// Mirrors the look of [`table_string`], but column widths are decided up front (from the
+/// terminal width) and cell contents are truncated to fit instead of wrapped.
+pub struct StreamingTable<W: Write> {
+    out: W,
+    widths: Vec<usize>,
+    rows: usize,
+}
+
+impl<W: Write> StreamingTable<W> {
+    /// Columns are [`TableRow::WIDTHS`] wide, sharing what's left of `width` between the rest.
+    pub fn new<T: TableRow>(mut out: W, width: u16, color: bool) -> io::Result<Self> {
+        let fixed = T::WIDTHS;
+        let n = T::HEADERS.len();
+        // Borders plus one space of padding on each side of every cell.
+        let chrome = n + 1 + 2 * n;
+        let available = (width as usize).saturating_sub(chrome);
+        let fixed_total: usize = fixed.iter().flatten().sum();
+        let flexible = (0..n)
+            .filter(|i| fixed.get(*i).copied().flatten().is_none())
+            .count();
+        let share = available
+            .saturating_sub(fixed_total)
+            .checked_div(flexible)
+            .map_or(0, |w| w.max(5));
+        let widths: Vec<usize> = (0..n)
+            .map(|i| fixed.get(i).copied().flatten().unwrap_or(share))
+            .collect();
+
+        writeln!(out, "{}", border(&widths, '╭', '─', '┬', '╮'))?;
+        let headers: Vec<String> = T::HEADERS
+            .iter()
+            .zip(&widths)
+            .map(|(h, w)| {
+                let text = fit(h, *w);
+                if color {
+                    format!("\x1b[1;36m{text}\x1b[0m")
+                } else {
+                    text
+                }
+            })
+            .collect();
+        writeln!(out, "│ {} │", headers.join(" ┆ "))?;
+        writeln!(out, "{}", border(&widths, '╞', '═', '╪', '╡'))?;
+        Ok(Self {
+            out,
+            widths,
+            rows: 0,
+        })
+    }
+
+    pub fn push<T: TableRow>(&mut self, row: &T) -> io::Result<()> {
+        if self.rows > 0 {
+            writeln!(self.out, "{}", border(&self.widths, '├', '╌', '┼', '┤'))?;
+        }
+        let cells: Vec<String> = row
+            .cells()
+            .iter()
+            .zip(&self.widths)
+            .map(|(c, w)| fit(&c.content().replace('\n', " "), *w))
+            .collect();
+        writeln!(self.out, "│ {} │", cells.join(" ┆ "))?;
+        self.out.flush()?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        writeln!(self.out, "{}", border(&self.widths, '╰', '─', '┴', '╯'))?;
+        self.out.flush()
+    }
+}
+
+fn border(widths: &[usize], left: char, fill: char, mid: char, right: char) -> String {
+    let parts: Vec<String> = widths
+        .iter()
+        .map(|w| fill.to_string().repeat(w + 2))
+        .collect();
+    format!("{left}{}{right}", parts.join(&mid.to_string()))
+}
+
+/// Pads or truncates `s` (with `…`) to exactly `width` terminal columns.
+fn fit(s: &str, width: usize) -> String {
+    let mut out = String::new();
+    let mut used = 0usize;
+    if s.width() <= width {
+        out.push_str(s);
+        used = s.width();
+    } else {
+        for c in s.chars() {
+            let w = c.width().unwrap_or(0);
+            if used + w + 1 > width {
+                break;
+            }
+            out.push(c);
+            used += w;
+        }
+        if width > 0 {
+            out.push('…');
+            used += 1;
+        }
+    }
+    out.push_str(&" ".repeat(width.saturating_sub(used)));
+    out
+}
+
 fn header_cell(text: &str, color: bool) -> Cell {
     if color {
         Cell::new(text)
@@ -72,11 +188,22 @@ fn header_cell(text: &str, color: bool) -> Cell {
     }
 }
 
-fn should_color() -> bool {
+static COLOR: OnceLock<ColorChoice> = OnceLock::new();
+
+/// Applies the config file's `color` setting. Call once at startup; the default is `auto`.
+pub fn configure_color(choice: ColorChoice) {
+    let _ = COLOR.set(choice);
+}
+
+pub fn should_color() -> bool {
     if std::env::var_os("NO_COLOR").is_some() {
         return false;
     }
-    std::io::stdout().is_terminal()
+    match COLOR.get().copied().unwrap_or_default() {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => std::io::stdout().is_terminal(),
+    }
 }
 
 #[cfg(test)]
@@ -92,6 +219,7 @@ mod tests {
 
     impl TableRow for DemoRow {
         const HEADERS: &'static [&'static str] = &["ID", "Title"];
+        const WIDTHS: &'static [Option<usize>] = &[Some(6), None];
         fn cells(&self) -> Vec<Cell> {
             vec![Cell::new(&self.id), Cell::new(&self.title)]
         }
@@ -111,6 +239,31 @@ mod tests {
         assert!(s.ends_with("p1393"));
     }
 
+    #[test]
+    fn streaming_table_prints_rows_incrementally_and_truncates() {
+        let mut buf = Vec::new();
+        let mut t = StreamingTable::new::<DemoRow>(&mut buf, 30, false).unwrap();
+        t.push(&DemoRow {
+            id: "n1".into(),
+            title: "Short".into(),
+        })
+        .unwrap();
+        t.push(&DemoRow {
+            id: "n2".into(),
+            title: "A much longer title that will not fit".into(),
+        })
+        .unwrap();
+        assert_eq!(t.rows(), 2);
+        t.finish().unwrap();
+
+        let s = String::from_utf8(buf).unwrap();
+        let lines: Vec<&str> = s.lines().collect();
+        assert_eq!(lines.len(), 7);
+        assert!(lines.iter().all(|l| l.width() == lines[0].width()));
+        assert_eq!(lines[3], "│ n1     ┆ Short             │");
+        assert_eq!(lines[5], "│ n2     ┆ A much longer ti… │");
+    }
+
     #[test]
     fn table_string_snapshot_no_color_fixed_width() {
         let s = table_string(
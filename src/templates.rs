@@ -0,0 +1,101 @@
+//! Named note templates for `notes create --template`: Markdown files in
+//! `~/.config/apple-notes/templates/` with `{{var}}` placeholders.
+
+use crate::config;
+use anyhow::{Context, anyhow};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Where the template called `name` lives. Names with a `/` or an `.md` extension are paths.
+pub fn template_path(name: &str) -> anyhow::Result<PathBuf> {
+    if name.contains('/') || name.ends_with(".md") {
+        return Ok(config::expand_home(Path::new(name)));
+    }
+    let dir = config::config_dir().ok_or_else(|| anyhow!("can't find the config directory"))?;
+    Ok(dir.join("templates").join(format!("{name}.md")))
+}
+
+/// Reads the template called `name`.
+pub fn load(name: &str) -> anyhow::Result<String> {
+    let path = template_path(name)?;
+    std::fs::read_to_string(&path).with_context(|| format!("read template {name:?} ({path:?})"))
+}
+
+/// Replaces every `{{name}}` (spaces inside the braces are ignored) with its value from `vars`.
+/// A placeholder without a value is an error, so a typo doesn't end up in the note.
+pub fn fill(template: &str, vars: &BTreeMap<String, String>) -> anyhow::Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + 2 + len].trim();
+        let value = vars.get(name).ok_or_else(|| {
+            let known: Vec<&str> = vars.keys().map(String::as_str).collect();
+            anyhow!(
+                "template uses {{{{{name}}}}}, which has no value (pass --var {name}=...; known: {})",
+                known.join(", ")
+            )
+        })?;
+        out.push_str(&rest[..start]);
+        out.push_str(value);
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Parses a `--var KEY=VALUE` argument.
+pub fn parse_var(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("invalid --var {s:?} (expected KEY=VALUE)")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn fill_substitutes_placeholders() {
+        let vars = vars(&[("attendee", "Bob"), ("date", "2026-10-17")]);
+        assert_eq!(
+            fill(
+                "# {{date}}\n- {{ attendee }} and {{attendee}}\n{{ unclosed",
+                &vars
+            )
+            .unwrap(),
+            "# 2026-10-17\n- Bob and Bob\n{{ unclosed"
+        );
+        let err = fill("{{room}}", &vars).unwrap_err().to_string();
+        assert!(
+            err.contains("{{room}}") && err.contains("--var room="),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn vars_and_names_parse() {
+        assert_eq!(
+            parse_var("topic=a=b").unwrap(),
+            ("topic".to_string(), "a=b".to_string())
+        );
+        assert!(parse_var("=x").is_err());
+        assert!(parse_var("novalue").is_err());
+        assert_eq!(
+            template_path("./meeting.md").unwrap(),
+            PathBuf::from("./meeting.md")
+        );
+    }
+}
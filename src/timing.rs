@@ -0,0 +1,277 @@
+//! `--timing`: where a command spent its time, summed per kind of work from `tracing` spans.
+//!
+//! Backend calls get a span each from [`TimedBackend`]; `osascript` runs, database lookups,
+//! rendering and export writes have their own spans.
+
+use crate::cancel::CancelToken;
+use crate::error::Result;
+use crate::model::{Account, Folder, Note, NoteSummary};
+use crate::transport::NotesBackend;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::span;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+
+/// Calls and total time per span name, for the whole process.
+static TOTALS: Mutex<Option<HashMap<&'static str, (u64, Duration)>>> = Mutex::new(None);
+
+/// Adds up how long every span took, by name.
+#[derive(Debug, Default)]
+pub struct TimingLayer;
+
+struct Started(Instant);
+
+impl<S> tracing_subscriber::Layer<S> for TimingLayer
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(Started(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(elapsed) = span.extensions().get::<Started>().map(|s| s.0.elapsed()) else {
+            return;
+        };
+        let mut totals = TOTALS.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = totals
+            .get_or_insert_with(HashMap::new)
+            .entry(span.name())
+            .or_default();
+        entry.0 += 1;
+        entry.1 += elapsed;
+    }
+}
+
+/// The summary `--timing` prints: one line per kind of work, slowest first, after the total.
+/// Kinds overlap (an `osascript` run happens inside a backend call).
+pub fn summary() -> String {
+    let totals = TOTALS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut rows: Vec<(&str, u64, Duration)> = totals
+        .iter()
+        .flatten()
+        .map(|(name, (calls, time))| (*name, *calls, *time))
+        .collect();
+    rows.sort_by(|a, b| {
+        (b.0 == "command")
+            .cmp(&(a.0 == "command"))
+            .then(b.2.cmp(&a.2))
+            .then(a.0.cmp(b.0))
+    });
+    let width = rows.iter().map(|r| r.0.len()).max().unwrap_or(0);
+    let mut out = String::from("Timing:");
+    for (name, calls, time) in rows {
+        let calls = match calls {
+            1 => "1 call".to_string(),
+            n => format!("{n} calls"),
+        };
+        out.push_str(&format!(
+            "\n  {name:width$}  {calls:>10}  {:>10}",
+            format_duration(time)
+        ));
+    }
+    out
+}
+
+fn format_duration(d: Duration) -> String {
+    match d.as_secs_f64() {
+        s if s >= 1.0 => format!("{s:.2}s"),
+        s if s >= 0.001 => format!("{:.1}ms", s * 1000.0),
+        s => format!("{:.0}µs", s * 1_000_000.0),
+    }
+}
+
+/// Wraps a backend so each call runs in a span named after the method.
+pub struct TimedBackend {
+    inner: Box<dyn NotesBackend>,
+}
+
+impl TimedBackend {
+    pub fn new(inner: Box<dyn NotesBackend>) -> Self {
+        Self { inner }
+    }
+}
+
+macro_rules! timed {
+    ($name:literal, $call:expr) => {{
+        let _span = tracing::info_span!($name).entered();
+        $call
+    }};
+}
+
+impl NotesBackend for TimedBackend {
+    fn list_accounts(&self) -> Result<Vec<Account>> {
+        timed!("list_accounts", self.inner.list_accounts())
+    }
+
+    fn list_folders(&self, account: &str) -> Result<Vec<Folder>> {
+        timed!("list_folders", self.inner.list_folders(account))
+    }
+
+    fn list_notes(&self, account: &str) -> Result<Vec<NoteSummary>> {
+        timed!("list_notes", self.inner.list_notes(account))
+    }
+
+    fn list_notes_in_folder(
+        &self,
+        account: &str,
+        folder_path: &[String],
+    ) -> Result<Vec<NoteSummary>> {
+        timed!(
+            "list_notes_in_folder",
+            self.inner.list_notes_in_folder(account, folder_path)
+        )
+    }
+
+    fn stream_note_summaries(
+        &self,
+        account: &str,
+        folder_path: Option<&[String]>,
+        on_note: &mut dyn FnMut(NoteSummary),
+    ) -> Result<()> {
+        timed!(
+            "stream_note_summaries",
+            self.inner
+                .stream_note_summaries(account, folder_path, on_note)
+        )
+    }
+
+    fn stream_note_summaries_cancellable(
+        &self,
+        account: &str,
+        folder_path: Option<&[String]>,
+        cancel: &CancelToken,
+        on_note: &mut dyn FnMut(NoteSummary),
+    ) -> Result<()> {
+        timed!(
+            "stream_note_summaries",
+            self.inner
+                .stream_note_summaries_cancellable(account, folder_path, cancel, on_note)
+        )
+    }
+
+    fn list_deleted_notes(&self, account: &str) -> Result<Vec<NoteSummary>> {
+        timed!("list_deleted_notes", self.inner.list_deleted_notes(account))
+    }
+
+    fn get_note(&self, id: &str) -> Result<Note> {
+        timed!("get_note", self.inner.get_note(id))
+    }
+
+    fn get_note_streaming(&self, id: &str, on_chunk: &mut dyn FnMut(&str)) -> Result<Note> {
+        timed!("get_note", self.inner.get_note_streaming(id, on_chunk))
+    }
+
+    fn create_note_html(
+        &self,
+        account: &str,
+        folder_path: &[String],
+        title: &str,
+        body_html: &str,
+    ) -> Result<String> {
+        timed!(
+            "create_note",
+            self.inner
+                .create_note_html(account, folder_path, title, body_html)
+        )
+    }
+
+    fn set_note_title(&self, id: &str, title: &str) -> Result<()> {
+        timed!("set_note_title", self.inner.set_note_title(id, title))
+    }
+
+    fn set_note_body_html(&self, id: &str, body_html: &str) -> Result<()> {
+        timed!(
+            "set_note_body",
+            self.inner.set_note_body_html(id, body_html)
+        )
+    }
+
+    fn append_note_body_html(&self, id: &str, body_html: &str) -> Result<()> {
+        timed!(
+            "append_note_body",
+            self.inner.append_note_body_html(id, body_html)
+        )
+    }
+
+    fn delete_note(&self, id: &str) -> Result<()> {
+        timed!("delete_note", self.inner.delete_note(id))
+    }
+
+    fn deleted_note_ids(&self, ids: &[String]) -> Result<HashSet<String>> {
+        timed!("deleted_note_ids", self.inner.deleted_note_ids(ids))
+    }
+
+    fn is_note_deleted(&self, id: &str) -> Result<bool> {
+        timed!("is_note_deleted", self.inner.is_note_deleted(id))
+    }
+
+    fn purge_note(&self, id: &str) -> Result<()> {
+        timed!("purge_note", self.inner.purge_note(id))
+    }
+
+    fn move_note(&self, id: &str, account: &str, folder_path: &[String]) -> Result<()> {
+        timed!("move_note", self.inner.move_note(id, account, folder_path))
+    }
+
+    fn open_note(&self, id: &str) -> Result<()> {
+        timed!("open_note", self.inner.open_note(id))
+    }
+
+    fn attach_file(&self, id: &str, path: &Path) -> Result<()> {
+        timed!("attach_file", self.inner.attach_file(id, path))
+    }
+
+    fn create_folder(&self, account: &str, parent_path: &[String], name: &str) -> Result<String> {
+        timed!(
+            "create_folder",
+            self.inner.create_folder(account, parent_path, name)
+        )
+    }
+
+    fn rename_folder(&self, account: &str, folder_path: &[String], name: &str) -> Result<()> {
+        timed!(
+            "rename_folder",
+            self.inner.rename_folder(account, folder_path, name)
+        )
+    }
+
+    fn move_folder(
+        &self,
+        account: &str,
+        folder_path: &[String],
+        parent_path: &[String],
+    ) -> Result<()> {
+        timed!(
+            "move_folder",
+            self.inner.move_folder(account, folder_path, parent_path)
+        )
+    }
+
+    fn delete_folder(&self, account: &str, folder_path: &[String]) -> Result<()> {
+        timed!(
+            "delete_folder",
+            self.inner.delete_folder(account, folder_path)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_use_a_readable_unit() {
+        assert_eq!(format_duration(Duration::from_millis(2500)), "2.50s");
+        assert_eq!(format_duration(Duration::from_micros(12_340)), "12.3ms");
+        assert_eq!(format_duration(Duration::from_micros(450)), "450µs");
+    }
+}
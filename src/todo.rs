@@ -0,0 +1,347 @@
+//! Notes checklists: `<ul class="checklist">` lists whose checked items carry a `checked` class,
+//! and `<input type="checkbox">` items.
+
+use crate::render::escape_html;
+use anyhow::anyhow;
+use regex::Regex;
+use serde::Serialize;
+use std::ops::Range;
+use std::sync::LazyLock;
+
+/// One checklist item of a note, numbered from 1 in body order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TodoItem {
+    pub index: usize,
+    pub text: String,
+    pub checked: bool,
+}
+
+/// Where an item's checked state lives in the HTML: its `<li>` or its checkbox `<input>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StateTag {
+    Li,
+    Input,
+}
+
+#[derive(Debug)]
+struct Span {
+    tag: Range<usize>,
+    state: StateTag,
+    checked: bool,
+    text: String,
+}
+
+static INPUT_CHECKED: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)\s+checked(\s*=\s*("[^"]*"|'[^']*'|[^\s>]+))?"#).unwrap());
+
+/// A checklist of unchecked `items`, in the HTML Notes shows as checkboxes.
+pub fn checklist_html(items: &[String]) -> String {
+    let mut out = String::from("<ul class=\"checklist\">");
+    for item in items {
+        out.push_str("<li>");
+        out.push_str(&escape_html(item));
+        out.push_str("</li>");
+    }
+    out.push_str("</ul>");
+    out
+}
+
+/// The checklist items of a note body.
+pub fn checklist_items(html: &str) -> Vec<TodoItem> {
+    spans(html)
+        .into_iter()
+        .enumerate()
+        .map(|(i, s)| TodoItem {
+            index: i + 1,
+            text: s.text,
+            checked: s.checked,
+        })
+        .collect()
+}
+
+/// The task list items (`- [ ] …` / `- [x] …`) of a note's Markdown, numbered like
+/// [`checklist_items`] numbers them in the HTML.
+pub fn markdown_items(markdown: &str) -> Vec<TodoItem> {
+    markdown
+        .lines()
+        .filter_map(|line| {
+            let item = line.trim_start().strip_prefix("- [")?;
+            let (mark, text) = item.split_at_checked(1)?;
+            let text = text.strip_prefix("] ")?;
+            let checked = match mark {
+                " " => false,
+                "x" | "X" => true,
+                _ => return None,
+            };
+            Some((text.trim().to_string(), checked))
+        })
+        .enumerate()
+        .map(|(i, (text, checked))| TodoItem {
+            index: i + 1,
+            text,
+            checked,
+        })
+        .collect()
+}
+
+/// The index of the item `reference` names: its number, or a unique, case-insensitive part of
+/// its text.
+pub fn find_item(items: &[TodoItem], reference: &str) -> anyhow::Result<usize> {
+    if let Ok(index) = reference.trim().parse::<usize>() {
+        return items
+            .iter()
+            .find(|i| i.index == index)
+            .map(|i| i.index)
+            .ok_or_else(|| anyhow!("no checklist item {index} (the note has {})", items.len()));
+    }
+    let needle = reference.to_lowercase();
+    let found: Vec<&TodoItem> = items
+        .iter()
+        .filter(|i| i.text.to_lowercase().contains(&needle))
+        .collect();
+    match found.as_slice() {
+        [item] => Ok(item.index),
+        [] => Err(anyhow!("no checklist item matches {reference:?}")),
+        _ => Err(anyhow!(
+            "{} checklist items match {reference:?}: {}",
+            found.len(),
+            found
+                .iter()
+                .map(|i| format!("{}. {}", i.index, i.text))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// `html` with item `index` checked if it was unchecked and vice versa, or `None` if there is no
+/// such item.
+pub fn toggle(html: &str, index: usize) -> Option<String> {
+    let span = spans(html).into_iter().nth(index.checked_sub(1)?)?;
+    let tag = &html[span.tag.clone()];
+    let new_tag = match span.state {
+        StateTag::Li => set_li_checked(tag, !span.checked),
+        StateTag::Input if span.checked => INPUT_CHECKED.replace_all(tag, "").into_owned(),
+        StateTag::Input => {
+            let end = tag.len() - if tag.ends_with("/>") { 2 } else { 1 };
+            format!("{} checked{}", tag[..end].trim_end(), &tag[end..])
+        }
+    };
+    Some(format!(
+        "{}{new_tag}{}",
+        &html[..span.tag.start],
+        &html[span.tag.end..]
+    ))
+}
+
+fn spans(html: &str) -> Vec<Span> {
+    let mut out: Vec<Span> = Vec::new();
+    let mut lists: Vec<bool> = Vec::new();
+    // The item whose text is being read, and whether we're inside a plain (non-checklist) `<li>`.
+    let mut current: Option<usize> = None;
+    let mut plain_li = false;
+    let mut pos = 0;
+    while let Some(start) = html[pos..].find('<').map(|i| pos + i) {
+        if let Some(item) = current {
+            out[item].text.push_str(&html[pos..start]);
+        }
+        let Some(len) = html[start..].find('>') else {
+            break;
+        };
+        let tag = start..start + len + 1;
+        pos = tag.end;
+        let lower = html[tag.clone()].to_ascii_lowercase();
+        let closing = lower.starts_with("</");
+        let name = lower
+            .trim_start_matches(['<', '/'])
+            .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .next()
+            .unwrap_or("");
+        match (name, closing) {
+            ("ul" | "ol", false) => lists.push(class_list(&lower).contains("checklist")),
+            ("ul" | "ol", true) => {
+                lists.pop();
+            }
+            ("li", false) if lists.last() == Some(&true) => {
+                let checked = class_list(&lower)
+                    .split_whitespace()
+                    .any(|c| c == "checked" || c == "done");
+                out.push(Span {
+                    tag,
+                    state: StateTag::Li,
+                    checked,
+                    text: String::new(),
+                });
+                current = Some(out.len() - 1);
+            }
+            ("li", false) => plain_li = true,
+            ("li", true) => {
+                current = None;
+                plain_li = false;
+            }
+            ("input", false) if lower.contains("checkbox") => {
+                let checked = INPUT_CHECKED.is_match(&lower);
+                match current {
+                    // A checklist `<li>` with a checkbox: the checkbox holds the state.
+                    Some(item) => {
+                        out[item].tag = tag;
+                        out[item].state = StateTag::Input;
+                        out[item].checked = checked;
+                    }
+                    None => {
+                        out.push(Span {
+                            tag,
+                            state: StateTag::Input,
+                            checked,
+                            text: String::new(),
+                        });
+                        current = plain_li.then_some(out.len() - 1);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    for span in &mut out {
+        span.text = unescape(&span.text)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+    }
+    out
+}
+
+fn class_list(tag: &str) -> &str {
+    let Some(i) = tag.find("class=") else {
+        return "";
+    };
+    let value = &tag[i + "class=".len()..];
+    match value.chars().next() {
+        Some(q @ ('"' | '\'')) => {
+            let value = &value[1..];
+            &value[..value.find(q).unwrap_or(value.len())]
+        }
+        _ => value
+            .split(|c: char| c.is_whitespace() || c == '>')
+            .next()
+            .unwrap_or(""),
+    }
+}
+
+fn set_li_checked(tag: &str, checked: bool) -> String {
+    let lower = tag.to_ascii_lowercase();
+    let Some(i) = lower.find("class=") else {
+        return match checked {
+            true => format!("<li class=\"checked\"{}", &tag["<li".len()..]),
+            false => tag.to_string(),
+        };
+    };
+    let start = i + "class=".len();
+    let (start, end) = match tag[start..].chars().next() {
+        Some(q @ ('"' | '\'')) => {
+            let end = tag[start + 1..]
+                .find(q)
+                .map_or(tag.len(), |e| start + 1 + e);
+            (start + 1, end)
+        }
+        _ => {
+            let end = tag[start..]
+                .find(|c: char| c.is_whitespace() || c == '>')
+                .map_or(tag.len(), |e| start + e);
+            (start, end)
+        }
+    };
+    let mut classes: Vec<&str> = tag[start..end]
+        .split_whitespace()
+        .filter(|c| !c.eq_ignore_ascii_case("checked") && !c.eq_ignore_ascii_case("done"))
+        .collect();
+    if checked {
+        classes.push("checked");
+    }
+    format!("{}{}{}", &tag[..start], classes.join(" "), &tag[end..])
+}
+
+fn unescape(s: &str) -> String {
+    s.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::html_to_markdown;
+
+    #[test]
+    fn checklist_html_is_read_back_as_unchecked_tasks() {
+        let html = checklist_html(&["Buy milk".into(), "Call <Bob>".into()]);
+        assert_eq!(
+            html,
+            "<ul class=\"checklist\"><li>Buy milk</li><li>Call &lt;Bob&gt;</li></ul>"
+        );
+        assert_eq!(
+            html_to_markdown(&html),
+            "- [ ] Buy milk\n- [ ] Call \\<Bob\\>"
+        );
+        assert_eq!(checklist_items(&html)[1].text, "Call <Bob>");
+    }
+
+    #[test]
+    fn checklist_items_cover_checklists_and_checkboxes() {
+        let html = "<div>Title</div><ul class=\"checklist\"><li class=\"checked\">Milk</li>\
+                    <li><b>Eggs</b></li></ul><ul><li>Plain</li>\
+                    <li><input type=\"checkbox\" checked>Bread</li></ul>";
+        let items = checklist_items(html);
+        let got: Vec<_> = items
+            .iter()
+            .map(|i| (i.index, i.text.as_str(), i.checked))
+            .collect();
+        assert_eq!(
+            got,
+            [(1, "Milk", true), (2, "Eggs", false), (3, "Bread", true)]
+        );
+        assert_eq!(find_item(&items, "2").unwrap(), 2);
+        assert_eq!(find_item(&items, "brea").unwrap(), 3);
+        assert!(find_item(&items, "4").is_err());
+        assert!(find_item(&items, "e").is_err());
+        assert!(find_item(&items, "tea").is_err());
+    }
+
+    #[test]
+    fn markdown_items_read_task_lists() {
+        let md = "# Groceries\n\n- [x] Milk\n  - [ ] Eggs \n- plain\n* [ ] star\n- [X] Bread";
+        let got: Vec<_> = markdown_items(md)
+            .into_iter()
+            .map(|i| (i.index, i.text, i.checked))
+            .collect();
+        assert_eq!(
+            got,
+            [
+                (1, "Milk".to_string(), true),
+                (2, "Eggs".to_string(), false),
+                (3, "Bread".to_string(), true)
+            ]
+        );
+    }
+
+    #[test]
+    fn toggle_flips_the_state_tag_of_one_item() {
+        let html = "<ul class=\"checklist\"><li class=\"checked\">Milk</li><li>Eggs</li></ul>\
+                    <ul><li><input type=\"checkbox\" checked=\"checked\">Bread</li></ul>";
+        let checked =
+            |html: &str| -> Vec<bool> { checklist_items(html).iter().map(|i| i.checked).collect() };
+        let once = toggle(html, 1).unwrap();
+        assert!(once.contains("<li class=\"\">Milk"), "{once}");
+        assert_eq!(checked(&once), [false, false, true]);
+        let twice = toggle(&toggle(&once, 2).unwrap(), 3).unwrap();
+        assert!(twice.contains("<li class=\"checked\">Eggs"), "{twice}");
+        assert!(twice.contains("<input type=\"checkbox\">Bread"), "{twice}");
+        assert_eq!(checked(&twice), [false, true, false]);
+        assert_eq!(checked(&toggle(&twice, 3).unwrap()), [false, true, true]);
+        assert!(toggle(html, 4).is_none());
+        assert!(toggle(html, 0).is_none());
+    }
+}
@@ -1,5 +1,7 @@
+use crate::cancel::CancelToken;
+use crate::db;
+use crate::error::{NotesError, Result};
 use crate::model::{Account, Folder, Note, NoteSummary};
-use crate::{cli, db};
 use anyhow::{Context, anyhow};
 use serde::Serialize;
 use serde::de::DeserializeOwned;
@@ -7,21 +9,55 @@ use std::collections::HashSet;
 use std::ffi::OsString;
 use std::io::Write;
 use std::io::{BufRead, BufReader};
+use std::path::Path;
 use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+/// How many `osascript` processes list an account's notes at once (each takes a chunk of its
+/// folders).
+const LIST_JOBS: usize = 4;
+
+/// JXA that splits a note body into chunks of at most `size` UTF-16 units for
+/// `notes.get_streaming`. A chunk that would end on the first half of a surrogate pair ends one
+/// unit early (or late, if that would leave it empty) instead, since a lone half can't be sent as
+/// JSON.
+const BODY_CHUNKS_JS: &str = r#"function bodyChunks(s, size) {
+  const out = [];
+  for (let i = 0; i < s.length; ) {
+    let end = Math.min(i + size, s.length);
+    const last = s.charCodeAt(end - 1);
+    if (end < s.length && last >= 0xd800 && last <= 0xdbff) end += end - i > 1 ? -1 : 1;
+    out.push(s.slice(i, end));
+    i = end;
+  }
+  return out;
+}
+"#;
+
+/// The name Notes gives the folder deleted notes wait in before they're purged.
+pub const RECENTLY_DELETED: &str = "Recently Deleted";
+
+/// The folder every account has. `--folder Notes` finds it even where Notes calls it something
+/// else (e.g. in another language).
+pub const DEFAULT_FOLDER: &str = "Notes";
 
 fn osascript_bin() -> OsString {
     std::env::var_os("APPLE_NOTES_OSASCRIPT_BIN").unwrap_or_else(|| OsString::from("osascript"))
 }
 
+/// Everything the CLI does with Notes. Folder paths are name lists from the top level down
+/// (`["Personal", "Archive"]`); ids are Notes' own (`x-coredata://…`).
 pub trait NotesBackend: Send + Sync {
-    fn list_accounts(&self) -> anyhow::Result<Vec<Account>>;
-    fn list_folders(&self, account: &str) -> anyhow::Result<Vec<Folder>>;
-    fn list_notes(&self, account: &str) -> anyhow::Result<Vec<NoteSummary>>;
+    fn list_accounts(&self) -> Result<Vec<Account>>;
+    fn list_folders(&self, account: &str) -> Result<Vec<Folder>>;
+    fn list_notes(&self, account: &str) -> Result<Vec<NoteSummary>>;
     fn list_notes_in_folder(
         &self,
         account: &str,
         folder_path: &[String],
-    ) -> anyhow::Result<Vec<NoteSummary>>;
+    ) -> Result<Vec<NoteSummary>>;
 
     /// Streams note summaries, invoking `on_note` for every note found.
     ///
@@ -31,43 +67,144 @@ pub trait NotesBackend: Send + Sync {
         account: &str,
         folder_path: Option<&[String]>,
         on_note: &mut dyn FnMut(NoteSummary),
-    ) -> anyhow::Result<()>;
+    ) -> Result<()>;
 
-    fn get_note(&self, id: &str) -> anyhow::Result<Note>;
+    /// Like [`NotesBackend::stream_note_summaries`], but stops once `cancel` fires or its
+    /// deadline passes, returning a [`crate::cancel::Cancelled`] error.
+    ///
+    /// Backends that shell out (e.g. `osascript`) kill the child process on cancellation.
+    fn stream_note_summaries_cancellable(
+        &self,
+        account: &str,
+        folder_path: Option<&[String]>,
+        cancel: &CancelToken,
+        on_note: &mut dyn FnMut(NoteSummary),
+    ) -> Result<()> {
+        cancel.check()?;
+        self.stream_note_summaries(account, folder_path, &mut |n| {
+            if !cancel.is_cancelled() {
+                on_note(n);
+            }
+        })?;
+        cancel.check()?;
+        Ok(())
+    }
+
+    /// Notes in the account's Recently Deleted folder, which Notes empties after 30 days. Empty
+    /// if the account has no such folder.
+    fn list_deleted_notes(&self, account: &str) -> Result<Vec<NoteSummary>> {
+        match self.list_notes_in_folder(account, &[RECENTLY_DELETED.to_string()]) {
+            Err(NotesError::NotFound(_)) => Ok(Vec::new()),
+            listed => listed,
+        }
+    }
+
+    fn get_note(&self, id: &str) -> Result<Note>;
 
+    /// Fetches a note, handing its body HTML to `on_chunk` piece by piece as it arrives.
+    ///
+    /// Lets callers (e.g. the server or TUI) start rendering very large notes early. The returned
+    /// note's `body_html` is left empty; the body is only delivered through `on_chunk`.
+    fn get_note_streaming(&self, id: &str, on_chunk: &mut dyn FnMut(&str)) -> Result<Note> {
+        let mut note = self.get_note(id)?;
+        on_chunk(&note.body_html);
+        note.body_html.clear();
+        Ok(note)
+    }
+
+    /// Creates a note in `folder_path`, or in the account's default folder when the path is
+    /// empty. Returns the new note's id.
     fn create_note_html(
         &self,
         account: &str,
         folder_path: &[String],
         title: &str,
         body_html: &str,
-    ) -> anyhow::Result<String>;
+    ) -> Result<String>;
+
+    fn set_note_title(&self, id: &str, title: &str) -> Result<()>;
+    fn set_note_body_html(&self, id: &str, body_html: &str) -> Result<()>;
+    fn append_note_body_html(&self, id: &str, body_html: &str) -> Result<()>;
+    /// Moves the note to Recently Deleted, where Notes keeps it for 30 days.
+    fn delete_note(&self, id: &str) -> Result<()>;
+
+    /// Which of `ids` are in their account's Recently Deleted folder, listing each account's
+    /// Recently Deleted once however many ids are asked about.
+    fn deleted_note_ids(&self, ids: &[String]) -> Result<HashSet<String>> {
+        let mut deleted = HashSet::new();
+        for account in self.list_accounts()? {
+            deleted.extend(
+                self.list_deleted_notes(&account.name)?
+                    .into_iter()
+                    .map(|n| n.id)
+                    .filter(|id| ids.contains(id)),
+            );
+        }
+        Ok(deleted)
+    }
 
-    fn set_note_title(&self, id: &str, title: &str) -> anyhow::Result<()>;
-    fn set_note_body_html(&self, id: &str, body_html: &str) -> anyhow::Result<()>;
-    fn append_note_body_html(&self, id: &str, body_html: &str) -> anyhow::Result<()>;
-    fn delete_note(&self, id: &str) -> anyhow::Result<()>;
+    /// Whether the note is in its account's Recently Deleted folder.
+    fn is_note_deleted(&self, id: &str) -> Result<bool> {
+        Ok(!self.deleted_note_ids(&[id.to_string()])?.is_empty())
+    }
 
-    fn move_note(&self, id: &str, account: &str, folder_path: &[String]) -> anyhow::Result<()>;
+    /// Fails with [`NotesError::AlreadyDeleted`] if any of `ids` is in Recently Deleted, since
+    /// [`delete_note`](Self::delete_note) would delete it for good; that's
+    /// [`purge_note`](Self::purge_note)'s job.
+    fn ensure_not_deleted(&self, ids: &[String]) -> Result<()> {
+        let deleted = self.deleted_note_ids(ids)?;
+        match ids.iter().find(|id| deleted.contains(*id)) {
+            Some(id) => Err(NotesError::AlreadyDeleted(format!(
+                "{id} is already in Recently Deleted"
+            ))),
+            None => Ok(()),
+        }
+    }
 
-    fn create_folder(
-        &self,
-        account: &str,
-        parent_path: &[String],
-        name: &str,
-    ) -> anyhow::Result<String>;
-    fn rename_folder(
+    /// [`delete_note`](Self::delete_note), refusing a note that's in Recently Deleted already
+    /// (see [`ensure_not_deleted`](Self::ensure_not_deleted)).
+    fn trash_note(&self, id: &str) -> Result<()> {
+        self.ensure_not_deleted(std::slice::from_ref(&id.to_string()))?;
+        self.delete_note(id)
+    }
+
+    /// Deletes the note for good: moves it to Recently Deleted unless it's there already, then
+    /// deletes it from there (Notes purges notes deleted a second time). The note keeps its id in
+    /// Recently Deleted.
+    fn purge_note(&self, id: &str) -> Result<()> {
+        if !self.is_note_deleted(id)? {
+            self.delete_note(id)?;
+        }
+        self.delete_note(id)
+    }
+
+    fn move_note(&self, id: &str, account: &str, folder_path: &[String]) -> Result<()>;
+
+    /// Brings Notes.app to the front with the note selected, for editing in the GUI.
+    fn open_note(&self, id: &str) -> Result<()>;
+
+    /// Attaches the file at `path` (absolute) to the end of the note.
+    fn attach_file(&self, id: &str, path: &Path) -> Result<()>;
+
+    fn create_folder(&self, account: &str, parent_path: &[String], name: &str) -> Result<String>;
+    fn rename_folder(&self, account: &str, folder_path: &[String], name: &str) -> Result<()>;
+    /// Moves the folder, with its notes and subfolders, into the folder at `parent_path`.
+    fn move_folder(
         &self,
         account: &str,
         folder_path: &[String],
-        name: &str,
-    ) -> anyhow::Result<()>;
-    fn delete_folder(&self, account: &str, folder_path: &[String]) -> anyhow::Result<()>;
+        parent_path: &[String],
+    ) -> Result<()>;
+    /// Deletes the folder; its notes move to Recently Deleted.
+    fn delete_folder(&self, account: &str, folder_path: &[String]) -> Result<()>;
 }
 
+/// Talks to Notes.app through `osascript` (or `$APPLE_NOTES_OSASCRIPT_BIN`).
 #[derive(Debug, Clone, Default)]
 pub struct OsascriptBackend;
 
+/// Reads from the Notes database, writes through [`OsascriptBackend`]. Note ids may be given as
+/// UUIDs ([`NoteSummary::uuid`]); they're mapped to `x-coredata://` ids through the database.
 #[derive(Debug)]
 pub struct HybridBackend {
     db: db::NotesDb,
@@ -84,21 +221,31 @@ impl HybridBackend {
 }
 
 impl OsascriptBackend {
-    fn run_osascript_jxa(&self, script: &str) -> anyhow::Result<String> {
+    /// Runs a trivial Notes query so a missing Automation permission surfaces (and the consent
+    /// prompt appears) before a long export starts, rather than at its first note.
+    pub fn preflight(&self) -> anyhow::Result<()> {
+        match self.run_osascript_applescript("tell application \"Notes\" to count accounts") {
+            Ok(_) => Ok(()),
+            Err(NotesError::PermissionDenied(_)) => Err(anyhow!(
+                "not allowed to control Notes.app; enable it under System Settings > Privacy & \
+                 Security > Automation for this terminal, then re-run (or pass --no-preflight)"
+            )),
+            Err(e) => Err(anyhow::Error::new(e)
+                .context("Notes.app pre-flight check failed (pass --no-preflight to skip)")),
+        }
+    }
+
+    fn run_osascript_jxa(&self, script: &str) -> Result<String> {
         self.run_osascript(&["-l", "JavaScript", "-"], script)
     }
 
-    fn run_osascript_applescript(&self, script: &str) -> anyhow::Result<String> {
+    fn run_osascript_applescript(&self, script: &str) -> Result<String> {
         self.run_osascript(&["-"], script)
     }
 
-    fn run_osascript(&self, osascript_args: &[&str], stdin: &str) -> anyhow::Result<String> {
-        if std::env::var_os("APPLE_NOTES_DEBUG_SCRIPT").is_some() {
-            eprintln!(
-                "DEBUG apple-notes: running osascript {:?} with stdin:\n{}\n---",
-                osascript_args, stdin
-            );
-        }
+    #[tracing::instrument(name = "osascript", skip_all, fields(args = ?osascript_args))]
+    fn run_osascript(&self, osascript_args: &[&str], stdin: &str) -> Result<String> {
+        tracing::debug!(script = stdin, "running osascript");
 
         let mut cmd = Command::new(osascript_bin());
         cmd.args(osascript_args);
@@ -107,7 +254,11 @@ impl OsascriptBackend {
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
-            .context("failed to spawn osascript (are you on macOS?)")?;
+            .map_err(|e| {
+                NotesError::BackendUnavailable(format!(
+                    "failed to spawn osascript (are you on macOS?): {e}"
+                ))
+            })?;
 
         {
             let mut stdin_pipe = child.stdin.take().context("stdin was not piped")?;
@@ -118,11 +269,11 @@ impl OsascriptBackend {
 
         let out = child.wait_with_output().context("osascript failed")?;
         if !out.status.success() {
-            return Err(anyhow!(
+            return Err(NotesError::from_script_failure(format!(
                 "osascript failed ({}): {}",
                 out.status,
                 String::from_utf8_lossy(&out.stderr)
-            ));
+            )));
         }
 
         // In some environments, osascript emits output on stderr even on success.
@@ -133,18 +284,15 @@ impl OsascriptBackend {
         }
     }
 
+    #[tracing::instrument(name = "osascript", skip_all, fields(args = ?osascript_args))]
     fn run_osascript_streaming(
         &self,
         osascript_args: &[&str],
         stdin: &str,
+        cancel: &CancelToken,
         mut on_stderr_line: impl FnMut(&str),
-    ) -> anyhow::Result<()> {
-        if std::env::var_os("APPLE_NOTES_DEBUG_SCRIPT").is_some() {
-            eprintln!(
-                "DEBUG apple-notes: streaming osascript {:?} with stdin:\n{}\n---",
-                osascript_args, stdin
-            );
-        }
+    ) -> Result<()> {
+        tracing::debug!(script = stdin, "streaming osascript");
 
         let mut cmd = Command::new(osascript_bin());
         cmd.args(osascript_args);
@@ -153,7 +301,11 @@ impl OsascriptBackend {
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
-            .context("failed to spawn osascript (are you on macOS?)")?;
+            .map_err(|e| {
+                NotesError::BackendUnavailable(format!(
+                    "failed to spawn osascript (are you on macOS?): {e}"
+                ))
+            })?;
 
         {
             let mut stdin_pipe = child.stdin.take().context("stdin was not piped")?;
@@ -170,28 +322,53 @@ impl OsascriptBackend {
             s
         });
 
-        let mut stderr_buf = String::new();
-        {
-            let stderr = child.stderr.take().context("stderr was not piped")?;
+        // Read stderr on a helper thread so we can poll `cancel` while waiting for lines.
+        let stderr = child.stderr.take().context("stderr was not piped")?;
+        let (line_tx, line_rx) = mpsc::channel::<std::io::Result<String>>();
+        std::thread::spawn(move || {
             let mut reader = BufReader::new(stderr);
             let mut line = String::new();
-            while reader
-                .read_line(&mut line)
-                .context("read osascript stderr")?
-                > 0
-            {
-                let trimmed = line.trim_end_matches(['\r', '\n']);
-                stderr_buf.push_str(trimmed);
-                stderr_buf.push('\n');
-                on_stderr_line(trimmed);
+            loop {
                 line.clear();
+                match reader.read_line(&mut line) {
+                    Ok(0) => break,
+                    Ok(_) => {
+                        if line_tx.send(Ok(line.clone())).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        let _ = line_tx.send(Err(e));
+                        break;
+                    }
+                }
+            }
+        });
+
+        let mut stderr_buf = String::new();
+        loop {
+            if let Err(cancelled) = cancel.check() {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(cancelled.into());
+            }
+            match line_rx.recv_timeout(Duration::from_millis(50)) {
+                Ok(line) => {
+                    let line = line.context("read osascript stderr")?;
+                    let trimmed = line.trim_end_matches(['\r', '\n']);
+                    stderr_buf.push_str(trimmed);
+                    stderr_buf.push('\n');
+                    on_stderr_line(trimmed);
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => continue,
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
             }
         }
 
         let status = child.wait().context("osascript failed")?;
         let stdout_buf = stdout_thread.join().unwrap_or_default();
         if !status.success() {
-            return Err(anyhow!(
+            return Err(NotesError::from_script_failure(format!(
                 "osascript failed ({}): {}{}",
                 status,
                 if stderr_buf.trim().is_empty() {
@@ -204,23 +381,22 @@ impl OsascriptBackend {
                 } else {
                     format!("\n{}", stdout_buf)
                 }
-            ));
+            )));
         }
 
         Ok(())
     }
 
-    fn jxa_json<T: DeserializeOwned>(&self, script: &str) -> anyhow::Result<T> {
-        let out = self
-            .run_osascript_jxa(script)
-            .context("osascript (JXA) failed")?;
+    fn jxa_json<T: DeserializeOwned>(&self, script: &str) -> Result<T> {
+        let out = self.run_osascript_jxa(script)?;
         let out = out.trim();
-        serde_json::from_str(out)
-            .with_context(|| format!("failed to parse osascript JSON output: {out}"))
+        Ok(serde_json::from_str(out)
+            .with_context(|| format!("failed to parse osascript JSON output: {out}"))?)
     }
 
-    fn build_jxa(action: &str, payload: &impl Serialize) -> anyhow::Result<String> {
-        let payload_json = serde_json::to_string(payload)?;
+    fn build_jxa(action: &str, payload: &impl Serialize) -> Result<String> {
+        let payload_json = serde_json::to_string(payload).context("serialize JXA payload")?;
+        let default_folder = DEFAULT_FOLDER;
         Ok(format!(
             r#"
 const Notes = Application("Notes");
@@ -228,6 +404,7 @@ Notes.includeStandardAdditions = true;
 
 const input = {payload_json};
 
+{BODY_CHUNKS_JS}
 function folderPathFor(folder, accountId) {{
   const parts = [folder.name()];
   const seen = {{}};
@@ -278,6 +455,10 @@ function resolveFolderIds(accountName, wantParts) {{
   const matches = candidates
     .filter(f => folderPathFor(f, accountId).join(" > ") === want)
     .map(f => f.id());
+  // The account's default folder is named in the user's language.
+  if (matches.length === 0 && want === {default_folder:?}) {{
+    try {{ return [acct.defaultFolder().id()]; }} catch (e) {{}}
+  }}
   return matches;
 }}
 
@@ -303,6 +484,22 @@ function main() {{
         body_html: String(n.body()),
       }};
     }}
+    case "notes.get_streaming": {{
+      // Metadata first (cheap), then the body as JSON-encoded chunks on stderr.
+      const n = Notes.notes.byId(input.id);
+      console.log(JSON.stringify({{ meta: {{
+        id: n.id(),
+        title: n.name(),
+        folder_id: n.container().id(),
+        created_at: n.creationDate().toISOString(),
+        modified_at: n.modificationDate().toISOString(),
+        body_html: "",
+      }} }}));
+      for (const chunk of bodyChunks(String(n.body()), input.chunk_chars)) {{
+        console.log(JSON.stringify({{ chunk }}));
+      }}
+      return "OK";
+    }}
     default:
       throw new Error("unknown action: " + {action:?});
   }}
@@ -313,7 +510,7 @@ console.log(JSON.stringify(main()));
         ))
     }
 
-    fn resolve_folder_id(&self, account: &str, folder_path: &[String]) -> anyhow::Result<String> {
+    fn resolve_folder_id(&self, account: &str, folder_path: &[String]) -> Result<String> {
         #[derive(Serialize)]
         struct Payload<'a> {
             account: &'a str,
@@ -333,16 +530,71 @@ console.log(JSON.stringify(main()));
         )?;
         let out: Out = self.jxa_json(&script)?;
         match out.matches.len() {
-            0 => Err(anyhow!("folder not found: {}", folder_path.join(" > "))),
+            0 => Err(NotesError::NotFound(format!(
+                "folder not found: {}",
+                folder_path.join(" > ")
+            ))),
             1 => Ok(out.matches[0].clone()),
-            n => Err(anyhow!(
+            n => Err(NotesError::Ambiguous(format!(
                 "folder path is ambiguous ({} matches): {}",
                 n,
                 folder_path.join(" > ")
-            )),
+            ))),
         }
     }
 
+    /// AppleScript that logs one `id<TAB>title<TAB>folder id<TAB>created<TAB>modified` line per
+    /// note in `folder_ids`. Dates are `days:seconds` of local time since 1970-01-01 00:00 local
+    /// time, since AppleScript formats dates by locale and prints large numbers in scientific
+    /// notation, and only knows today's UTC offset (see [`parse_epoch_days_seconds`]).
+    fn folder_notes_script(folder_ids: &[String]) -> String {
+        let ids = folder_ids
+            .iter()
+            .map(|id| format!("{id:?}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            r#"
+on replace_chars(s, find, repl)
+  set AppleScript's text item delimiters to find
+  set parts to every text item of s
+  set AppleScript's text item delimiters to repl
+  set s2 to parts as text
+  set AppleScript's text item delimiters to ""
+  return s2
+end replace_chars
+
+on epoch_text(d, epoch)
+  set s to d - epoch
+  return ((s div 86400) as text) & ":" & ((s mod 86400 div 1) as text)
+end epoch_text
+
+set epoch to current date
+set day of epoch to 1
+set year of epoch to 1970
+set month of epoch to January
+set time of epoch to 0
+
+tell application "Notes"
+  repeat with wantedId in {{{ids}}}
+    set f to folder id (wantedId as text)
+    set folderId to (id of f as text)
+    set ns to every note of f
+    repeat with n in ns
+      set t to (name of n as text)
+      set t to my replace_chars(t, tab, " ")
+      set t to my replace_chars(t, return, " ")
+      set created to my epoch_text(creation date of n, epoch)
+      set modified to my epoch_text(modification date of n, epoch)
+      log (id of n as text) & tab & t & tab & folderId & tab & created & tab & modified
+    end repeat
+  end repeat
+  return "OK"
+end tell
+"#
+        )
+    }
+
     fn extract_osascript_log_payload(line: &str) -> &str {
         // In some environments, `osascript` prefixes log output. Be permissive.
         if let Some(idx) = line.find("log:") {
@@ -354,10 +606,11 @@ console.log(JSON.stringify(main()));
     fn stream_note_summaries_applescript(
         &self,
         script: &str,
+        cancel: &CancelToken,
         on_note: &mut dyn FnMut(NoteSummary),
-    ) -> anyhow::Result<()> {
+    ) -> Result<()> {
         let mut seen_ids: HashSet<String> = HashSet::new();
-        self.run_osascript_streaming(&["-"], script, |line| {
+        self.run_osascript_streaming(&["-"], script, cancel, |line| {
             let payload = Self::extract_osascript_log_payload(line);
             if payload.is_empty() || !payload.contains('\t') {
                 return;
@@ -373,24 +626,28 @@ console.log(JSON.stringify(main()));
 }
 
 impl NotesBackend for HybridBackend {
-    fn list_accounts(&self) -> anyhow::Result<Vec<Account>> {
-        self.db.list_accounts()
+    fn list_accounts(&self) -> Result<Vec<Account>> {
+        Ok(self.db.list_accounts()?)
     }
 
-    fn list_folders(&self, account: &str) -> anyhow::Result<Vec<Folder>> {
-        self.db.list_folders(account)
+    fn list_folders(&self, account: &str) -> Result<Vec<Folder>> {
+        Ok(self.db.list_folders(account)?)
     }
 
-    fn list_notes(&self, account: &str) -> anyhow::Result<Vec<NoteSummary>> {
-        self.db.list_notes(account)
+    fn list_notes(&self, account: &str) -> Result<Vec<NoteSummary>> {
+        Ok(self.db.list_notes(account)?)
     }
 
     fn list_notes_in_folder(
         &self,
         account: &str,
         folder_path: &[String],
-    ) -> anyhow::Result<Vec<NoteSummary>> {
-        self.db.list_notes_in_folder(account, folder_path)
+    ) -> Result<Vec<NoteSummary>> {
+        Ok(self.db.list_notes_in_folder(account, folder_path)?)
+    }
+
+    fn list_deleted_notes(&self, account: &str) -> Result<Vec<NoteSummary>> {
+        Ok(self.db.list_deleted_notes(account)?)
     }
 
     fn stream_note_summaries(
@@ -398,7 +655,7 @@ impl NotesBackend for HybridBackend {
         account: &str,
         folder_path: Option<&[String]>,
         on_note: &mut dyn FnMut(NoteSummary),
-    ) -> anyhow::Result<()> {
+    ) -> Result<()> {
         let notes = if let Some(folder_path) = folder_path {
             self.list_notes_in_folder(account, folder_path)?
         } else {
@@ -410,8 +667,13 @@ impl NotesBackend for HybridBackend {
         Ok(())
     }
 
-    fn get_note(&self, id: &str) -> anyhow::Result<Note> {
-        self.osascript.get_note(id)
+    fn get_note(&self, id: &str) -> Result<Note> {
+        self.osascript.get_note(&self.db.resolve_note_id(id)?)
+    }
+
+    fn get_note_streaming(&self, id: &str, on_chunk: &mut dyn FnMut(&str)) -> Result<Note> {
+        self.osascript
+            .get_note_streaming(&self.db.resolve_note_id(id)?, on_chunk)
     }
 
     fn create_note_html(
@@ -420,63 +682,76 @@ impl NotesBackend for HybridBackend {
         folder_path: &[String],
         title: &str,
         body_html: &str,
-    ) -> anyhow::Result<String> {
+    ) -> Result<String> {
         self.osascript
             .create_note_html(account, folder_path, title, body_html)
     }
 
-    fn set_note_title(&self, id: &str, title: &str) -> anyhow::Result<()> {
-        self.osascript.set_note_title(id, title)
+    fn set_note_title(&self, id: &str, title: &str) -> Result<()> {
+        self.osascript
+            .set_note_title(&self.db.resolve_note_id(id)?, title)
     }
 
-    fn set_note_body_html(&self, id: &str, body_html: &str) -> anyhow::Result<()> {
-        self.osascript.set_note_body_html(id, body_html)
+    fn set_note_body_html(&self, id: &str, body_html: &str) -> Result<()> {
+        self.osascript
+            .set_note_body_html(&self.db.resolve_note_id(id)?, body_html)
     }
 
-    fn append_note_body_html(&self, id: &str, body_html: &str) -> anyhow::Result<()> {
-        self.osascript.append_note_body_html(id, body_html)
+    fn append_note_body_html(&self, id: &str, body_html: &str) -> Result<()> {
+        self.osascript
+            .append_note_body_html(&self.db.resolve_note_id(id)?, body_html)
     }
 
-    fn delete_note(&self, id: &str) -> anyhow::Result<()> {
-        self.osascript.delete_note(id)
+    fn delete_note(&self, id: &str) -> Result<()> {
+        self.osascript.delete_note(&self.db.resolve_note_id(id)?)
     }
 
-    fn move_note(&self, id: &str, account: &str, folder_path: &[String]) -> anyhow::Result<()> {
-        self.osascript.move_note(id, account, folder_path)
+    fn move_note(&self, id: &str, account: &str, folder_path: &[String]) -> Result<()> {
+        self.osascript
+            .move_note(&self.db.resolve_note_id(id)?, account, folder_path)
     }
 
-    fn create_folder(
-        &self,
-        account: &str,
-        parent_path: &[String],
-        name: &str,
-    ) -> anyhow::Result<String> {
+    fn open_note(&self, id: &str) -> Result<()> {
+        self.osascript.open_note(&self.db.resolve_note_id(id)?)
+    }
+
+    fn attach_file(&self, id: &str, path: &Path) -> Result<()> {
+        self.osascript
+            .attach_file(&self.db.resolve_note_id(id)?, path)
+    }
+
+    fn create_folder(&self, account: &str, parent_path: &[String], name: &str) -> Result<String> {
         self.osascript.create_folder(account, parent_path, name)
     }
 
-    fn rename_folder(
+    fn rename_folder(&self, account: &str, folder_path: &[String], name: &str) -> Result<()> {
+        self.osascript.rename_folder(account, folder_path, name)
+    }
+
+    fn move_folder(
         &self,
         account: &str,
         folder_path: &[String],
-        name: &str,
-    ) -> anyhow::Result<()> {
-        self.osascript.rename_folder(account, folder_path, name)
+        parent_path: &[String],
+    ) -> Result<()> {
+        self.osascript
+            .move_folder(account, folder_path, parent_path)
     }
 
-    fn delete_folder(&self, account: &str, folder_path: &[String]) -> anyhow::Result<()> {
+    fn delete_folder(&self, account: &str, folder_path: &[String]) -> Result<()> {
         self.osascript.delete_folder(account, folder_path)
     }
 }
 
 impl NotesBackend for OsascriptBackend {
-    fn list_accounts(&self) -> anyhow::Result<Vec<Account>> {
+    fn list_accounts(&self) -> Result<Vec<Account>> {
         #[derive(Serialize)]
         struct Payload {}
         let script = Self::build_jxa("accounts.list", &Payload {})?;
         self.jxa_json(&script)
     }
 
-    fn list_folders(&self, account: &str) -> anyhow::Result<Vec<Folder>> {
+    fn list_folders(&self, account: &str) -> Result<Vec<Folder>> {
         #[derive(Serialize)]
         struct Payload<'a> {
             account: &'a str,
@@ -485,7 +760,7 @@ impl NotesBackend for OsascriptBackend {
         self.jxa_json(&script)
     }
 
-    fn list_notes(&self, account: &str) -> anyhow::Result<Vec<NoteSummary>> {
+    fn list_notes(&self, account: &str) -> Result<Vec<NoteSummary>> {
         let mut out = Vec::new();
         self.stream_note_summaries(account, None, &mut |n| out.push(n))?;
         Ok(out)
@@ -495,7 +770,7 @@ impl NotesBackend for OsascriptBackend {
         &self,
         account: &str,
         folder_path: &[String],
-    ) -> anyhow::Result<Vec<NoteSummary>> {
+    ) -> Result<Vec<NoteSummary>> {
         let mut out = Vec::new();
         self.stream_note_summaries(account, Some(folder_path), &mut |n| out.push(n))?;
         Ok(out)
@@ -506,76 +781,87 @@ impl NotesBackend for OsascriptBackend {
         account: &str,
         folder_path: Option<&[String]>,
         on_note: &mut dyn FnMut(NoteSummary),
-    ) -> anyhow::Result<()> {
-        // AppleScript is significantly faster/reliable for listing metadata across large accounts.
-        // We stream via `log` to avoid building giant return strings and to enable progress counts.
-        let folder_id = if let Some(folder_path) = folder_path {
-            Some(self.resolve_folder_id(account, folder_path)?)
-        } else {
-            None
-        };
-
-        let script = if let Some(folder_id) = folder_id {
-            format!(
-                r#"
-on replace_chars(s, find, repl)
-  set AppleScript's text item delimiters to find
-  set parts to every text item of s
-  set AppleScript's text item delimiters to repl
-  set s2 to parts as text
-  set AppleScript's text item delimiters to ""
-  return s2
-end replace_chars
+    ) -> Result<()> {
+        self.stream_note_summaries_cancellable(account, folder_path, &CancelToken::new(), on_note)
+    }
 
-tell application "Notes"
-  set f to folder id {folder_id:?}
-  set folderId to (id of f as text)
-  set ns to every note of f
-  repeat with n in ns
-    set t to (name of n as text)
-    set t to my replace_chars(t, tab, " ")
-    set t to my replace_chars(t, return, " ")
-    log (id of n as text) & tab & t & tab & folderId
-  end repeat
-  return "OK"
-end tell
-"#
-            )
-        } else {
-            format!(
-                r#"
-on replace_chars(s, find, repl)
-  set AppleScript's text item delimiters to find
-  set parts to every text item of s
-  set AppleScript's text item delimiters to repl
-  set s2 to parts as text
-  set AppleScript's text item delimiters to ""
-  return s2
-end replace_chars
+    fn stream_note_summaries_cancellable(
+        &self,
+        account: &str,
+        folder_path: Option<&[String]>,
+        cancel: &CancelToken,
+        on_note: &mut dyn FnMut(NoteSummary),
+    ) -> Result<()> {
+        if let Some(folder_path) = folder_path {
+            let folder_id = self.resolve_folder_id(account, folder_path)?;
+            let script = Self::folder_notes_script(&[folder_id]);
+            return self.stream_note_summaries_applescript(&script, cancel, on_note);
+        }
 
-tell application "Notes"
-  tell account {account:?}
-    repeat with f in folders
-      set folderId to (id of f as text)
-      set ns to every note of f
-      repeat with n in ns
-        set t to (name of n as text)
-        set t to my replace_chars(t, tab, " ")
-        set t to my replace_chars(t, return, " ")
-        log (id of n as text) & tab & t & tab & folderId
-      end repeat
-    end repeat
-    return "OK"
-  end tell
-end tell
-"#
-            )
-        };
+        // Split the account's folders into chunks, list each chunk in its own `osascript`, and
+        // merge the results here (the callback isn't `Send`, so it stays on this thread).
+        let folder_ids: Vec<String> = self
+            .list_folders(account)?
+            .into_iter()
+            .map(|f| f.id)
+            .collect();
+        if folder_ids.is_empty() {
+            return Ok(());
+        }
+        let chunk_len = folder_ids.len().div_ceil(LIST_JOBS);
+        let local = cancel.child();
+        let (tx, rx) = mpsc::channel::<NoteSummary>();
+        let results = std::thread::scope(|scope| {
+            let workers: Vec<_> = folder_ids
+                .chunks(chunk_len)
+                .map(|chunk| {
+                    let script = Self::folder_notes_script(chunk);
+                    let tx = tx.clone();
+                    let local = &local;
+                    scope.spawn(move || {
+                        let res =
+                            self.stream_note_summaries_applescript(&script, local, &mut |n| {
+                                let _ = tx.send(n);
+                            });
+                        if res.is_err() {
+                            local.cancel();
+                        }
+                        res
+                    })
+                })
+                .collect();
+            drop(tx);
+
+            let mut seen_ids: HashSet<String> = HashSet::new();
+            for n in rx {
+                if seen_ids.insert(n.id.clone()) {
+                    on_note(n);
+                }
+            }
+            workers
+                .into_iter()
+                .map(|w| w.join().expect("osascript listing worker panicked"))
+                .collect::<Vec<_>>()
+        });
 
-        self.stream_note_summaries_applescript(&script, on_note)
+        // Report the failure that stopped the others, not their resulting cancellations.
+        let mut first_err: Option<NotesError> = None;
+        for err in results.into_iter().filter_map(Result::err) {
+            if first_err
+                .as_ref()
+                .is_none_or(|e| matches!(e, NotesError::Cancelled(_)))
+            {
+                first_err = Some(err);
+            }
+        }
+        cancel.check()?;
+        match first_err {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
     }
 
-    fn get_note(&self, id: &str) -> anyhow::Result<Note> {
+    fn get_note(&self, id: &str) -> Result<Note> {
         #[derive(Serialize)]
         struct Payload<'a> {
             id: &'a str,
@@ -584,14 +870,75 @@ end tell
         self.jxa_json(&script)
     }
 
+    fn get_note_streaming(&self, id: &str, on_chunk: &mut dyn FnMut(&str)) -> Result<Note> {
+        #[derive(Serialize)]
+        struct Payload<'a> {
+            id: &'a str,
+            chunk_chars: usize,
+        }
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Line {
+            Meta { meta: Note },
+            Chunk { chunk: String },
+        }
+
+        let script = Self::build_jxa(
+            "notes.get_streaming",
+            &Payload {
+                id,
+                chunk_chars: 64 * 1024,
+            },
+        )?;
+        let mut note = None;
+        let mut bad_line = None;
+        self.run_osascript_streaming(
+            &["-l", "JavaScript", "-"],
+            &script,
+            &CancelToken::new(),
+            |line| {
+                if bad_line.is_some() {
+                    return;
+                }
+                match serde_json::from_str::<Line>(line.trim()) {
+                    Ok(Line::Meta { meta }) => note = Some(meta),
+                    Ok(Line::Chunk { chunk }) => on_chunk(&chunk),
+                    // The script's return value, after the last chunk.
+                    Err(_) if line.trim() == r#""OK""# => {}
+                    Err(e) => bad_line = Some(format!("{e}: {}", line.trim())),
+                }
+            },
+        )?;
+        if let Some(bad_line) = bad_line {
+            return Err(NotesError::ScriptError(format!(
+                "osascript sent an unreadable chunk of note {id}: {bad_line}"
+            )));
+        }
+        note.ok_or_else(|| {
+            NotesError::ScriptError(format!("osascript did not return note metadata for {id}"))
+        })
+    }
+
     fn create_note_html(
         &self,
         account: &str,
         folder_path: &[String],
         title: &str,
         body_html: &str,
-    ) -> anyhow::Result<String> {
+    ) -> Result<String> {
         // Use AppleScript for write operations (JXA make is unreliable on some systems).
+        if folder_path.is_empty() {
+            let script = format!(
+                r#"
+tell application "Notes"
+  set n to make new note at account {account:?} with properties {{name:{title:?}, body:{body_html:?}}}
+  return id of n as text
+end tell
+"#
+            );
+            let out = self.run_osascript_applescript(&script)?;
+            return Ok(out.trim().to_string());
+        }
         let folder_id = self.resolve_folder_id(account, folder_path)?;
         let script = format!(
             r#"
@@ -606,7 +953,7 @@ end tell
         Ok(out.trim().to_string())
     }
 
-    fn set_note_title(&self, id: &str, title: &str) -> anyhow::Result<()> {
+    fn set_note_title(&self, id: &str, title: &str) -> Result<()> {
         let script = format!(
             r#"
 tell application "Notes"
@@ -619,7 +966,7 @@ end tell
         Ok(())
     }
 
-    fn set_note_body_html(&self, id: &str, body_html: &str) -> anyhow::Result<()> {
+    fn set_note_body_html(&self, id: &str, body_html: &str) -> Result<()> {
         let script = format!(
             r#"
 tell application "Notes"
@@ -632,7 +979,7 @@ end tell
         Ok(())
     }
 
-    fn append_note_body_html(&self, id: &str, body_html: &str) -> anyhow::Result<()> {
+    fn append_note_body_html(&self, id: &str, body_html: &str) -> Result<()> {
         let script = format!(
             r#"
 tell application "Notes"
@@ -645,7 +992,7 @@ end tell
         Ok(())
     }
 
-    fn delete_note(&self, id: &str) -> anyhow::Result<()> {
+    fn delete_note(&self, id: &str) -> Result<()> {
         let script = format!(
             r#"
 tell application "Notes"
@@ -658,7 +1005,34 @@ end tell
         Ok(())
     }
 
-    fn move_note(&self, id: &str, account: &str, folder_path: &[String]) -> anyhow::Result<()> {
+    fn open_note(&self, id: &str) -> Result<()> {
+        let script = format!(
+            r#"
+tell application "Notes"
+  show note id {id:?}
+  activate
+end tell
+"#
+        );
+        self.run_osascript_applescript(&script)?;
+        Ok(())
+    }
+
+    fn attach_file(&self, id: &str, path: &Path) -> Result<()> {
+        let path = path.to_string_lossy();
+        let script = format!(
+            r#"
+tell application "Notes"
+  set n to note id {id:?}
+  make new attachment at n with data (POSIX file {path:?})
+end tell
+"#
+        );
+        self.run_osascript_applescript(&script)?;
+        Ok(())
+    }
+
+    fn move_note(&self, id: &str, account: &str, folder_path: &[String]) -> Result<()> {
         let folder_id = self.resolve_folder_id(account, folder_path)?;
         let script = format!(
             r#"
@@ -673,12 +1047,7 @@ end tell
         Ok(())
     }
 
-    fn create_folder(
-        &self,
-        account: &str,
-        parent_path: &[String],
-        name: &str,
-    ) -> anyhow::Result<String> {
+    fn create_folder(&self, account: &str, parent_path: &[String], name: &str) -> Result<String> {
         let parent_id = self.resolve_folder_id(account, parent_path)?;
         let script = format!(
             r#"
@@ -693,18 +1062,32 @@ end tell
         Ok(out.trim().to_string())
     }
 
-    fn rename_folder(
+    fn rename_folder(&self, account: &str, folder_path: &[String], name: &str) -> Result<()> {
+        let folder_id = self.resolve_folder_id(account, folder_path)?;
+        let script = format!(
+            r#"
+tell application "Notes"
+  set f to folder id {folder_id:?}
+  set name of f to {name:?}
+end tell
+"#
+        );
+        self.run_osascript_applescript(&script)?;
+        Ok(())
+    }
+
+    fn move_folder(
         &self,
         account: &str,
         folder_path: &[String],
-        name: &str,
-    ) -> anyhow::Result<()> {
+        parent_path: &[String],
+    ) -> Result<()> {
         let folder_id = self.resolve_folder_id(account, folder_path)?;
+        let parent_id = self.resolve_folder_id(account, parent_path)?;
         let script = format!(
             r#"
 tell application "Notes"
-  set f to folder id {folder_id:?}
-  set name of f to {name:?}
+  move folder id {folder_id:?} to folder id {parent_id:?}
 end tell
 "#
         );
@@ -712,7 +1095,7 @@ end tell
         Ok(())
     }
 
-    fn delete_folder(&self, account: &str, folder_path: &[String]) -> anyhow::Result<()> {
+    fn delete_folder(&self, account: &str, folder_path: &[String]) -> Result<()> {
         let folder_id = self.resolve_folder_id(account, folder_path)?;
         let script = format!(
             r#"
@@ -727,21 +1110,51 @@ end tell
     }
 }
 
+/// Which [`NotesBackend`] [`make_backend`] picks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Backend {
+    /// Auto-detect the fastest available backend (prefers DB when present).
+    Auto,
+    /// Use `osascript` for all reads and writes.
+    Osascript,
+    /// Use the Apple Notes database for reads (macOS only); writes still use `osascript`.
+    Db,
+}
+
+/// The backend to use: a [`FixtureBackend`](crate::fixture::FixtureBackend) for `fixture` (or
+/// `$APPLE_NOTES_FIXTURE`) if given, otherwise the one `backend` asks for.
 pub fn make_backend(
     fixture: Option<std::path::PathBuf>,
-    backend: cli::Backend,
+    backend: Backend,
 ) -> anyhow::Result<Box<dyn NotesBackend>> {
+    open_backend(fixture, backend).map(|(backend, _)| backend)
+}
+
+/// Like [`make_backend`], also saying whether it opened the Notes database, i.e. whether reads
+/// may go to the database directly (`--backend auto` falls back to `osascript` when it can't).
+pub(crate) fn open_backend(
+    fixture: Option<std::path::PathBuf>,
+    backend: Backend,
+) -> anyhow::Result<(Box<dyn NotesBackend>, bool)> {
     if let Some(path) = fixture.or_else(|| std::env::var_os("APPLE_NOTES_FIXTURE").map(Into::into))
     {
-        return Ok(Box::new(crate::fixture::FixtureBackend::from_path(path)?));
+        return Ok((
+            Box::new(crate::fixture::FixtureBackend::from_path(path)?),
+            false,
+        ));
     }
 
     match backend {
-        cli::Backend::Osascript => Ok(Box::new(OsascriptBackend)),
-        cli::Backend::Db => Ok(Box::new(HybridBackend::new(db::NotesDb::open_default()?))),
-        cli::Backend::Auto => match db::NotesDb::open_default() {
-            Ok(db) => Ok(Box::new(HybridBackend::new(db))),
-            Err(_) => Ok(Box::new(OsascriptBackend)),
+        Backend::Osascript => Ok((Box::new(OsascriptBackend), false)),
+        Backend::Db => Ok((
+            Box::new(HybridBackend::new(db::NotesDb::open_default()?)),
+            true,
+        )),
+        Backend::Auto => match db::NotesDb::open_default() {
+            Ok(db) => Ok((Box::new(HybridBackend::new(db)), true)),
+            Err(_) => Ok((Box::new(OsascriptBackend), false)),
         },
     }
 }
@@ -753,7 +1166,7 @@ fn parse_note_summaries_tsv(s: &str) -> anyhow::Result<Vec<NoteSummary>> {
         if line.is_empty() {
             continue;
         }
-        let mut parts = line.splitn(3, '\t');
+        let mut parts = line.split('\t');
         let id = parts
             .next()
             .ok_or_else(|| anyhow!("invalid notes TSV on line {}: missing id", idx + 1))?;
@@ -763,25 +1176,71 @@ fn parse_note_summaries_tsv(s: &str) -> anyhow::Result<Vec<NoteSummary>> {
         let folder_id = parts
             .next()
             .ok_or_else(|| anyhow!("invalid notes TSV on line {}: missing folder id", idx + 1))?;
+        let mut date = || parts.next().and_then(parse_epoch_days_seconds);
         out.push(NoteSummary {
             id: id.to_string(),
             title: title.to_string(),
             folder_id: folder_id.to_string(),
+            uuid: None,
+            created_at: date(),
+            modified_at: date(),
+            pinned: false,
+            locked: false,
         });
     }
     Ok(out)
 }
 
+/// Parses the `days:seconds` dates of [`OsascriptBackend::folder_notes_script`], local times that
+/// become UTC with the offset in effect on that date (not today's, which differs across DST).
+fn parse_epoch_days_seconds(s: &str) -> Option<OffsetDateTime> {
+    let (days, secs) = s.trim().split_once(':')?;
+    let secs = days.parse::<i64>().ok()? * 86400 + secs.parse::<i64>().ok()?;
+    let local = OffsetDateTime::from_unix_timestamp(secs).ok()?;
+    local_time_to_utc(time::PrimitiveDateTime::new(local.date(), local.time()))
+}
+
+/// The UTC time of a local wall-clock time, by `mktime`, which knows the time zone's history.
+#[cfg(unix)]
+fn local_time_to_utc(local: time::PrimitiveDateTime) -> Option<OffsetDateTime> {
+    // Safety: `tm` is plain data, fully initialized here, and `mktime` only reads and normalizes
+    // it.
+    let secs = unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        tm.tm_year = local.year() - 1900;
+        tm.tm_mon = i32::from(u8::from(local.month())) - 1;
+        tm.tm_mday = i32::from(local.day());
+        tm.tm_hour = i32::from(local.hour());
+        tm.tm_min = i32::from(local.minute());
+        tm.tm_sec = i32::from(local.second());
+        tm.tm_isdst = -1;
+        libc::mktime(&mut tm)
+    };
+    OffsetDateTime::from_unix_timestamp(secs).ok()
+}
+
+#[cfg(not(unix))]
+fn local_time_to_utc(local: time::PrimitiveDateTime) -> Option<OffsetDateTime> {
+    let offset = time::UtcOffset::current_local_offset().unwrap_or(time::UtcOffset::UTC);
+    Some(local.assume_offset(offset).to_offset(time::UtcOffset::UTC))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(unix)]
     use std::env;
+    #[cfg(unix)]
     use std::os::unix::fs::PermissionsExt;
+    #[cfg(unix)]
     use std::sync::{Mutex, OnceLock};
+    #[cfg(unix)]
     use tempfile::tempdir;
 
+    #[cfg(unix)]
     static ENV_LOCK: OnceLock<Mutex<()>> = OnceLock::new();
 
+    #[cfg(unix)]
     fn lock_env() -> std::sync::MutexGuard<'static, ()> {
         ENV_LOCK
             .get_or_init(|| Mutex::new(()))
@@ -789,6 +1248,7 @@ mod tests {
             .unwrap_or_else(|e| e.into_inner())
     }
 
+    #[cfg(unix)]
     fn with_stub_osascript<T>(stub_mode: &str, f: impl FnOnce() -> T) -> T {
         let _guard = lock_env();
         let dir = tempdir().unwrap();
@@ -800,6 +1260,11 @@ MODE="${APPLE_NOTES_STUB_MODE:-ok}"
 ARGS="$*"
 SCRIPT="$(cat)"
 
+if [[ "$MODE" == "denied" ]]; then
+  echo "execution error: Not authorized to send Apple events to Notes. (-1743)" >&2
+  exit 1
+fi
+
 if [[ "$ARGS" == *"-l JavaScript"* ]]; then
   FLAT="$(printf '%s' "$SCRIPT" | tr '\n' ' ')"
   ACTION=""
@@ -836,14 +1301,41 @@ if [[ "$ARGS" == *"-l JavaScript"* ]]; then
       echo '{"id":"x-coredata://UUID/ICNote/p20","title":"Hello","folder_id":"x-coredata://UUID/ICFolder/p10","created_at":"2025-12-20T00:00:00Z","modified_at":"2025-12-20T01:00:00Z","body_html":"<div>Hi</div>"}'
       exit 0
       ;;
+    notes.get_streaming)
+      echo '{"meta":{"id":"x-coredata://UUID/ICNote/p20","title":"Hello","folder_id":"x-coredata://UUID/ICFolder/p10","created_at":"2025-12-20T00:00:00Z","modified_at":"2025-12-20T01:00:00Z","body_html":""}}' >&2
+      echo '{"chunk":"<div>Hi\n"}' >&2
+      if [[ "$MODE" == "astral" ]]; then
+        echo '{"chunk":"ab"}' >&2
+        echo '{"chunk":"\ud83d\ude00c"}' >&2
+      elif [[ "$MODE" == "split_pair" ]]; then
+        echo '{"chunk":"ab\ud83d"}' >&2
+        echo '{"chunk":"\ude00c"}' >&2
+      else
+        echo '{"chunk":"there</div>"}' >&2
+      fi
+      echo '"OK"' >&2
+      exit 0
+      ;;
   esac
 
   echo "unknown JXA stub action" >&2
   exit 1
 fi
 
+if [[ "$SCRIPT" == *'make new note at account "iCloud"'* ]]; then
+  echo "x-coredata://UUID/ICNote/p21"
+  exit 0
+fi
+
 # AppleScript streaming path (stderr logs)
+if [[ "$MODE" == "fail_archive" && "$SCRIPT" == *"ICFolder/p11"* ]]; then
+  echo "execution error: boom (-2700)" >&2
+  exit 1
+fi
 printf 'log: id1\ttitle1\tfolder1\n' >&2
+if [[ "$MODE" == "slow" ]]; then
+  sleep 5
+fi
 printf 'log: id1\ttitle1\tfolder1\n' >&2
 printf 'log: id2\ttitle2\tfolder2\n' >&2
 exit 0
@@ -875,13 +1367,69 @@ exit 0
         res
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn preflight_explains_missing_automation_permission() {
+        with_stub_osascript("ok", || OsascriptBackend.preflight()).unwrap();
+        let err = with_stub_osascript("denied", || OsascriptBackend.preflight()).unwrap_err();
+        assert!(
+            err.to_string().contains("Privacy & Security > Automation"),
+            "{err}"
+        );
+    }
+
+    /// Runs `f` with the local time zone set to `tz` (a POSIX `TZ` value).
+    #[cfg(unix)]
+    fn with_time_zone<T>(tz: &str, f: impl FnOnce() -> T) -> T {
+        let _guard = lock_env();
+        let old = env::var_os("TZ");
+        // Safety: environment variables are process-global; we serialize these tests with ENV_LOCK.
+        unsafe { env::set_var("TZ", tz) };
+        let res = f();
+        match old {
+            Some(v) => unsafe { env::set_var("TZ", v) },
+            None => unsafe { env::remove_var("TZ") },
+        }
+        res
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn osascript_dates_use_the_utc_offset_of_their_own_day() {
+        with_time_zone("EST5EDT,M3.2.0,M11.1.0", || {
+            // 2025-01-15 and 2025-07-15, 12:00 local time.
+            assert_eq!(
+                parse_epoch_days_seconds("20103:43200"),
+                Some(time::macros::datetime!(2025-01-15 17:00 UTC))
+            );
+            assert_eq!(
+                parse_epoch_days_seconds("20284:43200"),
+                Some(time::macros::datetime!(2025-07-15 16:00 UTC))
+            );
+        });
+    }
+
+    #[cfg(unix)]
     #[test]
     fn parse_note_summaries_tsv_parses_lines() {
-        let parsed =
-            parse_note_summaries_tsv("id1\ttitle1\tfolder1\nid2\ttitle2\tfolder2\n").unwrap();
+        let parsed = with_time_zone("UTC0", || {
+            parse_note_summaries_tsv(
+                "id1\ttitle1\tfolder1\nid2\ttitle2\tfolder2\t20442:3600\t-1:86399\n",
+            )
+            .unwrap()
+        });
         assert_eq!(parsed.len(), 2);
         assert_eq!(parsed[0].id, "id1");
+        assert_eq!(parsed[0].created_at, None);
         assert_eq!(parsed[1].folder_id, "folder2");
+        assert_eq!(
+            parsed[1].created_at,
+            Some(time::macros::datetime!(2025-12-20 01:00 UTC))
+        );
+        assert_eq!(
+            parsed[1].modified_at,
+            Some(time::macros::datetime!(1969-12-31 23:59:59 UTC))
+        );
     }
 
     #[test]
@@ -908,6 +1456,16 @@ exit 0
         assert!(s.contains("unknown action"));
     }
 
+    #[test]
+    fn folder_resolution_falls_back_to_the_default_folder() {
+        #[derive(Serialize)]
+        struct Payload {}
+        let s = OsascriptBackend::build_jxa("folders.resolve", &Payload {}).unwrap();
+        assert!(s.contains(r#"want === "Notes""#), "{s}");
+        assert!(s.contains("acct.defaultFolder().id()"));
+    }
+
+    #[cfg(unix)]
     #[test]
     fn osascript_backend_list_accounts_works_with_stub() {
         with_stub_osascript("ok", || {
@@ -918,6 +1476,7 @@ exit 0
         });
     }
 
+    #[cfg(unix)]
     #[test]
     fn osascript_backend_get_note_works_with_stub() {
         with_stub_osascript("ok", || {
@@ -928,6 +1487,86 @@ exit 0
         });
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn osascript_backend_creates_notes_in_the_default_folder_without_a_path() {
+        with_stub_osascript("ok", || {
+            let id = OsascriptBackend
+                .create_note_html("iCloud", &[], "Idea", "<div>x</div>")
+                .unwrap();
+            assert_eq!(id, "x-coredata://UUID/ICNote/p21");
+        });
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn osascript_backend_get_note_streaming_yields_chunks() {
+        with_stub_osascript("ok", || {
+            let b = OsascriptBackend;
+            let mut chunks = Vec::new();
+            let note = b
+                .get_note_streaming("x-coredata://UUID/ICNote/p20", &mut |c| {
+                    chunks.push(c.to_string())
+                })
+                .unwrap();
+            assert_eq!(note.title, "Hello");
+            assert!(note.body_html.is_empty());
+            assert_eq!(chunks, vec!["<div>Hi\n", "there</div>"]);
+        });
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn osascript_backend_get_note_streaming_rejects_split_surrogates() {
+        with_stub_osascript("astral", || {
+            let mut body = String::new();
+            OsascriptBackend
+                .get_note_streaming("x-coredata://UUID/ICNote/p20", &mut |c| body.push_str(c))
+                .unwrap();
+            assert_eq!(body, "<div>Hi\nab\u{1F600}c");
+        });
+        with_stub_osascript("split_pair", || {
+            let err = OsascriptBackend
+                .get_note_streaming("x-coredata://UUID/ICNote/p20", &mut |_| {})
+                .unwrap_err();
+            assert!(matches!(err, NotesError::ScriptError(_)), "{err}");
+        });
+    }
+
+    #[test]
+    fn body_chunks_keep_surrogate_pairs_together() {
+        // The chunking runs in JXA; check it with Node when it's installed.
+        let script = format!(
+            "{BODY_CHUNKS_JS}{}",
+            r"console.log(JSON.stringify([
+                bodyChunks('ab\uD83D\uDE00c', 3), bodyChunks('\uD83D\uDE00', 1), bodyChunks('abcd', 2)
+            ]));"
+        );
+        let Ok(mut node) = Command::new("node")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+        else {
+            return;
+        };
+        node.stdin
+            .take()
+            .unwrap()
+            .write_all(script.as_bytes())
+            .unwrap();
+        let out = node.wait_with_output().unwrap();
+        let chunks: Vec<Vec<String>> = serde_json::from_slice(&out.stdout).unwrap();
+        assert_eq!(
+            chunks,
+            [
+                vec!["ab".to_string(), "\u{1F600}c".to_string()],
+                vec!["\u{1F600}".to_string()],
+                vec!["ab".to_string(), "cd".to_string()],
+            ]
+        );
+    }
+
+    #[cfg(unix)]
     #[test]
     fn osascript_backend_stream_note_summaries_dedups() {
         with_stub_osascript("ok", || {
@@ -941,6 +1580,48 @@ exit 0
         });
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn osascript_backend_lists_folder_chunks_concurrently_and_reports_the_real_failure() {
+        let script = OsascriptBackend::folder_notes_script(&["a".into(), "b\"c".into()]);
+        assert!(
+            script.contains(r#"repeat with wantedId in {"a", "b\"c"}"#),
+            "{script}"
+        );
+
+        // Two folders → two `osascript` workers; the one listing "Archive" fails.
+        with_stub_osascript("fail_archive", || {
+            let err = OsascriptBackend
+                .stream_note_summaries("iCloud", None, &mut |_| {})
+                .unwrap_err();
+            assert!(
+                matches!(&err, NotesError::ScriptError(msg) if msg.contains("boom")),
+                "{err}"
+            );
+        });
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn osascript_backend_stream_note_summaries_cancels_on_deadline() {
+        with_stub_osascript("slow", || {
+            let b = OsascriptBackend;
+            let cancel = CancelToken::new().with_timeout(Duration::from_millis(200));
+            let started = std::time::Instant::now();
+            let mut out = Vec::new();
+            let err = b
+                .stream_note_summaries_cancellable("iCloud", None, &cancel, &mut |n| out.push(n))
+                .unwrap_err();
+            assert!(started.elapsed() < Duration::from_secs(3));
+            assert!(matches!(
+                err,
+                NotesError::Cancelled(crate::cancel::Cancelled::DeadlineExceeded)
+            ));
+            assert_eq!(out.len(), 1);
+        });
+    }
+
+    #[cfg(unix)]
     #[test]
     fn resolve_folder_id_errors_on_no_matches() {
         with_stub_osascript("resolve_empty", || {
@@ -952,6 +1633,7 @@ exit 0
         });
     }
 
+    #[cfg(unix)]
     #[test]
     fn resolve_folder_id_errors_on_multiple_matches() {
         with_stub_osascript("resolve_ambiguous", || {
@@ -0,0 +1,583 @@
+//! `apple-notes tui`: an interactive browser with a folder tree, a filterable notes list and a
+//! Markdown preview. Every read and edit goes through the [`NotesBackend`].
+
+use crate::model::{Folder, NoteSummary};
+use crate::render;
+use crate::transport::NotesBackend;
+use anyhow::anyhow;
+use ratatui::Frame;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use std::collections::HashMap;
+use std::io::IsTerminal;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pane {
+    Folders,
+    Notes,
+    Preview,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Mode {
+    Browse,
+    /// Typing into the notes filter.
+    Filter,
+    /// Typing the selected note's new title.
+    Rename(String),
+    /// Typing the folder path to move the selected note to.
+    Move(String),
+    ConfirmDelete,
+    /// The selected note full-screen.
+    Open,
+}
+
+struct App<'a> {
+    backend: &'a dyn NotesBackend,
+    account: String,
+    read_only: bool,
+    /// Sorted by path, so parents come right before their subfolders.
+    folders: Vec<Folder>,
+    notes: Vec<NoteSummary>,
+    /// 0 is "All notes"; `i + 1` is `folders[i]`.
+    folder_sel: usize,
+    /// Index into [`App::visible`].
+    note_sel: usize,
+    filter: String,
+    focus: Pane,
+    mode: Mode,
+    /// Rendered Markdown per note id, fetched on first view.
+    previews: HashMap<String, String>,
+    scroll: u16,
+    status: String,
+}
+
+impl<'a> App<'a> {
+    fn new(backend: &'a dyn NotesBackend, account: &str, read_only: bool) -> anyhow::Result<Self> {
+        let mut folders = backend.list_folders(account)?;
+        folders.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut notes = backend.list_notes(account)?;
+        notes.sort_by_key(|n| n.title.to_lowercase());
+        Ok(Self {
+            backend,
+            account: account.to_string(),
+            read_only,
+            folders,
+            notes,
+            folder_sel: 0,
+            note_sel: 0,
+            filter: String::new(),
+            focus: Pane::Notes,
+            mode: Mode::Browse,
+            previews: HashMap::new(),
+            scroll: 0,
+            status: String::new(),
+        })
+    }
+
+    /// Notes in the selected folder whose title matches the filter (case-insensitive).
+    fn visible(&self) -> Vec<&NoteSummary> {
+        let folder_id = self
+            .folder_sel
+            .checked_sub(1)
+            .map(|i| self.folders[i].id.as_str());
+        let filter = self.filter.to_lowercase();
+        self.notes
+            .iter()
+            .filter(|n| folder_id.is_none_or(|id| n.folder_id == id))
+            .filter(|n| n.title.to_lowercase().contains(&filter))
+            .collect()
+    }
+
+    fn selected(&self) -> Option<&NoteSummary> {
+        self.visible().get(self.note_sel).copied()
+    }
+
+    fn select_note(&mut self, index: usize) {
+        self.note_sel = index.min(self.visible().len().saturating_sub(1));
+        self.scroll = 0;
+    }
+
+    /// The selected note as Markdown, fetching it the first time.
+    fn preview(&mut self) -> Option<&str> {
+        let id = self.selected()?.id.clone();
+        let backend = self.backend;
+        Some(
+            self.previews
+                .entry(id)
+                .or_insert_with_key(|id| match backend.get_note(id) {
+                    Ok(note) => render::note_to_markdown(&note),
+                    Err(e) => format!("Couldn't load this note: {e}"),
+                }),
+        )
+    }
+
+    /// Handles one key press. Returns `false` once the user quits.
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return false;
+        }
+        match std::mem::replace(&mut self.mode, Mode::Browse) {
+            Mode::Browse => return self.browse_key(key),
+            Mode::Filter => match key.code {
+                KeyCode::Enter => {}
+                KeyCode::Esc => {
+                    self.filter.clear();
+                    self.select_note(0);
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                    self.select_note(0);
+                    self.mode = Mode::Filter;
+                }
+                KeyCode::Char(c) => {
+                    self.filter.push(c);
+                    self.select_note(0);
+                    self.mode = Mode::Filter;
+                }
+                _ => self.mode = Mode::Filter,
+            },
+            Mode::Rename(mut title) => match key.code {
+                KeyCode::Enter => self.rename(title.trim()),
+                KeyCode::Esc => {}
+                code => {
+                    edit(&mut title, code);
+                    self.mode = Mode::Rename(title);
+                }
+            },
+            Mode::Move(mut path) => match key.code {
+                KeyCode::Enter => self.move_to(&path),
+                KeyCode::Esc => {}
+                code => {
+                    edit(&mut path, code);
+                    self.mode = Mode::Move(path);
+                }
+            },
+            Mode::ConfirmDelete => {
+                if key.code == KeyCode::Char('y') {
+                    self.delete();
+                } else {
+                    self.status = "Not deleted".to_string();
+                }
+            }
+            Mode::Open => match key.code {
+                KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => {}
+                code => {
+                    self.scroll_by(code);
+                    self.mode = Mode::Open;
+                }
+            },
+        }
+        true
+    }
+
+    fn browse_key(&mut self, key: KeyEvent) -> bool {
+        self.status.clear();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Pane::Folders => Pane::Notes,
+                    Pane::Notes => Pane::Preview,
+                    Pane::Preview => Pane::Folders,
+                }
+            }
+            KeyCode::Left | KeyCode::Char('h') => self.focus = Pane::Folders,
+            KeyCode::Right | KeyCode::Char('l') => self.focus = Pane::Notes,
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Char('/') => {
+                self.focus = Pane::Notes;
+                self.mode = Mode::Filter;
+            }
+            KeyCode::Enter => match self.focus {
+                Pane::Folders => self.focus = Pane::Notes,
+                Pane::Notes | Pane::Preview if self.selected().is_some() => {
+                    self.scroll = 0;
+                    self.mode = Mode::Open;
+                }
+                _ => {}
+            },
+            KeyCode::Char(c @ ('r' | 'm' | 'd')) => {
+                let Some(note) = self.selected() else {
+                    return true;
+                };
+                if self.read_only {
+                    self.status = "Read-only mode: notes can't be changed".to_string();
+                    return true;
+                }
+                self.mode = match c {
+                    'r' => Mode::Rename(note.title.clone()),
+                    'm' => Mode::Move(String::new()),
+                    _ => Mode::ConfirmDelete,
+                };
+            }
+            code if self.focus == Pane::Preview => self.scroll_by(code),
+            _ => {}
+        }
+        true
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        match self.focus {
+            Pane::Folders => {
+                self.folder_sel = self
+                    .folder_sel
+                    .saturating_add_signed(delta)
+                    .min(self.folders.len());
+                self.select_note(0);
+            }
+            Pane::Notes => self.select_note(self.note_sel.saturating_add_signed(delta)),
+            Pane::Preview => self.scroll = self.scroll.saturating_add_signed(delta as i16),
+        }
+    }
+
+    fn scroll_by(&mut self, code: KeyCode) {
+        let delta: i16 = match code {
+            KeyCode::Up | KeyCode::Char('k') => -1,
+            KeyCode::Down | KeyCode::Char('j') => 1,
+            KeyCode::PageUp => -20,
+            KeyCode::PageDown | KeyCode::Char(' ') => 20,
+            _ => 0,
+        };
+        self.scroll = self.scroll.saturating_add_signed(delta);
+    }
+
+    /// Runs an edit on the selected note, reporting the outcome in the status line.
+    fn edit_selected(
+        &mut self,
+        done: &str,
+        edit: impl FnOnce(&dyn NotesBackend, &NoteSummary) -> anyhow::Result<NoteSummary>,
+    ) -> Option<NoteSummary> {
+        let note = self.selected()?.clone();
+        match edit(self.backend, &note) {
+            Ok(updated) => {
+                self.status = format!("{done} “{}”", note.title);
+                Some(updated)
+            }
+            Err(e) => {
+                self.status = format!("Error: {e:#}");
+                None
+            }
+        }
+    }
+
+    fn replace_note(&mut self, updated: NoteSummary) {
+        if let Some(n) = self.notes.iter_mut().find(|n| n.id == updated.id) {
+            *n = updated;
+        }
+        self.select_note(self.note_sel);
+    }
+
+    fn rename(&mut self, title: &str) {
+        if title.is_empty() {
+            self.status = "Title can't be empty".to_string();
+            return;
+        }
+        let updated = self.edit_selected("Renamed", |backend, note| {
+            backend.set_note_title(&note.id, title)?;
+            Ok(NoteSummary {
+                title: title.to_string(),
+                ..note.clone()
+            })
+        });
+        if let Some(updated) = updated {
+            self.previews.remove(&updated.id);
+            self.replace_note(updated);
+        }
+    }
+
+    fn move_to(&mut self, path: &str) {
+        let folder = crate::cli::split_folder_path(path)
+            .ok()
+            .and_then(|path| self.folders.iter().find(|f| f.path == path).cloned());
+        let Some(folder) = folder else {
+            self.status = format!("No folder {path:?}");
+            return;
+        };
+        let account = self.account.clone();
+        let updated = self.edit_selected("Moved", |backend, note| {
+            backend.move_note(&note.id, &account, &folder.path)?;
+            Ok(NoteSummary {
+                folder_id: folder.id.clone(),
+                ..note.clone()
+            })
+        });
+        if let Some(updated) = updated {
+            self.replace_note(updated);
+        }
+    }
+
+    fn delete(&mut self) {
+        let deleted = self.edit_selected("Deleted", |backend, note| {
+            backend.trash_note(&note.id)?;
+            Ok(note.clone())
+        });
+        if let Some(deleted) = deleted {
+            self.notes.retain(|n| n.id != deleted.id);
+            self.previews.remove(&deleted.id);
+            self.select_note(self.note_sel);
+        }
+    }
+}
+
+/// Applies a key to a single-line text prompt.
+fn edit(buf: &mut String, code: KeyCode) {
+    match code {
+        KeyCode::Backspace => {
+            buf.pop();
+        }
+        KeyCode::Char(c) => buf.push(c),
+        _ => {}
+    }
+}
+
+/// Markdown as styled lines: headings bold, code blocks and quotes dimmed.
+fn markdown_lines(md: &str) -> Vec<Line<'static>> {
+    let mut in_code = false;
+    md.lines()
+        .map(|line| {
+            if line.trim_start().starts_with("```") {
+                in_code = !in_code;
+                return Line::from(line.to_string()).dim();
+            }
+            if in_code || line.starts_with('>') {
+                return Line::from(line.to_string()).dim();
+            }
+            match line.trim_start_matches('#') {
+                rest if rest.len() < line.len() && rest.starts_with(' ') => {
+                    Line::from(rest.trim_start().to_string()).bold().cyan()
+                }
+                _ => Line::from(line.to_string()),
+            }
+        })
+        .collect()
+}
+
+fn pane_block(title: &str, focused: bool) -> Block<'_> {
+    let block = Block::bordered().title(title);
+    if focused {
+        block.border_style(Style::new().cyan())
+    } else {
+        block
+    }
+}
+
+fn draw(frame: &mut Frame, app: &mut App) {
+    let [main, status] =
+        Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+    frame.render_widget(status_line(app), status);
+
+    if app.mode == Mode::Open {
+        let title = app.selected().map(|n| n.title.clone()).unwrap_or_default();
+        let scroll = app.scroll;
+        let md = app.preview().unwrap_or_default().to_string();
+        frame.render_widget(
+            Paragraph::new(markdown_lines(&md))
+                .block(Block::bordered().title(title))
+                .wrap(Wrap { trim: false })
+                .scroll((scroll, 0)),
+            main,
+        );
+        return;
+    }
+
+    let [folders_area, notes_area, preview_area] = Layout::horizontal([
+        Constraint::Percentage(25),
+        Constraint::Percentage(30),
+        Constraint::Percentage(45),
+    ])
+    .areas(main);
+
+    let folder_items: Vec<ListItem> = std::iter::once(ListItem::new("All notes"))
+        .chain(app.folders.iter().map(|f| {
+            let depth = f.path.len().saturating_sub(1);
+            ListItem::new(format!("{}{}", "  ".repeat(depth), f.name))
+        }))
+        .collect();
+    let mut folder_state = ListState::default().with_selected(Some(app.folder_sel));
+    frame.render_stateful_widget(
+        List::new(folder_items)
+            .block(pane_block("Folders", app.focus == Pane::Folders))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+        folders_area,
+        &mut folder_state,
+    );
+
+    let visible = app.visible();
+    let notes_title = if app.filter.is_empty() {
+        format!("Notes ({})", visible.len())
+    } else {
+        format!("Notes ({}) /{}", visible.len(), app.filter)
+    };
+    let note_items: Vec<ListItem> = visible
+        .iter()
+        .map(|n| ListItem::new(n.title.clone()))
+        .collect();
+    let mut note_state =
+        ListState::default().with_selected((!note_items.is_empty()).then_some(app.note_sel));
+    frame.render_stateful_widget(
+        List::new(note_items)
+            .block(pane_block(&notes_title, app.focus == Pane::Notes))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+        notes_area,
+        &mut note_state,
+    );
+
+    let focused = app.focus == Pane::Preview;
+    let scroll = app.scroll;
+    let md = app.preview().unwrap_or_default().to_string();
+    frame.render_widget(
+        Paragraph::new(markdown_lines(&md))
+            .block(pane_block("Preview", focused))
+            .wrap(Wrap { trim: false })
+            .scroll((scroll, 0)),
+        preview_area,
+    );
+}
+
+fn status_line(app: &App) -> Line<'static> {
+    match &app.mode {
+        Mode::Filter => Line::from(format!("/{}", app.filter)),
+        Mode::Rename(title) => Line::from(format!("Rename to: {title}")),
+        Mode::Move(path) => Line::from(format!("Move to folder (e.g. Personal > Archive): {path}")),
+        Mode::ConfirmDelete => Line::from(format!(
+            "Delete “{}”? (y/n)",
+            app.selected().map(|n| n.title.as_str()).unwrap_or_default()
+        )),
+        Mode::Open => Line::from("↑/↓ scroll · esc back").dim(),
+        Mode::Browse if !app.status.is_empty() => Line::from(app.status.clone()),
+        Mode::Browse => Line::from(vec![Span::raw(
+            "q quit · tab pane · / filter · enter open · r rename · m move · d delete",
+        )])
+        .dim(),
+    }
+}
+
+/// Runs the browser until the user quits.
+pub fn run(backend: &dyn NotesBackend, account: &str, read_only: bool) -> anyhow::Result<()> {
+    if !std::io::stdout().is_terminal() {
+        return Err(anyhow!("`tui` needs an interactive terminal"));
+    }
+    let mut app = App::new(backend, account, read_only)?;
+    let mut terminal = ratatui::init();
+    let result = (|| -> anyhow::Result<()> {
+        loop {
+            terminal.draw(|frame| draw(frame, &mut app))?;
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && !app.handle_key(key)
+            {
+                return Ok(());
+            }
+        }
+    })();
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::FixtureBackend;
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+
+    fn backend() -> FixtureBackend {
+        FixtureBackend::from_path(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/basic.json"),
+        )
+        .unwrap()
+    }
+
+    fn press(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                '\t' => KeyCode::Tab,
+                c => KeyCode::Char(c),
+            };
+            assert!(app.handle_key(KeyEvent::from(code)));
+        }
+    }
+
+    fn titles(app: &App) -> Vec<String> {
+        app.visible().iter().map(|n| n.title.clone()).collect()
+    }
+
+    #[test]
+    fn folders_and_filter_narrow_the_notes_list() {
+        let backend = backend();
+        let mut app = App::new(&backend, "iCloud", false).unwrap();
+        assert_eq!(titles(&app), ["Alpha", "Beta", "Gamma"]);
+
+        // "All notes", Personal, Personal > Archive.
+        press(&mut app, "hjj");
+        assert_eq!(titles(&app), ["Beta", "Gamma"]);
+        press(&mut app, "/gam\n");
+        assert_eq!(titles(&app), ["Gamma"]);
+        assert_eq!(app.mode, Mode::Browse);
+        assert!(app.preview().unwrap().contains("Gamma"));
+    }
+
+    #[test]
+    fn edits_go_through_the_backend_and_update_the_lists() {
+        let backend = backend();
+        let mut app = App::new(&backend, "iCloud", false).unwrap();
+        press(&mut app, "r");
+        for _ in 0.."Alpha".len() {
+            app.handle_key(KeyEvent::from(KeyCode::Backspace));
+        }
+        press(&mut app, "Zeta\n");
+        assert_eq!(titles(&app), ["Zeta", "Beta", "Gamma"]);
+        assert_eq!(app.status, "Renamed “Alpha”");
+
+        press(&mut app, "mPersonal > Archive\n");
+        assert_eq!(app.notes[0].folder_id, "f_archive");
+        press(&mut app, "mNowhere\n");
+        assert_eq!(app.status, "No folder \"Nowhere\"");
+
+        press(&mut app, "dn");
+        assert_eq!(app.notes.len(), 3);
+        press(&mut app, "dy");
+        assert_eq!(titles(&app), ["Beta", "Gamma"]);
+
+        let mut read_only = App::new(&backend, "iCloud", true).unwrap();
+        press(&mut read_only, "d");
+        assert_eq!(read_only.mode, Mode::Browse);
+        assert!(read_only.status.starts_with("Read-only"));
+    }
+
+    #[test]
+    fn draws_folder_tree_notes_and_preview() {
+        let backend = backend();
+        let mut app = App::new(&backend, "iCloud", false).unwrap();
+        let mut terminal = Terminal::new(TestBackend::new(100, 12)).unwrap();
+        terminal.draw(|frame| draw(frame, &mut app)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        for text in ["All notes", "  Archive", "Notes (3)", "Alpha", "Preview"] {
+            assert!(screen.contains(text), "missing {text:?} in {screen}");
+        }
+
+        press(&mut app, "\n");
+        terminal.draw(|frame| draw(frame, &mut app)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(!screen.contains("All notes"));
+        assert!(screen.contains("esc back"));
+    }
+}
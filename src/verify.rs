@@ -0,0 +1,182 @@
+use crate::backup::{ExportedNote, LiveNote};
+use crate::checksums;
+use serde::Serialize;
+use std::collections::HashMap;
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NoteRef {
+    pub id: String,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StaleReason {
+    /// The live note's modification date differs from the exported one.
+    Modified,
+    /// `contents.md` no longer matches the live note's content.
+    Contents,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StaleNote {
+    pub id: String,
+    pub title: String,
+    pub reason: StaleReason,
+    #[serde(with = "time::serde::rfc3339")]
+    pub exported_modified_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub live_modified_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyReport {
+    pub exported: usize,
+    pub live: usize,
+    /// In the live account but not in the export.
+    pub missing: Vec<NoteRef>,
+    /// In the export but no longer in the live account.
+    pub extra: Vec<NoteRef>,
+    pub stale: Vec<StaleNote>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.stale.is_empty()
+    }
+}
+
+/// Compares an export tree against the live account.
+///
+/// Content is only compared for live notes that carry `contents_md`.
+pub fn compare(exported: &[ExportedNote], live: &[LiveNote]) -> anyhow::Result<VerifyReport> {
+    let exported_by_id: HashMap<&str, &ExportedNote> = exported
+        .iter()
+        .map(|e| (e.metadata.id.as_str(), e))
+        .collect();
+    let live_by_id: HashMap<&str, &LiveNote> = live.iter().map(|n| (n.id.as_str(), n)).collect();
+
+    let mut report = VerifyReport {
+        exported: exported.len(),
+        live: live.len(),
+        missing: Vec::new(),
+        extra: Vec::new(),
+        stale: Vec::new(),
+    };
+
+    for n in live {
+        let Some(e) = exported_by_id.get(n.id.as_str()) else {
+            report.missing.push(NoteRef {
+                id: n.id.clone(),
+                title: n.title.clone(),
+            });
+            continue;
+        };
+
+        let reason = if e.metadata.modified_at != n.modified_at {
+            Some(StaleReason::Modified)
+        } else if let Some(md) = &n.contents_md {
+            let path = e.dir.join("contents.md");
+            let on_disk = if path.is_file() {
+                Some(checksums::sha256_file(&path)?)
+            } else {
+                None
+            };
+            let live_hash = checksums::sha256_bytes(md.as_bytes());
+            (on_disk.as_deref() != Some(live_hash.as_str())).then_some(StaleReason::Contents)
+        } else {
+            None
+        };
+
+        if let Some(reason) = reason {
+            report.stale.push(StaleNote {
+                id: n.id.clone(),
+                title: n.title.clone(),
+                reason,
+                exported_modified_at: e.metadata.modified_at,
+                live_modified_at: n.modified_at,
+            });
+        }
+    }
+
+    for e in exported {
+        if !live_by_id.contains_key(e.metadata.id.as_str()) {
+            report.extra.push(NoteRef {
+                id: e.metadata.id.clone(),
+                title: e.metadata.title.clone(),
+            });
+        }
+    }
+
+    report.missing.sort_by(|a, b| a.title.cmp(&b.title));
+    report.extra.sort_by(|a, b| a.title.cmp(&b.title));
+    report.stale.sort_by(|a, b| a.title.cmp(&b.title));
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::BackupNoteMetadata;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    fn ts(secs: i64) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(secs).unwrap()
+    }
+
+    fn exported(dir: PathBuf, id: &str, modified: i64) -> ExportedNote {
+        ExportedNote {
+            dir,
+            metadata: BackupNoteMetadata {
+                id: id.into(),
+                title: id.to_uppercase(),
+                account: "iCloud".into(),
+                folder_path: vec!["Personal".into()],
+                created_at: ts(0),
+                modified_at: ts(modified),
+                shared: false,
+                participants: Vec::new(),
+            },
+        }
+    }
+
+    fn live(id: &str, modified: i64, contents_md: Option<&str>) -> LiveNote {
+        LiveNote {
+            id: id.into(),
+            title: id.to_uppercase(),
+            modified_at: ts(modified),
+            contents_md: contents_md.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn compare_reports_missing_extra_and_stale() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("contents.md"), "# A\n\nold").unwrap();
+
+        let report = compare(
+            &[
+                exported(dir.path().to_path_buf(), "a", 10),
+                exported(dir.path().to_path_buf(), "b", 10),
+                exported(dir.path().to_path_buf(), "gone", 10),
+            ],
+            &[
+                live("a", 10, Some("# A\n\nnew")),
+                live("b", 20, None),
+                live("new", 10, None),
+            ],
+        )
+        .unwrap();
+
+        assert!(!report.is_ok());
+        assert_eq!(report.missing[0].id, "new");
+        assert_eq!(report.extra[0].id, "gone");
+        let reasons: Vec<_> = report.stale.iter().map(|s| (&*s.id, s.reason)).collect();
+        assert_eq!(
+            reasons,
+            vec![("a", StaleReason::Contents), ("b", StaleReason::Modified)]
+        );
+    }
+}
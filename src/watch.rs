@@ -0,0 +1,197 @@
+//! `export --watch` and `notes watch`: notice when the Notes database changes, by polling it and
+//! its WAL.
+
+use crate::backup::LiveNote;
+use crate::cancel::{CancelToken, Cancelled};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use time::OffsetDateTime;
+
+/// Longest we sleep between cancellation checks.
+const TICK: Duration = Duration::from_millis(100);
+
+/// Files whose changes mean Notes wrote something: the database and its write-ahead log (where
+/// writes land first, until the next checkpoint).
+pub fn db_files(db: &Path) -> Vec<PathBuf> {
+    let mut wal = db.as_os_str().to_owned();
+    wal.push("-wal");
+    vec![db.to_path_buf(), PathBuf::from(wal)]
+}
+
+/// Size and mtime of each file (`None` for missing ones).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Fingerprint(Vec<Option<(u64, SystemTime)>>);
+
+impl Fingerprint {
+    fn of(files: &[PathBuf]) -> Self {
+        Self(
+            files
+                .iter()
+                .map(|f| {
+                    let meta = std::fs::metadata(f).ok()?;
+                    Some((meta.len(), meta.modified().ok()?))
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Sleeps for `d`, waking up early (with an error) if `cancel` fires.
+pub fn sleep(d: Duration, cancel: &CancelToken) -> Result<(), Cancelled> {
+    let until = std::time::Instant::now() + d;
+    loop {
+        cancel.check()?;
+        let now = std::time::Instant::now();
+        if now >= until {
+            return Ok(());
+        }
+        std::thread::sleep((until - now).min(TICK));
+    }
+}
+
+/// Polls `files` every `poll` until one of them changes and then stays unchanged for a whole
+/// interval (Notes writes in bursts), or `cancel` fires.
+pub fn wait_for_change(
+    files: &[PathBuf],
+    poll: Duration,
+    cancel: &CancelToken,
+) -> Result<(), Cancelled> {
+    let before = Fingerprint::of(files);
+    let mut last = before.clone();
+    loop {
+        sleep(poll, cancel)?;
+        let now = Fingerprint::of(files);
+        if now != before && now == last {
+            return Ok(());
+        }
+        last = now;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    Created,
+    Modified,
+    Deleted,
+}
+
+/// One line of `notes watch` output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NoteEvent {
+    /// When the change was noticed.
+    #[serde(with = "time::serde::rfc3339")]
+    pub at: OffsetDateTime,
+    pub event: Change,
+    pub id: String,
+    pub title: String,
+    /// The note's modification date (its last known one, for deleted notes).
+    #[serde(with = "time::serde::rfc3339")]
+    pub modified_at: OffsetDateTime,
+}
+
+/// Events turning `before` into `after`, ordered by title. A note counts as modified when its
+/// modification date or title changed.
+pub fn note_events(before: &[LiveNote], after: &[LiveNote], at: OffsetDateTime) -> Vec<NoteEvent> {
+    let event = |event, n: &LiveNote| NoteEvent {
+        at,
+        event,
+        id: n.id.clone(),
+        title: n.title.clone(),
+        modified_at: n.modified_at,
+    };
+    let old: HashMap<&str, &LiveNote> = before.iter().map(|n| (n.id.as_str(), n)).collect();
+    let new: HashMap<&str, &LiveNote> = after.iter().map(|n| (n.id.as_str(), n)).collect();
+    let mut events = Vec::new();
+    for n in after {
+        match old.get(n.id.as_str()) {
+            None => events.push(event(Change::Created, n)),
+            Some(o) if o.modified_at != n.modified_at || o.title != n.title => {
+                events.push(event(Change::Modified, n))
+            }
+            Some(_) => {}
+        }
+    }
+    for n in before {
+        if !new.contains_key(n.id.as_str()) {
+            events.push(event(Change::Deleted, n));
+        }
+    }
+    events.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.id.cmp(&b.id)));
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn note_events_cover_created_modified_and_deleted() {
+        let note = |id: &str, title: &str, modified: i64| LiveNote {
+            id: id.to_string(),
+            title: title.to_string(),
+            modified_at: OffsetDateTime::from_unix_timestamp(modified).unwrap(),
+            contents_md: None,
+        };
+        let before = [
+            note("n1", "Alpha", 1),
+            note("n2", "Beta", 1),
+            note("n3", "Gamma", 1),
+        ];
+        let after = [
+            note("n1", "Alpha", 2),
+            note("n3", "Gamma", 1),
+            note("n4", "Delta", 1),
+        ];
+        let at = OffsetDateTime::UNIX_EPOCH;
+        let events = note_events(&before, &after, at);
+        let events: Vec<(Change, &str)> =
+            events.iter().map(|e| (e.event, e.title.as_str())).collect();
+        assert_eq!(
+            events,
+            [
+                (Change::Modified, "Alpha"),
+                (Change::Deleted, "Beta"),
+                (Change::Created, "Delta"),
+            ]
+        );
+    }
+
+    #[test]
+    fn db_files_include_the_wal() {
+        assert_eq!(
+            db_files(Path::new("/x/NoteStore.sqlite")),
+            [
+                PathBuf::from("/x/NoteStore.sqlite"),
+                PathBuf::from("/x/NoteStore.sqlite-wal")
+            ]
+        );
+    }
+
+    #[test]
+    fn wait_returns_after_a_change_settles_or_when_cancelled() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = db_files(&dir.path().join("NoteStore.sqlite"));
+        std::fs::write(&files[0], "db").unwrap();
+
+        let cancel = CancelToken::new().with_timeout(Duration::from_millis(150));
+        assert_eq!(
+            wait_for_change(&files, Duration::from_millis(20), &cancel),
+            Err(Cancelled::DeadlineExceeded)
+        );
+
+        let wal = files[1].clone();
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            std::fs::write(wal, "appended").unwrap();
+        });
+        let cancel = CancelToken::new().with_timeout(Duration::from_secs(10));
+        assert_eq!(
+            wait_for_change(&files, Duration::from_millis(20), &cancel),
+            Ok(())
+        );
+        writer.join().unwrap();
+    }
+}
@@ -11,7 +11,9 @@ fn run_ok(args: &[&str]) -> String {
         .arg(fixture_path())
         .env("NO_COLOR", "1")
         .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
         .env("COLUMNS", "120")
+        .env("TZ", "UTC")
         .args(args);
 
     let out = cmd.assert().success().get_output().stdout.clone();
@@ -24,7 +26,9 @@ fn run_err(args: &[&str]) -> String {
         .arg(fixture_path())
         .env("NO_COLOR", "1")
         .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
         .env("COLUMNS", "120")
+        .env("TZ", "UTC")
         .args(args);
 
     let out = cmd.assert().failure().get_output().stderr.clone();
@@ -61,6 +65,47 @@ fn snapshot_notes_list_folder_table() {
     assert_snapshot!("notes_list_folder", out);
 }
 
+#[test]
+fn notes_list_picker_feeds_notes_show() {
+    let out = run_ok(&["notes", "list", "--picker", "--limit", "2"]);
+    assert_eq!(out, "n1\tPersonal\tAlpha\nn2\tPersonal > Archive\tBeta\n");
+
+    let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+    cmd.arg("--fixture")
+        .arg(fixture_path())
+        .env("NO_COLOR", "1")
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .args(["notes", "show", "-", "--markdown"])
+        .write_stdin("n2\tPersonal > Archive\tBeta\n");
+    let shown = cmd.assert().success().get_output().stdout.clone();
+    assert_eq!(
+        String::from_utf8(shown).unwrap(),
+        run_ok(&["notes", "show", "n2", "--markdown"])
+    );
+    assert_eq!(
+        run_ok(&[
+            "notes",
+            "show",
+            "--from-picker-line",
+            "n2\tPersonal > Archive\tBeta",
+            "--markdown"
+        ]),
+        run_ok(&["notes", "show", "n2", "--markdown"])
+    );
+}
+
+#[test]
+fn notes_list_alfred_prints_script_filter_items() {
+    let out = run_ok(&["notes", "list", "--output", "alfred", "--query", "a"]);
+    let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+    let items = v["items"].as_array().unwrap();
+    let titles: Vec<&str> = items.iter().map(|i| i["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, ["Alpha", "Beta", "Gamma"]);
+    assert_eq!(items[1]["subtitle"], "Personal > Archive");
+    assert_eq!(items[1]["arg"], "n2");
+}
+
 #[test]
 fn snapshot_notes_show_markdown() {
     let out = run_ok(&["notes", "show", "n2", "--markdown"]);
@@ -110,6 +155,7 @@ fn backup_export_writes_all_notes() {
         .arg(fixture_path())
         .env("NO_COLOR", "1")
         .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
         .env("COLUMNS", "120")
         .args(["export", "--out"])
         .arg(&out_dir);
@@ -139,7 +185,15 @@ fn backup_export_writes_all_notes() {
         files.iter().any(|p| p.ends_with("/contents.md")),
         "expected contents.md files"
     );
-    assert_eq!(files.len(), 6, "expected 2 files per note (3 notes)");
+    assert!(
+        files.iter().any(|p| p == "SHA256SUMS"),
+        "expected SHA256SUMS at the export root"
+    );
+    assert_eq!(
+        files.len(),
+        9,
+        "expected 2 files per note (3 notes) + folders.json + manifest.json + SHA256SUMS"
+    );
     assert_snapshot!("backup_files", files.join("\n"));
 }
 
@@ -153,6 +207,7 @@ fn backup_export_writes_all_notes_jobs_1() {
         .arg(fixture_path())
         .env("NO_COLOR", "1")
         .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
         .env("COLUMNS", "120")
         .args(["export", "--out"])
         .arg(&out_dir)
@@ -175,6 +230,2720 @@ fn backup_export_writes_all_notes_jobs_1() {
         }
     }
     files.sort();
-    assert_eq!(files.len(), 6, "expected 2 files per note (3 notes)");
+    assert_eq!(
+        files.len(),
+        9,
+        "expected 2 files per note (3 notes) + folders.json + manifest.json + SHA256SUMS"
+    );
     assert_snapshot!("backup_files_jobs_1", files.join("\n"));
 }
+
+#[cfg(unix)]
+#[test]
+fn export_snapshot_hardlinks_unchanged_notes() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let root = dir.path().join("snapshots");
+
+    let mut first = None;
+    for _ in 0..2 {
+        let out = run_ok(&["export", "--snapshot", "--out", root.to_str().unwrap()]);
+        let snap = PathBuf::from(out.trim());
+        assert!(snap.starts_with(&root));
+        assert!(snap.join("Personal").is_dir());
+        first.get_or_insert(snap);
+    }
+
+    let latest = std::fs::read_link(root.join("latest")).expect("latest symlink");
+    let latest = root.join(latest);
+    assert_ne!(first.as_ref(), Some(&latest));
+
+    use std::os::unix::fs::MetadataExt;
+    for entry in walkdir::WalkDir::new(&latest) {
+        let entry = entry.expect("walkdir entry");
+        if entry.file_type().is_file() {
+            let meta = entry.metadata().expect("metadata");
+            assert_eq!(meta.nlink(), 2, "{:?} should be hardlinked", entry.path());
+        }
+    }
+}
+
+#[test]
+fn export_sha256sums_matches_exported_files() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let out_dir = dir.path().join("backup");
+    run_ok(&["export", "--out", out_dir.to_str().unwrap()]);
+
+    let sums = std::fs::read_to_string(out_dir.join("SHA256SUMS")).expect("SHA256SUMS");
+    assert_eq!(sums.lines().count(), 8);
+    for line in sums.lines() {
+        let (hash, rel) = line.split_once("  ").expect("shasum line");
+        let data = std::fs::read(out_dir.join(rel)).expect("listed file");
+        let digest = sha256_hex(&data);
+        assert_eq!(hash, digest, "{rel}");
+    }
+}
+
+#[test]
+fn export_format_html_writes_standalone_pages() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let out_dir = dir.path().join("backup");
+    run_ok(&[
+        "export",
+        "--format",
+        "html",
+        "--out",
+        out_dir.to_str().unwrap(),
+    ]);
+
+    let note_dir = out_dir.join("Personal/Alpha-n1");
+    let page = std::fs::read_to_string(note_dir.join("note.html")).expect("note.html");
+    assert!(page.starts_with("<!doctype html>"), "{page}");
+    assert!(page.contains("<title>Alpha</title>"), "{page}");
+    assert!(page.contains("<div><b>Hello</b> Alpha</div>"), "{page}");
+    assert!(note_dir.join("contents.md").is_file());
+    assert!(!note_dir.join("contents.html").exists());
+}
+
+#[cfg(unix)]
+fn write_stub(path: &std::path::Path, script: &str) {
+    // Copy it into place from a child process: a write fd opened here could leak into another
+    // test's fork and make exec'ing the stub fail with ETXTBSY.
+    let staged = path.with_extension("txt");
+    std::fs::write(&staged, script).expect("write stub");
+    let status = std::process::Command::new("install")
+        .args(["-m", "755"])
+        .arg(&staged)
+        .arg(path)
+        .status()
+        .expect("install stub");
+    assert!(status.success(), "install stub: {status}");
+}
+
+#[cfg(unix)]
+#[test]
+fn notes_show_pdf_renders_through_textutil_and_cupsfilter() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let textutil = dir.path().join("textutil");
+    let cupsfilter = dir.path().join("cupsfilter");
+    // textutil: HTML on stdin -> "RTF" on stdout; cupsfilter: file argument -> "PDF" on stdout.
+    write_stub(&textutil, "#!/bin/sh\nprintf '{\\\\rtf1 '; cat\n");
+    write_stub(
+        &cupsfilter,
+        "#!/bin/sh\nfor last; do :; done\nprintf '%%PDF-1.4\\n'; cat \"$last\"\n",
+    );
+
+    let pdf = dir.path().join("alpha.pdf");
+    let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+    cmd.arg("--fixture")
+        .arg(fixture_path())
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .env("APPLE_NOTES_TEXTUTIL_BIN", &textutil)
+        .env("APPLE_NOTES_CUPSFILTER_BIN", &cupsfilter)
+        .args(["notes", "show", "n1", "--pdf", pdf.to_str().unwrap()]);
+    let out = cmd.assert().success().get_output().stdout.clone();
+    assert_eq!(
+        String::from_utf8(out).unwrap().trim(),
+        pdf.to_str().unwrap()
+    );
+
+    let data = std::fs::read_to_string(&pdf).expect("pdf written");
+    assert!(
+        data.starts_with("%PDF-1.4\n{\\rtf1 <!doctype html>"),
+        "{data}"
+    );
+    assert!(data.contains("<title>Alpha</title>"), "{data}");
+    assert!(!dir.path().join("alpha.pdf.rtf.tmp").exists());
+}
+
+/// Runs `apple-notes` with the default `--backend auto`, no Notes database and an `osascript`
+/// stub that knows one note (n1) and no accounts, folders or note lists.
+#[cfg(unix)]
+fn run_without_db(args: &[&str]) -> assert_cmd::assert::Assert {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let osascript = dir.path().join("osascript");
+    write_stub(
+        &osascript,
+        concat!(
+            "#!/bin/sh\n",
+            "case \"$(cat)\" in\n",
+            "  *'switch (\"notes.get\")'*) echo '{\"id\":\"n1\",\"title\":\"Alpha\",",
+            "\"folder_id\":\"f1\",\"created_at\":\"2024-01-01T00:00:00Z\",",
+            "\"modified_at\":\"2024-01-02T00:00:00Z\",\"body_html\":\"<div>Alpha one two</div>\"}' ;;\n",
+            "  *) echo '[]' ;;\n",
+            "esac\n",
+        ),
+    );
+    assert_cmd::cargo_bin_cmd!("apple-notes")
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .env("APPLE_NOTES_OSASCRIPT_BIN", &osascript)
+        .env(
+            "APPLE_NOTES_DB_PATH",
+            dir.path().join("missing/NoteStore.sqlite"),
+        )
+        .args(args)
+        .assert()
+}
+
+#[cfg(unix)]
+#[test]
+fn auto_backend_without_the_database_reads_through_osascript() {
+    let commands: [&[&str]; 8] = [
+        &["notes", "list", "--preview"],
+        &["todos"],
+        &["notes", "wordcount", "--all"],
+        &["notes", "dedupe"],
+        &["notes", "lint"],
+        &["notes", "grep", "x"],
+        &["notes", "replace", "--query", "a", "--with", "b"],
+        &["notes", "merge", "n1", "--new-title", "Both"],
+    ];
+    for args in commands {
+        run_without_db(args).success();
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn show_meta_without_the_database_counts_from_the_note() {
+    let out = run_without_db(&["--json", "notes", "show", "--meta", "n1"])
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let v: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(v["title"], "Alpha");
+    assert_eq!(v["words"], 3);
+    assert_eq!(v["shared"], false);
+}
+
+#[cfg(unix)]
+#[test]
+fn verbose_logs_osascript_calls_with_timing() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let osascript = dir.path().join("osascript");
+    write_stub(
+        &osascript,
+        "#!/bin/sh\ncat >/dev/null\necho '[{\"name\":\"iCloud\"}]'\n",
+    );
+    let stderr = |args: &[&str]| {
+        let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+        cmd.env("NO_PROGRESS", "1")
+            .env("APPLE_NOTES_CONFIG", "/dev/null")
+            .env("APPLE_NOTES_OSASCRIPT_BIN", &osascript)
+            .args(["--backend", "osascript", "accounts", "list"])
+            .args(args);
+        let err = cmd.assert().success().get_output().stderr.clone();
+        String::from_utf8(err).expect("utf8 stderr")
+    };
+    assert_eq!(stderr(&[]), "");
+
+    let text = stderr(&["-v"]);
+    assert!(text.contains("osascript{args="), "{text}");
+    assert!(text.contains("close time.busy="), "{text}");
+    assert!(!text.contains("Application(\"Notes\")"), "{text}");
+
+    let json = stderr(&["-vv", "--log-format", "json"]);
+    let events: Vec<serde_json::Value> = json
+        .lines()
+        .map(|l| serde_json::from_str(l).expect("one JSON object per line"))
+        .collect();
+    assert!(
+        events.iter().any(|e| e["fields"]["script"]
+            .as_str()
+            .is_some_and(|s| s.contains("Notes"))),
+        "{json}"
+    );
+    assert!(
+        events
+            .iter()
+            .any(|e| e["span"]["name"] == "osascript" && e["fields"]["message"] == "close"),
+        "{json}"
+    );
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    use sha2::Digest;
+    format!("{:x}", sha2::Sha256::digest(data))
+}
+
+#[test]
+fn backup_verify_reports_missing_notes() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let out_dir = dir.path().join("backup");
+    run_ok(&["export", "--out", out_dir.to_str().unwrap()]);
+
+    let out = run_ok(&["backup", "verify", "--dir", out_dir.to_str().unwrap()]);
+    assert_eq!(out.trim(), "OK: 3 exported notes match the live account");
+
+    std::fs::remove_dir_all(out_dir.join("Personal/Alpha-n1")).expect("remove note dir");
+    let err = run_err(&["backup", "verify", "--dir", out_dir.to_str().unwrap()]);
+    assert!(err.contains("1 missing, 0 extra, 0 stale"), "{err}");
+}
+
+#[test]
+fn backup_diff_lists_changes_between_exports() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let a = dir.path().join("a");
+    let b = dir.path().join("b");
+    run_ok(&["export", "--out", a.to_str().unwrap()]);
+    run_ok(&["export", "--out", b.to_str().unwrap()]);
+
+    assert_eq!(
+        run_ok(&["backup", "diff", a.to_str().unwrap(), b.to_str().unwrap()]).trim(),
+        "No differences"
+    );
+
+    std::fs::remove_dir_all(b.join("Personal/Alpha-n1")).expect("remove note dir");
+    std::fs::write(
+        b.join("Personal/Archive/Beta-n2/contents.md"),
+        "# Beta\n\nBeta body, edited",
+    )
+    .expect("edit contents");
+
+    let out = run_ok(&[
+        "backup",
+        "diff",
+        a.to_str().unwrap(),
+        b.to_str().unwrap(),
+        "--patch",
+    ]);
+    assert!(out.contains("removed"), "{out}");
+    assert!(out.contains("modified (contents)"), "{out}");
+    assert!(out.contains("+Beta body, edited"), "{out}");
+}
+
+#[test]
+fn backup_restore_recreates_or_replaces_note() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let out_dir = dir.path().join("backup");
+    run_ok(&["export", "--out", out_dir.to_str().unwrap()]);
+    let note_dir = out_dir.join("Personal/Archive/Beta-n2");
+
+    let out = run_ok(&["backup", "restore", note_dir.to_str().unwrap()]);
+    assert_eq!(out.trim(), "fixture://note/1");
+
+    let out = run_ok(&["backup", "restore", note_dir.to_str().unwrap(), "--replace"]);
+    assert_eq!(out.trim(), "n2");
+
+    // `--replace` finds the note in the account it was exported from, and `--folder` moves it.
+    let local_dir = dir.path().join("local");
+    run_ok(&[
+        "--account",
+        "On My Mac",
+        "export",
+        "--out",
+        local_dir.to_str().unwrap(),
+    ]);
+    let delta = local_dir.join("Notes/Delta-n4");
+    let out = run_ok(&[
+        "backup",
+        "restore",
+        delta.to_str().unwrap(),
+        "--replace",
+        "--folder",
+        "Notes",
+    ]);
+    assert_eq!(out.trim(), "n4");
+    let output = assert_cmd::cargo_bin_cmd!("apple-notes")
+        .arg("--fixture")
+        .arg(fixture_path())
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .args(["--dry-run", "backup", "restore"])
+        .arg(&delta)
+        .args(["--replace", "--folder", "Notes"])
+        .output()
+        .expect("run apple-notes");
+    let err = String::from_utf8(output.stderr).unwrap();
+    assert!(err.contains("Would move n4 to On My Mac > Notes"), "{err}");
+
+    // A note that no longer exists is created again.
+    let metadata = note_dir.join("metadata.json");
+    let edited = std::fs::read_to_string(&metadata)
+        .unwrap()
+        .replace("\"n2\"", "\"n9\"");
+    std::fs::write(&metadata, edited).unwrap();
+    let out = run_ok(&["backup", "restore", note_dir.to_str().unwrap(), "--replace"]);
+    assert_eq!(out.trim(), "fixture://note/1");
+}
+
+#[test]
+fn invalid_progress_config_is_reported() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let config = dir.path().join("config.toml");
+    std::fs::write(&config, "[progress]\nbar_template = \"{msg:?}\"\n").unwrap();
+
+    let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+    let out = cmd
+        .arg("--fixture")
+        .arg(fixture_path())
+        .env("APPLE_NOTES_CONFIG", &config)
+        .args(["--quiet", "accounts", "list"])
+        .assert()
+        .failure()
+        .get_output()
+        .stderr
+        .clone();
+    assert!(
+        String::from_utf8(out)
+            .unwrap()
+            .contains("progress.bar_template")
+    );
+}
+
+#[test]
+fn read_only_rejects_mutating_commands() {
+    let err = run_err(&["--read-only", "notes", "delete", "n1", "--yes"]);
+    assert!(err.contains("`notes delete` modifies notes"), "{err}");
+    let out = run_ok(&["--read-only", "notes", "list", "--json"]);
+    assert!(out.contains("\"n1\""), "{out}");
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let config = dir.path().join("config.toml");
+    std::fs::write(&config, "read_only = true\n").expect("write config");
+    let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+    cmd.arg("--fixture")
+        .arg(fixture_path())
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", &config)
+        .args(["folders", "create", "--parent", "Personal", "--name", "X"]);
+    let err = cmd.assert().failure().get_output().stderr.clone();
+    let err = String::from_utf8(err).expect("utf8 stderr");
+    assert!(err.contains("read-only mode"), "{err}");
+}
+
+#[test]
+fn dry_run_reports_writes_without_making_them() {
+    let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+    cmd.arg("--fixture")
+        .arg(fixture_path())
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .args([
+            "--read-only",
+            "--dry-run",
+            "notes",
+            "move",
+            "n1",
+            "--folder",
+        ])
+        .arg("Personal > Archive");
+    let output = cmd.assert().success().get_output().clone();
+    let err = String::from_utf8(output.stderr).expect("utf8 stderr");
+    assert_eq!(err, "Would move n1 to iCloud > Personal > Archive\n");
+
+    let out = run_ok(&[
+        "notes",
+        "create",
+        "--title",
+        "Hi",
+        "--body",
+        "x",
+        "--dry-run",
+    ]);
+    assert_eq!(out.trim(), "dry-run://note/1");
+    // Commands with a preview of their own show it instead.
+    let out = run_ok(&[
+        "notes",
+        "replace",
+        "--query",
+        "Alpha",
+        "--with",
+        "Omega",
+        "--dry-run",
+        "--json",
+    ]);
+    let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(v["applied"], false);
+}
+
+#[test]
+fn exit_codes_tell_failures_apart() {
+    let code = |args: &[&str]| {
+        let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+        cmd.arg("--fixture")
+            .arg(fixture_path())
+            .env("NO_PROGRESS", "1")
+            .env("APPLE_NOTES_CONFIG", "/dev/null")
+            .args(args);
+        cmd.assert().failure().get_output().status.code()
+    };
+    assert_eq!(code(&["notes", "show", "nope"]), Some(2));
+    assert_eq!(
+        code(&[
+            "folders", "move", "--folder", "Nope", "--parent", "Personal"
+        ]),
+        Some(2)
+    );
+    let dir = tempfile::tempdir().expect("tempdir");
+    let out = dir.path().to_str().unwrap();
+    assert_eq!(code(&["export", "--folder", "Nope", "--out", out]), Some(2));
+    assert_eq!(code(&["notes", "show", "n"]), Some(3));
+    assert_eq!(code(&["notes", "list", "--bogus"]), Some(64));
+    assert_eq!(
+        code(&["export", "--single-file", "all.md", "--jobs", "2"]),
+        Some(64)
+    );
+    assert_eq!(
+        code(&["--backend", "db", "export", "--single-file", "all.md"]),
+        Some(64)
+    );
+    assert_eq!(code(&["notes", "delete", "n1"]), Some(1));
+}
+
+#[test]
+fn json_errors_are_structured() {
+    let err = run_err(&["notes", "show", "nope", "--json"]);
+    let v: serde_json::Value = serde_json::from_str(&err).expect("one JSON object");
+    assert_eq!(v["error"]["kind"], "not_found");
+    assert_eq!(v["error"]["exit_code"], 2);
+    assert!(v["error"]["hint"].is_string(), "{v}");
+    assert!(
+        v["error"]["message"].as_str().unwrap().contains("nope"),
+        "{v}"
+    );
+
+    let err = run_err(&["--json", "--read-only", "notes", "delete", "n1", "--yes"]);
+    let v: serde_json::Value = serde_json::from_str(&err).expect("one JSON object");
+    assert_eq!(v["error"]["kind"], "other");
+    assert!(v["error"]["hint"].is_null(), "{v}");
+}
+
+#[test]
+fn timing_prints_a_breakdown_after_the_output() {
+    let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+    cmd.arg("--fixture")
+        .arg(fixture_path())
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .args(["--timing", "notes", "show", "n1", "--markdown"]);
+    let output = cmd.assert().success().get_output().clone();
+    assert!(String::from_utf8(output.stdout).unwrap().contains("Alpha"));
+    let err = String::from_utf8(output.stderr).unwrap();
+    let lines: Vec<&str> = err.lines().collect();
+    assert_eq!(lines[0], "Timing:", "{err}");
+    assert!(lines[1].trim_start().starts_with("command "), "{err}");
+    for kind in ["get_note", "render"] {
+        assert!(
+            lines.iter().any(|l| l.trim_start().starts_with(kind)),
+            "{kind}: {err}"
+        );
+    }
+}
+
+#[test]
+fn export_single_file_concatenates_notes() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("all.md");
+    let out = run_ok(&["export", "--single-file", path.to_str().unwrap()]);
+    assert_eq!(out.trim(), path.to_str().unwrap());
+
+    let doc = std::fs::read_to_string(&path).expect("single file");
+    assert_snapshot!("export_single_file", doc);
+}
+
+#[test]
+fn folder_timestamps_appear_in_json_and_export() {
+    let out = run_ok(&["folders", "list", "--json"]);
+    let folders: serde_json::Value = serde_json::from_str(&out).expect("json");
+    assert_eq!(folders[0]["created_at"], "2025-01-02T03:04:05Z");
+    assert!(folders[1].get("created_at").is_none(), "{out}");
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let out_dir = dir.path().join("backup");
+    run_ok(&["export", "--out", out_dir.to_str().unwrap()]);
+    let exported = std::fs::read_to_string(out_dir.join("folders.json")).expect("folders.json");
+    let exported: serde_json::Value = serde_json::from_str(&exported).expect("json");
+    assert_eq!(exported[0]["path"], serde_json::json!(["Personal"]));
+    assert_eq!(exported[0]["modified_at"], "2025-12-20T00:00:00Z");
+}
+
+#[test]
+fn export_format_site_writes_index_and_search() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let out_dir = dir.path().join("site");
+    run_ok(&[
+        "export",
+        "--format",
+        "site",
+        "--out",
+        out_dir.to_str().unwrap(),
+    ]);
+
+    let index = std::fs::read_to_string(out_dir.join("index.html")).expect("index.html");
+    assert!(index.contains("<h1>iCloud</h1>"), "{index}");
+    assert!(
+        index.contains(r#"<a href="Personal/Archive/Beta-n2/note.html">Beta</a>"#),
+        "{index}"
+    );
+
+    let search = std::fs::read_to_string(out_dir.join("search-index.json")).expect("search index");
+    let search: serde_json::Value = serde_json::from_str(&search).expect("json");
+    assert_eq!(search.as_array().map(Vec::len), Some(3));
+    assert_eq!(search[0]["title"], "Alpha");
+
+    let page = std::fs::read_to_string(out_dir.join("Personal/Alpha-n1/note.html")).expect("page");
+    assert!(page.contains(r#"<a href="../../index.html">"#), "{page}");
+}
+
+#[test]
+fn notes_rename_query_previews_then_applies() {
+    let out = run_ok(&[
+        "--read-only",
+        "notes",
+        "rename",
+        "--query",
+        "^(\\w)(\\w+)a$",
+        "--replace",
+        "${2}-${1}",
+    ]);
+    assert_snapshot!("notes_rename_query_preview", out);
+
+    let out = run_ok(&[
+        "notes",
+        "rename",
+        "--query",
+        "^Beta$",
+        "--replace",
+        "Beta (old)",
+        "--yes",
+        "--json",
+    ]);
+    let out: serde_json::Value = serde_json::from_str(&out).expect("json");
+    assert_eq!(out["applied"], true);
+    assert_eq!(out["renames"][0]["new_title"], "Beta (old)");
+
+    let err = run_err(&["notes", "rename", "--query", "^Beta$"]);
+    assert!(err.contains("--replace"), "{err}");
+}
+
+#[test]
+fn export_format_epub_writes_one_book() {
+    use std::io::Read;
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let book = dir.path().join("notes.epub");
+    run_ok(&[
+        "export",
+        "--format",
+        "epub",
+        "--epub-chapters",
+        "folder",
+        "--out",
+        book.to_str().unwrap(),
+    ]);
+
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(&book).expect("epub")).expect("zip");
+    let first = zip.by_index(0).expect("first entry");
+    assert_eq!(first.name().expect("entry name"), "mimetype");
+    assert_eq!(first.compression(), zip::CompressionMethod::Stored);
+    drop(first);
+
+    let mut nav = String::new();
+    zip.by_name("OEBPS/nav.xhtml")
+        .expect("nav")
+        .read_to_string(&mut nav)
+        .expect("read nav");
+    assert!(nav.contains(">Personal</a>"), "{nav}");
+    assert!(nav.contains(">Personal &gt; Archive</a>"), "{nav}");
+
+    let mut chapter = String::new();
+    zip.by_name("OEBPS/chapter-0002.xhtml")
+        .expect("chapter 2")
+        .read_to_string(&mut chapter)
+        .expect("read chapter");
+    assert!(chapter.contains("<h2>Beta</h2>"), "{chapter}");
+    assert!(chapter.contains("<h2>Gamma</h2>"), "{chapter}");
+}
+
+#[cfg(unix)]
+#[test]
+fn export_on_complete_runs_command_with_summary_env() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let out = dir.path().join("out");
+    let env_file = dir.path().join("env.txt");
+    let hook = format!("env | grep ^APPLE_NOTES_EXPORT_ > '{}'", env_file.display());
+    run_ok(&[
+        "export",
+        "--out",
+        out.to_str().unwrap(),
+        "--on-complete",
+        &hook,
+    ]);
+    let env = std::fs::read_to_string(&env_file).expect("hook ran");
+    assert!(env.contains("APPLE_NOTES_EXPORT_STATUS=ok\n"), "{env}");
+    assert!(env.contains("APPLE_NOTES_EXPORT_EXPORTED=3\n"), "{env}");
+    assert!(env.contains("APPLE_NOTES_EXPORT_FAILURES=0\n"), "{env}");
+    assert!(env.contains("APPLE_NOTES_EXPORT_DURATION_MS="), "{env}");
+
+    // A failed export still runs the hook, and the export's error wins.
+    let blocker = dir.path().join("blocker");
+    std::fs::write(&blocker, "").unwrap();
+    run_err(&[
+        "export",
+        "--out",
+        blocker.to_str().unwrap(),
+        "--on-complete",
+        &hook,
+    ]);
+    let env = std::fs::read_to_string(&env_file).expect("hook ran");
+    assert!(env.contains("APPLE_NOTES_EXPORT_STATUS=failed\n"), "{env}");
+    assert!(env.contains("APPLE_NOTES_EXPORT_FAILURES=1\n"), "{env}");
+    assert!(env.contains("APPLE_NOTES_EXPORT_ERROR="), "{env}");
+}
+
+#[test]
+fn export_folder_limits_to_subtree() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let out_dir = dir.path().join("backup");
+    run_ok(&[
+        "export",
+        "--folder",
+        "Personal > Archive",
+        "--out",
+        out_dir.to_str().unwrap(),
+    ]);
+
+    assert!(
+        out_dir
+            .join("Personal/Archive/Beta-n2/contents.md")
+            .is_file()
+    );
+    assert!(
+        out_dir
+            .join("Personal/Archive/Gamma-n3/contents.md")
+            .is_file()
+    );
+    assert!(!out_dir.join("Personal/Alpha-n1").exists());
+    let sums = std::fs::read_to_string(out_dir.join("SHA256SUMS")).expect("SHA256SUMS");
+    assert_eq!(sums.lines().count(), 6);
+
+    let err = run_err(&[
+        "export",
+        "--folder",
+        "Nope",
+        "--out",
+        out_dir.to_str().unwrap(),
+    ]);
+    assert!(err.contains("folder not found: Nope"), "{err}");
+}
+
+#[cfg(unix)]
+#[test]
+fn export_preflight_fails_fast_without_automation_permission() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let osascript = dir.path().join("osascript");
+    // The stub drains the script from stdin, as osascript does; exiting without reading it
+    // could kill the CLI with SIGPIPE.
+    write_stub(
+        &osascript,
+        "#!/bin/sh\ncat >/dev/null\necho 'execution error: Not authorized to send Apple events to Notes. (-1743)' >&2\nexit 1\n",
+    );
+    let out_dir = dir.path().join("backup");
+
+    let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+    cmd.env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .env("APPLE_NOTES_OSASCRIPT_BIN", &osascript)
+        .args(["--backend", "osascript", "export", "--out"])
+        .arg(&out_dir);
+    let err = cmd.assert().failure().get_output().stderr.clone();
+    let err = String::from_utf8(err).expect("utf8 stderr");
+    assert!(err.contains("Privacy & Security > Automation"), "{err}");
+    assert!(!out_dir.exists());
+}
+
+#[test]
+fn export_filters_by_query_and_dates() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let out_dir = dir.path().join("backup");
+    // "hello" only appears in Alpha's body.
+    run_ok(&[
+        "export",
+        "--query",
+        "HELLO",
+        "--created-before",
+        "2026-01-01",
+        "--out",
+        out_dir.to_str().unwrap(),
+    ]);
+    assert!(out_dir.join("Personal/Alpha-n1/contents.md").is_file());
+    assert!(!out_dir.join("Personal/Archive").exists());
+
+    let out_dir = dir.path().join("none");
+    run_ok(&[
+        "export",
+        "--modified-after",
+        "2025-12-20T00:00:00Z",
+        "--out",
+        out_dir.to_str().unwrap(),
+    ]);
+    let sums = std::fs::read_to_string(out_dir.join("SHA256SUMS")).expect("SHA256SUMS");
+    assert_eq!(sums.lines().count(), 2, "{sums}");
+}
+
+#[test]
+fn export_all_accounts_nests_each_account() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let out_dir = dir.path().join("backup");
+    run_ok(&[
+        "export",
+        "--all-accounts",
+        "--out",
+        out_dir.to_str().unwrap(),
+    ]);
+
+    assert!(
+        out_dir
+            .join("iCloud/Personal/Alpha-n1/contents.md")
+            .is_file()
+    );
+    assert!(out_dir.join("iCloud/SHA256SUMS").is_file());
+    assert!(
+        out_dir
+            .join("On My Mac/Notes/Delta-n4/contents.md")
+            .is_file()
+    );
+    assert!(out_dir.join("On My Mac/SHA256SUMS").is_file());
+    assert!(!out_dir.join("Personal").exists());
+}
+
+#[test]
+fn export_stamps_note_dates_on_files() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let out_dir = dir.path().join("backup");
+    run_ok(&["export", "--out", out_dir.to_str().unwrap()]);
+
+    // Fixture notes were modified at 2025-12-20T00:00:00Z.
+    let want = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_766_188_800);
+    let note_dir = out_dir.join("Personal/Alpha-n1");
+    for path in [
+        note_dir.join("contents.md"),
+        note_dir.join("metadata.json"),
+        note_dir.clone(),
+    ] {
+        let modified = std::fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .expect("mtime");
+        assert_eq!(modified, want, "{path:?}");
+    }
+}
+
+#[test]
+fn db_path_reads_a_copied_notes_database() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let db_path = dir.path().join("NoteStore.sqlite");
+    let conn = rusqlite::Connection::open(&db_path).expect("create db");
+    conn.execute_batch(
+        "CREATE TABLE Z_METADATA (Z_VERSION INTEGER PRIMARY KEY, Z_UUID VARCHAR(255), Z_PLIST BLOB);
+         INSERT INTO Z_METADATA(Z_VERSION, Z_UUID) VALUES (1, 'UUID');
+         CREATE TABLE ZICCLOUDSYNCINGOBJECT (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZNAME VARCHAR, ZTITLE1 VARCHAR, ZTITLE2 VARCHAR, ZFOLDER INTEGER, ZPARENT INTEGER, ZACCOUNT8 INTEGER, ZMARKEDFORDELETION INTEGER);
+         INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZNAME) VALUES (1, 14, 'iCloud');
+         INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZNAME, ZPARENT, ZACCOUNT8) VALUES (10, 15, 'Copied', NULL, 1);",
+    )
+    .expect("seed db");
+    drop(conn);
+
+    // No osascript and no Notes container: everything must come from the copied database.
+    let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+    cmd.env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .env("APPLE_NOTES_OSASCRIPT_BIN", dir.path().join("missing"))
+        .env("HOME", dir.path())
+        .args(["folders", "list", "--json", "--db-path"])
+        .arg(&db_path);
+    let out = cmd.assert().success().get_output().stdout.clone();
+    let out = String::from_utf8(out).expect("utf8 stdout");
+    assert!(out.contains("\"name\": \"Copied\""), "{out}");
+}
+
+#[test]
+fn export_archive_streams_the_same_files_as_a_directory_export() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let out_dir = dir.path().join("backup");
+    run_ok(&["export", "--out", out_dir.to_str().unwrap()]);
+    let mut want: Vec<String> = walkdir::WalkDir::new(&out_dir)
+        .into_iter()
+        .map(|e| e.expect("walkdir entry"))
+        .filter(|e| e.file_type().is_file())
+        .map(|e| {
+            e.path()
+                .strip_prefix(&out_dir)
+                .unwrap()
+                .to_string_lossy()
+                .replace('\\', "/")
+        })
+        .collect();
+    want.sort();
+
+    // `-` streams a .tar.zst to stdout.
+    let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+    cmd.arg("--fixture")
+        .arg(fixture_path())
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .args(["export", "--archive", "-"]);
+    let stream = cmd.assert().success().get_output().stdout.clone();
+    let mut tar = tar::Archive::new(zstd::Decoder::new(stream.as_slice()).expect("zstd"));
+    let mut got = Vec::new();
+    for entry in tar.entries().expect("tar entries") {
+        let mut entry = entry.expect("tar entry");
+        let path = entry.path().expect("path").to_string_lossy().to_string();
+        if path == "Personal/Alpha-n1/contents.md" {
+            assert_eq!(entry.header().mtime().unwrap(), 1_766_188_800);
+            let mut md = String::new();
+            std::io::Read::read_to_string(&mut entry, &mut md).unwrap();
+            assert_eq!(
+                md,
+                std::fs::read_to_string(out_dir.join("Personal/Alpha-n1/contents.md")).unwrap()
+            );
+        }
+        got.push(path);
+    }
+    got.sort();
+    assert_eq!(got, want);
+
+    let zip_path = dir.path().join("backup.zip");
+    let out = run_ok(&["export", "--archive", zip_path.to_str().unwrap()]);
+    assert_eq!(out.trim(), zip_path.to_str().unwrap());
+    let zip =
+        zip::ZipArchive::new(std::fs::File::open(&zip_path).expect("open zip")).expect("read zip");
+    let mut names: Vec<String> = zip
+        .file_names()
+        .map(|name| name.expect("zip entry name").to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names, want);
+
+    let err = run_err(&[
+        "export",
+        "--archive",
+        dir.path().join("b.tar.gz").to_str().unwrap(),
+    ]);
+    assert!(err.contains("use a .tar.zst or .zip name"), "{err}");
+}
+
+#[test]
+fn export_journal_appends_changes_since_the_previous_export() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let out_dir = dir.path().join("backup");
+    let journal = dir.path().join("journal.ndjson");
+    let export = |fixture: &std::path::Path| {
+        let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+        cmd.arg("--fixture")
+            .arg(fixture)
+            .env("NO_PROGRESS", "1")
+            .env("APPLE_NOTES_CONFIG", "/dev/null")
+            .args(["export", "--out"])
+            .arg(&out_dir)
+            .arg("--journal")
+            .arg(&journal);
+        cmd.assert().success();
+    };
+    let actions = || -> Vec<(String, String)> {
+        std::fs::read_to_string(&journal)
+            .expect("journal")
+            .lines()
+            .map(|l| {
+                let v: serde_json::Value = serde_json::from_str(l).expect("ndjson");
+                (
+                    v["action"].as_str().unwrap().into(),
+                    v["id"].as_str().unwrap().into(),
+                )
+            })
+            .collect()
+    };
+
+    export(&fixture_path());
+    export(&fixture_path());
+    assert_eq!(
+        actions(),
+        [("added", "n1"), ("added", "n2"), ("added", "n3")].map(|(a, i)| (a.into(), i.into()))
+    );
+
+    // Alpha edited, Beta deleted.
+    let mut fixture: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture_path()).unwrap()).unwrap();
+    fixture["notes_by_id"]["n1"]["body_html"] = "<div>Edited</div>".into();
+    fixture["note_summaries_by_account"]["iCloud"]
+        .as_array_mut()
+        .unwrap()
+        .retain(|n| n["id"] != "n2");
+    let edited = dir.path().join("edited.json");
+    std::fs::write(&edited, fixture.to_string()).unwrap();
+    export(&edited);
+
+    let actions = actions();
+    assert_eq!(
+        actions[3..],
+        [("updated", "n1"), ("removed", "n2")].map(|(a, i)| (a.into(), i.into()))
+    );
+}
+
+#[test]
+fn export_encrypt_writes_an_age_archive_only_the_recipient_can_open() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let identity = age::x25519::Identity::generate();
+    let recipient = identity.to_public().to_string();
+    let path = dir.path().join("backup.tar.zst.age");
+    run_ok(&[
+        "export",
+        "--archive",
+        path.to_str().unwrap(),
+        "--encrypt",
+        "--recipient",
+        &recipient,
+    ]);
+
+    let data = std::fs::read(&path).expect("archive");
+    assert!(data.starts_with(b"age-encryption.org/v1\n"));
+    let plain = age::Decryptor::new(data.as_slice())
+        .expect("age header")
+        .decrypt(std::iter::once(&identity as &dyn age::Identity))
+        .expect("decrypt");
+    let mut tar = tar::Archive::new(zstd::Decoder::new(plain).expect("zstd"));
+    let names: Vec<String> = tar
+        .entries()
+        .expect("tar entries")
+        .map(|e| e.unwrap().path().unwrap().to_string_lossy().to_string())
+        .collect();
+    assert!(
+        names.contains(&"Personal/Alpha-n1/contents.md".to_string()),
+        "{names:?}"
+    );
+
+    let err = run_err(&[
+        "export",
+        "--archive",
+        path.to_str().unwrap(),
+        "--encrypt",
+        "--recipient",
+        "not-a-key",
+    ]);
+    assert!(err.contains("invalid --recipient"), "{err}");
+}
+
+#[test]
+fn export_manifest_lists_notes_with_content_hashes() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let out_dir = dir.path().join("backup");
+    run_ok(&["export", "--out", out_dir.to_str().unwrap()]);
+
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(out_dir.join("manifest.json")).unwrap())
+            .expect("manifest.json");
+    assert_eq!(manifest["account"], "iCloud");
+    let notes = manifest["notes"].as_array().expect("notes");
+    let ids: Vec<&str> = notes.iter().map(|n| n["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["n1", "n2", "n3"]);
+    for note in notes {
+        let note_dir = out_dir.join(note["path"].as_str().unwrap());
+        let md = std::fs::read(note_dir.join("contents.md")).expect("contents.md");
+        let hash = format!("{:x}", <sha2::Sha256 as sha2::Digest>::digest(&md));
+        assert_eq!(note["sha256"], hash.as_str());
+        assert_eq!(note["modified_at"], "2025-12-20T00:00:00Z");
+    }
+}
+
+#[test]
+fn export_resumes_after_an_interrupted_run() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let out_dir = dir.path().join("backup");
+    let fixture: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture_path()).unwrap()).unwrap();
+    let without = |id: &str| {
+        let mut fixture = fixture.clone();
+        fixture["notes_by_id"].as_object_mut().unwrap().remove(id);
+        let path = dir.path().join(format!("without-{id}.json"));
+        std::fs::write(&path, fixture.to_string()).unwrap();
+        path
+    };
+    let export = |fixture: &std::path::Path| {
+        let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+        cmd.arg("--fixture")
+            .arg(fixture)
+            .env("NO_COLOR", "1")
+            .env("NO_PROGRESS", "1")
+            .env("APPLE_NOTES_CONFIG", "/dev/null")
+            .args(["export", "--jobs", "1", "--out", out_dir.to_str().unwrap()])
+            .assert()
+    };
+
+    // Fails on Gamma, after Alpha and Beta are written.
+    export(&without("n3")).failure().code(6);
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(out_dir.join("manifest.json")).unwrap())
+            .unwrap();
+    assert_eq!(manifest["partial"], true);
+    assert_eq!(manifest["notes"].as_array().unwrap().len(), 2);
+
+    // Alpha can't be fetched any more, so this only succeeds by keeping what was written.
+    export(&without("n1")).success();
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(out_dir.join("manifest.json")).unwrap())
+            .unwrap();
+    assert!(manifest.get("partial").is_none(), "{manifest}");
+    let ids: Vec<&str> = manifest["notes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|n| n["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, ["n1", "n2", "n3"]);
+    assert!(
+        out_dir
+            .join("Personal/Archive/Gamma-n3/contents.md")
+            .exists()
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn backup_schedule_installs_and_removes_a_launch_agent() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let home = dir.path().join("home");
+    let launchctl = dir.path().join("launchctl");
+    let calls = dir.path().join("calls.txt");
+    // Loaded once `load` has run; `list` reports like launchctl does.
+    write_stub(
+        &launchctl,
+        &format!(
+            "#!/bin/sh\necho \"$*\" >> '{calls}'\nif [ \"$1\" = list ]; then grep -q '^load' '{calls}' || exit 113; printf '{{\\n\\t\"LastExitStatus\" = 0;\\n}};\\n'; fi\n",
+            calls = calls.display()
+        ),
+    );
+    let schedule = |args: &[&str]| {
+        let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+        cmd.env("HOME", &home)
+            .env("APPLE_NOTES_LAUNCHCTL_BIN", &launchctl)
+            .env("APPLE_NOTES_CONFIG", "/dev/null")
+            .args(["backup", "schedule"])
+            .args(args);
+        let out = cmd.assert().success().get_output().stdout.clone();
+        String::from_utf8(out).unwrap()
+    };
+
+    let plist = home.join("Library/LaunchAgents/com.javisoto.apple-notes.backup.plist");
+    let out = schedule(&[
+        "install",
+        "--out",
+        "/backups/notes",
+        "--interval",
+        "weekly",
+        "--at",
+        "04:15",
+        "--",
+        "--snapshot",
+    ]);
+    assert_eq!(out.trim(), plist.to_str().unwrap());
+    let contents = std::fs::read_to_string(&plist).expect("plist written");
+    assert!(
+        contents.contains(
+            "<string>export</string>\n        <string>--out</string>\n        <string>/backups/notes</string>\n        <string>--snapshot</string>"
+        ),
+        "{contents}"
+    );
+    assert!(contents.contains("<key>Hour</key>\n        <integer>4</integer>"));
+
+    let status: serde_json::Value = serde_json::from_str(&schedule(&["status", "--json"])).unwrap();
+    assert_eq!(status["installed"], true);
+    assert_eq!(status["loaded"], true);
+    assert_eq!(status["last_exit_status"], 0);
+
+    assert!(schedule(&["remove"]).starts_with("Removed "));
+    assert!(!plist.exists());
+    assert_eq!(
+        schedule(&["remove"]).trim(),
+        "No scheduled backup installed"
+    );
+    let calls = std::fs::read_to_string(&calls).unwrap();
+    let verbs: Vec<&str> = calls
+        .lines()
+        .map(|l| l.split(' ').next().unwrap())
+        .collect();
+    assert_eq!(verbs, ["load", "list", "unload"]);
+}
+
+/// A minimal Notes database with an iCloud account, a "Notes" folder and an "Alpha" note
+/// (pk 20), for tests that watch it change.
+fn seed_notes_db(path: &std::path::Path) -> rusqlite::Connection {
+    let conn = rusqlite::Connection::open(path).expect("create db");
+    conn.execute_batch(
+        "CREATE TABLE Z_METADATA (Z_VERSION INTEGER PRIMARY KEY, Z_UUID VARCHAR(255), Z_PLIST BLOB);
+         INSERT INTO Z_METADATA(Z_VERSION, Z_UUID) VALUES (1, 'UUID');
+         CREATE TABLE ZICCLOUDSYNCINGOBJECT (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZNAME VARCHAR, ZTITLE1 VARCHAR, ZTITLE2 VARCHAR, ZFOLDER INTEGER, ZPARENT INTEGER, ZACCOUNT8 INTEGER, ZMARKEDFORDELETION INTEGER, ZIDENTIFIER VARCHAR, ZMERGEABLEDATA1 BLOB, ZCREATIONDATE1 REAL, ZCREATIONDATE2 REAL, ZCREATIONDATE3 REAL, ZMODIFICATIONDATE1 REAL, ZMODIFICATIONDATEATIMPORT REAL);
+         CREATE TABLE ZICNOTEDATA (Z_PK INTEGER PRIMARY KEY, ZNOTE INTEGER, ZDATA BLOB);
+         INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZNAME) VALUES (1, 14, 'iCloud');
+         INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZNAME, ZACCOUNT8) VALUES (10, 15, 'Notes', 1);
+         INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZTITLE1, ZFOLDER, ZCREATIONDATE1, ZMODIFICATIONDATE1) VALUES (20, 12, 'Alpha', 10, 100, 100);
+         INSERT INTO ZICNOTEDATA(ZNOTE, ZDATA) VALUES (20, CAST('Alpha body' AS BLOB));",
+    )
+    .expect("seed db");
+    conn
+}
+
+#[test]
+fn export_watch_mirrors_database_changes() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let db_path = dir.path().join("NoteStore.sqlite");
+    let out_dir = dir.path().join("mirror");
+    let conn = seed_notes_db(&db_path);
+
+    let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_apple-notes"))
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .env("APPLE_NOTES_OSASCRIPT_BIN", dir.path().join("missing"))
+        .env("HOME", dir.path())
+        .arg("--db-path")
+        .arg(&db_path)
+        .args([
+            "--timeout",
+            "60",
+            "export",
+            "--watch",
+            "--watch-interval",
+            "1",
+        ])
+        .arg("--out")
+        .arg(&out_dir)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .expect("spawn watch");
+    let wait_until = |what: &str, ready: &dyn Fn() -> bool| {
+        for _ in 0..300 {
+            if ready() {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        panic!("timed out waiting for {what}");
+    };
+    let contents = |rel: &str| std::fs::read_to_string(out_dir.join(rel)).unwrap_or_default();
+    let manifest_lists = |title: &str| contents("manifest.json").contains(title);
+
+    let alpha = "Notes/Alpha-p20/contents.md";
+    wait_until("first export", &|| manifest_lists("Alpha-"));
+    assert!(contents(alpha).contains("Alpha body"));
+
+    conn.execute_batch(
+        "UPDATE ZICCLOUDSYNCINGOBJECT SET ZMODIFICATIONDATE1 = 200 WHERE Z_PK = 20;
+         UPDATE ZICNOTEDATA SET ZDATA = CAST('Alpha edited' AS BLOB) WHERE ZNOTE = 20;
+         INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZTITLE1, ZFOLDER, ZCREATIONDATE1, ZMODIFICATIONDATE1) VALUES (21, 12, 'Beta', 10, 100, 100);
+         INSERT INTO ZICNOTEDATA(ZNOTE, ZDATA) VALUES (21, CAST('Beta body' AS BLOB));",
+    )
+    .expect("edit db");
+    wait_until("the edit", &|| manifest_lists("Beta-"));
+    assert!(contents(alpha).contains("Alpha edited"));
+
+    conn.execute_batch("UPDATE ZICCLOUDSYNCINGOBJECT SET ZMARKEDFORDELETION = 1 WHERE Z_PK = 20;")
+        .expect("delete note");
+    wait_until("the deletion", &|| !manifest_lists("Alpha-"));
+    assert!(!out_dir.join("Notes/Alpha-p20").exists());
+    assert!(contents("Notes/Beta-p21/contents.md").contains("Beta body"));
+
+    child.kill().ok();
+    child.wait().ok();
+}
+
+#[test]
+fn notes_watch_streams_changes_as_ndjson() {
+    use std::io::BufRead;
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let db_path = dir.path().join("NoteStore.sqlite");
+    let conn = seed_notes_db(&db_path);
+    let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_apple-notes"))
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .env("HOME", dir.path())
+        .arg("--db-path")
+        .arg(&db_path)
+        .args(["--timeout", "60", "notes", "watch", "--interval", "1"])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .expect("spawn watch");
+    let mut lines = std::io::BufReader::new(child.stdout.take().unwrap()).lines();
+    let mut next_event = || -> serde_json::Value {
+        serde_json::from_str(&lines.next().expect("an event").expect("read line")).expect("json")
+    };
+    // Let the watcher take its baseline before changing anything.
+    std::thread::sleep(std::time::Duration::from_millis(1500));
+
+    conn.execute_batch(
+        "INSERT INTO ZICCLOUDSYNCINGOBJECT(Z_PK, Z_ENT, ZTITLE1, ZFOLDER, ZCREATIONDATE1, ZMODIFICATIONDATE1) VALUES (21, 12, 'Beta', 10, 100, 100);",
+    )
+    .expect("create note");
+    let event = next_event();
+    assert_eq!(event["event"], "created");
+    assert_eq!(event["title"], "Beta");
+    assert_eq!(event["id"], "x-coredata://UUID/ICNote/p21");
+
+    conn.execute_batch(
+        "UPDATE ZICCLOUDSYNCINGOBJECT SET ZMODIFICATIONDATE1 = 200 WHERE Z_PK = 20;",
+    )
+    .expect("edit note");
+    let event = next_event();
+    assert_eq!(event["event"], "modified");
+    assert_eq!(event["title"], "Alpha");
+    assert_eq!(event["modified_at"], "2001-01-01T00:03:20Z");
+
+    conn.execute_batch("UPDATE ZICCLOUDSYNCINGOBJECT SET ZMARKEDFORDELETION = 1 WHERE Z_PK = 21;")
+        .expect("delete note");
+    let event = next_event();
+    assert_eq!(event["event"], "deleted");
+    assert_eq!(event["title"], "Beta");
+
+    child.kill().ok();
+    child.wait().ok();
+}
+
+#[test]
+fn notes_watch_needs_the_database() {
+    let err = run_err(&["notes", "watch"]);
+    assert!(err.contains("needs the Notes database"), "{err}");
+}
+
+#[test]
+fn tui_needs_a_terminal() {
+    let err = run_err(&["tui"]);
+    assert!(err.contains("needs an interactive terminal"), "{err}");
+}
+
+#[test]
+fn serve_stdio_answers_json_rpc_requests() {
+    let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+    cmd.arg("--fixture")
+        .arg(fixture_path())
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .args(["serve", "--stdio"])
+        .write_stdin(concat!(
+            r#"{"jsonrpc":"2.0","id":1,"method":"list_accounts"}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","id":2,"method":"get_note","params":{"id":"n4"}}"#,
+            "\n",
+        ));
+    let out = cmd.assert().success().get_output().stdout.clone();
+    let responses: Vec<serde_json::Value> = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(responses[0]["id"], 1);
+    assert_eq!(responses[0]["result"][1]["name"], "On My Mac");
+    assert_eq!(responses[1]["result"]["title"], "Delta");
+}
+
+#[test]
+fn serve_http_answers_rest_requests() {
+    use std::io::{BufRead, Read, Write};
+
+    let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_apple-notes"))
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .env("APPLE_NOTES_SERVE_TOKEN", "secret")
+        .arg("--fixture")
+        .arg(fixture_path())
+        .args(["serve", "--http", "127.0.0.1:0"])
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("spawn server");
+    let mut banner = String::new();
+    std::io::BufReader::new(child.stderr.take().unwrap())
+        .read_line(&mut banner)
+        .expect("read banner");
+    let addr = banner
+        .trim()
+        .strip_prefix("Listening on http://")
+        .unwrap_or_else(|| panic!("unexpected banner {banner:?}"))
+        .to_string();
+
+    let get = |path: &str, token: &str| -> String {
+        let mut stream = std::net::TcpStream::connect(&addr).expect("connect");
+        write!(
+            stream,
+            "GET {path} HTTP/1.1\r\nHost: {addr}\r\nAuthorization: Bearer {token}\r\nConnection: close\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).expect("read response");
+        response
+    };
+    let response = get("/notes/n2", "secret");
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    let body = response.split("\r\n\r\n").nth(1).unwrap();
+    let note: serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(note["title"], "Beta");
+    assert!(get("/notes", "wrong").starts_with("HTTP/1.1 401"));
+
+    child.kill().ok();
+    child.wait().ok();
+}
+
+#[test]
+fn serve_http_generates_a_token_when_none_is_given() {
+    use std::io::{BufRead, Read, Write};
+
+    let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_apple-notes"))
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .env_remove("APPLE_NOTES_SERVE_TOKEN")
+        .arg("--fixture")
+        .arg(fixture_path())
+        .args(["serve", "--http", "127.0.0.1:0"])
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("spawn server");
+    let mut stderr = std::io::BufReader::new(child.stderr.take().unwrap());
+    let mut line = || {
+        let mut line = String::new();
+        stderr.read_line(&mut line).expect("read banner");
+        line.trim().to_string()
+    };
+    let token = line()
+        .strip_prefix("Token: ")
+        .expect("token line")
+        .to_string();
+    assert_eq!(token.len(), 32);
+    let addr = line()
+        .strip_prefix("Listening on http://")
+        .expect("listening line")
+        .to_string();
+
+    let get = |auth: &str| -> String {
+        let mut stream = std::net::TcpStream::connect(&addr).expect("connect");
+        write!(
+            stream,
+            "GET /notes HTTP/1.1\r\nHost: {addr}\r\n{auth}Connection: close\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).expect("read response");
+        response
+    };
+    assert!(get("").starts_with("HTTP/1.1 401"));
+    let auth = format!("Authorization: Bearer {token}\r\n");
+    assert!(get(&auth).starts_with("HTTP/1.1 200"));
+
+    child.kill().ok();
+    child.wait().ok();
+}
+
+#[test]
+fn config_file_defaults_yield_to_env_and_flags() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let config = dir.path().join("config.toml");
+    std::fs::write(
+        &config,
+        format!(
+            "account = \"On My Mac\"\nexport_dir = {:?}\n",
+            dir.path().join("backup")
+        ),
+    )
+    .unwrap();
+    let run = |env: Option<&str>, args: &[&str]| -> String {
+        let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+        cmd.arg("--fixture")
+            .arg(fixture_path())
+            .arg("--config")
+            .arg(&config)
+            .env("NO_COLOR", "1")
+            .env("NO_PROGRESS", "1")
+            .env_remove("APPLE_NOTES_ACCOUNT")
+            .args(args);
+        if let Some(account) = env {
+            cmd.env("APPLE_NOTES_ACCOUNT", account);
+        }
+        let out = cmd.assert().success().get_output().stdout.clone();
+        String::from_utf8(out).unwrap()
+    };
+
+    let from_file = run(None, &["notes", "list", "--picker"]);
+    assert_eq!(from_file, "n4\tNotes\tDelta\n");
+    let from_env = run(Some("iCloud"), &["notes", "list", "--picker", "-n", "1"]);
+    assert_eq!(from_env, "n1\tPersonal\tAlpha\n");
+    let from_flag = run(
+        Some("nope"),
+        &[
+            "--account",
+            "iCloud",
+            "notes",
+            "list",
+            "--picker",
+            "-n",
+            "1",
+        ],
+    );
+    assert_eq!(from_flag, from_env);
+
+    run(None, &["export"]);
+    assert!(dir.path().join("backup/manifest.json").exists());
+}
+
+#[test]
+fn notes_create_falls_back_to_the_default_folder() {
+    let out = run_ok(&["notes", "create", "--title", "Idea", "--body", "x"]);
+    assert_eq!(out.trim(), "fixture://note/1");
+
+    let create = |folder: &str| {
+        let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+        cmd.arg("--fixture")
+            .arg(fixture_path())
+            .env("NO_PROGRESS", "1")
+            .env("APPLE_NOTES_CONFIG", "/dev/null")
+            .env("APPLE_NOTES_FOLDER", folder)
+            .args(["notes", "create", "--title", "Idea", "--body", "x"])
+            .assert()
+    };
+    let out = create("Personal").success().get_output().stdout.clone();
+    assert!(!String::from_utf8(out).unwrap().trim().is_empty());
+    let err = create(" > ").failure().get_output().stderr.clone();
+    assert!(
+        String::from_utf8(err)
+            .unwrap()
+            .contains("folder path is empty")
+    );
+}
+
+#[test]
+fn quick_captures_arguments_and_stdin() {
+    let err = run_err(&["quick", "Buy milk"]);
+    assert!(err.contains("--folder is required"), "{err}");
+    let out = run_ok(&["--json", "quick", "--folder", "Personal", "Buy milk"]);
+    let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert!(v["id"].is_string());
+
+    let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+    let out = cmd
+        .arg("--fixture")
+        .arg(fixture_path())
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .env("APPLE_NOTES_FOLDER", "Personal")
+        .arg("quick")
+        .write_stdin("Call the bank\nabout the card\n")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert!(!String::from_utf8(out).unwrap().trim().is_empty());
+    let err = run_err(&["quick", "--folder", "Personal", "  "]);
+    assert!(err.contains("nothing to capture"), "{err}");
+}
+
+#[test]
+fn notes_create_fills_named_templates() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::create_dir_all(dir.path().join("apple-notes/templates")).unwrap();
+    std::fs::write(
+        dir.path().join("apple-notes/templates/meeting.md"),
+        "# {{title}} ({{date}})\n\nAttendees: {{attendee}}\n",
+    )
+    .unwrap();
+    let create = |vars: &[&str]| {
+        let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+        cmd.arg("--fixture")
+            .arg(fixture_path())
+            .env("NO_PROGRESS", "1")
+            .env("APPLE_NOTES_CONFIG", "/dev/null")
+            .env("XDG_CONFIG_HOME", dir.path())
+            .args(["notes", "create", "--folder", "Personal", "--title", "Sync"])
+            .args(["--template", "meeting"])
+            .args(vars)
+            .assert()
+    };
+    create(&["--var", "attendee=Bob"]).success();
+    let err = create(&[]).failure().get_output().stderr.clone();
+    let err = String::from_utf8(err).unwrap();
+    assert!(err.contains("{{attendee}}"), "{err}");
+}
+
+#[cfg(unix)]
+#[test]
+fn clipboard_feeds_bodies_and_receives_notes() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let pbpaste = dir.path().join("pbpaste");
+    let pbcopy = dir.path().join("pbcopy");
+    let copied = dir.path().join("copied.txt");
+    write_stub(&pbpaste, "#!/bin/sh\nprintf 'from the clipboard'\n");
+    write_stub(
+        &pbcopy,
+        &format!("#!/bin/sh\ncat > '{}'\n", copied.display()),
+    );
+    let run = |args: &[&str]| {
+        let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+        cmd.arg("--fixture")
+            .arg(fixture_path())
+            .env("NO_PROGRESS", "1")
+            .env("APPLE_NOTES_CONFIG", "/dev/null")
+            .env("APPLE_NOTES_PBPASTE_BIN", &pbpaste)
+            .env("APPLE_NOTES_PBCOPY_BIN", &pbcopy)
+            .args(args)
+            .assert()
+    };
+
+    run(&["notes", "append", "n1", "--clipboard"]).success();
+    run(&["notes", "append", "n1", "--clipboard", "--body", "x"]).failure();
+
+    let err = run(&["notes", "show", "n1", "--copy"])
+        .success()
+        .get_output()
+        .stderr
+        .clone();
+    assert!(
+        String::from_utf8(err)
+            .unwrap()
+            .contains("Copied the Markdown of \"Alpha\"")
+    );
+    assert!(std::fs::read_to_string(&copied).unwrap().contains("Alpha"));
+    run(&["notes", "show", "n1", "--copy", "--html"]).success();
+    assert!(std::fs::read_to_string(&copied).unwrap().starts_with('<'));
+}
+
+#[cfg(unix)]
+#[test]
+fn notes_open_shows_the_note_in_notes_app() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let osascript = dir.path().join("osascript");
+    let script = dir.path().join("script.applescript");
+    write_stub(
+        &osascript,
+        &format!("#!/bin/sh\ncat > '{}'\n", script.display()),
+    );
+    let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+    cmd.env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .env("APPLE_NOTES_OSASCRIPT_BIN", &osascript)
+        .args([
+            "--backend",
+            "osascript",
+            "notes",
+            "open",
+            "x-coredata://U/ICNote/p7",
+        ])
+        .assert()
+        .success();
+    let script = std::fs::read_to_string(&script).unwrap();
+    assert!(
+        script.contains(r#"show note id "x-coredata://U/ICNote/p7""#)
+            && script.contains("activate"),
+        "{script}"
+    );
+
+    let err = run_err(&["notes", "open", "missing"]);
+    assert!(err.contains("missing note id"), "{err}");
+}
+
+#[test]
+fn notes_are_addressable_by_uuid() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let db_path = dir.path().join("NoteStore.sqlite");
+    let conn = seed_notes_db(&db_path);
+    conn.execute(
+        "UPDATE ZICCLOUDSYNCINGOBJECT SET ZIDENTIFIER = 'A1B2-C3' WHERE Z_PK = 20",
+        [],
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+        let out = cmd
+            .env("NO_PROGRESS", "1")
+            .env("APPLE_NOTES_CONFIG", "/dev/null")
+            .arg("--db-path")
+            .arg(&db_path)
+            .args(args)
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        String::from_utf8(out).unwrap()
+    };
+    let url = "notes://showNote?identifier=A1B2-C3\n";
+    assert_eq!(run(&["notes", "url", "x-coredata://UUID/ICNote/p20"]), url);
+    assert_eq!(run(&["notes", "url", "A1B2-C3"]), url);
+    let v: serde_json::Value =
+        serde_json::from_str(&run(&["--json", "notes", "url", "A1B2-C3"])).unwrap();
+    assert_eq!(v["uuid"], "A1B2-C3");
+    assert_eq!(v["id"], "x-coredata://UUID/ICNote/p20");
+
+    // Listings lead with the UUID, and keep the x-coredata id in JSON.
+    assert_eq!(
+        run(&["notes", "list", "--picker"]),
+        "A1B2-C3\tNotes\tAlpha\n"
+    );
+    let v: serde_json::Value = serde_json::from_str(&run(&["notes", "list", "--json"])).unwrap();
+    assert_eq!(v[0]["uuid"], "A1B2-C3");
+    assert_eq!(v[0]["id"], "x-coredata://UUID/ICNote/p20");
+    assert_eq!(v[0]["created_at"], "2001-01-01T00:01:40Z");
+    assert_eq!(v[0]["modified_at"], "2001-01-01T00:01:40Z");
+
+    let err = run_err(&["notes", "url", "n1"]);
+    assert!(err.contains("needs the Notes database"), "{err}");
+}
+
+#[test]
+fn notes_resolve_by_title_or_id_prefix() {
+    assert_eq!(
+        run_ok(&["notes", "show", "alp", "--markdown"]),
+        run_ok(&["notes", "show", "n1", "--markdown"])
+    );
+    assert!(run_ok(&["notes", "show", "GAMMA", "--markdown"]).contains("Gamma"));
+
+    let err = run_err(&["notes", "show", "a"]);
+    assert!(err.contains("ambiguous note \"a\", did you mean:"), "{err}");
+    assert!(err.contains("n2  Beta"), "{err}");
+
+    // Exact ids from another account skip the title lookup.
+    assert!(run_ok(&["notes", "show", "n4", "--markdown"]).contains("Delta"));
+    run_ok(&["notes", "delete", "beta", "--yes"]);
+}
+
+#[test]
+fn notes_show_meta_prints_metadata_only() {
+    assert_eq!(
+        run_ok(&["notes", "show", "n1", "--meta"]),
+        "Title:       Alpha\nFolder:      Personal\nCreated:     2025-12-20T00:00:00Z\n\
+         Modified:    2025-12-20T00:00:00Z\nAttachments: 0\nWords:       2\n"
+    );
+    let v: serde_json::Value =
+        serde_json::from_str(&run_ok(&["--json", "notes", "show", "n4", "--meta"])).unwrap();
+    assert_eq!(v["folder"], "Notes");
+    assert_eq!(v["words"], 2);
+
+    // With the database, nothing goes through Notes.app.
+    let dir = tempfile::tempdir().expect("tempdir");
+    let db_path = dir.path().join("NoteStore.sqlite");
+    seed_notes_db(&db_path);
+    let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+    let out = cmd
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .env("APPLE_NOTES_OSASCRIPT_BIN", dir.path().join("missing"))
+        .arg("--db-path")
+        .arg(&db_path)
+        .args(["--json", "notes", "show", "alpha", "--meta"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let v: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(v["id"], "x-coredata://UUID/ICNote/p20");
+    assert_eq!(v["folder"], "Notes");
+    assert_eq!(v["created_at"], "2001-01-01T00:01:40Z");
+    assert_eq!(v["words"], 2);
+}
+
+#[test]
+fn notes_show_never_pages_piped_output() {
+    let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+    cmd.arg("--fixture")
+        .arg(fixture_path())
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .env("APPLE_NOTES_PAGER", "false")
+        .env("LINES", "1")
+        .args(["notes", "show", "n1"])
+        .assert()
+        .success()
+        .stdout("# Alpha\n\n**Hello** Alpha\n");
+    assert_eq!(
+        run_ok(&["notes", "show", "n1", "--no-pager"]),
+        run_ok(&["notes", "show", "n1"])
+    );
+}
+
+#[test]
+fn notes_show_head_prints_the_first_lines() {
+    assert_eq!(
+        run_ok(&["notes", "show", "n1", "--head", "1"]),
+        "# Alpha\n\n**Hello** Alpha\n"
+    );
+    let v: serde_json::Value =
+        serde_json::from_str(&run_ok(&["--json", "notes", "show", "n2", "--head", "3"])).unwrap();
+    assert_eq!(v["head"], "Beta body");
+    let err = run_err(&["notes", "show", "n1", "--head", "1", "--html"]);
+    assert!(err.contains("cannot be used with"), "{err}");
+}
+
+/// The basic fixture with distinct creation and modification dates for the iCloud notes
+/// (n1, n2, n3), written to `dir`.
+fn dated_fixture(dir: &std::path::Path) -> PathBuf {
+    let mut fixture: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture_path()).unwrap()).unwrap();
+    for (i, (created, modified)) in [
+        ("2025-01-03", "2025-03-01"),
+        ("2025-01-02", "2025-12-01"),
+        ("2025-01-01", "2025-06-01"),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let n = &mut fixture["note_summaries_by_account"]["iCloud"][i];
+        n["created_at"] = format!("{created}T00:00:00Z").into();
+        n["modified_at"] = format!("{modified}T00:00:00Z").into();
+    }
+    let path = dir.join("dated.json");
+    std::fs::write(&path, fixture.to_string()).unwrap();
+    path
+}
+
+/// `run_ok` with another fixture file.
+fn run_fixture_ok(fixture: &std::path::Path, args: &[&str]) -> String {
+    let out = assert_cmd::cargo_bin_cmd!("apple-notes")
+        .arg("--fixture")
+        .arg(fixture)
+        .env("NO_COLOR", "1")
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .env("COLUMNS", "120")
+        .env("TZ", "UTC")
+        .args(args)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    String::from_utf8(out).expect("utf8")
+}
+
+/// The note ids `notes list <args>` prints with `fixture`, from `--json` or `--picker` output.
+fn listed_ids(fixture: &std::path::Path, args: &[&str]) -> Vec<String> {
+    let mut cmd = assert_cmd::cargo_bin_cmd!("apple-notes");
+    let out = cmd
+        .arg("--fixture")
+        .arg(fixture)
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .args(["notes", "list"])
+        .args(args)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let out = String::from_utf8(out).unwrap();
+    match serde_json::from_str::<serde_json::Value>(&out) {
+        Ok(v) => v
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|n| n["id"].as_str().unwrap().to_string())
+            .collect(),
+        Err(_) => out
+            .lines()
+            .map(|l| l.split('\t').next().unwrap().to_string())
+            .collect(),
+    }
+}
+
+#[test]
+fn notes_list_sorts_every_output_format() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dated_fixture(dir.path());
+    let ids = |args: &[&str]| listed_ids(&path, args);
+    assert_eq!(ids(&["--picker"]), ["n1", "n2", "n3"]);
+    assert_eq!(
+        ids(&["--picker", "--sort", "modified", "--reverse"]),
+        ["n2", "n3", "n1"]
+    );
+    assert_eq!(
+        ids(&["--output", "json", "--sort", "created"]),
+        ["n3", "n2", "n1"]
+    );
+    assert_eq!(
+        ids(&["--picker", "--sort", "folder", "--reverse"]),
+        ["n3", "n2", "n1"]
+    );
+    assert_eq!(
+        ids(&["--output", "json", "--sort", "modified", "-n", "1"]),
+        ["n1"]
+    );
+}
+
+#[test]
+fn notes_list_filters_by_date_before_the_limit() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dated_fixture(dir.path());
+    let ids = |args: &[&str]| listed_ids(&path, args);
+    assert_eq!(
+        ids(&["--picker", "--modified-after", "2025-05-01"]),
+        ["n2", "n3"]
+    );
+    assert_eq!(
+        ids(&["--picker", "--modified-after", "2025-05-01", "-n", "1"]),
+        ["n2"]
+    );
+    assert_eq!(
+        ids(&[
+            "--picker",
+            "--created-after",
+            "2025-01-01T12:00:00Z",
+            "--created-before",
+            "2025-01-03"
+        ]),
+        ["n2"]
+    );
+    assert_eq!(
+        ids(&["--picker", "--modified-before", "2025-06-01"]),
+        ["n1"]
+    );
+    // Relative dates count back from now; the fixture is long past a week ago.
+    assert!(ids(&["--picker", "--modified-after", "7d"]).is_empty());
+
+    let err = run_err(&["notes", "list", "--created-after", "soon"]);
+    assert!(err.contains("expected YYYY-MM-DD"), "{err}");
+}
+
+#[test]
+fn notes_list_pages_with_offset_and_limit() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dated_fixture(dir.path());
+    let ids = |args: &[&str]| listed_ids(&path, args);
+    assert_eq!(ids(&["--picker", "--offset", "1", "-n", "1"]), ["n2"]);
+    assert_eq!(ids(&["--output", "json", "--offset", "2"]), ["n3"]);
+    assert!(ids(&["--picker", "--offset", "5"]).is_empty());
+    // Unsorted listings stop loading once the page is full.
+    assert_eq!(
+        ids(&["--picker", "--sort", "none", "--offset", "1", "-n", "1"]),
+        ["n2"]
+    );
+}
+
+#[test]
+fn notes_list_preview_adds_body_snippets() {
+    let table = run_ok(&["notes", "list", "--folder", "Personal", "--preview"]);
+    assert!(table.contains("Preview"), "{table}");
+    assert!(table.contains("Hello Alpha"), "{table}");
+
+    let json = run_ok(&["--json", "notes", "list", "--preview"]);
+    let notes: serde_json::Value = serde_json::from_str(&json).expect("json");
+    let previews: Vec<&str> = notes
+        .as_array()
+        .expect("array")
+        .iter()
+        .map(|n| n["preview"].as_str().expect("preview"))
+        .collect();
+    assert_eq!(previews, ["Hello Alpha", "Beta body", "Gamma body"]);
+}
+
+#[test]
+fn notes_list_filters_titles_by_regex() {
+    let ids = |args: &[&str]| listed_ids(&fixture_path(), args);
+    assert_eq!(
+        ids(&["--picker", "--regex", "^(alpha|gamma)$"]),
+        ["n1", "n3"]
+    );
+    assert!(ids(&["--picker", "--regex", "^alpha", "--case-sensitive"]).is_empty());
+    assert_eq!(
+        ids(&["--picker", "--query", "Beta", "--case-sensitive"]),
+        ["n2"]
+    );
+
+    let err = run_err(&["notes", "list", "--regex", "("]);
+    assert!(err.contains("invalid --regex"), "{err}");
+}
+
+#[test]
+fn notes_list_groups_by_folder() {
+    insta::assert_snapshot!(run_ok(&["notes", "list", "--group-by-folder"]));
+
+    let json = run_ok(&["--json", "notes", "list", "--group-by-folder"]);
+    let groups: serde_json::Value = serde_json::from_str(&json).expect("json");
+    let summary: Vec<(String, usize)> = groups
+        .as_array()
+        .expect("array")
+        .iter()
+        .map(|g| {
+            let notes = g["notes"].as_array().expect("notes").len();
+            (g["folder"].as_str().expect("folder").to_string(), notes)
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("Personal".to_string(), 1),
+            ("Personal > Archive".to_string(), 2)
+        ]
+    );
+}
+
+#[test]
+fn notes_wordcount_counts_notes_and_totals() {
+    insta::assert_snapshot!(run_ok(&[
+        "notes",
+        "wordcount",
+        "--folder",
+        "Personal > Archive"
+    ]));
+
+    let json = run_ok(&["--json", "notes", "wordcount", "alpha"]);
+    let counts: serde_json::Value = serde_json::from_str(&json).expect("json");
+    assert_eq!(counts["notes"][0]["words"], 2);
+    assert_eq!(counts["notes"][0]["characters"], "Hello Alpha".len());
+    assert_eq!(counts["total"]["notes"], 1);
+
+    let err = run_err(&["notes", "wordcount"]);
+    assert!(err.contains("required"), "{err}");
+}
+
+#[test]
+fn notes_dedupe_keeps_the_newest_duplicate() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let dated = dated_fixture(dir.path());
+    let mut fixture: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&dated).unwrap()).unwrap();
+    // Gamma (modified 2025-06-01) becomes a copy of Beta (modified 2025-12-01).
+    fixture["note_summaries_by_account"]["iCloud"][2]["title"] = "Beta".into();
+    fixture["notes_by_id"]["n3"]["title"] = "Beta".into();
+    fixture["notes_by_id"]["n3"]["body_html"] = "<div>Beta  body</div>".into();
+    let path = dir.path().join("dupes.json");
+    std::fs::write(&path, fixture.to_string()).unwrap();
+
+    let dedupe = |args: &[&str]| run_fixture_ok(&path, &[&["notes", "dedupe"], args].concat());
+    let out = dedupe(&[]);
+    assert!(out.contains("1 duplicate notes in 1 groups"), "{out}");
+
+    let json: serde_json::Value =
+        serde_json::from_str(&dedupe(&["--json", "--apply", "--yes"])).unwrap();
+    assert_eq!(json["applied"], true);
+    assert_eq!(json["clusters"][0]["keep"]["id"], "n2");
+    assert_eq!(json["clusters"][0]["duplicates"][0]["id"], "n3");
+
+    let out = dedupe(&["--by", "content", "--dry-run"]);
+    assert!(out.contains("keep") && out.contains("duplicate"), "{out}");
+    assert!(run_ok(&["notes", "dedupe"]).contains("No duplicate notes."));
+
+    // Without --yes (and no terminal to ask at), nothing is deleted.
+    let output = assert_cmd::cargo_bin_cmd!("apple-notes")
+        .arg("--fixture")
+        .arg(&path)
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .args(["notes", "dedupe", "--apply"])
+        .output()
+        .expect("run apple-notes");
+    assert!(!output.status.success());
+    let out = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        out.contains("duplicate"),
+        "the plan is printed first: {out}"
+    );
+    let err = String::from_utf8(output.stderr).unwrap();
+    assert!(err.contains("refusing to delete without --yes"), "{err}");
+
+    // Same title, different body: kept unless --any-body.
+    fixture["notes_by_id"]["n3"]["body_html"] = "<div>Other body</div>".into();
+    std::fs::write(&path, fixture.to_string()).unwrap();
+    let by_title = |extra: &[&str]| -> serde_json::Value {
+        let args = [&["--json", "--by", "title", "--apply", "--yes"], extra].concat();
+        serde_json::from_str(&dedupe(&args)).unwrap()
+    };
+    let json = by_title(&[]);
+    assert_eq!(json["clusters"][0]["duplicates"], serde_json::json!([]));
+    assert_eq!(json["clusters"][0]["different_bodies"][0]["id"], "n3");
+    let json = by_title(&["--any-body"]);
+    assert_eq!(json["clusters"][0]["duplicates"][0]["id"], "n3");
+
+    // Locked notes are never touched.
+    fixture["note_summaries_by_account"]["iCloud"][2]["locked"] = true.into();
+    std::fs::write(&path, fixture.to_string()).unwrap();
+    let json = by_title(&["--any-body"]);
+    assert_eq!(json["clusters"], serde_json::json!([]));
+}
+
+#[test]
+fn notes_lint_reports_problems() {
+    assert!(run_ok(&["notes", "lint"]).contains("No problems found in 3 notes."));
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let mut fixture: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture_path()).unwrap()).unwrap();
+    fixture["note_summaries_by_account"]["iCloud"][0]["folder_id"] = "f_gone".into();
+    fixture["notes_by_id"]["n2"]["body_html"] = "<div>Beta</div><div><br></div>".into();
+    fixture["notes_by_id"]["n3"]["body_html"] = "<div>Gamma</div><div><b>Gamma</b></div>".into();
+    let path = dir.path().join("lint.json");
+    std::fs::write(&path, fixture.to_string()).unwrap();
+
+    let out = run_fixture_ok(&path, &["--json", "notes", "lint"]);
+    let findings: serde_json::Value = serde_json::from_str(&out).expect("json");
+    let issues: Vec<(&str, &str)> = findings
+        .as_array()
+        .expect("array")
+        .iter()
+        .map(|f| (f["id"].as_str().unwrap(), f["issue"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        issues,
+        [
+            ("n1", "missing_folder"),
+            ("n2", "empty"),
+            ("n3", "duplicate_title")
+        ]
+    );
+}
+
+/// The basic fixture with UUIDs, where Alpha links to Beta and Beta back to Alpha and to Gamma.
+fn linked_fixture(dir: &std::path::Path) -> PathBuf {
+    let mut fixture: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture_path()).unwrap()).unwrap();
+    let uuid = |i: usize| format!("00000000-0000-0000-0000-00000000000{i}");
+    for i in 0..3 {
+        fixture["note_summaries_by_account"]["iCloud"][i]["uuid"] = uuid(i + 1).into();
+    }
+    fixture["notes_by_id"]["n1"]["body_html"] = format!(
+        "<div><a href=\"applenotes:note/{}\">Beta</a></div>",
+        uuid(2)
+    )
+    .into();
+    fixture["notes_by_id"]["n2"]["body_html"] = format!(
+        "<div><a href=\"notes://showNote?identifier={}\">Alpha</a> <a href=\"applenotes:note/{}\">Gamma</a></div>",
+        uuid(1),
+        uuid(3)
+    )
+    .into();
+    let path = dir.join("linked.json");
+    std::fs::write(&path, fixture.to_string()).unwrap();
+    path
+}
+
+#[test]
+fn notes_graph_prints_links_as_dot_or_json() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = linked_fixture(dir.path());
+    insta::assert_snapshot!(run_fixture_ok(&path, &["notes", "graph"]));
+
+    let json = run_fixture_ok(&path, &["notes", "graph", "--format", "json"]);
+    let graph: serde_json::Value = serde_json::from_str(&json).expect("json");
+    assert_eq!(graph["nodes"][1]["links"], serde_json::json!(["n1", "n3"]));
+
+    let err = run_err(&["notes", "graph"]);
+    assert!(err.contains("needs note UUIDs"), "{err}");
+}
+
+#[test]
+fn notes_backlinks_lists_linking_notes() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = linked_fixture(dir.path());
+    let ids = |reference: &str| -> Vec<String> {
+        let out = run_fixture_ok(&path, &["--json", "notes", "backlinks", reference]);
+        let notes: Vec<serde_json::Value> = serde_json::from_str(&out).expect("json");
+        notes
+            .iter()
+            .map(|n| n["id"].as_str().unwrap().to_string())
+            .collect()
+    };
+    assert_eq!(ids("Alpha"), ["n2"]);
+    assert_eq!(ids("n3"), ["n2"]);
+    assert_eq!(ids("Beta"), ["n1"]);
+    assert!(run_fixture_ok(&path, &["notes", "backlinks", "n2"]).contains("Alpha"));
+}
+
+#[test]
+fn notes_grep_prints_matches_with_context() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let mut fixture: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture_path()).unwrap()).unwrap();
+    fixture["notes_by_id"]["n2"]["body_html"] =
+        "<div>one</div><div>Two BODY</div><div>three</div><div>four</div><div>five</div><div>six body</div>"
+            .into();
+    let path = dir.path().join("grep.json");
+    std::fs::write(&path, fixture.to_string()).unwrap();
+
+    insta::assert_snapshot!(run_fixture_ok(
+        &path,
+        &["notes", "grep", "-i", "-C", "1", "body"]
+    ));
+    assert_eq!(
+        run_fixture_ok(&path, &["notes", "grep", "-l", "body"]),
+        "n2\nn3\n"
+    );
+    let json = run_fixture_ok(&path, &["--json", "notes", "grep", "Hello"]);
+    let found: serde_json::Value = serde_json::from_str(&json).expect("json");
+    assert_eq!(found[0]["id"], "n1");
+    assert_eq!(found[0]["lines"][0]["match"], true);
+}
+
+/// The basic fixture plus a Recently Deleted folder holding Old (n5) and Recent (n6).
+fn trash_fixture(dir: &std::path::Path) -> PathBuf {
+    let mut fixture: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture_path()).unwrap()).unwrap();
+    fixture["folders_by_account"]["iCloud"]
+        .as_array_mut()
+        .unwrap()
+        .push(serde_json::json!({
+            "id": "f_trash",
+            "name": "Recently Deleted",
+            "account": "iCloud",
+            "path": ["Recently Deleted"]
+        }));
+    for (id, title, modified) in [
+        ("n5", "Old", "2025-11-01T00:00:00Z"),
+        ("n6", "Recent", "2025-12-01T00:00:00Z"),
+    ] {
+        fixture["note_summaries_by_account"]["iCloud"]
+            .as_array_mut()
+            .unwrap()
+            .push(serde_json::json!({
+                "id": id,
+                "title": title,
+                "folder_id": "f_trash",
+                "modified_at": modified
+            }));
+        fixture["notes_by_id"][id] = serde_json::json!({
+            "id": id,
+            "title": title,
+            "folder_id": "f_trash",
+            "created_at": modified,
+            "modified_at": modified,
+            "body_html": format!("<div>{title} body</div>")
+        });
+    }
+    let path = dir.join("trash.json");
+    std::fs::write(&path, fixture.to_string()).unwrap();
+    path
+}
+
+#[test]
+fn notes_trash_list_shows_recently_deleted_notes() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = trash_fixture(dir.path());
+    insta::assert_snapshot!(run_fixture_ok(&path, &["notes", "trash", "list"]));
+
+    let json = run_fixture_ok(&path, &["--json", "notes", "trash", "list"]);
+    let notes: Vec<serde_json::Value> = serde_json::from_str(&json).expect("json");
+    let ids: Vec<&str> = notes.iter().map(|n| n["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["n6", "n5"]);
+
+    assert!(run_ok(&["notes", "trash", "list"]).contains("Recently Deleted is empty."));
+}
+
+#[test]
+fn notes_trash_restore_moves_deleted_notes_back() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = trash_fixture(dir.path());
+    let out = run_fixture_ok(&path, &["notes", "trash", "restore", "Old"]);
+    assert_eq!(out, "Restored to Notes.\n");
+
+    let json = run_fixture_ok(
+        &path,
+        &[
+            "--json",
+            "notes",
+            "trash",
+            "restore",
+            "n6",
+            "--folder",
+            "Archive > 2025",
+        ],
+    );
+    let restored: serde_json::Value = serde_json::from_str(&json).expect("json");
+    assert_eq!(restored["id"], "n6");
+    assert_eq!(restored["folder"], "Archive > 2025");
+
+    let err = run_err(&["notes", "trash", "restore", "Alpha"]);
+    assert!(err.contains("no note in Recently Deleted matches"), "{err}");
+}
+
+#[test]
+fn notes_delete_moves_to_recently_deleted_unless_permanent() {
+    let out = run_ok(&["notes", "delete", "Alpha", "--yes"]);
+    assert!(out.contains("Moved to Recently Deleted"), "{out}");
+    let json = run_ok(&["--json", "notes", "delete", "Alpha", "--yes", "--permanent"]);
+    let deleted: serde_json::Value = serde_json::from_str(&json).expect("json");
+    assert_eq!(deleted["id"], "n1");
+    assert_eq!(deleted["permanent"], true);
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = trash_fixture(dir.path());
+    let err = assert_cmd::cargo_bin_cmd!("apple-notes")
+        .arg("--fixture")
+        .arg(&path)
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .args(["notes", "delete", "n6", "--yes"])
+        .assert()
+        .failure()
+        .get_output()
+        .stderr
+        .clone();
+    let err = String::from_utf8(err).unwrap();
+    assert!(err.contains("already in Recently Deleted"), "{err}");
+    let out = run_fixture_ok(&path, &["notes", "delete", "n6", "--yes", "--permanent"]);
+    assert_eq!(out, "Deleted permanently.\n");
+}
+
+#[test]
+fn folders_delete_reports_the_notes_moved_to_recently_deleted() {
+    let out = run_ok(&[
+        "folders",
+        "delete",
+        "--folder",
+        "Personal > Archive",
+        "--yes",
+    ]);
+    assert_eq!(
+        out,
+        "Deleted Personal > Archive; 2 notes moved to Recently Deleted.\n"
+    );
+    // Notes in subfolders count too.
+    let out = run_ok(&["folders", "delete", "--folder", "Personal", "--yes"]);
+    assert_eq!(
+        out,
+        "Deleted Personal; 3 notes moved to Recently Deleted.\n"
+    );
+}
+
+#[test]
+fn folders_move_refuses_to_move_a_folder_into_its_own_subtree() {
+    let move_to = |parent: &str| {
+        run_err(&[
+            "folders", "move", "--folder", "Personal", "--parent", parent,
+        ])
+    };
+    let err = move_to("Personal > Archive");
+    assert!(
+        err.contains("can't move Personal into its own subtree"),
+        "{err}"
+    );
+    assert!(move_to("Personal").contains("own subtree"));
+    assert!(move_to("Work").contains("folder not found: Work"));
+
+    let out = run_ok(&[
+        "--json",
+        "folders",
+        "move",
+        "--folder",
+        "Personal > Archive",
+        "--parent",
+        "Personal",
+    ]);
+    let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(v["folder"], "Personal > Archive");
+}
+
+#[test]
+fn notes_merge_combines_notes_into_a_new_or_existing_note() {
+    let out = run_ok(&[
+        "--json",
+        "notes",
+        "merge",
+        "n2",
+        "n3",
+        "--new-title",
+        "Both",
+    ]);
+    let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(v["id"], "fixture://note/1");
+    assert_eq!(v["merged"], serde_json::json!(["n2", "n3"]));
+
+    let out = run_ok(&["notes", "merge", "n2", "n3", "--into", "n1"]);
+    assert_eq!(out, "Merged 2 notes into n1.\n");
+
+    let err = run_err(&["notes", "merge", "n1", "n2", "--into", "n1"]);
+    assert!(err.contains("can't merge n1 into itself"), "{err}");
+    let err = run_err(&["notes", "merge", "n2", "--into", "n1", "--delete-sources"]);
+    assert!(err.contains("without --yes"), "{err}");
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let mut fixture: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture_path()).unwrap()).unwrap();
+    fixture["notes_by_id"]["n3"]["body_html"] =
+        r#"<div>Gamma body</div><div><img src="data:image/png;base64,AAAA"></div>"#.into();
+    let path = dir.path().join("attachment.json");
+    std::fs::write(&path, fixture.to_string()).unwrap();
+    let output = assert_cmd::cargo_bin_cmd!("apple-notes")
+        .arg("--fixture")
+        .arg(&path)
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .args([
+            "notes",
+            "merge",
+            "n2",
+            "n3",
+            "--into",
+            "n1",
+            "--delete-sources",
+            "--yes",
+        ])
+        .output()
+        .expect("run apple-notes");
+    assert!(!output.status.success());
+    let err = String::from_utf8_lossy(&output.stderr);
+    assert!(err.contains(r#"refusing to delete "Gamma" (n3)"#), "{err}");
+    assert!(output.stdout.is_empty(), "nothing is merged");
+}
+
+#[test]
+fn bulk_commands_read_note_ids_from_stdin() {
+    let run_stdin = |args: &[&str], stdin: &str| {
+        let out = assert_cmd::cargo_bin_cmd!("apple-notes")
+            .arg("--fixture")
+            .arg(fixture_path())
+            .env("NO_PROGRESS", "1")
+            .env("APPLE_NOTES_CONFIG", "/dev/null")
+            .args(args)
+            .write_stdin(stdin)
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        String::from_utf8(out).unwrap()
+    };
+
+    let out = run_stdin(
+        &["notes", "move", "--ids-from", "-", "--folder", "Personal"],
+        "n2\n\nn3\nn2\n",
+    );
+    assert_eq!(out, "Moved 2 notes to Personal.\n");
+
+    let out = run_stdin(
+        &["--json", "notes", "delete", "--ids-from", "-", "--yes"],
+        "n1\nn2\n",
+    );
+    let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(v["ids"], serde_json::json!(["n1", "n2"]));
+
+    let out = run_stdin(&["--json", "notes", "show", "--ids-from", "-"], "n1\nn3\n");
+    let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(v[0]["title"], "Alpha");
+    assert_eq!(v[1]["title"], "Gamma");
+}
+
+#[test]
+fn notes_replace_previews_diffs_and_summarizes() {
+    let out = run_ok(&[
+        "notes",
+        "replace",
+        "--query",
+        "(?i)beta",
+        "--with",
+        "Omega",
+        "--dry-run",
+    ]);
+    assert!(out.starts_with("Beta (n2)\n"), "{out}");
+    assert!(out.contains("-Beta body\n"), "{out}");
+    assert!(out.contains("+Omega body\n"), "{out}");
+
+    let out = run_ok(&[
+        "--json", "notes", "replace", "--query", "body", "--with", "text",
+    ]);
+    let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(v["applied"], true);
+    assert_eq!(v["notes"].as_array().unwrap().len(), 2);
+
+    let out = run_ok(&["notes", "replace", "--query", "nothing", "--with", "x"]);
+    assert_eq!(out, "No notes match.\n");
+}
+
+#[test]
+fn notes_replace_leaves_notes_with_attachments_alone() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let mut fixture: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture_path()).unwrap()).unwrap();
+    fixture["notes_by_id"]["n3"]["body_html"] =
+        r#"<div>Gamma body</div><div><img src="data:image/png;base64,AAAA"></div>"#.into();
+    let path = dir.path().join("attachment.json");
+    std::fs::write(&path, fixture.to_string()).unwrap();
+
+    let output = assert_cmd::cargo_bin_cmd!("apple-notes")
+        .arg("--fixture")
+        .arg(&path)
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .args([
+            "--json", "notes", "replace", "--query", "body", "--with", "text",
+        ])
+        .output()
+        .expect("run apple-notes");
+    assert_eq!(output.status.code(), Some(6));
+    let v: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let changed: Vec<&str> = v["notes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|n| n["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(changed, ["n2"]);
+    assert_eq!(v["skipped"][0]["id"], "n3");
+    assert_eq!(v["skipped"][0]["reason"], "it has attachments");
+}
+
+#[test]
+fn notes_rename_all_applies_unless_dry_run() {
+    let rename_all = |extra: &[&str]| {
+        let mut args = vec![
+            "--json",
+            "notes",
+            "rename-all",
+            "--match",
+            "^(B|G)",
+            "--to",
+            "Old $1",
+        ];
+        args.extend(extra);
+        let v: serde_json::Value = serde_json::from_str(&run_ok(&args)).unwrap();
+        v
+    };
+    let v = rename_all(&["--dry-run"]);
+    assert_eq!(v["applied"], false);
+    assert_eq!(v["renames"][0]["new_title"], "Old Beta");
+    assert_eq!(v["renames"][1]["new_title"], "Old Gamma");
+    assert_eq!(rename_all(&[])["applied"], true);
+
+    let err = run_err(&[
+        "--read-only",
+        "notes",
+        "rename-all",
+        "--match",
+        "a",
+        "--to",
+        "b",
+    ]);
+    assert!(err.contains("rename-all"), "{err}");
+
+    let err = run_err(&["notes", "rename-all", "--match", "(", "--to", "b"]);
+    assert!(err.contains("invalid --match"), "{err}");
+}
+
+/// The basic fixture with checklists in Alpha (n1) and Beta (n2).
+fn todo_fixture(dir: &std::path::Path) -> PathBuf {
+    let mut fixture: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture_path()).unwrap()).unwrap();
+    fixture["notes_by_id"]["n1"]["body_html"] = concat!(
+        "<div>Alpha</div><ul class=\"checklist\">",
+        "<li class=\"checked\">Buy milk</li><li>Call the bank</li></ul>"
+    )
+    .into();
+    fixture["notes_by_id"]["n2"]["body_html"] =
+        "<div>Beta</div><ul class=\"checklist\"><li>Book flights</li></ul>".into();
+    let path = dir.join("todo.json");
+    std::fs::write(&path, fixture.to_string()).unwrap();
+    path
+}
+
+#[test]
+fn notes_todo_lists_and_toggles_checklist_items() {
+    let dir = tempfile::tempdir().unwrap();
+    let fixture = todo_fixture(dir.path());
+    let out = run_fixture_ok(&fixture, &["notes", "todo", "list", "n1"]);
+    assert_eq!(out, "1. [x] Buy milk\n2. [ ] Call the bank\n");
+
+    let out = run_fixture_ok(
+        &fixture,
+        &["notes", "todo", "toggle", "n1", "--item", "bank"],
+    );
+    assert_eq!(out, "Checked 2. Call the bank\n");
+    let out = run_fixture_ok(
+        &fixture,
+        &["--json", "notes", "todo", "toggle", "n1", "--item", "1"],
+    );
+    let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(v["item"]["text"], "Buy milk");
+    assert_eq!(v["item"]["checked"], false);
+}
+
+#[test]
+fn todos_aggregates_checklists_across_notes() {
+    let dir = tempfile::tempdir().unwrap();
+    let fixture = todo_fixture(dir.path());
+    let out = run_fixture_ok(&fixture, &["--json", "todos", "--open-only"]);
+    let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+    let todos: Vec<_> = v
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| (t["title"].as_str().unwrap(), t["text"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        todos,
+        [("Alpha", "Call the bank"), ("Beta", "Book flights")]
+    );
+
+    let out = run_fixture_ok(&fixture, &["todos", "--folder", "Personal"]);
+    assert!(out.contains("Buy milk"), "{out}");
+    assert!(!out.contains("Book flights"), "{out}");
+}
+
+#[test]
+fn notes_create_takes_the_title_from_the_body() {
+    let out = run_ok(&[
+        "--json",
+        "notes",
+        "create",
+        "--markdown",
+        "--body",
+        "# Trip\n\nDay 1",
+    ]);
+    let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert!(v["id"].is_string());
+    let err = run_err(&["notes", "create", "--body", " \n"]);
+    assert!(
+        err.contains("--title is required when the body is empty"),
+        "{err}"
+    );
+}
+
+#[test]
+fn open_flag_shows_the_edited_note_in_notes() {
+    run_ok(&["notes", "append", "n1", "--body", "more", "--open"]);
+    run_ok(&["notes", "set-body", "n1", "--body", "new", "--open"]);
+    // The fixture only opens notes it knows, so this proves `--open` went to the new note.
+    let err = run_err(&[
+        "notes", "create", "--title", "Idea", "--body", "x", "--open",
+    ]);
+    assert!(err.contains("fixture://note/1"), "{err}");
+}
+
+#[test]
+fn notes_create_attaches_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("receipt.pdf");
+    std::fs::write(&file, b"%PDF").unwrap();
+    let file = file.to_str().unwrap();
+    let out = run_ok(&[
+        "notes", "create", "--title", "Receipt", "--body", "x", "--attach", file,
+    ]);
+    assert_eq!(out.trim(), "fixture://note/1");
+
+    let missing = dir.path().join("missing.png");
+    let err = run_err(&[
+        "notes",
+        "create",
+        "--title",
+        "Receipt",
+        "--attach",
+        file,
+        "--attach",
+        missing.to_str().unwrap(),
+    ]);
+    assert!(
+        err.contains("can't attach") && err.contains("missing.png"),
+        "{err}"
+    );
+}
+
+/// The basic fixture with Beta (n2) password-protected.
+fn locked_fixture(dir: &std::path::Path) -> PathBuf {
+    let mut fixture: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture_path()).unwrap()).unwrap();
+    fixture["note_summaries_by_account"]["iCloud"][1]["locked"] = true.into();
+    let path = dir.join("locked.json");
+    std::fs::write(&path, fixture.to_string()).unwrap();
+    path
+}
+
+#[test]
+fn locked_notes_are_marked_in_listings_and_skipped_by_export() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = locked_fixture(dir.path());
+    let table = run_fixture_ok(&path, &["notes", "list"]);
+    let beta = table.lines().find(|l| l.contains("Beta")).unwrap();
+    assert!(beta.contains("yes"), "{table}");
+    let json = run_fixture_ok(&path, &["--json", "notes", "list"]);
+    let notes: Vec<serde_json::Value> = serde_json::from_str(&json).expect("json");
+    let locked: Vec<bool> = notes.iter().map(|n| n["locked"] == true).collect();
+    assert_eq!(locked, [false, true, false]);
+
+    let out_dir = dir.path().join("backup");
+    let out = assert_cmd::cargo_bin_cmd!("apple-notes")
+        .arg("--fixture")
+        .arg(&path)
+        .env("NO_PROGRESS", "1")
+        .env("APPLE_NOTES_CONFIG", "/dev/null")
+        .args(["export", "--out"])
+        .arg(&out_dir)
+        .assert()
+        .success()
+        .get_output()
+        .stderr
+        .clone();
+    let stderr = String::from_utf8(out).unwrap();
+    assert!(
+        stderr.contains("Skipped 1 locked notes (unlock them in Notes to export them): \"Beta\""),
+        "{stderr}"
+    );
+    let manifest = std::fs::read_to_string(out_dir.join("manifest.json")).unwrap();
+    assert!(!manifest.contains("Beta"), "{manifest}");
+    assert!(manifest.contains("Gamma"), "{manifest}");
+}
+
+#[test]
+fn notes_list_pinned_keeps_pinned_notes() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let mut fixture: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture_path()).unwrap()).unwrap();
+    fixture["note_summaries_by_account"]["iCloud"][2]["pinned"] = true.into();
+    let path = dir.path().join("pinned.json");
+    std::fs::write(&path, fixture.to_string()).unwrap();
+
+    assert_eq!(listed_ids(&path, &["--pinned", "--picker"]), ["n3"]);
+    assert_eq!(listed_ids(&path, &["--picker"]), ["n1", "n2", "n3"]);
+    let table = run_fixture_ok(&path, &["notes", "list"]);
+    let gamma = table.lines().find(|l| l.contains("Gamma")).unwrap();
+    assert!(gamma.contains("yes"), "{table}");
+}
+
+#[test]
+fn shared_notes_show_their_participants_in_meta_and_exports() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let db_path = dir.path().join("NoteStore.sqlite");
+    let conn = seed_notes_db(&db_path);
+    conn.execute_batch(
+        "ALTER TABLE ZICCLOUDSYNCINGOBJECT ADD COLUMN ZSERVERSHAREDATA BLOB;
+         UPDATE ZICCLOUDSYNCINGOBJECT
+            SET ZSERVERSHAREDATA = CAST('bplist00 mailto:Bob@Example.org ann@example.com bob@example.org' AS BLOB)
+          WHERE Z_PK = 20;",
+    )
+    .expect("share note");
+    let run = |args: &[&str]| {
+        let out = assert_cmd::cargo_bin_cmd!("apple-notes")
+            .env("NO_PROGRESS", "1")
+            .env("APPLE_NOTES_CONFIG", "/dev/null")
+            .env("APPLE_NOTES_OSASCRIPT_BIN", dir.path().join("missing"))
+            .env("TZ", "UTC")
+            .arg("--db-path")
+            .arg(&db_path)
+            .args(args)
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        String::from_utf8(out).unwrap()
+    };
+
+    let text = run(&["notes", "show", "alpha", "--meta"]);
+    assert!(text.contains("Shared:      yes\n"), "{text}");
+    assert!(
+        text.contains("Shared with: ann@example.com, bob@example.org\n"),
+        "{text}"
+    );
+    let meta: serde_json::Value =
+        serde_json::from_str(&run(&["--json", "notes", "show", "alpha", "--meta"])).unwrap();
+    assert_eq!(meta["shared"], true);
+    assert_eq!(
+        meta["participants"],
+        serde_json::json!(["ann@example.com", "bob@example.org"])
+    );
+
+    let out_dir = dir.path().join("backup");
+    run(&["export", "--out", out_dir.to_str().unwrap()]);
+    let metadata_path = walkdir::WalkDir::new(&out_dir)
+        .into_iter()
+        .map(|e| e.unwrap().into_path())
+        .find(|p| p.ends_with("metadata.json"))
+        .expect("metadata.json");
+    let metadata: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(metadata_path).unwrap()).unwrap();
+    assert_eq!(metadata["shared"], true);
+    assert_eq!(metadata["participants"][0], "ann@example.com");
+}